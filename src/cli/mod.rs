@@ -17,4 +17,5 @@
 pub mod parser;
 pub mod show;
 pub mod playbooks;
-pub mod version;
\ No newline at end of file
+pub mod version;
+pub mod vault;
\ No newline at end of file
@@ -17,6 +17,7 @@
 // we don't use any parsing libraries here because they are a bit too automagical
 // this may change later.
 
+use crate::inventory::pattern::HostPattern;
 use std::env;
 use std::fs;
 use std::vec::Vec;
@@ -25,12 +26,16 @@ use std::sync::{Arc,RwLock};
 use crate::util::io::directory_as_string;
 use crate::util::yaml::blend_variables;
 use crate::inventory::loading::convert_json_vars;
-use crate::util::io::jet_file_open;
-use crate::util::yaml::show_yaml_error_in_context;
+use crate::inventory::ini::split_tokens;
+use crate::util::vault::{load_vars_file,read_password_file,set_vault_password};
 use crate::cli::version::{GIT_VERSION,GIT_BRANCH,BUILD_TIME};
 use std::path::Path;
 use std::io;
 use std::collections::HashMap;
+use std::fs::OpenOptions;
+use crate::playbooks::visitor::{OutputFormat,SummaryFormat};
+use crate::playbooks::traversal::HostOrder;
+use crate::connection::ssh::{HostKeyChecking,parse_host_key_checking};
 
 // the CLI parser struct values hold various values calculated when calling parse() on
 // the struct
@@ -42,6 +47,7 @@ pub struct CliParser {
     pub module_paths: Arc<RwLock<Vec<PathBuf>>>,
     pub limit_groups: Vec<String>,
     pub limit_hosts: Vec<String>,
+    pub limit: Option<HostPattern>,
     pub inventory_set: bool,
     pub playbook_set: bool,
     pub mode: u32,
@@ -50,16 +56,52 @@ pub struct CliParser {
     pub show_hosts: Vec<String>,
     pub show_groups: Vec<String>,
     pub batch_size: Option<usize>,
+    pub order: HostOrder,
     pub default_user: String,
     pub sudo: Option<String>,
     pub default_port: i64,
     pub threads: usize,
-    pub verbosity: u32,
+    pub verbosity: i32,
     pub tags: Option<Vec<String>>,
+    pub skip_tags: Option<Vec<String>>,
+    pub start_at_task: Option<String>,
+    pub step: bool,
+    pub auto_continue: bool,
+    pub no_facts: bool,
+    pub list_facts: Option<String>,
+    pub list_hosts: bool,
+    pub list_tasks: bool,
+    pub syntax_check: bool,
+    pub plan_out: Option<String>,
+    pub diff_out: Option<String>,
+    pub force_handlers: bool,
     pub allow_localhost_delegation: bool,
     pub extra_vars: serde_yaml::Value,
     pub forward_agent: bool,
     pub login_password: Option<String>,
+    pub sudo_password: Option<String>,
+    pub timeout: Option<u64>,
+    pub connect_retries: usize,
+    pub inventory_cache_ttl: u64,
+    pub host_key_checking: HostKeyChecking,
+    pub flush_cache: bool,
+    pub vault_files: Vec<PathBuf>,
+    pub new_vault_password: Option<String>,
+    pub no_connection_reuse: bool,
+    pub preflight: bool,
+    pub preflight_max_fail: Option<usize>,
+    pub output_format: OutputFormat,
+    pub summary_format: SummaryFormat,
+    pub log_file: Option<String>,
+    pub metrics_out: Option<String>,
+    pub webhook: Option<String>,
+    pub host_log_dir: Option<String>,
+    pub retry_file: Option<String>,
+    pub show_timing: bool,
+    pub no_color: bool,
+    pub show_diff: bool,
+    pub changed_exit_code: Option<i32>,
+    pub progress: bool,
     pub argument_map: HashMap<String, Arguments>,
 }
 
@@ -74,6 +116,10 @@ pub const CLI_MODE_SSH: u32 = 4;
 pub const CLI_MODE_CHECK_SSH: u32 = 5;
 pub const CLI_MODE_SHOW: u32 = 6;
 pub const CLI_MODE_SIMULATE: u32 = 7;
+pub const CLI_MODE_CHECK_CONNECTIONS: u32 = 8;
+pub const CLI_MODE_ENCRYPT: u32 = 9;
+pub const CLI_MODE_DECRYPT: u32 = 10;
+pub const CLI_MODE_REKEY: u32 = 11;
 
 fn is_cli_mode_valid(value: &String) -> bool {
     match cli_mode_from_string(value) {
@@ -90,6 +136,10 @@ fn cli_mode_from_string(s: &String) -> Result<u32, String> {
         "check-ssh"       => Ok(CLI_MODE_CHECK_SSH),
         "__simulate"      => Ok(CLI_MODE_SIMULATE),
         "show-inventory"  => Ok(CLI_MODE_SHOW),
+        "check-connections" => Ok(CLI_MODE_CHECK_CONNECTIONS),
+        "encrypt"         => Ok(CLI_MODE_ENCRYPT),
+        "decrypt"         => Ok(CLI_MODE_DECRYPT),
+        "rekey"           => Ok(CLI_MODE_REKEY),
         _ => Err(format!("invalid mode: {}", s))
     }
 }
@@ -110,25 +160,66 @@ pub enum Arguments {
     ARGUMENT_SHOW_HOSTS,
     ARGUMENT_LIMIT_GROUPS,
     ARGUMENT_LIMIT_HOSTS,
+    ARGUMENT_LIMIT,
     ARGUMENT_HELP,
     ARGUMENT_PORT,
     ARGUMENT_USER,
     ARGUMENT_USER_SHORT,
     ARGUMENT_SUDO,
     ARGUMENT_TAGS,
+    ARGUMENT_SKIP_TAGS,
+    ARGUMENT_START_AT_TASK,
+    ARGUMENT_STEP,
+    ARGUMENT_AUTO_CONTINUE,
+    ARGUMENT_NO_FACTS,
+    ARGUMENT_LIST_FACTS,
+    ARGUMENT_LIST_HOSTS,
+    ARGUMENT_LIST_TASKS,
+    ARGUMENT_SYNTAX_CHECK,
+    ARGUMENT_PLAN_OUT,
+    ARGUMENT_FORCE_HANDLERS,
     ARGUMENT_ALLOW_LOCALHOST,
     ARGUMENT_FORWARD_AGENT,
     ARGUMENT_THREADS,
     ARGUMENT_THREADS_SHORT,
+    ARGUMENT_FORKS,
     ARGUMENT_BATCH_SIZE,
+    ARGUMENT_ORDER,
     ARGUMENT_VERBOSE,
     ARGUMENT_VERBOSER,
     ARGUMENT_VERBOSEST,
     ARGUMENT_EXTRA_VARS,
     ARGUMENT_EXTRA_VARS_SHORT,
     ARGUMENT_ASK_LOGIN_PASSWORD,
+    ARGUMENT_ASK_SUDO_PASSWORD,
+    ARGUMENT_TIMEOUT,
+    ARGUMENT_CONNECT_RETRIES,
+    ARGUMENT_INVENTORY_CACHE_TTL,
+    ARGUMENT_HOST_KEY_CHECKING,
+    ARGUMENT_FLUSH_CACHE,
+    ARGUMENT_VAULT_PASSWORD_FILE,
+    ARGUMENT_NEW_VAULT_PASSWORD_FILE,
+    ARGUMENT_FILES,
+    ARGUMENT_NO_CONNECTION_REUSE,
+    ARGUMENT_PREFLIGHT,
+    ARGUMENT_PREFLIGHT_MAX_FAIL,
     ARGUMENT_MODULES,
-    ARGUMENT_MODULES_SHORT
+    ARGUMENT_MODULES_SHORT,
+    ARGUMENT_OUTPUT_FORMAT,
+    ARGUMENT_SUMMARY_FORMAT,
+    ARGUMENT_LOG_FILE,
+    ARGUMENT_METRICS_OUT,
+    ARGUMENT_WEBHOOK,
+    ARGUMENT_HOST_LOG_DIR,
+    ARGUMENT_RETRY_FILE,
+    ARGUMENT_QUIET,
+    ARGUMENT_QUIET_SHORT,
+    ARGUMENT_TIMING,
+    ARGUMENT_NO_COLOR,
+    ARGUMENT_DIFF,
+    ARGUMENT_DIFF_OUT,
+    ARGUMENT_CHANGED_EXIT_CODE,
+    ARGUMENT_PROGRESS
 }
 
 impl Arguments {
@@ -147,23 +238,64 @@ impl Arguments {
             Arguments::ARGUMENT_SHOW_HOSTS => "--show-hosts",
             Arguments::ARGUMENT_LIMIT_GROUPS => "--limit-groups",
             Arguments::ARGUMENT_LIMIT_HOSTS => "--limit-hosts",
+            Arguments::ARGUMENT_LIMIT => "--limit",
             Arguments::ARGUMENT_HELP => "--help",
             Arguments::ARGUMENT_PORT => "--port",
             Arguments::ARGUMENT_USER => "--user",
             Arguments::ARGUMENT_USER_SHORT => "-u",
             Arguments::ARGUMENT_SUDO => "--sudo",
             Arguments::ARGUMENT_TAGS => "--tags",
+            Arguments::ARGUMENT_SKIP_TAGS => "--skip-tags",
+            Arguments::ARGUMENT_START_AT_TASK => "--start-at-task",
+            Arguments::ARGUMENT_STEP => "--step",
+            Arguments::ARGUMENT_AUTO_CONTINUE => "--auto-continue",
+            Arguments::ARGUMENT_NO_FACTS => "--no-facts",
+            Arguments::ARGUMENT_LIST_FACTS => "--list-facts",
+            Arguments::ARGUMENT_LIST_HOSTS => "--list-hosts",
+            Arguments::ARGUMENT_LIST_TASKS => "--list-tasks",
+            Arguments::ARGUMENT_SYNTAX_CHECK => "--syntax-check",
+            Arguments::ARGUMENT_PLAN_OUT => "--plan-out",
+            Arguments::ARGUMENT_FORCE_HANDLERS => "--force-handlers",
             Arguments::ARGUMENT_ALLOW_LOCALHOST => "--allow-localhost-delegation",
             Arguments::ARGUMENT_FORWARD_AGENT => "--forward-agent",
             Arguments::ARGUMENT_THREADS => "--threads",
             Arguments::ARGUMENT_THREADS_SHORT => "-t",
+            Arguments::ARGUMENT_FORKS => "--forks",
             Arguments::ARGUMENT_BATCH_SIZE => "--batch-size",
+            Arguments::ARGUMENT_ORDER => "--order",
             Arguments::ARGUMENT_VERBOSE => "-v",
             Arguments::ARGUMENT_VERBOSER => "-vv",
             Arguments::ARGUMENT_VERBOSEST => "-vvv",
             Arguments::ARGUMENT_EXTRA_VARS => "--extra-vars",
             Arguments::ARGUMENT_EXTRA_VARS_SHORT => "-e",
             Arguments::ARGUMENT_ASK_LOGIN_PASSWORD => "--ask-login-password",
+            Arguments::ARGUMENT_OUTPUT_FORMAT => "--output-format",
+            Arguments::ARGUMENT_SUMMARY_FORMAT => "--summary-format",
+            Arguments::ARGUMENT_LOG_FILE => "--log-file",
+            Arguments::ARGUMENT_METRICS_OUT => "--metrics-out",
+            Arguments::ARGUMENT_WEBHOOK => "--webhook",
+            Arguments::ARGUMENT_HOST_LOG_DIR => "--host-log-dir",
+            Arguments::ARGUMENT_RETRY_FILE => "--retry-file",
+            Arguments::ARGUMENT_QUIET => "--quiet",
+            Arguments::ARGUMENT_QUIET_SHORT => "-q",
+            Arguments::ARGUMENT_TIMING => "--timing",
+            Arguments::ARGUMENT_NO_COLOR => "--no-color",
+            Arguments::ARGUMENT_DIFF => "--diff",
+            Arguments::ARGUMENT_DIFF_OUT => "--diff-out",
+            Arguments::ARGUMENT_CHANGED_EXIT_CODE => "--changed-exit-code",
+            Arguments::ARGUMENT_PROGRESS => "--progress",
+            Arguments::ARGUMENT_ASK_SUDO_PASSWORD => "--ask-sudo-password",
+            Arguments::ARGUMENT_TIMEOUT => "--timeout",
+            Arguments::ARGUMENT_CONNECT_RETRIES => "--connect-retries",
+            Arguments::ARGUMENT_INVENTORY_CACHE_TTL => "--inventory-cache-ttl",
+            Arguments::ARGUMENT_HOST_KEY_CHECKING => "--host-key-checking",
+            Arguments::ARGUMENT_FLUSH_CACHE => "--flush-cache",
+            Arguments::ARGUMENT_VAULT_PASSWORD_FILE => "--vault-password-file",
+            Arguments::ARGUMENT_NEW_VAULT_PASSWORD_FILE => "--new-vault-password-file",
+            Arguments::ARGUMENT_FILES => "--files",
+            Arguments::ARGUMENT_NO_CONNECTION_REUSE => "--no-connection-reuse",
+            Arguments::ARGUMENT_PREFLIGHT => "--preflight",
+            Arguments::ARGUMENT_PREFLIGHT_MAX_FAIL => "--preflight-max-fail",
         }
     }
 }
@@ -184,23 +316,64 @@ fn build_argument_map() -> HashMap<String, Arguments> {
         (Arguments::ARGUMENT_SHOW_HOSTS, "--show-hosts"),
         (Arguments::ARGUMENT_LIMIT_GROUPS, "--limit-groups"),
         (Arguments::ARGUMENT_LIMIT_HOSTS, "--limit-hosts"),
+        (Arguments::ARGUMENT_LIMIT, "--limit"),
         (Arguments::ARGUMENT_HELP, "--help"),
         (Arguments::ARGUMENT_PORT, "--port"),
         (Arguments::ARGUMENT_USER, "--user"),
         (Arguments::ARGUMENT_USER_SHORT, "-u"),
         (Arguments::ARGUMENT_SUDO, "--sudo"),
         (Arguments::ARGUMENT_TAGS, "--tags"),
+        (Arguments::ARGUMENT_SKIP_TAGS, "--skip-tags"),
+        (Arguments::ARGUMENT_START_AT_TASK, "--start-at-task"),
+        (Arguments::ARGUMENT_STEP, "--step"),
+        (Arguments::ARGUMENT_AUTO_CONTINUE, "--auto-continue"),
+        (Arguments::ARGUMENT_NO_FACTS, "--no-facts"),
+        (Arguments::ARGUMENT_LIST_FACTS, "--list-facts"),
+        (Arguments::ARGUMENT_LIST_HOSTS, "--list-hosts"),
+        (Arguments::ARGUMENT_LIST_TASKS, "--list-tasks"),
+        (Arguments::ARGUMENT_SYNTAX_CHECK, "--syntax-check"),
+        (Arguments::ARGUMENT_PLAN_OUT, "--plan-out"),
+        (Arguments::ARGUMENT_FORCE_HANDLERS, "--force-handlers"),
         (Arguments::ARGUMENT_ALLOW_LOCALHOST, "--allow-localhost-delegation"),
         (Arguments::ARGUMENT_FORWARD_AGENT, "--forward-agent"),
         (Arguments::ARGUMENT_THREADS, "--threads"),
         (Arguments::ARGUMENT_THREADS_SHORT, "-t"),
+        (Arguments::ARGUMENT_FORKS, "--forks"),
         (Arguments::ARGUMENT_BATCH_SIZE, "--batch-size"),
+        (Arguments::ARGUMENT_ORDER, "--order"),
         (Arguments::ARGUMENT_VERBOSE, "-v"),
         (Arguments::ARGUMENT_VERBOSER, "-vv"),
         (Arguments::ARGUMENT_VERBOSEST, "-vvv"),
         (Arguments::ARGUMENT_EXTRA_VARS, "--extra-vars"),
         (Arguments::ARGUMENT_EXTRA_VARS_SHORT, "-e"),
         (Arguments::ARGUMENT_ASK_LOGIN_PASSWORD, "--ask-login-password"),
+        (Arguments::ARGUMENT_OUTPUT_FORMAT, "--output-format"),
+        (Arguments::ARGUMENT_SUMMARY_FORMAT, "--summary-format"),
+        (Arguments::ARGUMENT_LOG_FILE, "--log-file"),
+        (Arguments::ARGUMENT_METRICS_OUT, "--metrics-out"),
+        (Arguments::ARGUMENT_WEBHOOK, "--webhook"),
+        (Arguments::ARGUMENT_HOST_LOG_DIR, "--host-log-dir"),
+        (Arguments::ARGUMENT_RETRY_FILE, "--retry-file"),
+        (Arguments::ARGUMENT_QUIET, "--quiet"),
+        (Arguments::ARGUMENT_QUIET_SHORT, "-q"),
+        (Arguments::ARGUMENT_TIMING, "--timing"),
+        (Arguments::ARGUMENT_NO_COLOR, "--no-color"),
+        (Arguments::ARGUMENT_DIFF, "--diff"),
+        (Arguments::ARGUMENT_DIFF_OUT, "--diff-out"),
+        (Arguments::ARGUMENT_CHANGED_EXIT_CODE, "--changed-exit-code"),
+        (Arguments::ARGUMENT_PROGRESS, "--progress"),
+        (Arguments::ARGUMENT_ASK_SUDO_PASSWORD, "--ask-sudo-password"),
+        (Arguments::ARGUMENT_TIMEOUT, "--timeout"),
+        (Arguments::ARGUMENT_CONNECT_RETRIES, "--connect-retries"),
+        (Arguments::ARGUMENT_INVENTORY_CACHE_TTL, "--inventory-cache-ttl"),
+        (Arguments::ARGUMENT_HOST_KEY_CHECKING, "--host-key-checking"),
+        (Arguments::ARGUMENT_FLUSH_CACHE, "--flush-cache"),
+        (Arguments::ARGUMENT_VAULT_PASSWORD_FILE, "--vault-password-file"),
+        (Arguments::ARGUMENT_NEW_VAULT_PASSWORD_FILE, "--new-vault-password-file"),
+        (Arguments::ARGUMENT_FILES, "--files"),
+        (Arguments::ARGUMENT_NO_CONNECTION_REUSE, "--no-connection-reuse"),
+        (Arguments::ARGUMENT_PREFLIGHT, "--preflight"),
+        (Arguments::ARGUMENT_PREFLIGHT_MAX_FAIL, "--preflight-max-fail"),
     ];
     let mut map : HashMap<String, Arguments> = HashMap::new();
     for (e,i) in inputs.iter() {
@@ -245,9 +418,19 @@ fn show_help() {
                       | |\n\
                       | --- | --- | ---\n\
                       | remote machine management: |\n\
+                      | | check-connections | connects to every host in inventory and reports which are unreachable\n\
+                      | |\n\
                       | | check-ssh | looks for configuration differences over SSH\n\
                       | |\n\
                       | | ssh| manages multiple machines over SSH\n\
+                      | |\n\
+                      | --- | --- | ---\n\
+                      | vault files: |\n\
+                      | | decrypt | decrypts the vault files given with --files in place\n\
+                      | |\n\
+                      | | encrypt | encrypts the YAML variable files given with --files in place\n\
+                      | |\n\
+                      | | rekey | changes the password of the vault files given with --files, see --new-vault-password-file\n\
                       |-|-";
 
     crate::util::terminal::markdown_print(&String::from(mode_table));
@@ -259,7 +442,11 @@ fn show_help() {
                        | Basics:\n\
                        | | -p, --playbook path1:path2| specifies automation content\n\
                        | |\n\
-                       | | -i, --inventory path1:path2| (required for ssh only) specifies which systems to manage\n\
+                       | | -i, --inventory path1:path2| (required for ssh only) specifies which systems to manage. Given more than once, the sources are merged and later ones win\n\
+                       | |\n\
+                       | | --inventory-cache-ttl seconds | reuse output from inventory scripts for this long, instead of running them every time\n\
+                       | |\n\
+                       | | --flush-cache | throw away cached inventory script output before loading inventory\n\
                        | |\n\
                        | | -r, --roles path1:path2| adds additional role search paths. Also uses $JET_ROLES_PATH\n\
                        | |\n\
@@ -269,15 +456,31 @@ fn show_help() {
                        | |\n\
                        | | --batch-size N| fully configure this many hosts before moving to the next batch\n\
                        | |\n\
+                       | | --order mode | which hosts go into the first batches: sorted (the default), reverse, inventory or shuffle. Use shuffle:N to repeat a shuffle\n\
+                       | |\n\
                        | | --forward-agent | enables SSH agent forwarding but only on specific tasks (ex: git)\n\
                        | |\n\
                        | | --limit-groups group1:group2 | further limits scope for playbook runs\n\
                        | |\n\
                        | | --limit-hosts host1 | further limits scope for playbook runs\n\
                        | |\n\
+                       | | --limit pattern | hosts, groups or globs, ex: 'web*:&prod:!web-3' for web hosts in prod except web-3, or @file for the hosts listed in a file\n\
+                       | |\n\
+                       | | --no-connection-reuse | connect again for every task instead of keeping connections open, for debugging\n\
+                       | |\n\
+                       | | --connect-retries N | try to reach an SSH host N more times, waiting 1, 2, 4.. seconds in between, before it counts as unreachable\n\
+                       | |\n\
+                       | | --preflight | connect to all hosts of each play before running anything and report those that cannot be reached together\n\
+                       | |\n\
+                       | | --preflight-max-fail N | implies --preflight, stop the play when more than N percent of its hosts cannot be reached\n\
+                       | |\n\
+                       | | --host-key-checking mode | strict, accept-new (the default) or off. Host keys are checked against ~/.ssh/known_hosts\n\
+                       | |\n\
                        | | --port N | use this default port instead of $JET_SSH_PORT or 22\n\
                        | |\n\
-                       | | -t, --threads N| how many parallel threads to use. Alternatively set $JET_THREADS\n\
+                       | | -t, --threads, --forks N| how many hosts to work on at once. Alternatively set $JET_THREADS\n\
+                       | |\n\
+                       | | --timeout seconds | give up on connections and commands that take longer than this\n\
                        | |\n\
                        | | -u, --user username | use this default username instead of $JET_SSH_USER or $USER\n\
                        | |\n\
@@ -285,14 +488,74 @@ fn show_help() {
                        | Misc options:\n\
                        | | --allow-localhost-delegation | signs off on variable sourcing risks and enables localhost actions with delegate_to\n\
                        | |\n\
-                       | | -e, --extra-vars @filename | injects extra variables into the playbook runtime context from a YAML file, or quoted JSON\n\
+                       | | --changed-exit-code N | exit with N instead of 0 when there were no failures but some hosts changed, or would change in check modes, to detect drift\n\
+                       | |\n\
+                       | | --diff | in check modes, show how file contents would change\n\
+                       | |\n\
+                       | | --diff-out path | at the end, write every change made (or that would be made in check modes) per host, with diffs, as JSON, or YAML for .yml paths\n\
+                       | |\n\
+                       | | -e, --extra-vars @filename | injects extra variables from a YAML file, quoted JSON, or key=value pairs. These override all other variables\n\
+                       | |\n\
+                       | | --force-handlers | run notified handlers even on hosts that failed, or when the play fails\n\
+                       | |\n\
+                       | | --list-facts hostname | gather facts for one host and print them with its resolved inventory variables, then exit. No playbook is needed\n\
+                       | |\n\
+                       | | --list-hosts | print the hosts each play would manage, after limits, without connecting\n\
+                       | |\n\
+                       | | --list-tasks | print the tasks each play would run, after tags, without running them\n\
+                       | |\n\
+                       | | --plan-out path | before running, write the plays, hosts, tasks and resolved variables as JSON, or YAML for .yml paths\n\
+                       | |\n\
+                       | | --syntax-check | parse the playbooks and every role and task file they use, report all problems found and exit without connecting\n\
+                       | |\n\
+                       | | --log-file path | append a JSON record of every task result per host to this file instead of $JET_LOG\n\
+                       | |\n\
+                       | | --metrics-out path | at the end, write the summary counts as a Prometheus textfile for the node exporter\n\
+                       | |\n\
+                       | | --webhook url | at the end, POST the summary and any failed hosts as JSON, in a form Slack incoming webhooks accept\n\
+                       | |\n\
+                       | | --host-log-dir path | append every command run and its full output to path/hostname.log, at any verbosity\n\
+                       | |\n\
+                       | | --retry-file path | where to list failed hosts at the end, for --limit @path, default is playbook.retry next to the playbook\n\
+                       | |\n\
+                       | | --no-facts | do not gather facts such as jet_os_type and jet_memtotal_mb at the start of each play\n\
+                       | |\n\
+                       | | --no-color | disables colored output, which is also disabled when output is not a terminal\n\
+                       | |\n\
+                       | | --output-format text/json | json emits one event per line on stdout for CI systems, default is text\n\
+                       | |\n\
+                       | | --summary-format markdown/plain/csv | how the table at the end of a text run is drawn, plain suits logs and csv suits spreadsheets\n\
                        | |\n\
                        | | --sudo username | sudo to this user by default for all tasks\n\
                        | |\n\
-                       | | --tags tag1:tag2 | only run tasks or roles with one of these tags\n\
+                       | | --ask-sudo-password | prompt for the sudo password on standard input, also uses $JET_SUDO_PASSWORD\n\
+                       | |\n\
+                       | | --tags tag1:tag2 | only run tasks or roles with one of these tags. Tasks tagged 'always' still run, 'never' only runs if named\n\
+                       | |\n\
+                       | | --skip-tags tag1:tag2 | do not run tasks or roles with any of these tags\n\
+                       | |\n\
+                       | | --start-at-task 'task name' | skip every task before the first one with this name, to resume a failed run\n\
+                       | |\n\
+                       | | --step | confirm each task before it runs, ignored when standard input is not a terminal\n\
+                       | |\n\
+                       | | --auto-continue | pause prompts continue on their own when standard input is not a terminal, instead of failing\n\
+                       | |\n\
+                       | | --timing | adds play durations and the slowest tasks to the summary\n\
+                       | |\n\
+                       | | --progress | show a progress bar per task instead of a line per host, when on a terminal\n\
+                       | |\n\
+                       | | -q, --quiet | only show failures and the final summary\n\
                        | |\n\
                        | | -v -vv -vvv| ever increasing verbosity\n\
                        | |\n\
+                       | --- | ---\n\
+                       | Vault options:\n\
+                       | | --files path1:path2 | the files for the encrypt, decrypt and rekey modes\n\
+                       | |\n\
+                       | | --new-vault-password-file path | the new password for rekey, prompted for if not given\n\
+                       | |\n\
+                       | | --vault-password-file path | decrypts vault files in vars with the password in this file, prompted for if not given\n\
+                       | |\n\
                        |-|";
 
     crate::util::terminal::markdown_print(&String::from(flags_table));
@@ -321,6 +584,7 @@ impl CliParser  {
             show_hosts: Vec::new(),
             show_groups: Vec::new(),
             batch_size: None,
+            order: HostOrder::Sorted,
             default_user: match env::var("JET_SSH_USER") {
                 Ok(x) => {
                     println!("$JET_SSH_USER: {}", x);
@@ -354,11 +618,47 @@ impl CliParser  {
             verbosity: 0,
             limit_groups: Vec::new(),
             limit_hosts: Vec::new(),
+            limit: None,
             tags: None,
+            skip_tags: None,
+            start_at_task: None,
+            step: false,
+            auto_continue: false,
+            no_facts: false,
+            list_facts: None,
+            list_hosts: false,
+            list_tasks: false,
+            syntax_check: false,
+            plan_out: None,
+            diff_out: None,
+            force_handlers: false,
             allow_localhost_delegation: false,
             extra_vars: serde_yaml::Value::Mapping(serde_yaml::Mapping::new()),
             forward_agent: false,
             login_password: None,
+            sudo_password: None,
+            timeout: None,
+            connect_retries: 0,
+            inventory_cache_ttl: 0,
+            host_key_checking: HostKeyChecking::AcceptNew,
+            flush_cache: false,
+            vault_files: Vec::new(),
+            new_vault_password: None,
+            no_connection_reuse: false,
+            preflight: false,
+            preflight_max_fail: None,
+            output_format: OutputFormat::Text,
+            summary_format: SummaryFormat::Markdown,
+            log_file: None,
+            metrics_out: None,
+            webhook: None,
+            host_log_dir: None,
+            retry_file: None,
+            show_timing: false,
+            no_color: false,
+            show_diff: false,
+            changed_exit_code: None,
+            progress: false,
             argument_map: build_argument_map(),
         };
         return p;
@@ -444,7 +744,24 @@ impl CliParser  {
                             Arguments::ARGUMENT_VERBOSE            => self.increase_verbosity(1),
                             Arguments::ARGUMENT_VERBOSER           => self.increase_verbosity(2),
                             Arguments::ARGUMENT_VERBOSEST          => self.increase_verbosity(3),
+                            Arguments::ARGUMENT_QUIET              => self.increase_verbosity(-1),
+                            Arguments::ARGUMENT_QUIET_SHORT        => self.increase_verbosity(-1),
+                            Arguments::ARGUMENT_TIMING             => self.store_show_timing(),
+                            Arguments::ARGUMENT_NO_COLOR           => self.store_no_color(),
+                            Arguments::ARGUMENT_DIFF               => self.store_show_diff(),
+                            Arguments::ARGUMENT_PROGRESS           => self.store_progress(),
+                            Arguments::ARGUMENT_NO_CONNECTION_REUSE => self.store_no_connection_reuse(),
+                            Arguments::ARGUMENT_PREFLIGHT          => self.store_preflight(),
+                            Arguments::ARGUMENT_STEP               => self.store_step(),
+                            Arguments::ARGUMENT_AUTO_CONTINUE      => self.store_auto_continue(),
+                            Arguments::ARGUMENT_NO_FACTS           => self.store_no_facts(),
+                            Arguments::ARGUMENT_LIST_HOSTS         => self.store_list_hosts(),
+                            Arguments::ARGUMENT_LIST_TASKS         => self.store_list_tasks(),
+                            Arguments::ARGUMENT_SYNTAX_CHECK       => self.store_syntax_check(),
+                            Arguments::ARGUMENT_FORCE_HANDLERS     => self.store_force_handlers(),
+                            Arguments::ARGUMENT_FLUSH_CACHE        => self.store_flush_cache(),
                             Arguments::ARGUMENT_ASK_LOGIN_PASSWORD => self.store_login_password(),
+                            Arguments::ARGUMENT_ASK_SUDO_PASSWORD  => self.store_sudo_password(),
                             _ => Ok({ standalone_arg_found = false; next_is_value = true; })
                         };
 
@@ -465,18 +782,42 @@ impl CliParser  {
                                     Arguments::ARGUMENT_INVENTORY_SHORT   => self.append_inventory(&args[arg_count]),
                                     Arguments::ARGUMENT_SUDO              => self.store_sudo(&args[arg_count]),
                                     Arguments::ARGUMENT_TAGS              => self.store_tags(&args[arg_count]),
+                                    Arguments::ARGUMENT_SKIP_TAGS         => self.store_skip_tags(&args[arg_count]),
+                                    Arguments::ARGUMENT_START_AT_TASK     => self.store_start_at_task(&args[arg_count]),
+                                    Arguments::ARGUMENT_LIST_FACTS        => self.store_list_facts(&args[arg_count]),
                                     Arguments::ARGUMENT_USER              => self.store_default_user(&args[arg_count]),
                                     Arguments::ARGUMENT_USER_SHORT        => self.store_default_user(&args[arg_count]),
                                     Arguments::ARGUMENT_SHOW_GROUPS       => self.store_show_groups(&args[arg_count]),
                                     Arguments::ARGUMENT_SHOW_HOSTS        => self.store_show_hosts(&args[arg_count]),
                                     Arguments::ARGUMENT_LIMIT_GROUPS      => self.store_limit_groups(&args[arg_count]),
                                     Arguments::ARGUMENT_LIMIT_HOSTS       => self.store_limit_hosts(&args[arg_count]),
+                                    Arguments::ARGUMENT_LIMIT             => self.store_limit(&args[arg_count]),
                                     Arguments::ARGUMENT_BATCH_SIZE        => self.store_batch_size(&args[arg_count]),
+                                    Arguments::ARGUMENT_ORDER             => self.store_order(&args[arg_count]),
+                                    Arguments::ARGUMENT_PREFLIGHT_MAX_FAIL => self.store_preflight_max_fail(&args[arg_count]),
                                     Arguments::ARGUMENT_THREADS           => self.store_threads(&args[arg_count]),
                                     Arguments::ARGUMENT_THREADS_SHORT     => self.store_threads(&args[arg_count]),
+                                    Arguments::ARGUMENT_FORKS             => self.store_threads(&args[arg_count]),
                                     Arguments::ARGUMENT_PORT              => self.store_port(&args[arg_count]),
                                     Arguments::ARGUMENT_EXTRA_VARS        => self.store_extra_vars(&args[arg_count]),
                                     Arguments::ARGUMENT_EXTRA_VARS_SHORT  => self.store_extra_vars(&args[arg_count]),
+                                    Arguments::ARGUMENT_OUTPUT_FORMAT     => self.store_output_format(&args[arg_count]),
+                                    Arguments::ARGUMENT_SUMMARY_FORMAT    => self.store_summary_format(&args[arg_count]),
+                                    Arguments::ARGUMENT_LOG_FILE          => self.store_log_file(&args[arg_count]),
+                                    Arguments::ARGUMENT_METRICS_OUT       => self.store_metrics_out(&args[arg_count]),
+                                    Arguments::ARGUMENT_WEBHOOK           => self.store_webhook(&args[arg_count]),
+                                    Arguments::ARGUMENT_HOST_LOG_DIR      => self.store_host_log_dir(&args[arg_count]),
+                                    Arguments::ARGUMENT_RETRY_FILE        => self.store_retry_file(&args[arg_count]),
+                                    Arguments::ARGUMENT_PLAN_OUT          => self.store_plan_out(&args[arg_count]),
+                                    Arguments::ARGUMENT_DIFF_OUT          => self.store_diff_out(&args[arg_count]),
+                                    Arguments::ARGUMENT_CHANGED_EXIT_CODE => self.store_changed_exit_code(&args[arg_count]),
+                                    Arguments::ARGUMENT_TIMEOUT           => self.store_timeout(&args[arg_count]),
+                                    Arguments::ARGUMENT_CONNECT_RETRIES   => self.store_connect_retries(&args[arg_count]),
+                                    Arguments::ARGUMENT_INVENTORY_CACHE_TTL => self.store_inventory_cache_ttl(&args[arg_count]),
+                                    Arguments::ARGUMENT_HOST_KEY_CHECKING => self.store_host_key_checking(&args[arg_count]),
+                                    Arguments::ARGUMENT_VAULT_PASSWORD_FILE => self.store_vault_password_file(&args[arg_count]),
+                                    Arguments::ARGUMENT_NEW_VAULT_PASSWORD_FILE => self.store_new_vault_password_file(&args[arg_count]),
+                                    Arguments::ARGUMENT_FILES             => self.append_files(&args[arg_count]),
                                     _  => Err(format!("invalid flag: {}", argument_str)),
                                 };
                             }
@@ -502,6 +843,9 @@ impl CliParser  {
             CLI_MODE_CHECK_LOCAL => { self.threads = 1 },
             CLI_MODE_SYNTAX      => { self.threads = 1 },
             CLI_MODE_SHOW        => { self.threads = 1 },
+            CLI_MODE_ENCRYPT     => { self.threads = 1 },
+            CLI_MODE_DECRYPT     => { self.threads = 1 },
+            CLI_MODE_REKEY       => { self.threads = 1 },
             CLI_MODE_UNSET       => { self.needs_help = true; },
             _ => {}
         }
@@ -628,6 +972,14 @@ impl CliParser  {
         return Ok(());
     }
 
+    fn store_limit(&mut self, value: &String) -> Result<(), String> {
+        match HostPattern::new(value) {
+            Ok(pattern) => { self.limit = Some(pattern); },
+            Err(err_msg) => return Err(format!("{} {}", Arguments::ARGUMENT_LIMIT.as_str(), err_msg)),
+        }
+        return Ok(());
+    }
+
     fn store_tags(&mut self, value: &String) -> Result<(), String> {
         match split_string(value) {
             Ok(values)  =>  { self.tags = Some(values); },
@@ -636,6 +988,30 @@ impl CliParser  {
         return Ok(());
     }
 
+    fn store_skip_tags(&mut self, value: &String) -> Result<(), String> {
+        match split_string(value) {
+            Ok(values)  =>  { self.skip_tags = Some(values); },
+            Err(err_msg) =>  return Err(format!("--{} {}", Arguments::ARGUMENT_SKIP_TAGS.as_str(), err_msg)),
+        }
+        return Ok(());
+    }
+
+    fn store_start_at_task(&mut self, value: &String) -> Result<(), String> {
+        if value.trim().is_empty() {
+            return Err(format!("{}: a task name is required", Arguments::ARGUMENT_START_AT_TASK.as_str()));
+        }
+        self.start_at_task = Some(value.clone());
+        return Ok(());
+    }
+
+    fn store_list_facts(&mut self, value: &String) -> Result<(), String> {
+        if value.trim().is_empty() {
+            return Err(format!("{}: a host name is required", Arguments::ARGUMENT_LIST_FACTS.as_str()));
+        }
+        self.list_facts = Some(value.clone());
+        return Ok(());
+    }
+
     fn store_sudo(&mut self, value: &String) -> Result<(), String> {
         self.sudo = Some(value.clone());
         return Ok(());
@@ -656,11 +1032,94 @@ impl CliParser  {
         }
     }
 
+    fn store_preflight_max_fail(&mut self, value: &String) -> Result<(), String> {
+        match value.parse::<usize>() {
+            Ok(n) if n <= 100 => { self.preflight = true; self.preflight_max_fail = Some(n); return Ok(()); },
+            _ => { return Err(format!("{}: must be a percentage from 0 to 100", Arguments::ARGUMENT_PREFLIGHT_MAX_FAIL.as_str())); }
+        }
+    }
+
+    fn store_order(&mut self, value: &String) -> Result<(), String> {
+        self.order = match value.split_once(":") {
+            Some(("shuffle", seed)) => match seed.parse::<u64>() {
+                Ok(n) => HostOrder::Shuffle(n),
+                Err(_) => { return Err(format!("{}: invalid shuffle seed: {}", Arguments::ARGUMENT_ORDER.as_str(), seed)); }
+            },
+            // without a seed one is picked now, so that every play of the run uses the same order
+            None if value == "shuffle" => HostOrder::Shuffle(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|x| x.as_nanos() as u64).unwrap_or(0)),
+            None if value == "sorted"    => HostOrder::Sorted,
+            None if value == "reverse"   => HostOrder::Reverse,
+            None if value == "inventory" => HostOrder::Inventory,
+            _ => { return Err(format!("{}: invalid value, expecting sorted, reverse, inventory, shuffle or shuffle:N", Arguments::ARGUMENT_ORDER.as_str())); }
+        };
+        return Ok(());
+    }
+
     fn store_threads(&mut self, value: &String) -> Result<(), String> {
         match value.parse::<usize>() {
-            Ok(n) =>  { self.threads = n; return Ok(()); }
-            Err(_e) => { return Err(format!("{}: invalid value", Arguments::ARGUMENT_THREADS.as_str())); }
+            Ok(n) if n > 0 => { self.threads = n; return Ok(()); }
+            _ => { return Err(format!("{}: invalid value", Arguments::ARGUMENT_THREADS.as_str())); }
+        }
+    }
+
+    fn store_changed_exit_code(&mut self, value: &String) -> Result<(), String> {
+        // 0 and 1 already mean success and failure
+        match value.parse::<i32>() {
+            Ok(n) if n > 1 && n < 256 => { self.changed_exit_code = Some(n); return Ok(()); },
+            _ => { return Err(format!("{}: invalid value, expecting a number from 2 to 255", Arguments::ARGUMENT_CHANGED_EXIT_CODE.as_str())); }
+        }
+    }
+
+    fn store_timeout(&mut self, value: &String) -> Result<(), String> {
+        match value.parse::<u64>() {
+            Ok(n) if n > 0 => { self.timeout = Some(n); return Ok(()); },
+            _ => { return Err(format!("{}: invalid value, expecting a number of seconds", Arguments::ARGUMENT_TIMEOUT.as_str())); }
+        }
+    }
+
+    fn store_connect_retries(&mut self, value: &String) -> Result<(), String> {
+        match value.parse::<usize>() {
+            Ok(n) => { self.connect_retries = n; return Ok(()); },
+            _ => { return Err(format!("{}: invalid value, expecting a number", Arguments::ARGUMENT_CONNECT_RETRIES.as_str())); }
+        }
+    }
+
+    fn store_host_key_checking(&mut self, value: &String) -> Result<(), String> {
+        self.host_key_checking = parse_host_key_checking(value).map_err(|e| format!("{}: {}", Arguments::ARGUMENT_HOST_KEY_CHECKING.as_str(), e))?;
+        return Ok(());
+    }
+
+    fn store_inventory_cache_ttl(&mut self, value: &String) -> Result<(), String> {
+        match value.parse::<u64>() {
+            Ok(n) => { self.inventory_cache_ttl = n; return Ok(()); },
+            _ => { return Err(format!("{}: invalid value, expecting a number of seconds", Arguments::ARGUMENT_INVENTORY_CACHE_TTL.as_str())); }
+        }
+    }
+
+    fn store_vault_password_file(&mut self, value: &String) -> Result<(), String> {
+        let password = read_password_file(Path::new(value))?;
+        set_vault_password(&password);
+        return Ok(());
+    }
+
+    fn store_new_vault_password_file(&mut self, value: &String) -> Result<(), String> {
+        self.new_vault_password = Some(read_password_file(Path::new(value))?);
+        return Ok(());
+    }
+
+    fn append_files(&mut self, value: &String) -> Result<(), String> {
+        match parse_paths(&String::from("--files"), value) {
+            Ok(paths)  =>  {
+                for p in paths.iter() {
+                    if ! p.is_file() {
+                        return Err(format!("not a file: {:?}", p));
+                    }
+                    self.vault_files.push(p.clone());
+                }
+            },
+            Err(err_msg) =>  return Err(format!("{} {}", Arguments::ARGUMENT_FILES.as_str(), err_msg)),
         }
+        return Ok(());
     }
 
     fn store_port(&mut self, value: &String) -> Result<(), String> {
@@ -670,12 +1129,94 @@ impl CliParser  {
         }
     }
 
+    fn store_output_format(&mut self, value: &String) -> Result<(), String> {
+        match value.as_str() {
+            "text" => { self.output_format = OutputFormat::Text; return Ok(()); },
+            "json" => { self.output_format = OutputFormat::Json; return Ok(()); },
+            _ => { return Err(format!("{}: invalid value, expecting text or json", Arguments::ARGUMENT_OUTPUT_FORMAT.as_str())); }
+        }
+    }
+
+    fn store_summary_format(&mut self, value: &String) -> Result<(), String> {
+        match value.as_str() {
+            "markdown" => { self.summary_format = SummaryFormat::Markdown; return Ok(()); },
+            "plain"    => { self.summary_format = SummaryFormat::Plain; return Ok(()); },
+            "csv"      => { self.summary_format = SummaryFormat::Csv; return Ok(()); },
+            _ => { return Err(format!("{}: invalid value, expecting markdown, plain or csv", Arguments::ARGUMENT_SUMMARY_FORMAT.as_str())); }
+        }
+    }
+
+    fn store_log_file(&mut self, value: &String) -> Result<(), String> {
+        // unlike $JET_LOG, an explicitly requested logfile that cannot be written is an error
+        match OpenOptions::new().write(true).create(true).append(true).open(value) {
+            Ok(_) => { self.log_file = Some(value.clone()); return Ok(()); },
+            Err(e) => { return Err(format!("{}: cannot open {}: {}", Arguments::ARGUMENT_LOG_FILE.as_str(), value, e)); }
+        }
+    }
+
+    fn store_metrics_out(&mut self, value: &String) -> Result<(), String> {
+        self.metrics_out = Some(value.clone());
+        return Ok(());
+    }
+
+    fn store_webhook(&mut self, value: &String) -> Result<(), String> {
+        if ! (value.starts_with("http://") || value.starts_with("https://")) {
+            return Err(format!("{}: expecting an http:// or https:// URL", Arguments::ARGUMENT_WEBHOOK.as_str()));
+        }
+        self.webhook = Some(value.clone());
+        return Ok(());
+    }
+
+    fn store_host_log_dir(&mut self, value: &String) -> Result<(), String> {
+        match std::fs::create_dir_all(value) {
+            Ok(_) => { self.host_log_dir = Some(value.clone()); return Ok(()); },
+            Err(e) => { return Err(format!("{}: cannot create {}: {}", Arguments::ARGUMENT_HOST_LOG_DIR.as_str(), value, e)); }
+        }
+    }
+
+    fn store_retry_file(&mut self, value: &String) -> Result<(), String> {
+        self.retry_file = Some(value.clone());
+        return Ok(());
+    }
+
+    fn store_plan_out(&mut self, value: &String) -> Result<(), String> {
+        self.plan_out = Some(value.clone());
+        return Ok(());
+    }
+
+    fn store_diff_out(&mut self, value: &String) -> Result<(), String> {
+        // the report is only useful with the diffs in it, so this implies --diff
+        self.diff_out = Some(value.clone());
+        self.show_diff = true;
+        return Ok(());
+    }
+
     fn store_allow_localhost_delegation(&mut self) -> Result<(), String> {
         self.allow_localhost_delegation = true;
         Ok(())
     }
 
-    fn increase_verbosity(&mut self, amount: u32) -> Result<(), String> {
+    fn store_show_timing(&mut self) -> Result<(), String> {
+        self.show_timing = true;
+        return Ok(());
+    }
+
+    fn store_progress(&mut self) -> Result<(), String> {
+        self.progress = true;
+        return Ok(());
+    }
+
+    fn store_show_diff(&mut self) -> Result<(), String> {
+        self.show_diff = true;
+        return Ok(());
+    }
+
+    fn store_no_color(&mut self) -> Result<(), String> {
+        self.no_color = true;
+        return Ok(());
+    }
+
+    fn increase_verbosity(&mut self, amount: i32) -> Result<(), String> {
         self.verbosity = self.verbosity + amount;
         return Ok(())
     }
@@ -762,13 +1303,25 @@ impl CliParser  {
             if ! path.is_file() {
                 return Err(format!("--extra-vars parameter with @ expects a file: {}", rest_of_path))
             }
-            let extra_file = jet_file_open(path)?;
-            let parsed: Result<serde_yaml::Mapping, serde_yaml::Error> = serde_yaml::from_reader(extra_file);
-            if parsed.is_err() {
-                show_yaml_error_in_context(&parsed.unwrap_err(), &path);
-                return Err(format!("edit the file and try again?"));
-            }   
-            blend_variables(&mut self.extra_vars, serde_yaml::Value::Mapping(parsed.unwrap()));
+            let parsed = load_vars_file(path)?;
+            blend_variables(&mut self.extra_vars, serde_yaml::Value::Mapping(parsed));
+
+        } else if ! value.trim_start().starts_with("{") && value.contains("=") {
+            // input is one or more key=value pairs, values are always strings and may be quoted to hold spaces
+
+            let pairs = match split_tokens(value) {
+                Ok(x) => x,
+                Err(y) => { return Err(format!("--extra-vars: {}: {}", y, value)) }
+            };
+            let mut serde_map = serde_yaml::Mapping::new();
+            for pair in pairs.iter() {
+                let (k, v) = match pair.split_once("=") {
+                    Some((k, v)) if ! k.is_empty() => (k, v),
+                    _ => { return Err(format!("--extra-vars expects key=value pairs, got: {}", pair)) }
+                };
+                serde_map.insert(serde_yaml::Value::String(k.to_string()), serde_yaml::Value::String(v.to_string()));
+            }
+            blend_variables(&mut self.extra_vars, serde_yaml::Value::Mapping(serde_map));
 
         } else {
             // input is inline JSON (as YAML wouldn't make sense with the newlines)
@@ -787,6 +1340,56 @@ impl CliParser  {
 
      }
 
+     fn store_step(&mut self) -> Result<(), String>{
+        self.step = true;
+        return Ok(());
+    }
+
+     fn store_auto_continue(&mut self) -> Result<(), String>{
+        self.auto_continue = true;
+        return Ok(());
+     }
+
+     fn store_list_hosts(&mut self) -> Result<(), String>{
+        self.list_hosts = true;
+        return Ok(());
+     }
+
+     fn store_list_tasks(&mut self) -> Result<(), String>{
+        self.list_tasks = true;
+        return Ok(());
+     }
+
+     fn store_syntax_check(&mut self) -> Result<(), String>{
+        self.syntax_check = true;
+        return Ok(());
+     }
+
+     fn store_force_handlers(&mut self) -> Result<(), String>{
+        self.force_handlers = true;
+        return Ok(());
+     }
+
+     fn store_flush_cache(&mut self) -> Result<(), String>{
+        self.flush_cache = true;
+        return Ok(());
+     }
+
+     fn store_no_facts(&mut self) -> Result<(), String>{
+        self.no_facts = true;
+        return Ok(());
+     }
+
+     fn store_preflight(&mut self) -> Result<(), String>{
+        self.preflight = true;
+        return Ok(());
+     }
+
+     fn store_no_connection_reuse(&mut self) -> Result<(), String>{
+        self.no_connection_reuse = true;
+        return Ok(());
+     }
+
      fn store_forward_agent(&mut self) -> Result<(), String>{
         self.forward_agent = true;
         return Ok(());
@@ -802,6 +1405,16 @@ impl CliParser  {
         return Ok(());
      }
 
+     fn store_sudo_password(&mut self) -> Result<(), String>{
+        let mut value = String::new();
+        println!("enter sudo password:");
+        match io::stdin().read_line(&mut value) {
+            Ok(_) => { self.sudo_password = Some(String::from(value.trim())); }
+            Err(e) =>  return Err(format!("failure reading input: {}", e))
+        }
+        return Ok(());
+     }
+
 }
 
 fn split_string(value: &String) -> Result<Vec<String>, String> {
@@ -823,3 +1436,37 @@ fn parse_paths(from: &String, value: &String) -> Result<Vec<PathBuf>, String> {
     }
     return Ok(results);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extra_var(parser: &CliParser, key: &str) -> Option<String> {
+        return parser.extra_vars.get(key).and_then(|x| x.as_str()).map(|x| x.to_string());
+    }
+
+    #[test]
+    fn test_extra_vars_pairs() {
+        let mut parser = CliParser::new();
+        parser.store_extra_vars(&String::from("msg='hello world' greeting=\"it's fine\" empty= n=1")).unwrap();
+        assert_eq!(extra_var(&parser, "msg"), Some(String::from("hello world")));
+        assert_eq!(extra_var(&parser, "greeting"), Some(String::from("it's fine")));
+        assert_eq!(extra_var(&parser, "empty"), Some(String::from("")));
+        assert_eq!(extra_var(&parser, "n"), Some(String::from("1")));
+    }
+
+    #[test]
+    fn test_extra_vars_bad_pairs() {
+        let mut parser = CliParser::new();
+        assert!(parser.store_extra_vars(&String::from("msg=hello world")).is_err());
+        assert!(parser.store_extra_vars(&String::from("msg='hello")).is_err());
+        assert!(parser.store_extra_vars(&String::from("=x")).is_err());
+    }
+
+    #[test]
+    fn test_extra_vars_json() {
+        let mut parser = CliParser::new();
+        parser.store_extra_vars(&String::from("{\"msg\": \"a=b c\"}")).unwrap();
+        assert_eq!(extra_var(&parser, "msg"), Some(String::from("a=b c")));
+    }
+}
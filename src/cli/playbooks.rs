@@ -19,10 +19,12 @@ use crate::cli::parser::CliParser;
 use crate::connection::ssh::SshFactory;
 use crate::connection::local::LocalFactory;
 use crate::connection::no::NoFactory;
-use crate::playbooks::traversal::{playbook_traversal,RunState};
+use crate::playbooks::traversal::{playbook_traversal,connection_traversal,facts_traversal,RunState};
 use crate::playbooks::context::PlaybookContext;
 use crate::playbooks::visitor::{PlaybookVisitor,CheckMode};
+use crate::playbooks::webhook::WebhookCallback;
 use crate::inventory::inventory::Inventory;
+use crate::util::interrupt::{install_interrupt_handler,is_interrupted,INTERRUPTED_EXIT_CODE};
 use std::sync::{Arc,RwLock};
 
 // code behind *most* playbook related CLI commands, launched from main.rs
@@ -53,8 +55,58 @@ pub fn playbook_simulate(inventory: &Arc<RwLock<Inventory>>, parser: &CliParser)
     return playbook(inventory, parser, CheckMode::No, ConnectionMode::Simulate);
 }
 
+// jetp check-connections only connects to hosts, no playbook is involved
+
+pub fn check_connections(inventory: &Arc<RwLock<Inventory>>, parser: &CliParser) -> i32 {
+    let run_state = get_run_state(inventory, parser, CheckMode::Yes, ConnectionMode::Ssh);
+    return match connection_traversal(&run_state) {
+        Ok(_)  => run_state.visitor.read().unwrap().get_exit_status(&run_state.context),
+        Err(s) => { println!("{}", s); 1 }
+    };
+}
+
+// --list-facts gathers from one host and prints what templates would see, no playbook is involved
+
+pub fn list_facts_ssh(inventory: &Arc<RwLock<Inventory>>, parser: &CliParser) -> i32 {
+    return list_facts(inventory, parser, ConnectionMode::Ssh);
+}
+
+pub fn list_facts_local(inventory: &Arc<RwLock<Inventory>>, parser: &CliParser) -> i32 {
+    return list_facts(inventory, parser, ConnectionMode::Local);
+}
+
+fn list_facts(inventory: &Arc<RwLock<Inventory>>, parser: &CliParser, connection_mode: ConnectionMode) -> i32 {
+    let run_state = get_run_state(inventory, parser, CheckMode::Yes, connection_mode);
+    return match facts_traversal(&run_state, parser.list_facts.as_ref().unwrap()) {
+        Ok(facts) => { println!("{}", facts); 0 },
+        Err(s) => { println!("{}", s); 1 }
+    };
+}
+
 fn playbook(inventory: &Arc<RwLock<Inventory>>, parser: &CliParser, check_mode: CheckMode, connection_mode: ConnectionMode) -> i32 {
-    let run_state = Arc::new(RunState {
+    let check = check_mode == CheckMode::Yes;
+    let run_state = get_run_state(inventory, parser, check_mode, connection_mode);
+    if let Some(url) = &parser.webhook {
+        run_state.add_callback(Arc::new(WebhookCallback::new(url, parser, check)));
+    }
+    if let Err(s) = install_interrupt_handler() {
+        println!("{}", s);
+        return 1;
+    }
+    return match playbook_traversal(&run_state) {
+        Ok(_)  => run_state.visitor.read().unwrap().get_exit_status(&run_state.context),
+        Err(s) => {
+            println!("{}", s);
+            match is_interrupted() {
+                true => INTERRUPTED_EXIT_CODE,
+                false => 1
+            }
+        }
+    };
+}
+
+fn get_run_state(inventory: &Arc<RwLock<Inventory>>, parser: &CliParser, check_mode: CheckMode, connection_mode: ConnectionMode) -> Arc<RunState> {
+    return Arc::new(RunState {
         // every object gets an inventory, though with local modes it's empty.
         inventory: Arc::clone(inventory),
         playbook_paths: Arc::clone(&parser.playbook_paths),
@@ -62,23 +114,33 @@ fn playbook(inventory: &Arc<RwLock<Inventory>>, parser: &CliParser, check_mode:
         module_paths: Arc::clone(&parser.module_paths),
         limit_hosts: parser.limit_hosts.clone(),
         limit_groups: parser.limit_groups.clone(),
+        limit: parser.limit.clone(),
         batch_size: parser.batch_size.clone(),
+        order: parser.order,
+        preflight: parser.preflight,
+        preflight_max_fail: parser.preflight_max_fail,
         // the context is constructed with an instance of the parser instead of having a back-reference
         // to run-state.  Context should mostly *not* get parameters from the parser unless they
         // are going to appear in variables.
         context: Arc::new(RwLock::new(PlaybookContext::new(parser))),
-        visitor: Arc::new(RwLock::new(PlaybookVisitor::new(check_mode))),
+        visitor: Arc::new(RwLock::new(PlaybookVisitor::new(check_mode, parser))),
         connection_factory: match connection_mode {
-            ConnectionMode::Ssh => Arc::new(RwLock::new(SshFactory::new(inventory, parser.forward_agent, parser.login_password.clone()))),
+            ConnectionMode::Ssh => Arc::new(RwLock::new(SshFactory::new(inventory, parser.forward_agent, parser.login_password.clone(), parser.timeout, parser.connect_retries, ! parser.no_connection_reuse))),
             ConnectionMode::Local => Arc::new(RwLock::new(LocalFactory::new(inventory))),
             ConnectionMode::Simulate => Arc::new(RwLock::new(NoFactory::new()))
         },
         tags: parser.tags.clone(),
-        allow_localhost_delegation: parser.allow_localhost_delegation
+        skip_tags: parser.skip_tags.clone(),
+        allow_localhost_delegation: parser.allow_localhost_delegation,
+        show_diff: parser.show_diff,
+        timeout: parser.timeout,
+        // facts cannot be gathered without a connection, so simulate mode never tries
+        gather_facts: ! parser.no_facts && ! matches!(connection_mode, ConnectionMode::Simulate),
+        list_hosts: parser.list_hosts,
+        list_tasks: parser.list_tasks,
+        syntax_check: parser.syntax_check,
+        force_handlers: parser.force_handlers,
+        plan_out: parser.plan_out.clone()
     });
-    return match playbook_traversal(&run_state) {
-        Ok(_)  => run_state.visitor.read().unwrap().get_exit_status(&run_state.context),
-        Err(s) => { println!("{}", s); 1 }
-    };
 }
 
@@ -0,0 +1,76 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::cli::parser::{CliParser,CLI_MODE_ENCRYPT,CLI_MODE_DECRYPT,CLI_MODE_REKEY};
+use crate::util::io::read_local_file;
+use crate::util::vault::{encrypt,decrypt,is_encrypted,get_vault_password,ask_password};
+use std::path::Path;
+
+// cli support for the encrypt, decrypt and rekey subcommands, which rewrite vault files in place
+
+// ==============================================================================================================
+// PUBLIC API
+// ==============================================================================================================
+
+// jetp encrypt --files secrets.yml [--vault-password-file path]
+// jetp decrypt --files secrets.yml [--vault-password-file path]
+// jetp rekey --files secrets.yml [--vault-password-file path] [--new-vault-password-file path]
+
+pub fn handle_vault(parser: &CliParser) -> Result<(), String> {
+    if parser.vault_files.is_empty() {
+        return Err(String::from("--files is required"));
+    }
+    let password = get_vault_password()?;
+    let new_password = match parser.mode {
+        CLI_MODE_REKEY => match &parser.new_vault_password {
+            Some(x) => x.clone(),
+            None => ask_password("enter new vault password:")?
+        },
+        _ => password.clone()
+    };
+
+    for path in parser.vault_files.iter() {
+        let contents = read_local_file(path)?;
+        let updated = match parser.mode {
+            CLI_MODE_ENCRYPT => {
+                if is_encrypted(&contents) {
+                    return Err(format!("already encrypted: {}", path.display()));
+                }
+                // vault files are loaded as variables, so refuse to encrypt something that would not load
+                if serde_yaml::from_str::<serde_yaml::Mapping>(&contents).is_err() {
+                    return Err(format!("not a YAML mapping of variables: {}", path.display()));
+                }
+                encrypt(&contents, &password)?
+            },
+            CLI_MODE_DECRYPT => decrypt_file(path, &contents, &password)?,
+            CLI_MODE_REKEY   => encrypt(&decrypt_file(path, &contents, &password)?, &new_password)?,
+            _ => { return Err(String::from("invalid CLI mode")); }
+        };
+        match std::fs::write(path, updated) {
+            Ok(_) => {},
+            Err(e) => { return Err(format!("unable to write file: {}, {:?}", path.display(), e)); }
+        }
+        println!("updated: {}", path.display());
+    }
+    return Ok(());
+}
+
+fn decrypt_file(path: &Path, contents: &String, password: &String) -> Result<String, String> {
+    if ! is_encrypted(contents) {
+        return Err(format!("not a vault file: {}", path.display()));
+    }
+    return decrypt(contents, password).map_err(|e| format!("{}: {}", path.display(), e));
+}
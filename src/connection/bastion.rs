@@ -0,0 +1,175 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::connection::ssh::{SshAuth,HostKeyChecking,open_tcp_stream,start_session,verify_host_key,authenticate};
+use ssh2::{Session,Channel};
+use std::io::{Read,Write};
+use std::net::TcpStream;
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use std::io;
+
+// hosts that are only reachable through one or more bastions (jump hosts) are connected to like OpenSSH's
+// ProxyJump: we log into the first bastion, ask it to open a TCP connection to the next one, log into that one
+// over the forwarded connection, and so on until the last bastion opens a connection to the target. Each
+// forwarded connection is handed to the next SSH session as one end of a socket pair, with a thread copying
+// bytes between the other end and the SSH channel.  The threads, and with them the bastion sessions, go
+// away when the target session is dropped and its end of the socket pair closes.
+
+pub struct SshBastion {
+    pub hostname: String,
+    pub port: i64,
+    pub username: String,
+    pub auth: SshAuth,
+    pub host_key_checking: HostKeyChecking,
+    pub login_password: Option<String>,
+    pub key: Option<String>,
+    pub passphrase: Option<String>,
+    pub key_comment: Option<String>,
+}
+
+pub fn get_bastion_label(bastion: &SshBastion) -> String {
+    return format!("{}@{}:{}", bastion.username, bastion.hostname, bastion.port);
+}
+
+// the socket an SSH session runs over, kept so it can be switched to non-blocking once a thread starts forwarding
+
+enum Transport {
+    Tcp(TcpStream),
+    Unix(UnixStream)
+}
+
+impl Transport {
+    fn set_nonblocking(&self) -> io::Result<()> {
+        return match self {
+            Transport::Tcp(x) => x.set_nonblocking(true),
+            Transport::Unix(x) => x.set_nonblocking(true)
+        };
+    }
+}
+
+// returns a stream connected to hostname:port through the given chain of bastions, errors say which hop failed
+// so that an unreachable bastion can be told apart from a target the bastion cannot reach
+
+pub fn open_bastion_tunnel(bastions: &Vec<SshBastion>, hostname: &String, port: i64, timeout: Option<u64>) -> Result<UnixStream, String> {
+    assert!(! bastions.is_empty());
+    let mut previous : Option<(Session, Transport, String)> = None;
+
+    for bastion in bastions.iter() {
+        let label = get_bastion_label(bastion);
+        let (session, transport) = match previous.take() {
+            None => {
+                let tcp = open_tcp_stream(&bastion.hostname, bastion.port, timeout).map_err(
+                    |e| format!("SSH bastion {} unreachable: {}", label, e)
+                )?;
+                let transport = Transport::Tcp(tcp.try_clone().map_err(|e| format!("SSH bastion {}: {}", label, e))?);
+                let session = start_session(tcp, timeout).map_err(|e| format!("SSH bastion {} unreachable: {}", label, e))?;
+                (session, transport)
+            },
+            Some((prev_session, prev_transport, prev_label)) => {
+                let stream = forward(prev_session, prev_transport, &prev_label, &bastion.hostname, bastion.port)?;
+                let transport = Transport::Unix(stream.try_clone().map_err(|e| format!("SSH bastion {}: {}", label, e))?);
+                let session = start_session(stream, timeout).map_err(
+                    |e| format!("SSH bastion {} unreachable via {}: {}", label, prev_label, e)
+                )?;
+                (session, transport)
+            }
+        };
+        verify_host_key(&session, &bastion.hostname, bastion.port, bastion.host_key_checking).map_err(
+            |e| format!("SSH bastion {}: {}", label, e)
+        )?;
+        authenticate(&session, &bastion.username, bastion.auth, &bastion.login_password, &bastion.key, &bastion.passphrase, &bastion.key_comment).map_err(
+            |e| format!("SSH bastion {} refused login: {}", label, e)
+        )?;
+        previous = Some((session, transport, label));
+    }
+
+    let (session, transport, label) = previous.unwrap();
+    return forward(session, transport, &label, hostname, port);
+}
+
+// asks a logged in bastion to connect to hostname:port and returns our end of the forwarded connection
+
+fn forward(session: Session, transport: Transport, label: &String, hostname: &String, port: i64) -> Result<UnixStream, String> {
+    let channel = match session.channel_direct_tcpip(hostname, port as u16, None) {
+        Ok(x) => x,
+        Err(e) => { return Err(format!("SSH bastion {} cannot reach target {}:{}: {}", label, hostname, port, e)); }
+    };
+    let (ours, theirs) = match UnixStream::pair() {
+        Ok(x) => x,
+        Err(e) => { return Err(format!("unable to create socket pair for SSH bastion {}: {}", label, e)); }
+    };
+    // from here on nothing may block, as the one thread has to move data in both directions
+    session.set_blocking(false);
+    if transport.set_nonblocking().is_err() || theirs.set_nonblocking(true).is_err() {
+        return Err(format!("unable to configure forwarding through SSH bastion {}", label));
+    }
+    thread::spawn(move || {
+        pump(session, channel, theirs, transport);
+    });
+    return Ok(ours);
+}
+
+// copies bytes between the socket pair and the bastion channel until either side closes.  The session and
+// transport are only held so the connection to the bastion stays open as long as the thread runs.
+
+fn pump(_session: Session, mut channel: Channel, mut socket: UnixStream, _transport: Transport) {
+    let mut buffer = [0u8; 32768];
+    let mut upstream : Vec<u8> = Vec::new();
+    let mut downstream : Vec<u8> = Vec::new();
+    loop {
+        let mut progress = false;
+
+        if upstream.is_empty() {
+            match socket.read(&mut buffer) {
+                Ok(0) => { break; },
+                Ok(n) => { upstream.extend_from_slice(&buffer[..n]); progress = true; },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {},
+                Err(_) => { break; }
+            }
+        }
+        if ! upstream.is_empty() {
+            match channel.write(&upstream) {
+                Ok(n) => { upstream.drain(..n); progress = true; },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {},
+                Err(_) => { break; }
+            }
+        }
+
+        if downstream.is_empty() {
+            match channel.read(&mut buffer) {
+                Ok(0) => { if channel.eof() { break; } },
+                Ok(n) => { downstream.extend_from_slice(&buffer[..n]); progress = true; },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {},
+                Err(_) => { break; }
+            }
+        }
+        if ! downstream.is_empty() {
+            match socket.write(&downstream) {
+                Ok(n) => { downstream.drain(..n); progress = true; },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {},
+                Err(_) => { break; }
+            }
+        }
+
+        if ! progress {
+            thread::sleep(Duration::from_millis(2));
+        }
+    }
+    let _ = channel.close();
+}
@@ -21,8 +21,12 @@ use std::sync::Mutex;
 use std::sync::RwLock;
 use std::collections::HashMap;
 
+// connections are stored by host name along with a key describing how the connection was made
+// (user, address, port).  A later play that connects differently, for instance with a different
+// ssh_user, will not be handed a connection made for the previous one.
+
 pub struct ConnectionCache {
-    connections: HashMap<String, Arc<Mutex<dyn Connection>>>
+    connections: HashMap<String, (String, Arc<Mutex<dyn Connection>>)>
 }
 
 impl ConnectionCache {
@@ -32,19 +36,22 @@ impl ConnectionCache {
         }
     }
 
-    pub fn add_connection(&mut self, host:&Arc<RwLock<Host>>, connection: &Arc<Mutex<dyn Connection>>) {
+    pub fn add_connection(&mut self, host:&Arc<RwLock<Host>>, key: &String, connection: &Arc<Mutex<dyn Connection>>) {
         let host2 = host.read().expect("host read");
-        self.connections.insert(host2.name.clone(), Arc::clone(connection));
+        self.connections.insert(host2.name.clone(), (key.clone(), Arc::clone(connection)));
     }
 
-    pub fn has_connection(&self, host: &Arc<RwLock<Host>>) -> bool {
+    pub fn has_connection(&self, host: &Arc<RwLock<Host>>, key: &String) -> bool {
         let host2 = host.read().expect("host read");
-        return self.connections.contains_key(&host2.name.clone());
+        return match self.connections.get(&host2.name.clone()) {
+            Some((k, _)) => k.eq(key),
+            None => false
+        };
     }
 
     pub fn get_connection(&self, host: &Arc<RwLock<Host>>) -> Arc<Mutex<dyn Connection>> {
         let host2 = host.read().expect("host read");
-        return Arc::clone(self.connections.get(&host2.name.clone()).unwrap());
+        return Arc::clone(&self.connections.get(&host2.name.clone()).unwrap().1);
     }
 
     pub fn clear(&mut self) {
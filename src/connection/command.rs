@@ -24,6 +24,7 @@ use crate::tasks::response::TaskResponse;
 pub struct CommandResult {
     pub cmd: String,
     pub out: String,
+    pub err: String,
     pub rc: i32
 }
 
@@ -37,4 +38,11 @@ pub fn cmd_info(info: &Arc<TaskResponse>) -> (i32, String) {
     assert!(info.command_result.is_some(), "called cmd_info on a response that is not a command result");
     let result = info.command_result.as_ref().as_ref().unwrap();
     return (result.rc, result.out.clone());
+}
+
+pub fn cmd_info_with_err(info: &Arc<TaskResponse>) -> (i32, String, String) {
+    // 'out' only holds standard output, use this when the standard error is also of interest
+    assert!(info.command_result.is_some(), "called cmd_info_with_err on a response that is not a command result");
+    let result = info.command_result.as_ref().as_ref().unwrap();
+    return (result.rc, result.out.clone(), result.err.clone());
 }
\ No newline at end of file
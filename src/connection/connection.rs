@@ -22,7 +22,7 @@ use std::marker::{Send,Sync};
 use std::path::Path;
 use crate::connection::command::Forward;
 
-// the connection trait that serves as the base for SshConnection, LocalConnection, and NoConnection
+// the connection trait that serves as the base for SshConnection, LocalConnection, ContainerConnection, WinRmConnection, and NoConnection
 
 pub trait Connection : Send + Sync {
 
@@ -34,8 +34,14 @@ pub trait Connection : Send + Sync {
 
     fn copy_file(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, src: &Path, dest: &String) -> Result<(), Arc<TaskResponse>>;
 
+    // the reverse of copy_file, downloads a remote file to the control machine
+
+    fn fetch_file(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, src: &String, dest: &Path) -> Result<(), Arc<TaskResponse>>;
+
     fn whoami(&self) -> Result<String,String>;
 
-    fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, forward: Forward) -> Result<Arc<TaskResponse>,Arc<TaskResponse>>;
+    // input, if provided, is written to the standard input of the command, which is how sudo passwords are supplied
+
+    fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, forward: Forward, input: &Option<String>) -> Result<Arc<TaskResponse>,Arc<TaskResponse>>;
 
 }
\ No newline at end of file
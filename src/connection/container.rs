@@ -0,0 +1,156 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::connection::connection::Connection;
+use crate::connection::command::{CommandResult,Forward};
+use crate::connection::local::{convert_bytes,convert_out,run_with_input};
+use crate::inventory::hosts::Host;
+use crate::handle::response::Response;
+use crate::tasks::{TaskRequest,TaskResponse};
+use std::sync::{Arc,RwLock};
+use std::process::Command;
+use std::path::Path;
+use std::io::Write;
+use guid_create::GUID;
+
+// connections to docker or podman containers running on this machine. Commands go through
+// '<runtime> exec' and files through '<runtime> cp', so no sshd is needed in the container.
+// the runtime is the connection type, and the container is the jet_container variable or
+// else the inventory name of the host.
+
+pub struct ContainerConnection {
+    host: Arc<RwLock<Host>>,
+    runtime: String,
+    container: String,
+}
+
+impl ContainerConnection {
+
+    pub fn new(host: &Arc<RwLock<Host>>, runtime: &String, container: &String) -> Self {
+        Self { host: Arc::clone(&host), runtime: runtime.clone(), container: container.clone() }
+    }
+
+    fn exec(&self, cmd: &String, input: &Option<String>, timeout: Option<u64>) -> std::io::Result<std::process::Output> {
+        let mut base = Command::new(&self.runtime);
+        base.arg("exec");
+        if input.is_some() {
+            base.arg("-i");
+        }
+        let command = base.arg(&self.container).arg("sh").arg("-c").arg(format!("LANG=C {}", cmd));
+        return run_with_input(command, input, timeout);
+    }
+
+    fn cp(&self, src: &String, dest: &String) -> Result<(), String> {
+        return match Command::new(&self.runtime).arg("cp").arg(src).arg(dest).output() {
+            Ok(x) => match x.status.success() {
+                true => Ok(()),
+                false => Err(convert_out(&x.stdout, &x.stderr))
+            },
+            Err(y) => Err(format!("{}: {}", self.runtime, y))
+        };
+    }
+
+    fn trim_newlines(&self, s: &mut String) {
+        if s.ends_with('\n') {
+            s.pop();
+            if s.ends_with('\r') {
+                s.pop();
+            }
+        }
+    }
+}
+
+impl Connection for ContainerConnection {
+
+    fn whoami(&self) -> Result<String,String> {
+        return match self.exec(&String::from("whoami"), &None, None) {
+            Ok(x) if x.status.success() => Ok(convert_bytes(&x.stdout).trim().to_string()),
+            Ok(x) => Err(format!("whoami failed in container {}: {}", self.container, convert_out(&x.stdout, &x.stderr))),
+            Err(y) => Err(format!("{}: {}", self.runtime, y))
+        };
+    }
+
+    fn connect(&mut self) -> Result<(),String> {
+        // as with SSH, the OS type is detected right away so the command library knows what it is talking to
+        return match self.exec(&String::from("uname -a"), &None, None) {
+            Ok(x) if x.status.success() => {
+                match self.host.write().unwrap().set_os_info(&convert_bytes(&x.stdout).trim().to_string()) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(String::from("failed to set OS info"))
+                }
+            },
+            Ok(x) => Err(format!("unable to reach container {} with {}: {}", self.container, self.runtime, convert_out(&x.stdout, &x.stderr))),
+            Err(y) => Err(format!("{}: {}", self.runtime, y))
+        };
+    }
+
+    fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, _forward: Forward, input: &Option<String>) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+        match self.exec(cmd, input, request.timeout) {
+            Ok(x) => {
+                match x.status.code() {
+                    Some(rc) => {
+                        let mut out = convert_bytes(&x.stdout);
+                        let mut err = convert_bytes(&x.stderr);
+                        self.trim_newlines(&mut out);
+                        self.trim_newlines(&mut err);
+                        return Ok(response.command_ok(request,&Arc::new(Some(CommandResult { cmd: cmd.clone(), out: out, err: err, rc: rc }))));
+                    },
+                    None => {
+                        return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), err: String::from(""), rc: 418 }))));
+                    }
+                }
+            },
+            Err(x) if x.kind() == std::io::ErrorKind::TimedOut => {
+                return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), err: x.to_string(), rc: 124 }))));
+            },
+            Err(x) => {
+                return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), err: x.to_string(), rc: 404 }))));
+            }
+        };
+    }
+
+    fn copy_file(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, src: &Path, remote_path: &String) -> Result<(), Arc<TaskResponse>> {
+        return match self.cp(&src.display().to_string(), &format!("{}:{}", self.container, remote_path)) {
+            Ok(_) => Ok(()),
+            Err(y) => Err(response.is_failed(&request, &format!("copy failed: {}", y)))
+        };
+    }
+
+    fn fetch_file(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, remote_path: &String, dest: &Path) -> Result<(), Arc<TaskResponse>> {
+        return match self.cp(&format!("{}:{}", self.container, remote_path), &dest.display().to_string()) {
+            Ok(_) => Ok(()),
+            Err(y) => Err(response.is_failed(&request, &format!("fetch failed: {}", y)))
+        };
+    }
+
+    fn write_data(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, data: &String, remote_path: &String) -> Result<(),Arc<TaskResponse>> {
+        // the data is staged in a local temporary file and copied in, which also takes care of binary safe transfer
+        let staged = std::env::temp_dir().join(format!("jet-{}", GUID::rand().to_string()));
+        let write_result = match std::fs::File::create(&staged) {
+            Ok(mut file) => write!(file, "{}", data),
+            Err(y) => { return Err(response.is_failed(&request, &format!("failed to create: {}: {:?}", staged.display(), y))); }
+        };
+        if let Err(y) = write_result {
+            let _ = std::fs::remove_file(&staged);
+            return Err(response.is_failed(&request, &format!("failed to write: {}: {:?}", staged.display(), y)));
+        }
+        let result = self.copy_file(response, request, &staged, remote_path);
+        let _ = std::fs::remove_file(&staged);
+        return result;
+    }
+
+}
@@ -27,12 +27,13 @@ use crate::tasks::{TaskRequest,TaskResponse};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::RwLock;
-use std::process::Command;
+use std::process::{Command,Stdio};
 use crate::Inventory;
-use crate::util::io::jet_file_open;
 use std::fs::File;
 use std::path::Path;
-use std::io::Write;
+use std::io::{Read,Write};
+use std::thread;
+use std::time::{Duration,Instant};
 use std::env;
 
 // implementation for both the local connection factory and local connections
@@ -112,25 +113,30 @@ impl Connection for LocalConnection {
         }
     }
 
-    fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, _forward: Forward) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+    fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, _forward: Forward, input: &Option<String>) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
         let mut base = Command::new("sh");
         let cmd2 = format!("LANG=C {}", cmd);
-        let command = base.arg("-c").arg(cmd2).arg("2>&1");
-        match command.output() {
+        let command = base.arg("-c").arg(cmd2);
+        match run_with_input(command, input, request.timeout) {
             Ok(x) => {
                 match x.status.code() {
                     Some(rc) => {
-                        let mut out = convert_out(&x.stdout,&x.stderr);
+                        let mut out = convert_bytes(&x.stdout);
+                        let mut err = convert_bytes(&x.stderr);
                         self.trim_newlines(&mut out);
-                        return Ok(response.command_ok(request,&Arc::new(Some(CommandResult { cmd: cmd.clone(), out: out.clone(), rc: rc }))));
+                        self.trim_newlines(&mut err);
+                        return Ok(response.command_ok(request,&Arc::new(Some(CommandResult { cmd: cmd.clone(), out: out, err: err, rc: rc }))));
                     },
                     None => {
-                        return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), rc: 418 }))));
+                        return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), err: String::from(""), rc: 418 }))));
                     }
                 }
             },
+            Err(x) if x.kind() == std::io::ErrorKind::TimedOut => {
+                return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), err: x.to_string(), rc: 124 }))));
+            },
             Err(_x) => {
-                return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), rc: 404 }))));
+                return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), err: String::from(""), rc: 404 }))));
             }
         };
     }
@@ -146,10 +152,17 @@ impl Connection for LocalConnection {
         }
     }
 
+    fn fetch_file(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, remote_path: &String, dest: &Path) -> Result<(), Arc<TaskResponse>> {
+        return match std::fs::copy(Path::new(remote_path), dest) {
+            Ok(_x) => Ok(()),
+            Err(e) => Err(response.is_failed(&request, &format!("fetch failed: {:?}", e)))
+        };
+    }
+
     fn write_data(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, data: &String, remote_path: &String) -> Result<(),Arc<TaskResponse>> {
         let path = Path::new(&remote_path);
         if path.exists() {
-            let mut file = match jet_file_open(path) {
+            let mut file = match std::fs::OpenOptions::new().write(true).truncate(true).open(path) {
                 Ok(x) => x,
                 Err(y) => return Err(response.is_failed(&request, &format!("failed to open: {}: {:?}", remote_path, y)))
             };
@@ -174,17 +187,75 @@ impl Connection for LocalConnection {
 
 }
 
-pub fn convert_out(output: &Vec<u8>, err: &Vec<u8>) -> String {
+// runs a command, feeding it standard input if provided.  Without input the command inherits
+// our standard input as before, which is what lets a local sudo prompt on the terminal.
+// if a timeout is given the command is killed once it expires and a TimedOut error is returned.
+
+pub fn run_with_input(command: &mut Command, input: &Option<String>, timeout: Option<u64>) -> std::io::Result<std::process::Output> {
+    if input.is_none() && timeout.is_none() {
+        return command.output();
+    }
+    if input.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    if input.is_some() {
+        let mut stdin = child.stdin.take().expect("child stdin");
+        // a command that exits without reading its input is not an error here, the return code tells the story
+        let _ = writeln!(stdin, "{}", input.as_ref().unwrap());
+    }
+    if timeout.is_none() {
+        return child.wait_with_output();
+    }
+
+    // output is drained on other threads so a chatty command cannot fill the pipe and stall while we wait on it
+    let stdout_reader = drain(child.stdout.take());
+    let stderr_reader = drain(child.stderr.take());
+    let deadline = Instant::now() + Duration::from_secs(timeout.unwrap());
+    let status = loop {
+        match child.try_wait()? {
+            Some(status) => break status,
+            None => {
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    // the readers are abandoned rather than joined, as grandchildren may still hold the pipes open
+                    return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, format!("timed out after {} seconds", timeout.unwrap())));
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+    };
+    return Ok(std::process::Output {
+        status: status,
+        stdout: stdout_reader.join().unwrap_or_default(),
+        stderr: stderr_reader.join().unwrap_or_default()
+    });
+}
+
+fn drain<R: Read + Send + 'static>(source: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    return thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut x) = source {
+            let _ = x.read_to_end(&mut buffer);
+        }
+        return buffer;
+    });
+}
+
+pub fn convert_bytes(output: &Vec<u8>) -> String {
     // output from the Rust command class can contain junk bytes, here we mostly don't try to solve this yet
     // and will basically fail if output contains junk. This may be dealt with later.
-    let mut base = match std::str::from_utf8(output) {
-        Ok(val) => val.to_string(),
-        Err(_) => String::from("invalid UTF-8 characters in response"),
-    };
-    let rest = match std::str::from_utf8(err) {
+    return match std::str::from_utf8(output) {
         Ok(val) => val.to_string(),
         Err(_) => String::from("invalid UTF-8 characters in response"),
     };
+}
+
+pub fn convert_out(output: &Vec<u8>, err: &Vec<u8>) -> String {
+    // merges standard output and standard error, for callers that do not care which is which
+    let mut base = convert_bytes(output);
+    let rest = convert_bytes(err);
     base.push_str("\n");
     base.push_str(&rest);
     return base.trim().to_string();
@@ -17,7 +17,10 @@
 pub mod connection;
 pub mod factory;
 pub mod ssh;
+pub mod bastion;
 pub mod local;
+pub mod container;
+pub mod winrm;
 pub mod no;
 pub mod command;
 pub mod cache;
\ No newline at end of file
@@ -74,9 +74,9 @@ impl Connection for NoConnection {
        return Ok(());
    }
 
-   fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, _forward: Forward) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+   fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, _forward: Forward, _input: &Option<String>) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
        // all commands return junk output pretending they were successful
-       return Ok(response.command_ok(request,&Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from("__simulated__"), rc: 0 }))));
+       return Ok(response.command_ok(request,&Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from("__simulated__"), err: String::from(""), rc: 0 }))));
    }
 
    fn write_data(&self, _response: &Arc<Response>, _request: &Arc<TaskRequest>, _data: &String, _remote_path: &String) -> Result<(),Arc<TaskResponse>>{
@@ -89,4 +89,9 @@ impl Connection for NoConnection {
        return Ok(());
    }
 
+   fn fetch_file(&self, _response: &Arc<Response>, _request: &Arc<TaskRequest>, _src: &String, _dest: &Path) -> Result<(), Arc<TaskResponse>> {
+       // nothing is downloaded either
+       return Ok(());
+   }
+
 }
\ No newline at end of file
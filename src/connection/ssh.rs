@@ -18,22 +18,29 @@ use crate::connection::connection::Connection;
 use crate::connection::command::CommandResult;
 use crate::connection::factory::ConnectionFactory;
 use crate::playbooks::context::PlaybookContext;
-use crate::connection::local::LocalFactory;
+use crate::connection::local::{LocalFactory,LocalConnection};
+use crate::connection::container::ContainerConnection;
+use crate::connection::winrm::WinRmConnection;
+use crate::connection::bastion::{SshBastion,open_bastion_tunnel,get_bastion_label};
 use crate::tasks::*;
 use crate::inventory::hosts::Host;
 use crate::Inventory;
 use crate::handle::response::Response;
 use crate::connection::command::Forward;
-use crate::connection::local::convert_out;
+use crate::connection::local::{convert_bytes,run_with_input};
 use std::process::Command;
 use std::sync::{Arc,Mutex,RwLock};
-use ssh2::Session;
+use ssh2::{Session,CheckResult,KnownHostFileKind};
+use once_cell::sync::Lazy;
+use std::env;
 use std::io::{Read,Write};
 use std::net::TcpStream;
 use std::path::Path;
 use std::time::Duration;
 use std::net::ToSocketAddrs;
-use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::fs::{File,OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
 //use std::io;
 use std::io;
 
@@ -43,17 +50,23 @@ pub struct SshFactory {
     local_factory: LocalFactory,
     localhost: Arc<RwLock<Host>>,
     forward_agent: bool,
-    login_password: Option<String>
+    login_password: Option<String>,
+    timeout: Option<u64>,
+    connect_retries: usize,
+    reuse: bool
 }
 
 impl SshFactory { 
-    pub fn new(inventory: &Arc<RwLock<Inventory>>, forward_agent: bool, login_password: Option<String>) -> Self { 
+    pub fn new(inventory: &Arc<RwLock<Inventory>>, forward_agent: bool, login_password: Option<String>, timeout: Option<u64>, connect_retries: usize, reuse: bool) -> Self { 
         // we create a local connection factory for localhost rather than establishing local connections with SSH
         Self {
             localhost : inventory.read().expect("inventory read").get_host(&String::from("localhost")),
             local_factory: LocalFactory::new(inventory),
             forward_agent,
-            login_password
+            login_password,
+            timeout,
+            connect_retries,
+            reuse
         } 
     }
 }
@@ -74,20 +87,86 @@ impl ConnectionFactory for SshFactory {
             return Ok(conn);
         } 
 
-        {
-            // SSH connections are kept open between tasks generally but cleared at many strategic points during playbook traversal
-            // between plays, in between batches, etc.
-            let cache = ctx.connection_cache.read().unwrap();
-            if cache.has_connection(host) {
-                let conn = cache.get_connection(host);
-                return Ok(conn);
+        // a play or inventory may ask for a host to be managed without SSH, in which case
+        // commands run on this machine but facts and counters are still kept against the host
+        let connection_type = ctx.get_connection_type(host)?;
+        if connection_type.eq("local") {
+            let cache_key = String::from("local");
+            if self.reuse && ctx.connection_cache.read().unwrap().has_connection(host, &cache_key) {
+                return Ok(ctx.connection_cache.read().unwrap().get_connection(host));
+            }
+            let mut conn = LocalConnection::new(host);
+            conn.connect()?;
+            let conn2 : Arc<Mutex<dyn Connection>> = Arc::new(Mutex::new(conn));
+            if self.reuse {
+                ctx.connection_cache.write().expect("connection cache write").add_connection(
+                    &Arc::clone(&host), &cache_key, &Arc::clone(&conn2));
+            }
+            return Ok(conn2);
+        }
+
+        // containers are entered with docker/podman exec, for testing roles without running sshd in them
+        if connection_type.eq("docker") || connection_type.eq("podman") {
+            let container = ctx.get_container_name(host)?;
+            let cache_key = format!("{}:{}", connection_type, container);
+            if self.reuse && ctx.connection_cache.read().unwrap().has_connection(host, &cache_key) {
+                return Ok(ctx.connection_cache.read().unwrap().get_connection(host));
+            }
+            let mut conn = ContainerConnection::new(host, &connection_type, &container);
+            conn.connect()?;
+            let conn2 : Arc<Mutex<dyn Connection>> = Arc::new(Mutex::new(conn));
+            if self.reuse {
+                ctx.connection_cache.write().expect("connection cache write").add_connection(
+                    &Arc::clone(&host), &cache_key, &Arc::clone(&conn2));
             }
+            return Ok(conn2);
+        }
+
+        // Windows hosts do not speak SSH, commands to them are PowerShell sent over WinRM
+        if connection_type.eq("winrm") {
+            let (endpoint, password, validate_certs) = ctx.get_winrm_connection_details(host)?;
+            let (_hostname, user, _port, _key, _passphrase, _comment) = ctx.get_ssh_connection_details(host);
+            let cache_key = format!("{}@{}", user, endpoint);
+            if self.reuse && ctx.connection_cache.read().unwrap().has_connection(host, &cache_key) {
+                return Ok(ctx.connection_cache.read().unwrap().get_connection(host));
+            }
+            let password = match self.login_password.clone().or(password) {
+                Some(x) => x,
+                None => { return Err(String::from("WinRM needs a password, use --ask-login-password or set jet_winrm_password")); }
+            };
+            let mut conn = WinRmConnection::new(host, &endpoint, &user, &password, validate_certs, self.timeout)?;
+            conn.connect()?;
+            let conn2 : Arc<Mutex<dyn Connection>> = Arc::new(Mutex::new(conn));
+            if self.reuse {
+                ctx.connection_cache.write().expect("connection cache write").add_connection(
+                    &Arc::clone(&host), &cache_key, &Arc::clone(&conn2));
+            }
+            return Ok(conn2);
         }
 
         // how we connect to a host depends on some settings of the play (ssh_port, ssh_user), the CLI (--user) and
         // possibly magic variables on the host.  The context contains all of this logic.
         let (hostname2, user, port, key, passphrase, key_comment) = ctx.get_ssh_connection_details(host);
-        if hostname2.eq("localhost") { 
+        let auth = ctx.get_ssh_auth(host)?;
+        let host_key_checking = ctx.get_host_key_checking(host)?;
+        let bastions = ctx.get_ssh_bastions(host, &user, auth, host_key_checking, &key, &passphrase, &key_comment, &self.login_password)?;
+
+        // SSH connections are kept open and reused by every task and play in the playbook that connects the same way,
+        // they are only dropped between batches and when the program exits.  --no-connection-reuse turns this off, which
+        // means a new connection for every task.
+        let mut cache_key = format!("{}@{}:{}", user, hostname2, port);
+        for bastion in bastions.iter() {
+            cache_key.push_str(&format!(" via {}", get_bastion_label(bastion)));
+        }
+        if self.reuse {
+            let cache = ctx.connection_cache.read().unwrap();
+            if cache.has_connection(host, &cache_key) {
+                let conn = cache.get_connection(host);
+                return Ok(conn);
+            }
+        }
+
+        if hostname2.eq("localhost") && bastions.is_empty() { 
             // jet_ssh_hostname was set to localhost, which doesn't make a lot of sense but could happen in testing
             // contrived playbooks when we don't want a lot of real remote hosts
             let conn : Arc<Mutex<dyn Connection>> = self.local_factory.get_connection(context, &self.localhost)?;
@@ -95,12 +174,16 @@ impl ConnectionFactory for SshFactory {
         }
 
         // actually connect here
-        let mut conn = SshConnection::new(Arc::clone(&host), &user, port, hostname2, self.forward_agent, self.login_password.clone(), key, passphrase, key_comment);
+        let mut conn = SshConnection::new(Arc::clone(&host), &user, port, hostname2, self.forward_agent, self.login_password.clone(), key, passphrase, key_comment, self.timeout, auth, host_key_checking, bastions);
+        conn.connect_retries = self.connect_retries;
+        conn.verbosity = ctx.verbosity;
         return match conn.connect() {
             Ok(_)  => { 
                 let conn2 : Arc<Mutex<dyn Connection>> = Arc::new(Mutex::new(conn));
-                ctx.connection_cache.write().expect("connection cache write").add_connection(
-                    &Arc::clone(&host), &Arc::clone(&conn2));
+                if self.reuse {
+                    ctx.connection_cache.write().expect("connection cache write").add_connection(
+                        &Arc::clone(&host), &cache_key, &Arc::clone(&conn2));
+                }
                 Ok(conn2)
             },
             Err(x) => { Err(x) } 
@@ -119,11 +202,51 @@ pub struct SshConnection {
     pub key: Option<String>,
     pub passphrase: Option<String>,
     pub key_comment: Option<String>,
+    pub timeout: Option<u64>,
+    pub auth: SshAuth,
+    pub host_key_checking: HostKeyChecking,
+    pub bastions: Vec<SshBastion>,
+    // --connect-retries, only failures to reach the host are retried, see open_session
+    pub connect_retries: usize,
+    pub verbosity: i32,
 }
 
 impl SshConnection {
-    pub fn new(host: Arc<RwLock<Host>>, username: &String, port: i64, hostname: String, forward_agent: bool, login_password: Option<String>, key: Option<String>, passphrase: Option<String>, key_comment: Option<String>) -> Self {
-        Self { host: Arc::clone(&host), username: username.clone(), port, hostname, session: None, forward_agent, login_password, key, passphrase, key_comment }
+    pub fn new(host: Arc<RwLock<Host>>, username: &String, port: i64, hostname: String, forward_agent: bool, login_password: Option<String>, key: Option<String>, passphrase: Option<String>, key_comment: Option<String>, timeout: Option<u64>, auth: SshAuth, host_key_checking: HostKeyChecking, bastions: Vec<SshBastion>) -> Self {
+        Self { host: Arc::clone(&host), username: username.clone(), port, hostname, session: None, forward_agent, login_password, key, passphrase, key_comment, timeout, auth, host_key_checking, bastions, connect_retries: 0, verbosity: 0 }
+    }
+
+    // opens the TCP connection (or bastion tunnel) and does the SSH handshake.  These are the steps that fail
+    // when the network or sshd is briefly unavailable, so they are tried again with exponential backoff.  Host key
+    // and authentication problems happen later in connect() and will not go away by waiting, so they are not retried.
+
+    fn open_session(&self) -> Result<Session, String> {
+        let host_name = self.host.read().expect("host read").name.clone();
+        let mut attempt : usize = 0;
+        loop {
+            let result = match self.bastions.is_empty() {
+                true => {
+                    open_tcp_stream(&self.hostname, self.port, self.timeout).and_then(|tcp| start_session(tcp, self.timeout))
+                },
+                false => {
+                    let label = get_bastion_label(self.bastions.last().unwrap());
+                    open_bastion_tunnel(&self.bastions, &self.hostname, self.port, self.timeout)
+                        .and_then(|tunnel| start_session(tunnel, self.timeout).map_err(|e| format!("{}:{} via SSH bastion {}: {}", self.hostname, self.port, label, e)))
+                }
+            };
+            match result {
+                Ok(sess) => { return Ok(sess); },
+                Err(e) if attempt < self.connect_retries => {
+                    let delay = 1u64 << attempt.min(5);
+                    attempt += 1;
+                    if self.verbosity > 0 {
+                        println!("… {} => connection attempt {} of {} failed: {}, retrying in {}s", host_name, attempt, self.connect_retries + 1, e, delay);
+                    }
+                    std::thread::sleep(Duration::from_secs(delay));
+                },
+                Err(e) => { return Err(e); }
+            }
+        }
     }
 }
 
@@ -165,112 +288,23 @@ impl Connection for SshConnection {
         // currently we don't do anything with listing the identities in SSH agent.  It might be helpful to provide a nice error
         // if none were detected
 
-        // Connect to the local SSH server - need to get socketaddrs first in order to use Duration for timeout
-        let seconds = Duration::from_secs(10);
         assert!(!self.host.read().expect("host read").name.eq("localhost"));
-        let connect_str = format!("{host}:{port}", host=self.hostname, port=self.port.to_string());
-        // connect with timeout requires SocketAddr objects instead of just connection strings
-        let addrs_iter = connect_str.as_str().to_socket_addrs();
-        
-        // check for errors
-        let mut addrs_iter2 = match addrs_iter { Err(_x) => { return Err(String::from("unable to resolve")); }, Ok(y) => y };
-        let addr = addrs_iter2.next();
-        if ! addr.is_some() { return Err(String::from("unable to resolve(2)"));  }
-        
-        // actually connect (finally) here
-        let tcp = match TcpStream::connect_timeout(&addr.unwrap(), seconds) { Ok(x) => x, _ => { 
-            return Err(format!("SSH connection attempt failed for {}:{}", self.hostname, self.port)); } };
-        
-        // new session & handshake
-        let mut sess = match Session::new() { Ok(x) => x, _ => { return Err(String::from("SSH session failed")); } };
-        sess.set_tcp_stream(tcp);
-        match sess.handshake() { Ok(_) => {}, _ => { return Err(String::from("SSH handshake failed")); } } ;
-        
-        if self.login_password.is_some() {
-            match sess.userauth_password(&self.username.clone(), self.login_password.clone().unwrap().as_str()) {
-                Ok(_) => {},
-                Err(x) => {
-                    return Err(format!("SSH password authentication failed for user {}: {}", self.username, x));
-                }
-            }
-        }
 
-        if self.key.is_some() {
-            // a specific key was specified, 
-            let k2 = self.key.as_ref().unwrap().clone();
-            let keypath = Path::new(&k2);
-            if ! keypath.exists() {
-                return Err(format!("cannot find designed keyfile {}", k2));
-            }
-            match sess.userauth_pubkey_file(&self.username.clone(), None, keypath, self.passphrase.as_deref()) {
-                Ok(_) => {},
-                Err(x) => {
-                    return Err(format!("SSH key authentication failed for user {} with key {:?}: {}", self.username, keypath, x));
-                }
-            };
-        }
-        
-        if self.key.is_none() && self.login_password.is_none() {
-            if self.key_comment.is_some() {
-                // use this specific SSH key
-                let mut agent = sess.agent().unwrap();
-                match agent.connect() {
-                    Ok(_) => {},
-                    Err(x) => {
-                        return Err(format!("SSH cannot connect to agent: {}", x));
-                    }
-                };
-                // list_identities is needed to populate the identities in memory,
-                // see: https://docs.rs/ssh2/latest/ssh2/struct.Agent.html#method.list_identities
-                match agent.list_identities() {
-                    Ok(_) => {},
-                    Err(x) => {
-                        return Err(format!("SSH list_identities returned an error, please check whether agent is running: {}", x));
-                    }
-                };
-                let mut found : bool = false;
-                for ident in agent.identities().unwrap() {
-                    match ident.comment() == self.key_comment.clone().unwrap() {
-                        true => {
-                            match agent.userauth(&self.username, &ident) {
-                                Ok(_) => {
-                                    // use this identity
-                                    found = true;
-                                    break;
-                                },
-                                Err(x) => { 
-                                    return Err(format!("SSH Key authentication failed for user {} with key {}: {}", 
-                                        self.username, self.key_comment.clone().unwrap(), x)); 
-                                }
-                            };
-                        }
-                        false => (),
-                    }
-                }
-                if !found {
-                    return Err(format!("specified SSH key not found with comment {}", self.key_comment.clone().unwrap()));
-                }
-            } else {
-                // no key comment specified, do not use a specific key
-                match sess.userauth_agent(&self.username) { 
-                    Ok(_) => {}, 
-                    Err(x) => { 
-                        return Err(format!("SSH agent authentication failed for user {}: {}", self.username, x));
-                    }
-                };
-            }
-        }
+        // with jet_ssh_bastion set the target is reached through a tunnel opened by the last bastion in the chain,
+        // otherwise we connect to it directly
+        let sess = self.open_session()?;
+
+        verify_host_key(&sess, &self.hostname, self.port, self.host_key_checking)?;
+        authenticate(&sess, &self.username, self.auth, &self.login_password, &self.key, &self.passphrase, &self.key_comment)?;
 
-        if !(sess.authenticated()) { return Err("failed to authenticate".to_string()); };
-      
         // OS detection -- always run uname -a on first connect so we know the OS type, which will allow the command library and facts
         // module to work correctly.
 
         self.session = Some(sess);
 
-        let uname_result = self.run_command_low_level(&String::from("uname -a"));
+        let uname_result = self.run_command_low_level(&String::from("uname -a"), &None, self.timeout);
         match uname_result {
-            Ok((_rc,out)) => {
+            Ok((_rc,out,_err)) => {
                 {
                     match self.host.write().unwrap().set_os_info(&out.clone()) {
                         Ok(_x) => {},
@@ -286,22 +320,22 @@ impl Connection for SshConnection {
         return Ok(());
     }
 
-    fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, forward: Forward) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+    fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, forward: Forward, input: &Option<String>) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
         let result = match forward {   
             Forward::Yes => match self.forward_agent {
-                false => self.run_command_low_level(cmd),
-                true  => self.run_command_with_ssh_a(cmd)
+                false => self.run_command_low_level(cmd, input, request.timeout),
+                true  => self.run_command_with_ssh_a(cmd, input, request.timeout)
             },
-            Forward::No => self.run_command_low_level(cmd)
+            Forward::No => self.run_command_low_level(cmd, input, request.timeout)
         };
 
         match result {
-            Ok((rc,s)) => {
+            Ok((rc,out,err)) => {
                 // note that non-zero return codes are "ok" to the connection plugin, handle elsewhere!
-                return Ok(response.command_ok(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: s.clone(), rc: rc }))));
+                return Ok(response.command_ok(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: out, err: err, rc: rc }))));
             }, 
             Err((rc,s)) => {
-                return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: s.clone(), rc: rc }))));
+                return Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), err: s.clone(), rc: rc }))));
             }
         }
     }
@@ -367,6 +401,35 @@ impl Connection for SshConnection {
 
         return Ok(());
     }
+
+    fn fetch_file(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, remote_path: &String, dest: &Path) -> Result<(), Arc<TaskResponse>> {
+
+        // also streaming, in the other direction
+
+        let session = self.session.as_ref().expect("session not established");
+        let sftp = match session.sftp() {
+            Ok(x) => x,
+            Err(y) => { return Err(response.is_failed(request, &format!("sftp connection failed: {y}"))); }
+        };
+        let fh = match sftp.open(Path::new(&remote_path)) {
+            Ok(x) => x,
+            Err(y) => { return Err(response.is_failed(request, &format!("sftp read failed (1): {y}"))) }
+        };
+        let dest_file = match File::create(dest) {
+            Ok(x) => x,
+            Err(y) => { return Err(response.is_failed(request, &format!("failed to create local file: {y}"))); }
+        };
+
+        let mut fh2 = std::io::BufReader::with_capacity(1000000, fh);
+        let mut dest2 = std::io::BufWriter::with_capacity(1000000, dest_file);
+
+        match io::copy(&mut fh2, &mut dest2) {
+            Ok(_) => {},
+            Err(y) => { return Err(response.is_failed(request, &format!("sftp copy failed (2): {y}"))) }
+        };
+
+        return Ok(());
+    }
 }
 
 impl SshConnection {
@@ -380,47 +443,99 @@ impl SshConnection {
         }
     }
 
-    fn run_command_low_level(&self, cmd: &String) -> Result<(i32,String),(i32,String)> {
+    fn run_command_low_level(&self, cmd: &String, input: &Option<String>, timeout: Option<u64>) -> Result<(i32,String,String),(i32,String)> {
         // FIXME: catch the rare possibility this unwrap fails and return a nice error?
         let session = self.session.as_ref().unwrap();
+        // the session timeout applies to every blocking call below, 0 means wait forever
+        session.set_timeout(match timeout { Some(x) => (x * 1000) as u32, None => 0 });
         let mut channel = match session.channel_session() {
             Ok(x) => x,
             Err(y) => { return Err((500, format!("channel session failed: {:?}", y))); }
         };
-        let actual_cmd = format!("LANG=C {} 2>&1", cmd);
+        let actual_cmd = format!("LANG=C {}", cmd);
         match channel.exec(&actual_cmd) { Ok(_x) => {}, Err(y) => { return Err((500,y.to_string())) } };
+        if input.is_some() {
+            // sudo passwords are written to the channel rather than the command line so they do not show up in process listings
+            match writeln!(channel, "{}", input.as_ref().unwrap()) { Ok(_x) => {}, Err(y) => { return Err((500,y.to_string())) } };
+            match channel.send_eof() { Ok(_x) => {}, Err(y) => { return Err((500,y.to_string())) } };
+        }
+        // standard error is read once standard output is exhausted, libssh2 buffers it in the meantime
+        // so this only stalls for commands writing more to standard error than the channel window
         let mut s = String::new();
-        match channel.read_to_string(&mut s) { Ok(_x) => {}, Err(y) => { return Err((500,y.to_string())) } };
-        // BOOKMARK: add sudo password prompt (configurable) support here (and below)
+        let mut e = String::new();
+        for result in [channel.read_to_string(&mut s), channel.stderr().read_to_string(&mut e)] {
+            match result { 
+                Ok(_x) => {}, 
+                Err(y) if y.kind() == io::ErrorKind::TimedOut => { return Err((124, format!("timed out after {} seconds", timeout.unwrap_or(0)))) },
+                Err(y) => { return Err((500,y.to_string())) } 
+            };
+        }
         let _w = channel.wait_close();
         let exit_status = match channel.exit_status() { Ok(x) => x, Err(y) => { return Err((500,y.to_string())) } };
         self.trim_newlines(&mut s);
-        return Ok((exit_status, s.clone()));
+        self.trim_newlines(&mut e);
+        return Ok((exit_status, s, e));
+    }
+
+    // the ssh(1) options matching how this connection was made, so forwarding the agent reaches the same host
+    // (jet_ssh_hostname, bastions) with the same key and host key policy.  ssh(1) cannot be given a password,
+    // BatchMode makes it fail rather than prompt when password authentication is all that is left.
+
+    fn get_openssh_args(&self) -> Vec<String> {
+        let mut args : Vec<String> = vec![
+            String::from("-p"), format!("{}", self.port),
+            String::from("-l"), self.username.clone(),
+            String::from("-A"),
+            String::from("-o"), String::from("BatchMode=yes")
+        ];
+        if let Some(key) = &self.key {
+            if self.auth == SshAuth::Key || self.auth == SshAuth::Auto {
+                args.push(String::from("-i"));
+                args.push(key.clone());
+            }
+        }
+        match self.host_key_checking {
+            HostKeyChecking::Strict    => { args.push(String::from("-o")); args.push(String::from("StrictHostKeyChecking=yes")); },
+            HostKeyChecking::AcceptNew => { args.push(String::from("-o")); args.push(String::from("StrictHostKeyChecking=accept-new")); },
+            HostKeyChecking::Off       => {
+                args.push(String::from("-o")); args.push(String::from("StrictHostKeyChecking=no"));
+                args.push(String::from("-o")); args.push(String::from("UserKnownHostsFile=/dev/null"));
+            }
+        }
+        if ! self.bastions.is_empty() {
+            let jumps : Vec<String> = self.bastions.iter().map(|b| format!("{}@{}:{}", b.username, b.hostname, b.port)).collect();
+            args.push(String::from("-J"));
+            args.push(jumps.join(","));
+        }
+        return args;
     }
 
-    fn run_command_with_ssh_a(&self, cmd: &String) -> Result<(i32,String),(i32,String)> {
+    fn run_command_with_ssh_a(&self, cmd: &String, input: &Option<String>, timeout: Option<u64>) -> Result<(i32,String,String),(i32,String)> {
         // this is annoying but libssh2 agent support is not really working, so if we need to SSH -A we need to invoke
         // SSHd directly, which we need to for example with git clones. we will likely use this again
         // for fanout support.
 
         let mut base = Command::new("ssh");
-        let hostname = &self.host.read().unwrap().name;
-        let port = format!("{}", self.port);
-        let cmd2 = format!("LANG=C {} 2>&1", cmd);
-        let command = base.arg(hostname).arg("-p").arg(port).arg("-l").arg(self.username.clone()).arg("-A").arg(cmd2);
-        match command.output() {
+        let cmd2 = format!("LANG=C {}", cmd);
+        let command = base.args(self.get_openssh_args()).arg(&self.hostname).arg(cmd2);
+        match run_with_input(command, input, timeout) {
             Ok(x) => {
                 match x.status.code() {
                     Some(rc) => {
-                        let mut out = convert_out(&x.stdout,&x.stderr);
+                        let mut out = convert_bytes(&x.stdout);
+                        let mut err = convert_bytes(&x.stderr);
                         self.trim_newlines(&mut out);
-                        return Ok((rc, out.clone()))
+                        self.trim_newlines(&mut err);
+                        return Ok((rc, out, err))
                     },
                     None => {
-                        return Ok((418, String::from("")))
+                        return Ok((418, String::from(""), String::from("")))
                     }
                 }
             },
+            Err(x) if x.kind() == io::ErrorKind::TimedOut => {
+                return Err((124, x.to_string()))
+            },
             Err(_x) => {
                 return Err((404, String::from("")))
             }
@@ -428,3 +543,290 @@ impl SshConnection {
     }
 
 }
+
+// connects the TCP socket for an SSH session, need to get socketaddrs first in order to use Duration for timeout.
+// --timeout also bounds the connection attempt, otherwise we allow 10 seconds
+
+pub fn open_tcp_stream(hostname: &String, port: i64, timeout: Option<u64>) -> Result<TcpStream, String> {
+    let seconds = Duration::from_secs(timeout.unwrap_or(10));
+    let connect_str = format!("{host}:{port}", host=hostname, port=port.to_string());
+    // connect with timeout requires SocketAddr objects instead of just connection strings
+    let addrs_iter = connect_str.as_str().to_socket_addrs();
+    
+    // check for errors
+    let mut addrs_iter2 = match addrs_iter { Err(_x) => { return Err(format!("unable to resolve {}", hostname)); }, Ok(y) => y };
+    let addr = addrs_iter2.next();
+    if ! addr.is_some() { return Err(format!("unable to resolve {} to an address", hostname));  }
+    
+    // actually connect (finally) here
+    return match TcpStream::connect_timeout(&addr.unwrap(), seconds) { 
+        Ok(x) => Ok(x), 
+        Err(y) if y.kind() == io::ErrorKind::TimedOut => { 
+            Err(format!("SSH connection attempt timed out after {} seconds for {}:{}", seconds.as_secs(), hostname, port))
+        },
+        _ => Err(format!("SSH connection attempt failed for {}:{}", hostname, port))
+    };
+}
+
+// new session & handshake, a host that accepts the connection but never speaks SSH is bounded by the same timeout
+
+pub fn start_session<S: 'static + AsRawFd>(stream: S, timeout: Option<u64>) -> Result<Session, String> {
+    let seconds = Duration::from_secs(timeout.unwrap_or(10));
+    let mut sess = match Session::new() { Ok(x) => x, _ => { return Err(String::from("SSH session failed")); } };
+    sess.set_tcp_stream(stream);
+    if timeout.is_some() {
+        sess.set_timeout((timeout.unwrap() * 1000) as u32);
+    }
+    match sess.handshake() { 
+        Ok(_) => {}, 
+        Err(y) if y.code() == ssh2::ErrorCode::Session(-9) => { return Err(format!("SSH handshake timed out after {} seconds", seconds.as_secs())); },
+        _ => { return Err(String::from("SSH handshake failed")); } 
+    };
+    return Ok(sess);
+}
+
+// what to do with the host key a server presents, from --host-key-checking or jet_ssh_host_key_checking.
+// Keys are checked against ~/.ssh/known_hosts.  Strict only connects to hosts already listed there, AcceptNew
+// (the default) also connects to unlisted hosts and records their key, and Off does not look at keys at all.
+// A key that differs from the recorded one is always refused unless checking is off.
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum HostKeyChecking {
+    Strict,
+    AcceptNew,
+    Off
+}
+
+pub fn parse_host_key_checking(value: &String) -> Result<HostKeyChecking, String> {
+    return match value.as_str() {
+        "strict"     => Ok(HostKeyChecking::Strict),
+        "accept-new" => Ok(HostKeyChecking::AcceptNew),
+        "off"        => Ok(HostKeyChecking::Off),
+        _ => Err(format!("invalid host key checking policy '{}', expecting 'strict', 'accept-new' or 'off'", value))
+    };
+}
+
+// known_hosts is read, and possibly appended to, by many connection threads at once
+static KNOWN_HOSTS_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+pub fn verify_host_key(sess: &Session, hostname: &String, port: i64, policy: HostKeyChecking) -> Result<(), String> {
+    if policy == HostKeyChecking::Off {
+        return Ok(());
+    }
+    let key = match sess.host_key() {
+        Some((x, _)) => x,
+        None => { return Err(format!("{}:{} did not present a host key", hostname, port)); }
+    };
+    let known_hosts_path = match env::var("HOME") {
+        Ok(home) => Path::new(&home).join(".ssh").join("known_hosts"),
+        Err(_) => { return Err(String::from("cannot check host keys, $HOME is not set")); }
+    };
+
+    let _guard = KNOWN_HOSTS_LOCK.lock().unwrap();
+    let mut known_hosts = match sess.known_hosts() {
+        Ok(x) => x,
+        Err(e) => { return Err(format!("cannot check host keys: {}", e)); }
+    };
+    if known_hosts_path.exists() {
+        if let Err(e) = known_hosts.read_file(&known_hosts_path, KnownHostFileKind::OpenSSH) {
+            return Err(format!("cannot read {}: {}", known_hosts_path.display(), e));
+        }
+    }
+    return match known_hosts.check_port(hostname, port as u16, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(format!("host key for {}:{} does not match the key in {}, refusing to connect. If the host was reinstalled, remove the old entry",
+            hostname, port, known_hosts_path.display())),
+        CheckResult::Failure => Err(format!("host key for {}:{} could not be checked against {}", hostname, port, known_hosts_path.display())),
+        CheckResult::NotFound => match policy {
+            HostKeyChecking::Strict => Err(format!("host key for {}:{} is not in {} and host key checking is strict", hostname, port, known_hosts_path.display())),
+            _ => append_known_host(&known_hosts_path, &get_known_hosts_line(hostname, port, key)?)
+        }
+    };
+}
+
+// a known_hosts line in the same form OpenSSH writes, hosts on other ports than 22 are written as [host]:port.
+// The key type is the name at the start of the key blob, so no mapping from libssh2's types is needed.
+
+fn get_known_hosts_line(hostname: &String, port: i64, key: &[u8]) -> Result<String, String> {
+    let entry = match port {
+        22 => hostname.clone(),
+        _ => format!("[{}]:{}", hostname, port)
+    };
+    if key.len() < 4 {
+        return Err(format!("host key for {} is malformed", entry));
+    }
+    let name_len = u32::from_be_bytes([key[0], key[1], key[2], key[3]]) as usize;
+    let key_type = match key.get(4..4+name_len).map(|x| std::str::from_utf8(x)) {
+        Some(Ok(x)) if ! x.is_empty() => x,
+        _ => { return Err(format!("host key for {} is malformed", entry)); }
+    };
+    return Ok(format!("{} {} {}\n", entry, key_type, base64::encode(key)));
+}
+
+// the new key is appended in a single write rather than having libssh2 rewrite the whole file, which
+// would drop comments and could lose entries other ssh processes add at the same time
+
+fn append_known_host(path: &Path, line: &String) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let needs_newline = match std::fs::read(path) {
+        Ok(x) => ! x.is_empty() && ! x.ends_with(b"\n"),
+        Err(_) => false
+    };
+    let data = match needs_newline {
+        true  => format!("\n{}", line),
+        false => line.clone()
+    };
+    let mut file = match OpenOptions::new().append(true).create(true).mode(0o600).open(path) {
+        Ok(x) => x,
+        Err(e) => { return Err(format!("unable to open {}: {}", path.display(), e)); }
+    };
+    return match file.write_all(data.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("unable to write {}: {}", path.display(), e))
+    };
+}
+
+// how to log in, from jet_ssh_auth.  Auto uses the key file if one is configured, otherwise the login password if
+// one was given, otherwise the agent.  Whichever method is chosen is the only one tried, so a rejected key is
+// reported as such instead of quietly falling back to something else.
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum SshAuth {
+    Auto,
+    Key,
+    Agent,
+    Password
+}
+
+pub fn authenticate(sess: &Session, username: &String, auth: SshAuth, login_password: &Option<String>, key: &Option<String>, passphrase: &Option<String>, key_comment: &Option<String>) -> Result<(), String> {
+
+    let method = match auth {
+        SshAuth::Auto => match (key.is_some(), login_password.is_some()) {
+            (true, _)      => SshAuth::Key,
+            (false, true)  => SshAuth::Password,
+            (false, false) => SshAuth::Agent
+        },
+        x => x
+    };
+
+    match method {
+        SshAuth::Password => {
+            let password = match login_password {
+                Some(x) => x,
+                None => { return Err(String::from("jet_ssh_auth is password but no login password was given, use --ask-login-password")); }
+            };
+            match sess.userauth_password(username, password.as_str()) {
+                Ok(_) => {},
+                Err(x) => {
+                    return Err(format!("SSH password authentication failed for user {}: {}", username, x));
+                }
+            }
+        },
+        SshAuth::Key => {
+            let k2 = match key {
+                Some(x) => x.clone(),
+                None => { return Err(String::from("jet_ssh_auth is key but jet_ssh_private_key_file is not set")); }
+            };
+            let keypath = Path::new(&k2);
+            if ! keypath.exists() {
+                return Err(format!("cannot find designed keyfile {}", k2));
+            }
+            match sess.userauth_pubkey_file(username, None, keypath, passphrase.as_deref()) {
+                Ok(_) => {},
+                Err(x) => {
+                    return Err(format!("SSH key authentication failed for user {} with key {:?}: {}", username, keypath, x));
+                }
+            };
+        },
+        SshAuth::Agent | SshAuth::Auto => {
+            if key_comment.is_some() {
+                // use this specific SSH key
+                let mut agent = match sess.agent() {
+                    Ok(x) => x,
+                    Err(x) => { return Err(format!("SSH cannot use agent: {}", x)); }
+                };
+                match agent.connect() {
+                    Ok(_) => {},
+                    Err(x) => {
+                        return Err(format!("SSH cannot connect to agent: {}", x));
+                    }
+                };
+                // list_identities is needed to populate the identities in memory,
+                // see: https://docs.rs/ssh2/latest/ssh2/struct.Agent.html#method.list_identities
+                match agent.list_identities() {
+                    Ok(_) => {},
+                    Err(x) => {
+                        return Err(format!("SSH list_identities returned an error, please check whether agent is running: {}", x));
+                    }
+                };
+                let mut found : bool = false;
+                for ident in agent.identities().unwrap() {
+                    match ident.comment() == key_comment.clone().unwrap() {
+                        true => {
+                            match agent.userauth(username, &ident) {
+                                Ok(_) => {
+                                    // use this identity
+                                    found = true;
+                                    break;
+                                },
+                                Err(x) => { 
+                                    return Err(format!("SSH Key authentication failed for user {} with key {}: {}", 
+                                        username, key_comment.clone().unwrap(), x)); 
+                                }
+                            };
+                        }
+                        false => (),
+                    }
+                }
+                if !found {
+                    return Err(format!("specified SSH key not found with comment {}", key_comment.clone().unwrap()));
+                }
+            } else {
+                // no key comment specified, do not use a specific key
+                match sess.userauth_agent(username) { 
+                    Ok(_) => {}, 
+                    Err(x) => { 
+                        return Err(format!("SSH agent authentication failed for user {}: {}", username, x));
+                    }
+                };
+            }
+        }
+    };
+
+    if !(sess.authenticated()) { return Err("failed to authenticate".to_string()); };
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_blob(key_type: &str) -> Vec<u8> {
+        let mut blob = (key_type.len() as u32).to_be_bytes().to_vec();
+        blob.extend_from_slice(key_type.as_bytes());
+        blob.extend_from_slice(&[0, 0, 0, 3, 1, 2, 3]);
+        return blob;
+    }
+
+    #[test]
+    fn test_known_hosts_line() {
+        let blob = key_blob("ssh-ed25519");
+        let encoded = base64::encode(&blob);
+        assert_eq!(get_known_hosts_line(&String::from("web1"), 22, &blob).unwrap(), format!("web1 ssh-ed25519 {}\n", encoded));
+        assert_eq!(get_known_hosts_line(&String::from("web1"), 2222, &blob).unwrap(), format!("[web1]:2222 ssh-ed25519 {}\n", encoded));
+        assert!(get_known_hosts_line(&String::from("web1"), 22, &[0, 0]).is_err());
+        assert!(get_known_hosts_line(&String::from("web1"), 22, &[0, 0, 0, 9, b'x']).is_err());
+    }
+
+    #[test]
+    fn test_append_known_host_keeps_existing_lines() {
+        let path = env::temp_dir().join(format!("jetp-known-hosts-{}", std::process::id()));
+        std::fs::write(&path, "# managed elsewhere\nold ssh-rsa AAAA").unwrap();
+        append_known_host(&path, &String::from("new ssh-ed25519 BBBB\n")).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contents, "# managed elsewhere\nold ssh-rsa AAAA\nnew ssh-ed25519 BBBB\n");
+    }
+}
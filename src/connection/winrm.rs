@@ -0,0 +1,308 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::connection::connection::Connection;
+use crate::connection::command::{CommandResult,Forward};
+use crate::inventory::hosts::{Host,HostOSType};
+use crate::handle::response::Response;
+use crate::tasks::{TaskRequest,TaskResponse};
+use std::sync::{Arc,RwLock};
+use std::path::Path;
+use std::time::{Duration,Instant};
+use guid_create::GUID;
+use regex::Regex;
+
+// connections to Windows hosts over WinRM (WS-Management) with basic authentication.  One remote
+// shell is opened on connect and every command is a PowerShell script run inside it, so for these
+// hosts module commands are written in PowerShell rather than sh.  Files travel as base64 chunks,
+// as WinRM has no file transfer of its own.
+
+const RESOURCE_URI: &str = "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/cmd";
+const ACTION_CREATE: &str = "http://schemas.xmlsoap.org/ws/2004/09/transfer/Create";
+const ACTION_DELETE: &str = "http://schemas.xmlsoap.org/ws/2004/09/transfer/Delete";
+const ACTION_COMMAND: &str = "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Command";
+const ACTION_RECEIVE: &str = "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Receive";
+const ACTION_SIGNAL: &str = "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/Signal";
+const SIGNAL_TERMINATE: &str = "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/signal/terminate";
+const STATE_DONE: &str = "http://schemas.microsoft.com/wbem/wsman/1/windows/shell/CommandState/Done";
+// a Receive that had nothing to report within the operation timeout comes back as this fault, and is simply retried
+const FAULT_OPERATION_TIMEOUT: &str = "2150858793";
+// raw bytes per upload command, sized so the encoded PowerShell stays under the cmd.exe line limit
+const UPLOAD_CHUNK: usize = 1500;
+
+pub struct WinRmConnection {
+    host: Arc<RwLock<Host>>,
+    endpoint: String,
+    username: String,
+    password: String,
+    agent: ureq::Agent,
+    shell_id: Option<String>,
+}
+
+impl WinRmConnection {
+
+    pub fn new(host: &Arc<RwLock<Host>>, endpoint: &String, username: &String, password: &String, validate_certs: bool, timeout: Option<u64>) -> Result<Self,String> {
+        let tls = match native_tls::TlsConnector::builder().danger_accept_invalid_certs(! validate_certs).build() {
+            Ok(x) => x,
+            Err(y) => { return Err(format!("unable to set up TLS for WinRM: {}", y)); }
+        };
+        let mut builder = ureq::AgentBuilder::new().tls_connector(Arc::new(tls));
+        if timeout.is_some() {
+            builder = builder.timeout_connect(Duration::from_secs(timeout.unwrap()));
+        }
+        return Ok(Self {
+            host: Arc::clone(&host),
+            endpoint: endpoint.clone(),
+            username: username.clone(),
+            password: password.clone(),
+            agent: builder.build(),
+            shell_id: None,
+        });
+    }
+
+    // every WinRM request is a SOAP envelope POSTed to the same endpoint, the action and selectors in the header say what it is
+
+    fn send(&self, action: &str, options: &str, body: &String) -> Result<String,String> {
+        let selectors = match &self.shell_id {
+            Some(id) => format!("<w:SelectorSet><w:Selector Name=\"ShellId\">{}</w:Selector></w:SelectorSet>", id),
+            None => String::new()
+        };
+        let envelope = format!(concat!(
+            "<s:Envelope xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\" xmlns:a=\"http://schemas.xmlsoap.org/ws/2004/08/addressing\" ",
+            "xmlns:w=\"http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd\" xmlns:rsp=\"http://schemas.microsoft.com/wbem/wsman/1/windows/shell\">",
+            "<s:Header><a:To>{}</a:To>",
+            "<a:ReplyTo><a:Address s:mustUnderstand=\"true\">http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous</a:Address></a:ReplyTo>",
+            "<w:ResourceURI s:mustUnderstand=\"true\">{}</w:ResourceURI>",
+            "<a:Action s:mustUnderstand=\"true\">{}</a:Action>",
+            "<a:MessageID>uuid:{}</a:MessageID>",
+            "<w:MaxEnvelopeSize s:mustUnderstand=\"true\">153600</w:MaxEnvelopeSize>",
+            "<w:OperationTimeout>PT20S</w:OperationTimeout>",
+            "{}{}</s:Header><s:Body>{}</s:Body></s:Envelope>"),
+            self.endpoint, RESOURCE_URI, action, GUID::rand().to_string(), selectors, options, body);
+        let auth = format!("Basic {}", base64::encode(format!("{}:{}", self.username, self.password)));
+        let result = self.agent.post(&self.endpoint)
+            .set("Content-Type", "application/soap+xml;charset=UTF-8")
+            .set("Authorization", &auth)
+            .send_string(&envelope);
+        return match result {
+            Ok(response) => response.into_string().map_err(|e| format!("WinRM response could not be read: {}", e)),
+            Err(ureq::Error::Status(401, _)) => Err(format!("WinRM authentication failed for user {}", self.username)),
+            Err(ureq::Error::Status(code, response)) => {
+                // faults come back as HTTP 500 with the details in the body
+                let text = response.into_string().unwrap_or_default();
+                match text.contains(FAULT_OPERATION_TIMEOUT) {
+                    true => Err(String::from(FAULT_OPERATION_TIMEOUT)),
+                    false => Err(format!("WinRM request failed ({}): {}", code, get_fault_text(&text)))
+                }
+            },
+            Err(y) => Err(format!("WinRM request failed: {}", y))
+        };
+    }
+
+    // runs a PowerShell script, returning the return code, standard output and standard error
+
+    fn run_script(&self, script: &String, timeout: Option<u64>) -> Result<(i32,String,String),(i32,String)> {
+        // the script is wrapped so the return code of the last program run, or 1 for a failed cmdlet, is what comes back
+        let wrapped = format!("$ProgressPreference = 'SilentlyContinue'\n$global:LASTEXITCODE = 0\n& {{\n{}\n}}\nif (-not $?) {{ exit 1 }}\nexit $LASTEXITCODE\n", script);
+        let utf16 : Vec<u8> = wrapped.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+        let body = format!("<rsp:CommandLine><rsp:Command>powershell.exe</rsp:Command><rsp:Arguments>-NoProfile -NonInteractive -ExecutionPolicy Bypass -EncodedCommand {}</rsp:Arguments></rsp:CommandLine>",
+            base64::encode(&utf16));
+        let options = "<w:OptionSet><w:Option Name=\"WINRS_CONSOLEMODE_STDIN\">TRUE</w:Option><w:Option Name=\"WINRS_SKIP_CMD_SHELL\">FALSE</w:Option></w:OptionSet>";
+        let response = self.send(ACTION_COMMAND, options, &body).map_err(|e| (500, e))?;
+        let command_id = match get_element(&response, "CommandId") {
+            Some(x) => x,
+            None => { return Err((500, String::from("WinRM did not return a command id"))); }
+        };
+
+        let stream_re = Regex::new(r#"<(?:\w+:)?Stream\s([^>]*?)(?:/>|>([^<]*)</(?:\w+:)?Stream>)"#).unwrap();
+        let mut out : Vec<u8> = Vec::new();
+        let mut err : Vec<u8> = Vec::new();
+        let started = Instant::now();
+        let receive = format!("<rsp:Receive><rsp:DesiredStream CommandId=\"{}\">stdout stderr</rsp:DesiredStream></rsp:Receive>", command_id);
+        let rc = loop {
+            if timeout.is_some() && started.elapsed().as_secs() >= timeout.unwrap() {
+                self.terminate(&command_id);
+                return Err((124, format!("timed out after {} seconds", timeout.unwrap())));
+            }
+            let response = match self.send(ACTION_RECEIVE, "", &receive) {
+                Ok(x) => x,
+                Err(y) if y.eq(FAULT_OPERATION_TIMEOUT) => { continue; },
+                Err(y) => { return Err((500, y)); }
+            };
+            for cap in stream_re.captures_iter(&response) {
+                let data = match cap.get(2) {
+                    Some(x) => match base64::decode(x.as_str().trim()) { Ok(bytes) => bytes, Err(_) => Vec::new() },
+                    None => Vec::new()
+                };
+                match cap.get(1).unwrap().as_str().contains("Name=\"stderr\"") {
+                    true => err.extend(data),
+                    false => out.extend(data)
+                };
+            }
+            if response.contains(STATE_DONE) {
+                break match get_element(&response, "ExitCode") {
+                    Some(x) => x.parse::<i32>().unwrap_or(1),
+                    None => 0
+                };
+            }
+        };
+        self.terminate(&command_id);
+
+        let mut out = String::from_utf8_lossy(&out).to_string();
+        let mut err = clean_clixml(&String::from_utf8_lossy(&err).to_string());
+        trim_newlines(&mut out);
+        trim_newlines(&mut err);
+        return Ok((rc, out, err));
+    }
+
+    fn terminate(&self, command_id: &String) {
+        let body = format!("<rsp:Signal CommandId=\"{}\"><rsp:Code>{}</rsp:Code></rsp:Signal>", command_id, SIGNAL_TERMINATE);
+        let _ = self.send(ACTION_SIGNAL, "", &body);
+    }
+
+    // writes bytes to a path on the Windows host a chunk at a time, the first chunk replacing whatever was there
+
+    fn upload(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, data: &[u8], remote_path: &String) -> Result<(), Arc<TaskResponse>> {
+        let path = powershell_quote(remote_path);
+        let mut chunks : Vec<&[u8]> = data.chunks(UPLOAD_CHUNK).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+        for (index, chunk) in chunks.iter().enumerate() {
+            let mode = match index { 0 => "Create", _ => "Append" };
+            let script = format!("$b = [Convert]::FromBase64String('{}')\n$f = [IO.File]::Open({}, [IO.FileMode]::{})\n$f.Write($b, 0, $b.Length)\n$f.Close()",
+                base64::encode(chunk), path, mode);
+            match self.run_script(&script, request.timeout) {
+                Ok((0, _, _)) => {},
+                Ok((_, _, e)) => { return Err(response.is_failed(request, &format!("failed to write: {}: {}", remote_path, e))); },
+                Err((_, e)) => { return Err(response.is_failed(request, &format!("failed to write: {}: {}", remote_path, e))); }
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Drop for WinRmConnection {
+    fn drop(&mut self) {
+        // shells left open count against the per user quota on the Windows host
+        if self.shell_id.is_some() {
+            let _ = self.send(ACTION_DELETE, "", &String::new());
+        }
+    }
+}
+
+impl Connection for WinRmConnection {
+
+    fn whoami(&self) -> Result<String,String> {
+        return match self.run_script(&String::from("[Environment]::UserName"), None) {
+            Ok((0, out, _)) => Ok(out.trim().to_string()),
+            Ok((_, _, err)) => Err(err),
+            Err((_, err)) => Err(err)
+        };
+    }
+
+    fn connect(&mut self) -> Result<(),String> {
+        let options = "<w:OptionSet><w:Option Name=\"WINRS_NOPROFILE\">FALSE</w:Option><w:Option Name=\"WINRS_CODEPAGE\">65001</w:Option></w:OptionSet>";
+        let body = String::from("<rsp:Shell><rsp:InputStreams>stdin</rsp:InputStreams><rsp:OutputStreams>stdout stderr</rsp:OutputStreams></rsp:Shell>");
+        let response = self.send(ACTION_CREATE, options, &body)?;
+        self.shell_id = match get_element(&response, "ShellId") {
+            Some(x) => Some(x),
+            None => match Regex::new(r#"Name="ShellId">([^<]+)<"#).unwrap().captures(&response) {
+                Some(cap) => Some(cap.get(1).unwrap().as_str().to_string()),
+                None => { return Err(String::from("WinRM did not return a shell id")); }
+            }
+        };
+        // there is no uname to ask, WinRM is only spoken by Windows
+        self.host.write().unwrap().os_type = Some(HostOSType::Windows);
+        return Ok(());
+    }
+
+    fn run_command(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, cmd: &String, _forward: Forward, _input: &Option<String>) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+        return match self.run_script(cmd, request.timeout) {
+            Ok((rc,out,err)) => {
+                Ok(response.command_ok(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: out, err: err, rc: rc }))))
+            },
+            Err((rc,s)) => {
+                Err(response.command_failed(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from(""), err: s, rc: rc }))))
+            }
+        };
+    }
+
+    fn copy_file(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, src: &Path, remote_path: &String) -> Result<(), Arc<TaskResponse>> {
+        let data = match std::fs::read(src) {
+            Ok(x) => x,
+            Err(y) => { return Err(response.is_failed(request, &format!("failed to read: {}: {:?}", src.display(), y))); }
+        };
+        return self.upload(response, request, &data, remote_path);
+    }
+
+    fn fetch_file(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, remote_path: &String, dest: &Path) -> Result<(), Arc<TaskResponse>> {
+        let script = format!("[Convert]::ToBase64String([IO.File]::ReadAllBytes({}))", powershell_quote(remote_path));
+        let encoded = match self.run_script(&script, request.timeout) {
+            Ok((0, out, _)) => out,
+            Ok((_, _, e)) | Err((_, e)) => { return Err(response.is_failed(request, &format!("fetch failed: {}: {}", remote_path, e))); }
+        };
+        let data = match base64::decode(encoded.trim()) {
+            Ok(x) => x,
+            Err(y) => { return Err(response.is_failed(request, &format!("fetch failed: {}: {}", remote_path, y))); }
+        };
+        return match std::fs::write(dest, data) {
+            Ok(_) => Ok(()),
+            Err(y) => Err(response.is_failed(request, &format!("fetch failed: {:?}", y)))
+        };
+    }
+
+    fn write_data(&self, response: &Arc<Response>, request: &Arc<TaskRequest>, data: &String, remote_path: &String) -> Result<(),Arc<TaskResponse>> {
+        return self.upload(response, request, data.as_bytes(), remote_path);
+    }
+
+}
+
+fn get_element(xml: &String, name: &str) -> Option<String> {
+    // responses use various namespace prefixes, so only the local name is matched
+    let re = Regex::new(&format!(r"<(?:\w+:)?{}>([^<]*)</", name)).unwrap();
+    return re.captures(xml).map(|cap| cap.get(1).unwrap().as_str().trim().to_string());
+}
+
+fn get_fault_text(xml: &String) -> String {
+    return match get_element(xml, "Message").or_else(|| get_element(xml, "Text")) {
+        Some(x) => x,
+        None => xml.clone()
+    };
+}
+
+// PowerShell reports errors on standard error as serialized objects when not attached to a console,
+// only the error text in them is interesting
+
+fn clean_clixml(err: &String) -> String {
+    if ! err.starts_with("#< CLIXML") {
+        return err.clone();
+    }
+    let re = Regex::new(r#"<S S="Error">([^<]*)</S>"#).unwrap();
+    let text : String = re.captures_iter(err).map(|cap| cap.get(1).unwrap().as_str().to_string()).collect();
+    return text.replace("_x000D__x000A_", "\n").replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&").replace("&quot;", "\"").replace("&apos;", "'");
+}
+
+fn powershell_quote(s: &String) -> String {
+    return format!("'{}'", s.replace("'", "''"));
+}
+
+fn trim_newlines(s: &mut String) {
+    while s.ends_with('\n') || s.ends_with('\r') {
+        s.pop();
+    }
+}
@@ -49,7 +49,7 @@ pub struct TaskHandle {
 
 impl TaskHandle {
 
-    pub fn new(run_state_handle: Arc<RunState>, connection_handle: Arc<Mutex<dyn Connection>>, host_handle: Arc<RwLock<Host>>) -> Self {
+    pub fn new(run_state_handle: Arc<RunState>, connection_handle: Arc<Mutex<dyn Connection>>, host_handle: Arc<RwLock<Host>>, delegate_handle: Option<Arc<RwLock<Host>>>) -> Self {
 
         // since we can't really have back-references (thanks Rust?) we pass to each namespace what we need of the others
         // thankfully, no circular references seem to be required :)
@@ -70,11 +70,17 @@ impl TaskHandle {
         ));
 
         // remote contains code for interacting with the host being configured.  The host could actually be 'localhost', but it's usually
-        // a machine different from the control machine.  this could be called "configuration_target" instead but that would be more typing
+        // a machine different from the control machine.  this could be called "configuration_target" instead but that would be more typing.
+        // with delegate_to the connection goes to the delegate, which is also the host used for OS specific commands.
+        let target_handle = match delegate_handle {
+            Some(delegate) => delegate,
+            None => Arc::clone(&host_handle)
+        };
         let remote = Arc::new(Remote::new(
             Arc::clone(&run_state_handle), 
             Arc::clone(&connection_handle), 
             Arc::clone(&host_handle),
+            Arc::clone(&target_handle),
             Arc::clone(&template),
             Arc::clone(&response)
         ));
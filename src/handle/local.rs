@@ -16,7 +16,7 @@
 
 use std::sync::{Arc,RwLock};
 use std::path::Path;
-use crate::connection::command::cmd_info;
+use crate::connection::command::{cmd_info,cmd_info_with_err};
 use crate::tasks::{TaskRequest,TaskRequestType,TaskResponse};
 use crate::inventory::hosts::Host;
 use crate::playbooks::traversal::RunState;
@@ -24,6 +24,7 @@ use crate::tasks::cmd_library::screen_general_input_loose;
 use crate::handle::handle::CheckRc;
 use crate::handle::response::Response;
 use crate::connection::command::Forward;
+use crate::tasks::files::ArchiveFormat;
 
 // local contains code that always executes on the control machine, whether in SSH mode or 'local' execution
 // mode. The code that refers to the machine being configured is always in 'remote.rs', whether in SSH
@@ -74,7 +75,7 @@ impl Local {
             Ok(x) => x,
             Err(y) => { return Err(self.response.is_failed(request, &y.clone())) }
         };
-        let result = local_conn.lock().unwrap().run_command(&self.response, request, cmd, Forward::No);
+        let result = local_conn.lock().unwrap().run_command(&self.response, request, cmd, Forward::No, &None);
 
         if check_rc == CheckRc::Checked {
             if result.is_ok() {
@@ -95,6 +96,23 @@ impl Local {
         };
     }
 
+    // returns the top level entries of an archive on the control machine, so the unarchive module can
+    // tell if a destination is complete without transferring anything
+
+    pub fn list_archive(&self, request: &Arc<TaskRequest>, path: &Path, format: ArchiveFormat) -> Result<Vec<String>,Arc<TaskResponse>> {
+        let path2 = format!("{}", path.display());
+        let localhost = self.get_localhost();
+        let os_type = localhost.read().unwrap().os_type.expect("unable to detect host OS type");
+        let get_cmd_result = crate::tasks::cmd_library::get_archive_list_command(os_type, &path2, format);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        let result = self.run(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out, err) = cmd_info_with_err(&result);
+        return match rc {
+            0 => Ok(ArchiveFormat::top_level_entries(&out)),
+            _ => Err(self.response.is_failed(request, &format!("unable to list archive {}: {}", path2, err)))
+        };
+    }
+
     fn internal_sha512(&self, request: &Arc<TaskRequest>, path: &String) -> Result<String,Arc<TaskResponse>> {
         let localhost = self.get_localhost();
         let os_type = localhost.read().unwrap().os_type.expect("unable to detect host OS type");
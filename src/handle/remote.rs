@@ -17,21 +17,23 @@
 use std::sync::{Arc,Mutex,RwLock};
 use std::path::Path;
 use crate::connection::connection::Connection;
-use crate::connection::command::cmd_info;
-use crate::tasks::request::{TaskRequest, TaskRequestType};
+use crate::connection::command::{cmd_info,cmd_info_with_err};
+use crate::tasks::request::{TaskRequest, TaskRequestType, AsyncDetails};
 use crate::tasks::response::TaskResponse;
 use crate::inventory::hosts::{Host,HostOSType};
 use crate::playbooks::traversal::RunState;
 use crate::tasks::fields::Field;
 use crate::tasks::FileAttributesEvaluated;
-use crate::connection::command::Forward;
+use crate::connection::command::{Forward,CommandResult};
 use crate::tasks::cmd_library::screen_general_input_loose;
 use crate::handle::handle::CheckRc;
 use crate::handle::template::Safety;
 use crate::handle::response::Response;
 use crate::handle::template::Template;
-use crate::tasks::files::Recurse;
+use crate::tasks::files::{Recurse,ArchiveFormat};
 use std::path::PathBuf;
+use std::{thread,time};
+use crate::tasks::diff::{is_binary,binary_differs,unified_diff};
 
 // contains all code that eventually reaches out and touches systems to be configured.
 // this includes the local system (somewhat confusingly) in 'local' mode, and of course
@@ -42,6 +44,8 @@ pub struct Remote {
     run_state: Arc<RunState>, 
     connection: Arc<Mutex<dyn Connection>>,
     host: Arc<RwLock<Host>>, 
+    // the host commands actually run on, which differs from 'host' when using delegate_to
+    target_host: Arc<RwLock<Host>>,
     template: Arc<Template>,
     response: Arc<Response>
 }
@@ -58,6 +62,7 @@ impl Remote {
         run_state: Arc<RunState>, 
         connection: Arc<Mutex<dyn Connection>>, 
         host: Arc<RwLock<Host>>, 
+        target_host: Arc<RwLock<Host>>,
         template: Arc<Template>,
         response: Arc<Response>) -> Self {
         
@@ -65,6 +70,7 @@ impl Remote {
             run_state,
             connection,
             host,
+            target_host,
             template,
             response,
         }
@@ -90,7 +96,7 @@ impl Remote {
     pub fn make_temp_path(&self, who: &String, request: &Arc<TaskRequest>) -> Result<(PathBuf, PathBuf), Arc<TaskResponse>> {
         let mut pb = PathBuf::new();
         let tmpdir = match who.eq("root") {
-            false => match self.host.read().unwrap().os_type {
+            false => match self.target_host.read().unwrap().os_type {
                 Some(HostOSType::MacOS) => format!("/Users/{}/.jet/tmp", who),
                 _ => format!("/home/{}/.jet/tmp", who),
             }
@@ -108,25 +114,36 @@ impl Remote {
     // wrappers around running CLI commands
 
     pub fn run(&self, request: &Arc<TaskRequest>, cmd: &String, check_rc: CheckRc) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
-        return self.internal_run(request, cmd, Safety::Safe, check_rc, UseSudo::Yes, Forward::No);
+        return self.internal_run(request, cmd, &None, Safety::Safe, check_rc, UseSudo::Yes, Forward::No);
     }
 
     pub fn run_forwardable(&self, request: &Arc<TaskRequest>, cmd: &String, check_rc: CheckRc) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
-        return self.internal_run(request, cmd, Safety::Safe, check_rc, UseSudo::Yes, Forward::Yes);
+        return self.internal_run(request, cmd, &None, Safety::Safe, check_rc, UseSudo::Yes, Forward::Yes);
     }
 
     pub fn run_no_sudo(&self, request: &Arc<TaskRequest>, cmd: &String, check_rc: CheckRc) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
-        return self.internal_run(request, cmd, Safety::Safe, check_rc, UseSudo::No, Forward::No);
+        return self.internal_run(request, cmd, &None, Safety::Safe, check_rc, UseSudo::No, Forward::No);
     }
 
     // the unsafe version of this doesn't check the shell string for possible shell variable injections, the most obvious and basic being ";"
     // usage of unsafe requires a special keyword in the 'shell' module for instance, or that no variables are present in the cmd parameter.
 
     pub fn run_unsafe(&self, request: &Arc<TaskRequest>, cmd: &String, check_rc: CheckRc) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
-        return self.internal_run(request, cmd, Safety::Unsafe, check_rc, UseSudo::Yes, Forward::No);
+        return self.internal_run(request, cmd, &None, Safety::Unsafe, check_rc, UseSudo::Yes, Forward::No);
     }
 
-    fn internal_run(&self, request: &Arc<TaskRequest>, cmd: &String, 
+    // the same as run and run_unsafe, but from a working directory given by the command modules' chdir parameter.
+    // modules should check the directory exists while querying, so a missing one is not reported as a shell error
+
+    pub fn run_in(&self, request: &Arc<TaskRequest>, cmd: &String, chdir: &Option<String>, check_rc: CheckRc) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+        return self.internal_run(request, cmd, chdir, Safety::Safe, check_rc, UseSudo::Yes, Forward::No);
+    }
+
+    pub fn run_unsafe_in(&self, request: &Arc<TaskRequest>, cmd: &String, chdir: &Option<String>, check_rc: CheckRc) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+        return self.internal_run(request, cmd, chdir, Safety::Unsafe, check_rc, UseSudo::Yes, Forward::No);
+    }
+
+    fn internal_run(&self, request: &Arc<TaskRequest>, cmd: &String, chdir: &Option<String>,
         safe: Safety, check_rc: CheckRc, use_sudo: UseSudo, forward: Forward) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
         
         assert!(request.request_type != TaskRequestType::Validate, "commands cannot be run in validate stage");
@@ -142,20 +159,57 @@ impl Remote {
             }
         }
 
+        // Windows hosts run PowerShell, where neither the sudo template nor the sh based async wrapper apply
+
+        if self.get_os_type() == HostOSType::Windows {
+            if use_sudo == UseSudo::Yes && request.is_sudoing() {
+                return Err(self.response.is_failed(request, &String::from("sudo is not supported on Windows hosts")));
+            }
+            if request.async_details.is_some() {
+                return Err(self.response.is_failed(request, &String::from("async is not supported on Windows hosts")));
+            }
+        }
+
+        // environment variables from the play and task and the working directory are set around the command, inside
+        // of sudo so they survive it resetting the environment.  The command shown to the user leaves them out.
+
+        let cmd_env = self.add_environment(request, &cmd, chdir);
+
         // use the sudo template to choose a new command to execute if specified.
         // this doesn't need to be sudo specifically, it's really a generic concept that can wrap a command with another tool
 
         let cmd_out = match use_sudo {
-            UseSudo::Yes => match self.template.add_sudo_details(request, &cmd) {
+            UseSudo::Yes => match self.template.add_sudo_details(request, &cmd_env) {
                 Ok(x) => x,
                 Err(y) => { return Err(self.response.is_failed(request, &format!("failure constructing sudo command: {}", y))); }
             },
-            UseSudo::No => cmd.clone() 
+            UseSudo::No => cmd_env.clone()
+        };
+        let sudoing = use_sudo == UseSudo::Yes && request.is_sudoing();
+        let input = match sudoing {
+            true => request.sudo_details.as_ref().unwrap().password.clone(),
+            false => None
         };
 
         self.response.get_visitor().read().expect("read visitor").on_command_run(&self.response.get_context(), &Arc::clone(&self.host), &cmd);
 
-        let result = self.connection.lock().unwrap().run_command(&self.response, request, &cmd_out, forward);
+        let result = match request.async_details {
+            Some(details) => self.run_async(request, &cmd_env, use_sudo, forward, &input, details),
+            None => self.connection.lock().unwrap().run_command(&self.response, request, &cmd_out, forward, &input)
+        };
+
+        // a command that never ran because sudo refused us is reported as such, regardless of whether
+        // the return code would otherwise be checked, so it is not confused with the command failing
+
+        if sudoing && result.is_ok() {
+            let ok_result = result.as_ref().unwrap();
+            let cmd_result = ok_result.command_result.as_ref().as_ref().unwrap();
+            if cmd_result.rc != 0 {
+                if let Some(reason) = get_sudo_failure(&cmd_result.err) {
+                    return Err(self.response.is_failed(request, &format!("privilege escalation failed: {}", reason)));
+                }
+            }
+        }
 
         // if requested, turn non-zero return codes into errors
 
@@ -170,12 +224,94 @@ impl Remote {
         return result;
     }
 
+    // prefixes a command with the variables from with/environment and a change of directory, values are always quoted
+
+    fn add_environment(&self, request: &Arc<TaskRequest>, cmd: &String, chdir: &Option<String>) -> String {
+        if request.environment.is_empty() && chdir.is_none() {
+            return cmd.clone();
+        }
+        if self.get_os_type() == HostOSType::Windows {
+            let mut sets : Vec<String> = request.environment.iter().map(|(k,v)| format!("$env:{} = '{}'; ", k, v.replace("'", "''"))).collect();
+            if let Some(dir) = chdir {
+                sets.push(format!("Set-Location -LiteralPath '{}' -ErrorAction Stop; ", dir.replace("'", "''")));
+            }
+            return format!("{}{}", sets.join(""), cmd);
+        }
+        let inner = match chdir {
+            Some(dir) => format!("cd {} || exit 1; {}", shell_quote(dir), cmd),
+            None => cmd.clone()
+        };
+        if request.environment.is_empty() {
+            return format!("sh -c {}", shell_quote(&inner));
+        }
+        let sets : Vec<String> = request.environment.iter().map(|(k,v)| format!("{}={}", k, shell_quote(v))).collect();
+        return format!("env {} sh -c {}", sets.join(" "), shell_quote(&inner));
+    }
+
+    // with/async starts the command in the background and then checks on it every 'poll' seconds, so no single command
+    // has to outlive the connection timeout.  The job writes its output, errors and return code next to each other under ~/.jet/async
+    // of whoever runs it, and the files are removed once read.  sudo applies to starting the job and to each check.
+
+    fn run_async(&self, request: &Arc<TaskRequest>, cmd: &String, use_sudo: UseSudo, forward: Forward, input: &Option<String>, details: AsyncDetails) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+
+        // the command gets a shell of its own so an 'exit' in it still leaves a return code behind, and the return code
+        // is written aside and moved into place, so a check never finds the file created but still empty
+        let job = format!("$HOME/.jet/async/{}", self.run_state.context.read().unwrap().get_guid());
+        let launch = format!("mkdir -p \"$HOME/.jet/async\" && nohup sh -c {} > \"{}.out\" 2> \"{}.err\" < /dev/null &",
+            shell_quote(&format!("sh -c {}; echo $? > \"{j}.rc.tmp\" && mv -f \"{j}.rc.tmp\" \"{j}.rc\"", shell_quote(cmd), j=job)), job, job);
+        let result = self.run_wrapped(request, &launch, use_sudo, forward, input)?;
+        let launched = result.command_result.as_ref().as_ref().unwrap();
+        if launched.rc != 0 {
+            return Ok(result);
+        }
+
+        // fire and forget, the output is left in ~/.jet/async for whoever wants to look
+        if details.poll == 0 {
+            return Ok(self.response.command_ok(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: String::from("started in the background"), err: String::from(""), rc: 0 }))));
+        }
+
+        // the first line of output is the return code of the job, the rest is what it printed, and its errors come back as ours
+        let check = format!("cat \"{j}.rc\" 2>/dev/null && cat \"{j}.out\" && cat \"{j}.err\" >&2 && rm -f \"{j}.rc\" \"{j}.out\" \"{j}.err\"", j=job);
+        let started = time::Instant::now();
+        let mut wait = 1;
+        loop {
+            thread::sleep(time::Duration::from_secs(wait));
+            let result = self.run_wrapped(request, &check, use_sudo, forward, input)?;
+            let checked = result.command_result.as_ref().as_ref().unwrap();
+            if checked.rc == 0 {
+                let (rc_line, out) = checked.out.split_once("\n").unwrap_or((checked.out.as_str(), ""));
+                return match rc_line.trim().parse::<i32>() {
+                    Ok(rc) => Ok(self.response.command_ok(request, &Arc::new(Some(CommandResult { cmd: cmd.clone(), out: out.to_string(), err: checked.err.clone(), rc: rc })))),
+                    Err(_) => Err(self.response.is_failed(request, &format!("async job left an unexpected return code: {}", rc_line)))
+                };
+            }
+            if started.elapsed().as_secs() >= details.limit {
+                return Err(self.response.is_failed(request, &format!("async job did not finish within {} seconds", details.limit)));
+            }
+            wait = details.poll;
+        }
+    }
+
+    // runs a shell one-liner as a single command, so that sudo applies to all of it
+
+    fn run_wrapped(&self, request: &Arc<TaskRequest>, cmd: &String, use_sudo: UseSudo, forward: Forward, input: &Option<String>) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+        let wrapped = format!("sh -c {}", shell_quote(cmd));
+        let cmd_out = match use_sudo {
+            UseSudo::Yes => match self.template.add_sudo_details(request, &wrapped) {
+                Ok(x) => x,
+                Err(y) => { return Err(self.response.is_failed(request, &format!("failure constructing sudo command: {}", y))); }
+            },
+            UseSudo::No => wrapped
+        };
+        return self.connection.lock().unwrap().run_command(&self.response, request, &cmd_out, forward, input);
+    }
+
     // the OS type of a host is set on connection by automatically running a discovery command
 
     pub fn get_os_type(&self) -> HostOSType {
-        let os_type = self.host.read().unwrap().os_type;
+        let os_type = self.target_host.read().unwrap().os_type;
         if os_type.is_none() {
-            panic!("failed to detect OS type for {}, bailing out", self.host.read().unwrap().name);
+            panic!("failed to detect OS type for {}, bailing out", self.target_host.read().unwrap().name);
         }
         return os_type.unwrap();
     }
@@ -183,6 +319,10 @@ impl Remote {
     // when we need to write a file we need to place it in a particular temp location and then move it
 
     pub fn get_transfer_location(&self, request: &Arc<TaskRequest>) -> Result<(Option<PathBuf>, Option<PathBuf>), Arc<TaskResponse>> {
+        // without sudo on Windows there is nothing to stage, files are written where they belong
+        if self.get_os_type() == HostOSType::Windows {
+            return Ok((None, None));
+        }
         let whoami = match self.get_whoami() {
             Ok(x) => x,
             Err(y) => { return Err(self.response.is_failed(request, &format!("cannot determine current user: {}", y))) }
@@ -232,6 +372,30 @@ impl Remote {
         return Ok(xfer_result);
     }
 
+    // replaces a system file such as /etc/hosts or /etc/fstab.  The new contents are staged next to the file
+    // and renamed over it, which is atomic within a filesystem, so an interrupted run leaves either the old
+    // or the new file and never a partial one.  The ownership and mode of the existing file are kept.
+
+    pub fn replace_file(&self, request: &Arc<TaskRequest>, data: &String, path: &String) -> Result<(), Arc<TaskResponse>> {
+        let mode = self.get_mode(request, path)?;
+        let ownership = self.get_ownership(request, path)?;
+        let staged = format!("{}.jetp-tmp", path);
+        self.write_data(request, data, &staged, |f| {
+            self.set_mode(request, f, &mode.clone().unwrap_or(String::from("0644")), Recurse::No)?;
+            if let Some((owner, group)) = &ownership {
+                self.set_owner(request, f, owner, Recurse::No)?;
+                self.set_group(request, f, group, Recurse::No)?;
+            }
+            return Ok(());
+        })?;
+        let result = self.run(request, &format!("mv -f {} {}", shell_quote(&staged), shell_quote(path)), CheckRc::Checked);
+        if result.is_err() {
+            let _ = self.delete_file(request, &staged);
+            return Err(result.unwrap_err());
+        }
+        return Ok(());
+    }
+
     // copies a file to a remote location
 
     pub fn copy_file<G>(&self, request: &Arc<TaskRequest>, src: &Path, dest: &String, mut before_complete: G) -> Result<(), Arc<TaskResponse>> 
@@ -245,6 +409,32 @@ impl Remote {
         return Ok(xfer_result);
     }
 
+    // downloads a remote file to the control machine.  The file is first copied to a temp location that the
+    // login user can read, which matters when sudo is in use and the original file is not readable to them.
+
+    pub fn fetch_file(&self, request: &Arc<TaskRequest>, src: &String, dest: &Path) -> Result<(), Arc<TaskResponse>> {
+        let (_temp_dir, temp_path) = self.get_transfer_location(request)?;
+        let temp_file = match temp_path {
+            Some(x) => format!("{}", x.display()),
+            None => {
+                // Windows hosts have no temp location, WinRM reads the file as the login user directly
+                self.response.get_visitor().read().expect("read visitor").on_before_fetch(&self.response.get_context(), &Arc::clone(&self.host), src);
+                return self.connection.lock().unwrap().fetch_file(&self.response, request, src, dest);
+            }
+        };
+        self.run(request, &format!("cp {} {}", shell_quote(src), shell_quote(&temp_file)), CheckRc::Checked)?;
+        let result = self.set_mode(request, &temp_file, &String::from("0644"), Recurse::No);
+        if result.is_ok() {
+            self.response.get_visitor().read().expect("read visitor").on_before_fetch(&self.response.get_context(), &Arc::clone(&self.host), src);
+        }
+        let xfer_result = match result {
+            Ok(_) => self.connection.lock().unwrap().fetch_file(&self.response, request, &temp_file, dest),
+            Err(y) => Err(y)
+        };
+        self.delete_file(request, &temp_file)?;
+        return xfer_result;
+    }
+
     // gets the octal string mode of a remote file
 
     pub fn get_mode(&self, request: &Arc<TaskRequest>, path: &String) -> Result<Option<String>,Arc<TaskResponse>> {
@@ -260,6 +450,42 @@ impl Remote {
         }
     }
 
+    // gets the size in bytes of a remote file, or None if it does not exist
+
+    pub fn get_size(&self, request: &Arc<TaskRequest>, path: &String) -> Result<Option<u64>,Arc<TaskResponse>> {
+        let get_cmd_result = crate::tasks::cmd_library::get_size_command(self.get_os_type(), path);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        let result = self.run(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out) = cmd_info(&result);
+        if rc != 0 {
+            return Ok(None);
+        }
+        return match out.trim().parse::<u64>() {
+            Ok(x) => Ok(Some(x)),
+            Err(_) => Err(self.response.is_failed(request, &format!("unexpected size output for {}: {}", path, out)))
+        };
+    }
+
+    // does a remote path exist at all, whatever type it is?
+
+    pub fn get_path_exists(&self, request: &Arc<TaskRequest>, path: &String) -> Result<bool,Arc<TaskResponse>> {
+        let get_cmd_result = crate::tasks::cmd_library::get_path_exists_command(self.get_os_type(), path);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        let result = self.run(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, _out) = cmd_info(&result);
+        return Ok(rc == 0);
+    }
+
+    // is a remote path an existing directory?  Unlike get_is_directory this does not fail when nothing is there
+
+    pub fn get_directory_exists(&self, request: &Arc<TaskRequest>, path: &String) -> Result<bool,Arc<TaskResponse>> {
+        let get_cmd_result = crate::tasks::cmd_library::get_directory_exists_command(self.get_os_type(), path);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        let result = self.run(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, _out) = cmd_info(&result);
+        return Ok(rc == 0);
+    }
+
     // is a remote path a file?
 
     pub fn get_is_file(&self, request: &Arc<TaskRequest>, path: &String) -> Result<bool,Arc<TaskResponse>> {
@@ -292,12 +518,44 @@ impl Remote {
         return self.run(request, &cmd, CheckRc::Checked);  
     }
 
+    // returns where a remote symlink points, or None if the path is missing or not a symlink
+
+    pub fn get_link_target(&self, request: &Arc<TaskRequest>, path: &String) -> Result<Option<String>,Arc<TaskResponse>> {
+        let get_cmd_result = crate::tasks::cmd_library::get_read_link_command(self.get_os_type(), path);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        let result = self.run(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out) = cmd_info(&result);
+        return match rc {
+            0 => Ok(Some(out.trim_end().to_string())),
+            _ => Ok(None)
+        };
+    }
+
+    // creates a symlink at path pointing to target, replacing any link already there
+
+    pub fn create_symlink(&self, request: &Arc<TaskRequest>, target: &String, path: &String) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+        let get_cmd_result = crate::tasks::cmd_library::get_create_link_command(self.get_os_type(), target, path);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        return self.run(request, &cmd, CheckRc::Checked);
+    }
+
     pub fn create_directory(&self, request: &Arc<TaskRequest>, path: &String) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
         let get_cmd_result = crate::tasks::cmd_library::get_create_directory_command(self.get_os_type(), path);
         let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
         return self.run(request, &cmd, CheckRc::Checked);  
     }
 
+    // saves a copy of a remote file next to it, named after the current time, before it is overwritten.
+    // returns the path of the copy.
+
+    pub fn backup_file(&self, request: &Arc<TaskRequest>, path: &String) -> Result<String,Arc<TaskResponse>> {
+        let backup_path = format!("{}.{}~", path, chrono::Local::now().format("%Y%m%d%H%M%S"));
+        let get_cmd_result = crate::tasks::cmd_library::get_backup_file_command(self.get_os_type(), path, &backup_path);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        self.run(request, &cmd, CheckRc::Checked)?;
+        return Ok(backup_path);
+    }
+
     pub fn delete_file(&self, request: &Arc<TaskRequest>, path: &String) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
         let get_cmd_result = crate::tasks::cmd_library::get_delete_file_command(self.get_os_type(), path);
         let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
@@ -394,6 +652,88 @@ impl Remote {
         };
     }
 
+    // used by modules that write file contents (template, copy) to preview changes with --diff.
+    // returns None outside of check mode or when --diff was not requested, so the extra remote
+    // command is skipped.
+
+    pub fn get_content_diff(&self, request: &Arc<TaskRequest>, path: &String, proposed: &[u8]) -> Result<Option<String>,Arc<TaskResponse>> {
+
+        if ! self.is_diff_wanted() {
+            return Ok(None);
+        }
+        if is_binary(proposed) {
+            return Ok(Some(binary_differs(path)));
+        }
+
+        let os_type = self.get_os_type();
+        let get_cmd_result = crate::tasks::cmd_library::get_cat_command(os_type, path);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        // the connection layer fails the command with 500 when it cannot decode the output as text
+        let result = match self.run(request, &cmd, CheckRc::Unchecked) {
+            Ok(x) => x,
+            Err(e) => match e.command_result.as_ref() {
+                Some(x) if x.rc == 500 => { return Ok(Some(binary_differs(path))); },
+                _ => { return Err(e); }
+            }
+        };
+        let (rc, out) = cmd_info(&result);
+        return match rc {
+            0 => match is_binary(out.as_bytes()) {
+                true  => Ok(Some(binary_differs(path))),
+                false => Ok(Some(unified_diff(path, &out, &String::from_utf8_lossy(proposed).to_string())))
+            },
+            _ => Ok(Some(format!("unable to read {} for diff: {}", path, out)))
+        };
+    }
+
+    // as above, for modules that already have both versions of the content in hand (lineinfile)
+
+    pub fn get_text_diff(&self, path: &String, before: &String, after: &String) -> Option<String> {
+        if ! self.is_diff_wanted() {
+            return None;
+        }
+        return Some(unified_diff(path, before, after));
+    }
+
+    fn is_diff_wanted(&self) -> bool {
+        return self.run_state.show_diff && self.run_state.visitor.read().unwrap().is_check_mode();
+    }
+
+    // reads a remote text file, returning None if it does not exist
+
+    pub fn read_file(&self, request: &Arc<TaskRequest>, path: &String) -> Result<Option<String>,Arc<TaskResponse>> {
+        if self.get_mode(request, path)?.is_none() {
+            return Ok(None);
+        }
+        let get_cmd_result = crate::tasks::cmd_library::get_cat_command(self.get_os_type(), path);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        let result = self.run(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out, err) = cmd_info_with_err(&result);
+        return match rc {
+            0 => Ok(Some(out)),
+            _ => Err(self.response.is_failed(request, &format!("unable to read {}: {}", path, err)))
+        };
+    }
+
+    // returns the top level entries of an archive already on the remote system
+
+    pub fn list_archive(&self, request: &Arc<TaskRequest>, path: &String, format: ArchiveFormat) -> Result<Vec<String>,Arc<TaskResponse>> {
+        let get_cmd_result = crate::tasks::cmd_library::get_archive_list_command(self.get_os_type(), path, format);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        let result = self.run(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out, err) = cmd_info_with_err(&result);
+        return match rc {
+            0 => Ok(ArchiveFormat::top_level_entries(&out)),
+            _ => Err(self.response.is_failed(request, &format!("unable to list archive {}: {}", path, err)))
+        };
+    }
+
+    pub fn extract_archive(&self, request: &Arc<TaskRequest>, path: &String, dest: &String, format: ArchiveFormat) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+        let get_cmd_result = crate::tasks::cmd_library::get_extract_command(self.get_os_type(), path, dest, format);
+        let cmd = self.unwrap_string_result(&request, &get_cmd_result)?;
+        return self.run(request, &cmd, CheckRc::Checked);
+    }
+
     // supporting code for any tasks that has an 'attributes' member, see 'template' for one example of usage
     // TODO: add SELinux
 
@@ -490,4 +830,21 @@ impl Remote {
     }
 
 
-}
\ No newline at end of file
+}
+
+// sudo explains itself on lines starting with "sudo:" when it cannot escalate, anything else
+// is output from the command we asked it to run
+
+fn get_sudo_failure(out: &String) -> Option<String> {
+    for line in out.lines() {
+        let line = line.trim();
+        if line.starts_with("sudo:") || line.contains("is not in the sudoers file") {
+            return Some(String::from(line.trim_start_matches("sudo:").trim()));
+        }
+    }
+    return None;
+}
+
+fn shell_quote(s: &String) -> String {
+    return format!("'{}'", s.replace("'", "'\\''"));
+}
@@ -59,7 +59,19 @@ impl Response {
             msg: Some(msg.clone()), 
             command_result: Arc::new(None), 
             with: Arc::new(None), 
-            and: Arc::new(None)
+            and: Arc::new(None), diff: None
+        });
+    }
+
+    pub fn is_failed_with_result(&self, _request: &Arc<TaskRequest>, msg: &String, last: &Arc<TaskResponse>) -> Arc<TaskResponse> {
+        // used when and/until gives up, keeping the command output of the last attempt for the visitor and register
+        return Arc::new(TaskResponse { 
+            status: TaskStatus::Failed, 
+            changes: Vec::new(), 
+            msg: Some(msg.clone()), 
+            command_result: Arc::clone(&last.command_result), 
+            with: Arc::new(None), 
+            and: Arc::new(None), diff: None
         });
     }
 
@@ -78,7 +90,7 @@ impl Response {
             msg: Some(String::from("command failed")), 
             command_result: Arc::clone(&result), 
             with: Arc::new(None), 
-            and: Arc::new(None)
+            and: Arc::new(None), diff: None
         });
     }
 
@@ -87,16 +99,17 @@ impl Response {
         self.get_visitor().read().expect("read visitor").on_command_ok(&self.get_context(), &Arc::clone(&self.host), &Arc::clone(result));
         return Arc::new(TaskResponse {
             status: TaskStatus::IsExecuted,
-            changes: Vec::new(), msg: None, command_result: Arc::clone(&result), with: Arc::new(None), and: Arc::new(None)
+            changes: Vec::new(), msg: None, command_result: Arc::clone(&result), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
 
-    pub fn is_skipped(&self, request: &Arc<TaskRequest>) -> Arc<TaskResponse> {
+    pub fn is_skipped(&self, request: &Arc<TaskRequest>, reason: &String) -> Arc<TaskResponse> {
         // returned by playbook traversal code when skipping over a task due to a condition not being met or other factors
+        // the reason is kept in msg so the visitor can explain the skip
         assert!(request.request_type == TaskRequestType::Validate, "is_skipped response can only be returned for a validation request");
         return Arc::new(TaskResponse { 
             status: TaskStatus::IsSkipped, 
-            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None)
+            changes: Vec::new(), msg: Some(reason.clone()), command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
 
@@ -107,7 +120,7 @@ impl Response {
             "is_matched response can only be returned for a query request, was {:?}", request.request_type);
         return Arc::new(TaskResponse { 
             status: TaskStatus::IsMatched, 
-            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None)
+            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
 
@@ -116,7 +129,16 @@ impl Response {
         assert!(request.request_type == TaskRequestType::Create, "is_executed response can only be returned for a creation request");
         return Arc::new(TaskResponse { 
             status: TaskStatus::IsCreated, 
-            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None)
+            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
+        });
+    }
+
+    pub fn is_created_with_msg(&self, request: &Arc<TaskRequest>, msg: &String) -> Arc<TaskResponse> {
+        // same as is_created, with a short note for the user such as the revision that was deployed
+        assert!(request.request_type == TaskRequestType::Create, "is_created response can only be returned for a creation request");
+        return Arc::new(TaskResponse { 
+            status: TaskStatus::IsCreated, 
+            changes: Vec::new(), msg: Some(msg.clone()), command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
     
@@ -126,7 +148,7 @@ impl Response {
         assert!(request.request_type == TaskRequestType::Execute, "is_executed response can only be returned for a creation request");
         return Arc::new(TaskResponse { 
             status: TaskStatus::IsExecuted, 
-            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None)
+            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
     
@@ -136,7 +158,7 @@ impl Response {
         return Arc::new(TaskResponse { 
             status: TaskStatus::IsRemoved, 
             changes: Vec::new(), 
-            msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None)
+            msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
 
@@ -145,7 +167,7 @@ impl Response {
         assert!(request.request_type == TaskRequestType::Passive || request.request_type == TaskRequestType::Execute, "is_passive response can only be returned for a passive or execute request");
         return Arc::new(TaskResponse { 
             status: TaskStatus::IsPassive, 
-            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None)
+            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
     
@@ -155,7 +177,17 @@ impl Response {
         return Arc::new(TaskResponse { 
             status: TaskStatus::IsModified, 
             changes: changes, 
-            msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None)
+            msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
+        });
+    }
+
+    pub fn is_modified_with_msg(&self, request: &Arc<TaskRequest>, changes: Vec<Field>, msg: &String) -> Arc<TaskResponse> {
+        // same as is_modified, with a short note for the user such as the revision that was deployed
+        assert!(request.request_type == TaskRequestType::Modify, "is_modified response can only be returned for a modification request");
+        return Arc::new(TaskResponse { 
+            status: TaskStatus::IsModified, 
+            changes: changes, 
+            msg: Some(msg.clone()), command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
 
@@ -164,7 +196,7 @@ impl Response {
         assert!(request.request_type == TaskRequestType::Query, "needs_creation response can only be returned for a query request");
         return Arc::new(TaskResponse { 
             status: TaskStatus::NeedsCreation, 
-            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), 
+            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
     
@@ -175,16 +207,27 @@ impl Response {
         return Arc::new(TaskResponse { 
             status: TaskStatus::NeedsModification, 
             changes: changes.clone(), 
-            msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None) 
+            msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None 
         });
     }
     
+    pub fn needs_modification_with_diff(&self, request: &Arc<TaskRequest>, changes: &Vec<Field>, diff: Option<String>) -> Arc<TaskResponse> {
+        // as above, but also carries a preview of content changes for check mode, see --diff
+        assert!(request.request_type == TaskRequestType::Query, "needs_modification response can only be returned for a query request");
+        assert!(!changes.is_empty(), "changes must not be empty");
+        return Arc::new(TaskResponse { 
+            status: TaskStatus::NeedsModification, 
+            changes: changes.clone(), 
+            msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: diff
+        });
+    }
+
     pub fn needs_removal(&self, request: &Arc<TaskRequest>) -> Arc<TaskResponse> {
         // a response from a query function that requests invocation of the removal leg.
         assert!(request.request_type == TaskRequestType::Query, "needs_removal response can only be returned for a query request");
         return Arc::new(TaskResponse { 
             status: TaskStatus::NeedsRemoval, 
-            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None)
+            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
 
@@ -194,7 +237,7 @@ impl Response {
         assert!(request.request_type == TaskRequestType::Query, "needs_execution response can only be returned for a query request");
         return Arc::new(TaskResponse { 
             status: TaskStatus::NeedsExecution, 
-            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None),and: Arc::new(None)
+            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None),and: Arc::new(None), diff: None
         });
     }
     
@@ -203,7 +246,7 @@ impl Response {
         assert!(request.request_type == TaskRequestType::Query, "needs_passive response can only be returned for a query request");
         return Arc::new(TaskResponse { 
             status: TaskStatus::NeedsPassive, 
-            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None)
+            changes: Vec::new(), msg: None, command_result: Arc::new(None), with: Arc::new(None), and: Arc::new(None), diff: None
         });
     }
 
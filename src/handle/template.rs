@@ -226,6 +226,7 @@ impl Template {
     pub fn integer(&self, request: &Arc<TaskRequest>, tm: TemplateMode, field: &String, template: &String)-> Result<u64,Arc<TaskResponse>> {
         // templates a required value that must resolve to an integer
         if tm == TemplateMode::Off {
+            self.check_literal::<u64>(request, field, template, "an integer")?;
             return Ok(0);
         }
         let st = self.string(request, tm, field, template)?;
@@ -240,6 +241,7 @@ impl Template {
     pub fn integer_option(&self, request: &Arc<TaskRequest>, tm: TemplateMode, field: &String, template: &Option<String>, default: Option<u64>) -> Result<Option<u64>,Arc<TaskResponse>> {
         // templates an optional value that must resolve to an integer or None
         if tm == TemplateMode::Off {
+            if let Some(t) = template { self.check_literal::<u64>(request, field, t, "an integer")?; }
             return Ok(None);
         }
         if template.is_none() {
@@ -257,6 +259,7 @@ impl Template {
     pub fn integer_option_to_integer(&self, request: &Arc<TaskRequest>, tm: TemplateMode, field: &String, template: &Option<String>, default: u64) -> Result<u64,Arc<TaskResponse>> {
         // templates an optional value that must resolve to an integer
         if tm == TemplateMode::Off {
+            if let Some(t) = template { self.check_literal::<u64>(request, field, t, "an integer")?; }
             return Ok(0);
         }
         if template.is_none() {
@@ -277,6 +280,7 @@ impl Template {
         // where possible, consider using boolean_option_default_true/false instead
         // jet mostly favors booleans defaulting to false, but it doesn't always make sense
         if tm == TemplateMode::Off {
+            self.check_literal::<bool>(request, field, template, "a boolean")?;
             return Ok(true);
         }
         let st = self.string(request, tm, field, template)?;
@@ -300,6 +304,7 @@ impl Template {
     fn internal_boolean_option(&self, request: &Arc<TaskRequest>, tm: TemplateMode, field: &String, template: &Option<String>, default: bool)-> Result<bool,Arc<TaskResponse>>{
         // supporting code for boolean parsing above
         if tm == TemplateMode::Off {
+            if let Some(t) = template { self.check_literal::<bool>(request, field, t, "a boolean")?; }
             return Ok(false);
         }
         if template.is_none() {
@@ -316,6 +321,7 @@ impl Template {
     pub fn boolean_option_default_none(&self, request: &Arc<TaskRequest>, tm: TemplateMode, field: &String, template: &Option<String>)-> Result<Option<bool>,Arc<TaskResponse>>{
         // supports an optional boolean value that does not default to true or false - effectively making the option a trinary value where None is "no preference"
         if tm == TemplateMode::Off {
+            if let Some(t) = template { self.check_literal::<bool>(request, field, t, "a boolean")?; }
             return Ok(None);
         }
         if template.is_none() {
@@ -329,6 +335,15 @@ impl Template {
         }
     }
 
+    fn check_literal<T: std::str::FromStr>(&self, request: &Arc<TaskRequest>, field: &String, template: &String, kind: &str) -> Result<(), Arc<TaskResponse>> {
+        // nothing is rendered during validation, but a value with no template in it is already what it will be,
+        // so a bad one can be reported before any host runs the task
+        if template.contains("{{") || template.parse::<T>().is_ok() {
+            return Ok(());
+        }
+        return Err(self.response.is_failed(request, &format!("field ({}) value is not {}: {}", field, kind, template)));
+    }
+
     pub fn test_condition(&self, request: &Arc<TaskRequest>, tm: TemplateMode, expr: &String) -> Result<bool, Arc<TaskResponse>> {
         // used to evaluate in-language conditionals throughout the program.
         if tm == TemplateMode::Off {
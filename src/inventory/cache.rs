@@ -0,0 +1,142 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use std::path::{Path,PathBuf};
+use std::time::{SystemTime,UNIX_EPOCH};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt,OpenOptionsExt};
+use serde::{Deserialize,Serialize};
+use crate::tasks::checksum::sha512;
+
+// output from inventory scripts can be kept on disk between runs with --inventory-cache-ttl, which saves
+// querying a cloud API on every invocation while debugging.  Entries are keyed by the path of the source,
+// and are thrown away when they are older than the TTL or when the source file has been modified since.
+// Anything unreadable in the cache directory is treated as a miss and is never an error.
+
+#[derive(Serialize,Deserialize)]
+struct CacheEntry {
+    source: String,
+    mtime: u64,
+    created: u64,
+    output: String,
+}
+
+fn get_cache_dir() -> Option<PathBuf> {
+    if let Ok(xdg) = env::var("XDG_CACHE_HOME") {
+        if ! xdg.is_empty() {
+            return Some(Path::new(&xdg).join("jetp").join("inventory"));
+        }
+    }
+    return match env::var("HOME") {
+        Ok(home) if ! home.is_empty() => Some(Path::new(&home).join(".cache").join("jetp").join("inventory")),
+        _ => None
+    };
+}
+
+fn get_cache_file(source: &Path) -> Option<PathBuf> {
+    let dir = get_cache_dir()?;
+    let absolute = match fs::canonicalize(source) {
+        Ok(x) => x,
+        Err(_) => source.to_path_buf()
+    };
+    let key = sha512(&format!("{}", absolute.display()));
+    return Some(dir.join(format!("{}.json", &key[0..32])));
+}
+
+fn get_mtime(source: &Path) -> Option<u64> {
+    let modified = fs::metadata(source).ok()?.modified().ok()?;
+    return Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs());
+}
+
+fn now() -> u64 {
+    return match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(x) => x.as_secs(),
+        Err(_) => 0
+    };
+}
+
+pub fn flush_inventory_cache() -> Result<(), String> {
+    let dir = match get_cache_dir() {
+        Some(x) => x,
+        None => { return Ok(()); }
+    };
+    if ! dir.exists() {
+        return Ok(());
+    }
+    return match fs::remove_dir_all(&dir) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("unable to flush the inventory cache at {}: {}", dir.display(), e))
+    };
+}
+
+pub fn read_cached_inventory(source: &Path, ttl: u64) -> Option<String> {
+    if ttl == 0 {
+        return None;
+    }
+    let cache_file = get_cache_file(source)?;
+    let contents = fs::read_to_string(&cache_file).ok()?;
+    let entry : CacheEntry = match serde_json::from_str(&contents) {
+        Ok(x) => x,
+        Err(_) => { let _ = fs::remove_file(&cache_file); return None; }
+    };
+    let expected_source = format!("{}", source.display());
+    if ! entry.source.eq(&expected_source) || Some(entry.mtime) != get_mtime(source) {
+        return None;
+    }
+    let age = now().saturating_sub(entry.created);
+    if age >= ttl {
+        return None;
+    }
+    return Some(entry.output);
+}
+
+pub fn write_cached_inventory(source: &Path, ttl: u64, output: &String) {
+    if ttl == 0 {
+        return;
+    }
+    let (cache_file, mtime) = match (get_cache_file(source), get_mtime(source)) {
+        (Some(f), Some(m)) => (f, m),
+        _ => { return; }
+    };
+    let entry = CacheEntry {
+        source: format!("{}", source.display()),
+        mtime: mtime,
+        created: now(),
+        output: output.clone()
+    };
+    let data = match serde_json::to_string(&entry) {
+        Ok(x) => x,
+        Err(_) => { return; }
+    };
+    // inventory variables may hold secrets, so only the current user may read the cache
+    if let Some(parent) = cache_file.parent() {
+        if fs::DirBuilder::new().recursive(true).mode(0o700).create(parent).is_err() {
+            return;
+        }
+    }
+    // written to the side and renamed so a concurrent run never reads half a file
+    let temp_file = cache_file.with_extension(format!("{}.tmp", std::process::id()));
+    let written = match fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(&temp_file) {
+        Ok(mut f) => f.write_all(data.as_bytes()).is_ok(),
+        Err(_) => false
+    };
+    if ! written || fs::rename(&temp_file, &cache_file).is_err() {
+        let _ = fs::remove_file(&temp_file);
+    }
+}
@@ -81,6 +81,22 @@ impl Group {
         return self.get_ancestor_groups(10usize).iter().map(|(k,_v)| k.clone()).collect();
     }
 
+    // how far a group is from the top of the tree along its longest parent chain, 'all' is 0
+
+    pub fn get_depth(&self, depth_limit: usize) -> usize {
+        let mut depth : usize = 0;
+        if depth_limit == 0 {
+            return depth;
+        }
+        for (_k,v) in self.parents.iter() {
+            let parent_depth = v.read().expect("group read").get_depth(depth_limit-1) + 1;
+            if parent_depth > depth {
+                depth = parent_depth;
+            }
+        }
+        return depth;
+    }
+
     pub fn get_descendant_groups(&self, depth_limit: usize) -> HashMap<String, Arc<RwLock<Group>>> {
 
         let mut results : HashMap<String, Arc<RwLock<Group>>> = HashMap::new();
@@ -185,8 +201,8 @@ impl Group {
 
     pub fn get_blended_variables(&self) -> serde_yaml::Mapping {
         let mut blended : serde_yaml::Value = serde_yaml::Value::from(serde_yaml::Mapping::new());
-        let ancestors = self.get_ancestor_groups(20);
-        for (_k,v) in ancestors.iter() {
+        let ancestors = sort_by_precedence(self.get_ancestor_groups(20));
+        for v in ancestors.iter() {
             let theirs : serde_yaml::Value = serde_yaml::Value::from(v.read().expect("group read").get_variables());
             blend_variables(&mut blended, theirs);
         }
@@ -217,10 +233,14 @@ impl Group {
 
 }
 
+// orders groups so that variables can be blended from least to most specific: parents come before
+// their children, and groups at the same depth are ordered by name so results do not change between runs
 
-
-
-
-
-
-
+pub fn sort_by_precedence(groups: HashMap<String, Arc<RwLock<Group>>>) -> Vec<Arc<RwLock<Group>>> {
+    let mut keyed : Vec<(usize, String, Arc<RwLock<Group>>)> = groups.into_iter().map(|(k,v)| {
+        let depth = v.read().expect("group read").get_depth(20usize);
+        (depth, k, v)
+    }).collect();
+    keyed.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+    return keyed.into_iter().map(|(_depth, _name, v)| v).collect();
+}
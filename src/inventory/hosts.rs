@@ -17,7 +17,7 @@
 use std::collections::HashMap;
 use crate::util::yaml::blend_variables;
 use std::sync::Arc;
-use crate::inventory::groups::Group;
+use crate::inventory::groups::{Group,sort_by_precedence};
 use std::sync::RwLock;
 use std::collections::HashSet;
 use serde_yaml;
@@ -26,6 +26,7 @@ use serde_yaml;
 pub enum HostOSType {
     Linux,
     MacOS,
+    Windows,
 }
 
 #[derive(Clone,Copy,Debug)]
@@ -41,6 +42,8 @@ pub struct Host {
     pub groups             : HashMap<String, Arc<RwLock<Group>>>,
     pub variables          : serde_yaml::Mapping,
     pub os_type            : Option<HostOSType>,
+    // position in inventory, for --order inventory
+    pub inventory_order    : usize,
     checksum_cache         : HashMap<String,String>,
     checksum_cache_task_id : usize,
     facts                  : serde_yaml::Value,
@@ -56,6 +59,7 @@ impl Host {
             variables : serde_yaml::Mapping::new(),
             groups: HashMap::new(),
             os_type: None,
+            inventory_order: 0,
             checksum_cache: HashMap::new(),
             checksum_cache_task_id: 0,
             facts: serde_yaml::Value::from(serde_yaml::Mapping::new()),
@@ -81,6 +85,10 @@ impl Host {
         }
     }
 
+    pub fn clear_notified(&mut self, play_number: usize) {
+        self.notified_handlers.remove(&play_number);
+    }
+
     pub fn set_checksum_cache(&mut self, path: &String, checksum: &String) {
         self.checksum_cache.insert(path.clone(), checksum.clone());
     }
@@ -186,8 +194,8 @@ impl Host {
 
     pub fn get_blended_variables(&self) -> serde_yaml::Mapping {
         let mut blended : serde_yaml::Value = serde_yaml::Value::from(serde_yaml::Mapping::new());
-        let ancestors = self.get_ancestor_groups(20);
-        for (_k,v) in ancestors.iter() {
+        let ancestors = sort_by_precedence(self.get_ancestor_groups(20));
+        for v in ancestors.iter() {
             let theirs : serde_yaml::Value = serde_yaml::Value::from(v.read().unwrap().get_variables());
             blend_variables(&mut blended, theirs);
         }
@@ -217,6 +225,14 @@ impl Host {
         }
     }
 
+    pub fn get_facts_yaml(&self) -> Result<String, String> {
+        let result = serde_yaml::to_string(&self.facts);
+        return match result {
+            Ok(x) => Ok(x),
+            Err(_y) => Err(String::from("error loading facts"))
+        }
+    }
+
     pub fn get_blended_variables_yaml(&self) -> Result<String,String> {
         let result = serde_yaml::to_string(&self.get_blended_variables());
         return match result {
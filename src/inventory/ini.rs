@@ -0,0 +1,273 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::RwLock;
+use crate::inventory::inventory::Inventory;
+use crate::util::io::read_local_file;
+
+// INI style inventory files, as used by other tools, for example:
+//
+//   db1.example.com
+//
+//   [web]
+//   web[01:10].example.com http_port=80
+//
+//   [web:vars]
+//   color=blue
+//
+//   [datacenter:children]
+//   web
+//
+// hosts listed before any section go in 'all'.  Values are read the same way as YAML scalars,
+// so numbers and booleans keep their types, and quotes may be used for values with spaces.
+
+enum Section {
+    Hosts(String),
+    Vars(String),
+    Children(String)
+}
+
+pub fn load_ini_inventory(inventory: &Arc<RwLock<Inventory>>, path: &Path) -> Result<(), String> {
+    let contents = read_local_file(path)?;
+    let mut inventory = inventory.write().unwrap();
+    let mut section = Section::Hosts(String::from("all"));
+
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line = raw_line.trim();
+        let error = |msg: String| { format!("{}, line {}: {}", path.display(), index + 1, msg) };
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            if ! line.ends_with(']') {
+                return Err(error(format!("unterminated section header: {}", line)));
+            }
+            let header = line[1 .. line.len() - 1].trim();
+            section = match header.split_once(':') {
+                None => Section::Hosts(String::from(header)),
+                Some((group, "vars")) => Section::Vars(String::from(group)),
+                Some((group, "children")) => Section::Children(String::from(group)),
+                Some((_, kind)) => { return Err(error(format!("unknown section type '{}', expecting vars or children", kind))); }
+            };
+            match &section {
+                Section::Hosts(group) | Section::Vars(group) | Section::Children(group) => {
+                    if group.is_empty() || group.contains(char::is_whitespace) {
+                        return Err(error(format!("invalid group name '{}'", group)));
+                    }
+                    inventory.store_group(group);
+                }
+            }
+            continue;
+        }
+
+        match &section {
+            Section::Hosts(group) => {
+                let tokens = split_tokens(line).map_err(|e| error(e))?;
+                let variables = parse_assignments(&tokens[1..]).map_err(|e| error(e))?;
+                for host_name in expand_host_ranges(&tokens[0]).map_err(|e| error(e))? {
+                    inventory.store_host(group, &host_name);
+                    inventory.get_host(&host_name).write().unwrap().update_variables(variables.clone());
+                }
+            },
+            Section::Vars(group) => {
+                let tokens = split_tokens(line).map_err(|e| error(e))?;
+                // spaces around '=' are allowed in vars sections, so rejoin before splitting on it
+                let variables = parse_assignments(&[tokens.join(" ")]).map_err(|e| error(e))?;
+                inventory.get_group(group).write().unwrap().update_variables(variables);
+            },
+            Section::Children(group) => {
+                if line.contains(char::is_whitespace) {
+                    return Err(error(format!("invalid group name '{}'", line)));
+                }
+                if ! group.eq(line) {
+                    inventory.store_subgroup(group, &String::from(line));
+                }
+            }
+        }
+    }
+    return Ok(());
+}
+
+// splits a line on whitespace, keeping quoted parts together and removing the quotes
+
+pub fn split_tokens(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens : Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut quote : Option<char> = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => { quote = None; },
+            Some(_) => { current.push(c); },
+            None if c == '"' || c == '\'' => { quote = Some(c); },
+            None if c.is_whitespace() => {
+                if ! current.is_empty() {
+                    tokens.push(current.clone());
+                    current.clear();
+                }
+            },
+            None => { current.push(c); }
+        }
+    }
+    if quote.is_some() {
+        return Err(String::from("unterminated quote"));
+    }
+    if ! current.is_empty() {
+        tokens.push(current);
+    }
+    return Ok(tokens);
+}
+
+fn parse_assignments(tokens: &[String]) -> Result<serde_yaml::Mapping, String> {
+    let mut mapping = serde_yaml::Mapping::new();
+    for token in tokens.iter() {
+        let (key, value) = match token.split_once('=') {
+            Some((k, v)) => (k.trim(), v.trim()),
+            None => { return Err(format!("expecting key=value, got '{}'", token)); }
+        };
+        if key.is_empty() {
+            return Err(format!("missing variable name in '{}'", token));
+        }
+        let parsed : serde_yaml::Value = match serde_yaml::from_str(value) {
+            Ok(serde_yaml::Value::Null) if ! value.is_empty() => serde_yaml::Value::String(String::from(value)),
+            Ok(x @ serde_yaml::Value::Bool(_)) | Ok(x @ serde_yaml::Value::Number(_)) => x,
+            _ => serde_yaml::Value::String(String::from(value))
+        };
+        mapping.insert(serde_yaml::Value::String(String::from(key)), parsed);
+    }
+    return Ok(mapping);
+}
+
+// expands patterns like web[01:10] (keeping the zero padding), db[a:c], or node[0:10:2]
+
+fn expand_host_ranges(pattern: &String) -> Result<Vec<String>, String> {
+    let start = match pattern.find('[') {
+        Some(x) => x,
+        None => { return Ok(vec![pattern.clone()]); }
+    };
+    let end = match pattern[start..].find(']') {
+        Some(x) => start + x,
+        None => { return Err(format!("unterminated host range in '{}'", pattern)); }
+    };
+    let (prefix, range, suffix) = (&pattern[..start], &pattern[start + 1 .. end], &pattern[end + 1 ..]);
+    let parts : Vec<&str> = range.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return Err(format!("invalid host range '[{}]', expecting [start:end] or [start:end:step]", range));
+    }
+    let step = match parts.get(2) {
+        Some(x) => match x.parse::<usize>() {
+            Ok(s) if s > 0 => s,
+            _ => { return Err(format!("invalid step in host range '[{}]'", range)); }
+        },
+        None => 1
+    };
+
+    let mut middles : Vec<String> = Vec::new();
+    match (parts[0].parse::<u64>(), parts[1].parse::<u64>()) {
+        (Ok(first), Ok(last)) => {
+            if first > last {
+                return Err(format!("host range '[{}]' runs backwards", range));
+            }
+            let width = match parts[0].starts_with('0') && parts[0].len() > 1 { true => parts[0].len(), false => 0 };
+            for n in (first ..= last).step_by(step) {
+                middles.push(format!("{:0width$}", n, width = width));
+            }
+        },
+        _ => {
+            let (a, b) = (parts[0].chars().collect::<Vec<char>>(), parts[1].chars().collect::<Vec<char>>());
+            if a.len() != 1 || b.len() != 1 || ! a[0].is_ascii_alphabetic() || ! b[0].is_ascii_alphabetic() || a[0] > b[0] {
+                return Err(format!("invalid host range '[{}]'", range));
+            }
+            for c in (a[0] ..= b[0]).step_by(step) {
+                middles.push(c.to_string());
+            }
+        }
+    }
+
+    // later ranges in the same name are expanded recursively
+    let mut results : Vec<String> = Vec::new();
+    for middle in middles.iter() {
+        for rest in expand_host_ranges(&String::from(suffix))? {
+            results.push(format!("{}{}{}", prefix, middle, rest));
+        }
+    }
+    return Ok(results);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(pattern: &str) -> Result<Vec<String>, String> {
+        return expand_host_ranges(&String::from(pattern));
+    }
+
+    #[test]
+    fn test_numeric_ranges() {
+        assert_eq!(expand("web[01:03].example.com").unwrap(), vec!["web01.example.com", "web02.example.com", "web03.example.com"]);
+        assert_eq!(expand("node[0:10:5]").unwrap(), vec!["node0", "node5", "node10"]);
+        assert_eq!(expand("db").unwrap(), vec!["db"]);
+        assert_eq!(expand("r[1:2]c[1:2]").unwrap(), vec!["r1c1", "r1c2", "r2c1", "r2c2"]);
+    }
+
+    #[test]
+    fn test_alpha_ranges() {
+        assert_eq!(expand("db[a:c]").unwrap(), vec!["dba", "dbb", "dbc"]);
+        assert_eq!(expand("db[a:e:2]").unwrap(), vec!["dba", "dbc", "dbe"]);
+    }
+
+    #[test]
+    fn test_bad_ranges() {
+        assert!(expand("web[01:03").is_err());
+        assert!(expand("web[3:1]").is_err());
+        assert!(expand("web[1]").is_err());
+        assert!(expand("web[1:2:3:4]").is_err());
+        assert!(expand("web[1:3:0]").is_err());
+        assert!(expand("web[c:a]").is_err());
+        assert!(expand("web[aa:b]").is_err());
+    }
+
+    #[test]
+    fn test_split_tokens() {
+        assert_eq!(split_tokens("web1  a=1 b='two words' c=\"x y\"").unwrap(), vec!["web1", "a=1", "b=two words", "c=x y"]);
+        assert!(split_tokens("web1 a='open").is_err());
+    }
+
+    #[test]
+    fn test_parse_assignments() {
+        let tokens = split_tokens("port=80 debug=true name=web ratio=0.5 empty=").unwrap();
+        let mapping = parse_assignments(&tokens).unwrap();
+        assert_eq!(mapping.get("port"), Some(&serde_yaml::Value::from(80)));
+        assert_eq!(mapping.get("debug"), Some(&serde_yaml::Value::Bool(true)));
+        assert_eq!(mapping.get("name"), Some(&serde_yaml::Value::from("web")));
+        assert_eq!(mapping.get("ratio"), Some(&serde_yaml::Value::from(0.5)));
+        assert_eq!(mapping.get("empty"), Some(&serde_yaml::Value::from("")));
+        assert!(parse_assignments(&[String::from("novalue")]).is_err());
+        assert!(parse_assignments(&[String::from("=1")]).is_err());
+    }
+
+    #[test]
+    fn test_vars_spacing() {
+        // vars sections rejoin the tokens so spaces around '=' are allowed
+        let tokens = split_tokens("color = \"dark blue\"").unwrap();
+        let mapping = parse_assignments(&[tokens.join(" ")]).unwrap();
+        assert_eq!(mapping.get("color"), Some(&serde_yaml::Value::from("dark blue")));
+    }
+
+}
@@ -59,7 +59,7 @@ impl Inventory {
     // ==============================================================================================================
 
     pub fn store_subgroup(&mut self, group_name: &String, subgroup_name: &String) {
-        if self.has_group(group_name) { self.create_group(group_name); }
+        if !self.has_group(group_name) { self.create_group(group_name); }
         if !self.has_group(subgroup_name) { self.create_group(subgroup_name); }
         self.associate_subgroup(group_name, subgroup_name);
     }
@@ -96,7 +96,9 @@ impl Inventory {
 
     pub fn create_host(&mut self, host_name: &String) {
         assert!(!self.has_host(host_name));
-        self.hosts.insert(host_name.clone(), Arc::new(RwLock::new(Host::new(&host_name.clone()))));
+        let mut host = Host::new(&host_name.clone());
+        host.inventory_order = self.hosts.len();
+        self.hosts.insert(host_name.clone(), Arc::new(RwLock::new(host)));
     }
 
     pub fn store_host(&mut self, group_name: &String, host_name: &String) {
@@ -17,15 +17,18 @@
 use std::path::{Path,PathBuf};
 use Vec;
 use serde::Deserialize;
+use crate::inventory::cache::{flush_inventory_cache,read_cached_inventory,write_cached_inventory};
+use crate::inventory::ini::load_ini_inventory;
 use crate::util::io::{path_walk,jet_file_open,path_basename_as_string,is_executable};
 use crate::util::yaml::show_yaml_error_in_context;
+use crate::util::vault::load_vars_file;
 use crate::inventory::inventory::Inventory;
 use std::sync::Arc;
 use std::sync::RwLock;
 use serde_json;
 use std::collections::HashMap;
 use std::process::Command;
-use crate::connection::local::convert_out;
+use crate::connection::local::convert_bytes;
 use crate::util::io::directory_as_string;
 
 // ==============================================================================================================
@@ -41,11 +44,9 @@ pub struct YamlGroup {
     subgroups : Option<Vec<String>>,
 }
 
-#[derive(Debug,Deserialize)]
-#[serde(deny_unknown_fields)]
-pub enum DynamicInventoryJson {
-    Entry(HashMap<String, DynamicInventoryJsonEntry>)
-}
+// for executable inventory scripts, which print a JSON object of group names to entries like:
+// { "web": { "hosts": [ "w1", "w2" ], "vars": { "x": 1 }, "children": [ "web_east" ] },
+//   "_meta": { "hostvars": { "w1": { "y": 2 } } } }
 
 /* groups named _meta are not real groups */
 #[derive(Debug,Deserialize)]
@@ -61,14 +62,26 @@ pub struct DynamicInventoryJsonEntry {
 // PUBLIC API
 // ==============================================================================================================
 
-pub fn load_inventory(inventory: &Arc<RwLock<Inventory>>, inventory_paths: Arc<RwLock<Vec<PathBuf>>>) -> Result<(), String> {
+// several inventory sources may be given, they are loaded one after the other into the same inventory.  Groups
+// and their hosts add up, and where two sources set the same variable on a host or group the later source
+// wins.  Those overrides are listed at verbosity > 0.
+
+pub fn load_inventory(inventory: &Arc<RwLock<Inventory>>, inventory_paths: Arc<RwLock<Vec<PathBuf>>>, cache_ttl: u64, flush_cache: bool, verbosity: i32) -> Result<(), String> {
+
+    if flush_cache {
+        flush_inventory_cache()?;
+    }
 
     {
         let mut inv_obj = inventory.write().unwrap();
         inv_obj.store_group(&String::from("all"));
     }
 
-    for inventory_path_buf in inventory_paths.read().unwrap().iter() {
+    for (index, inventory_path_buf) in inventory_paths.read().unwrap().iter().enumerate() {
+        let before = match index {
+            0 => None,
+            _ => Some(snapshot_variables(inventory))
+        };
         let inventory_path = inventory_path_buf.as_path();
         if inventory_path.is_dir() {
             let groups_pathbuf      = inventory_path_buf.join("groups");
@@ -80,14 +93,19 @@ pub fn load_inventory(inventory: &Arc<RwLock<Inventory>>, inventory_paths: Arc<R
                 return Err(format!("missing groups/ in --inventory path parameter ({})", inventory_path.display()))
             }
         } else {
-            if is_executable(&inventory_path) {
-                load_dynamic_inventory(inventory, &inventory_path)?;
-                let dirname = directory_as_string(&inventory_path);
-                let dir = Path::new(&dirname);
-                load_on_disk_inventory_tree(inventory, false, &dir)?;
-            } else {
-                return Err(format!("non-directory path to --inventory ({}) is not executable", inventory_path.display()))
-            }    
+            // executable files are inventory scripts, anything else is read as an INI file
+            match is_executable(&inventory_path) {
+                true  => load_dynamic_inventory(inventory, &inventory_path, cache_ttl)?,
+                false => load_ini_inventory(inventory, &inventory_path)?
+            };
+            let dirname = directory_as_string(&inventory_path);
+            let dir = Path::new(&dirname);
+            load_on_disk_inventory_tree(inventory, false, &dir)?;
+        }
+        if verbosity > 0 && before.is_some() {
+            for conflict in find_overrides(&before.unwrap(), &snapshot_variables(inventory)).iter() {
+                println!("inventory: {} overridden by {}", conflict, inventory_path.display());
+            }
         }
     }
     return Ok(())
@@ -97,6 +115,42 @@ pub fn load_inventory(inventory: &Arc<RwLock<Inventory>>, inventory_paths: Arc<R
 // PRIVATE INTERNALS
 // ==============================================================================================================
 
+// the variables of every host and group, keyed by "host name" or "group name", for find_overrides
+
+fn snapshot_variables(inventory: &Arc<RwLock<Inventory>>) -> HashMap<String, serde_yaml::Mapping> {
+    let inv = inventory.read().unwrap();
+    let mut results : HashMap<String, serde_yaml::Mapping> = HashMap::new();
+    for (name, host) in inv.hosts.iter() {
+        results.insert(format!("host {}", name), host.read().unwrap().get_variables());
+    }
+    for (name, group) in inv.groups.iter() {
+        results.insert(format!("group {}", name), group.read().unwrap().get_variables());
+    }
+    return results;
+}
+
+// variables that had a value before a source was loaded and a different one after, sorted
+
+fn find_overrides(before: &HashMap<String, serde_yaml::Mapping>, after: &HashMap<String, serde_yaml::Mapping>) -> Vec<String> {
+    let mut results : Vec<String> = Vec::new();
+    for (owner, old_vars) in before.iter() {
+        let new_vars = match after.get(owner) {
+            Some(x) => x,
+            None => { continue; }
+        };
+        for (k, v) in old_vars.iter() {
+            match new_vars.get(k) {
+                Some(x) if x != v => {
+                    results.push(format!("{}: variable {}", owner, k.as_str().unwrap_or("?")));
+                },
+                _ => {}
+            }
+        }
+    }
+    results.sort();
+    return results;
+}
+
 // loads an entire on-disk inventory tree structure (groups/, group_vars/, host_vars/)
 fn load_on_disk_inventory_tree(inventory: &Arc<RwLock<Inventory>>, include_groups: bool, path: &Path) -> Result<(), String> {
     let path_buf           = PathBuf::from(path);
@@ -195,25 +249,20 @@ fn load_vars_directory(inventory: &Arc<RwLock<Inventory>>, path: &Path, is_group
             }
         }
         
-        let file = jet_file_open(&vars_path)?;
-        let file_parse_result: Result<serde_yaml::Mapping, serde_yaml::Error> = serde_yaml::from_reader(file);
-        if file_parse_result.is_err() {
-             show_yaml_error_in_context(&file_parse_result.unwrap_err(), &vars_path);
-             return Err(format!("edit the file and try again?"));
-        } 
-        let yaml_result = file_parse_result.unwrap();
+        let yaml_result = load_vars_file(&vars_path)?;
         
         // serialize the vars again just to make them easier to store/output elsewhere
         // this will also remove any comments and shorten things up
         //let yaml_string = &serde_yaml::to_string(&yaml_result).unwrap();
+        // merged rather than replaced, so vars from INI files and inventory scripts are kept
         match is_group {
             true  => {
                 let group = inv.get_group(&effective_name.clone());
-                group.write().unwrap().set_variables(yaml_result);
+                group.write().unwrap().update_variables(yaml_result);
             }
             false => {
                 let host = inv.get_host(&effective_name);
-                host.write().unwrap().set_variables(yaml_result);
+                host.write().unwrap().update_variables(yaml_result);
             }
         }
         Ok(())
@@ -221,36 +270,54 @@ fn load_vars_directory(inventory: &Arc<RwLock<Inventory>>, path: &Path, is_group
     Ok(())
 }
 
-// TODO: implement
-fn load_dynamic_inventory(inv: &Arc<RwLock<Inventory>>, path: &Path) -> Result<(), String> {
-
-    let mut inventory = inv.write().unwrap();
-
+// runs an executable inventory source and returns what it prints.  Only standard output is used, and
+// anything the script says on standard error is only shown if it fails.
+fn run_inventory_script(path: &Path) -> Result<String, String> {
     let mut command = Command::new(format!("{}", path.display()));
-    let output = match command.output() {
+    return match command.output() {
         Ok(x) => {
             match x.status.code() {
-                Some(_rc) => convert_out(&x.stdout,&x.stderr),
-                None => { return Err(format!("unable to get status code from process: {}", path.display())) }
+                Some(0) => Ok(convert_bytes(&x.stdout)),
+                Some(rc) => Err(format!("inventory script failed: {}, rc={}: {}", path.display(), rc, convert_bytes(&x.stderr).trim())),
+                None => Err(format!("unable to get status code from process: {}", path.display()))
             }
         },
-        Err(y) => { return Err(format!("inventory script failed: {}, {}", path.display(), y)); }
+        Err(y) => Err(format!("inventory script failed: {}, {}", path.display(), y))
+    };
+}
+
+// loads the JSON an inventory script prints.  The script is run again every time inventory is loaded
+// unless --inventory-cache-ttl allows a recent copy of its output to be used instead.
+fn load_dynamic_inventory(inv: &Arc<RwLock<Inventory>>, path: &Path, cache_ttl: u64) -> Result<(), String> {
+
+    let mut inventory = inv.write().unwrap();
+
+    let output = match read_cached_inventory(path, cache_ttl) {
+        Some(x) => x,
+        None => run_inventory_script(path)?
     };
 
     let file_parse_result: Result<HashMap<String, DynamicInventoryJsonEntry>, serde_json::Error> = serde_json::from_str(&output);
     if file_parse_result.is_err() {
-       return Err(format!("error parsing dynamic inventory source: {:?}: {:?}", path.display(), &file_parse_result.unwrap_err()));
+       return Err(format!("error parsing dynamic inventory source: {}: {}", path.display(), &file_parse_result.unwrap_err()));
     } 
     let json_result = file_parse_result.unwrap();
+    // only output that parsed is worth keeping
+    write_cached_inventory(path, cache_ttl, &output);
 
-    for (possible_group_name, entry) in json_result.iter() {
-        let group_name = match possible_group_name.eq("_meta") {
+    // _meta.hostvars only adds variables to hosts, so it is read after the groups have placed them
+    let mut names : Vec<&String> = json_result.keys().filter(|x| ! x.starts_with("_")).collect();
+    if json_result.contains_key("_meta") {
+        names.push(json_result.get_key_value("_meta").unwrap().0);
+    }
+
+    for possible_group_name in names {
+        let entry = json_result.get(possible_group_name).unwrap();
+        let is_meta = possible_group_name.eq("_meta");
+        let group_name = match is_meta {
             true => String::from("all"),
             false => possible_group_name.clone(),
         };
-        if group_name.starts_with("_") {
-            continue;
-        }
         
         inventory.store_group(&group_name);
         let group = inventory.get_group(&group_name);
@@ -258,7 +325,9 @@ fn load_dynamic_inventory(inv: &Arc<RwLock<Inventory>>, path: &Path) -> Result<(
         if entry.hostvars.is_some() {
             let hostvars = entry.hostvars.as_ref().unwrap();
             for (host_name, values) in hostvars.iter() {
-                inventory.store_host(&group_name, &host_name);
+                if ! is_meta || ! inventory.has_host(&host_name) {
+                    inventory.store_host(&group_name, &host_name);
+                }
                 let host = inventory.get_host(&host_name);
                 let vars = convert_json_vars(&values);
                 let mut hst = host.write().unwrap();
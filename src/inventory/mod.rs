@@ -14,7 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // long with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod cache;
 pub mod groups;
 pub mod hosts;
+pub mod ini;
 pub mod loading;
 pub mod inventory;
+pub mod pattern;
@@ -0,0 +1,209 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::inventory::hosts::Host;
+
+// host patterns are used by --limit to select a subset of inventory without editing it.
+// a pattern is a list of terms separated by ',' or ':'.  Each term is a host name, a group
+// name, or a glob of either using '*' and '?'.  Terms are combined like so:
+//
+//    web:db          hosts in web or db
+//    web:&prod       hosts in web that are also in prod
+//    web:!web-3      hosts in web except web-3
+//
+// a term of '@path' stands for the host names listed in that file, one per line, such as
+// the .retry file written at the end of a run with failures.  It can be combined like any other
+// term, so '@site.retry:!web-3' retries every failed host but web-3.
+
+#[derive(Debug,Clone,PartialEq)]
+enum PatternOp {
+    Union,
+    Intersection,
+    Exclusion
+}
+
+#[derive(Debug,Clone)]
+pub struct HostPattern {
+    pattern: String,
+    // each term is one name, or the names read from an @file, any of which may match
+    terms: Vec<(PatternOp, Vec<String>)>
+}
+
+impl HostPattern {
+
+    pub fn new(pattern: &String) -> Result<Self, String> {
+        let mut terms : Vec<(PatternOp, Vec<String>)> = Vec::new();
+        for term in pattern.split(|c| c == ',' || c == ':') {
+            let term = term.trim();
+            let (op, name) = match term.chars().next() {
+                Some('&') => (PatternOp::Intersection, &term[1..]),
+                Some('!') => (PatternOp::Exclusion, &term[1..]),
+                _ => (PatternOp::Union, term)
+            };
+            if name.is_empty() {
+                return Err(format!("invalid host pattern: {}", pattern));
+            }
+            match name.strip_prefix("@") {
+                Some(path) => { terms.push((op, read_host_file(path)?)); },
+                None => { terms.push((op, vec![String::from(name)])); }
+            }
+        }
+        return Ok(Self { pattern: pattern.clone(), terms: terms });
+    }
+
+    pub fn as_str(&self) -> &str {
+        return self.pattern.as_str();
+    }
+
+    pub fn matches(&self, host: &Host) -> bool {
+        let mut has_union = false;
+        let mut in_union = false;
+        for (op, names) in self.terms.iter() {
+            let hit = names.iter().any(|name| term_matches(name, host));
+            match op {
+                PatternOp::Union        => { has_union = true; if hit { in_union = true; } },
+                PatternOp::Intersection => { if ! hit { return false; } },
+                PatternOp::Exclusion    => { if hit { return false; } }
+            }
+        }
+        // a pattern of only intersections or exclusions starts from every host
+        return in_union || ! has_union;
+    }
+}
+
+fn read_host_file(path: &str) -> Result<Vec<String>, String> {
+    return match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(contents.lines().map(|x| x.trim()).filter(|x| ! x.is_empty() && ! x.starts_with("#")).map(|x| String::from(x)).collect()),
+        Err(e) => Err(format!("cannot read host list {}: {}", path, e))
+    };
+}
+
+fn term_matches(term: &String, host: &Host) -> bool {
+    if glob_match(term.as_bytes(), host.name.as_bytes()) {
+        return true;
+    }
+    for group_name in host.get_ancestor_group_names().iter() {
+        if glob_match(term.as_bytes(), group_name.as_bytes()) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// minimal glob support: '*' matches any run of characters and '?' matches exactly one
+
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star : Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == text[t]) {
+            p = p + 1;
+            t = t + 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p = p + 1;
+        } else if let Some((sp, st)) = star {
+            // backtrack, letting the last '*' swallow one more character
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    while p < pattern.len() && pattern[p] == b'*' {
+        p = p + 1;
+    }
+    return p == pattern.len();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::groups::Group;
+    use std::sync::{Arc,RwLock};
+
+    fn host(name: &str, groups: Vec<&str>) -> Host {
+        let mut host = Host::new(&String::from(name));
+        for group in groups {
+            host.add_group(&String::from(group), Arc::new(RwLock::new(Group::new(&String::from(group)))));
+        }
+        return host;
+    }
+
+    fn matches(pattern: &str, host: &Host) -> bool {
+        return HostPattern::new(&String::from(pattern)).unwrap().matches(host);
+    }
+
+    #[test]
+    fn test_names_and_groups() {
+        let web1 = host("web-1", vec!["web", "prod"]);
+        let db1 = host("db-1", vec!["db"]);
+        assert!(matches("web-1", &web1));
+        assert!(! matches("web-1", &db1));
+        assert!(matches("web", &web1));
+        assert!(matches("web:db", &db1));
+        assert!(matches("web,db", &web1));
+    }
+
+    #[test]
+    fn test_globs() {
+        let web1 = host("web-1", vec!["web"]);
+        assert!(matches("web-*", &web1));
+        assert!(matches("w?b-1", &web1));
+        assert!(matches("*", &web1));
+        assert!(! matches("web-?2", &web1));
+        assert!(! matches("db*", &web1));
+    }
+
+    #[test]
+    fn test_intersection_and_exclusion() {
+        let web1 = host("web-1", vec!["web", "prod"]);
+        let web2 = host("web-2", vec!["web"]);
+        assert!(matches("web:&prod", &web1));
+        assert!(! matches("web:&prod", &web2));
+        assert!(! matches("web:!web-1", &web1));
+        assert!(matches("web:!web-1", &web2));
+        // without a union term the pattern starts from every host
+        assert!(matches("!web-1", &web2));
+        assert!(matches("&prod", &web1));
+    }
+
+    #[test]
+    fn test_host_file() {
+        let path = std::env::temp_dir().join(format!("jetp-pattern-{}.retry", std::process::id()));
+        std::fs::write(&path, "# failed hosts\nweb-1\n\ndb-1\n").unwrap();
+        let web1 = host("web-1", vec!["web"]);
+        let web2 = host("web-2", vec!["web"]);
+        let db1 = host("db-1", vec!["db"]);
+        let file = format!("@{}", path.display());
+        assert!(matches(&file, &web1));
+        assert!(matches(&file, &db1));
+        assert!(! matches(&file, &web2));
+        assert!(! matches(&format!("{}:!db-1", file), &db1));
+        assert!(matches(&format!("web:&{}", file), &web1));
+        assert!(! matches(&format!("web:&{}", file), &web2));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(HostPattern::new(&String::from("web:")).is_err());
+        assert!(HostPattern::new(&String::from("web,!")).is_err());
+        assert!(HostPattern::new(&String::from("@/nonexistent/jetp.retry")).is_err());
+    }
+
+}
@@ -28,8 +28,9 @@ use crate::util::io::{quit};
 use crate::inventory::inventory::Inventory;
 use crate::inventory::loading::{load_inventory};
 use crate::cli::show::{show_inventory_group,show_inventory_host};
+use crate::cli::vault::handle_vault;
 use crate::cli::parser::{CliParser};
-use crate::cli::playbooks::{playbook_ssh,playbook_local,playbook_check_ssh,playbook_check_local,playbook_simulate}; // FIXME: check modes coming
+use crate::cli::playbooks::{playbook_ssh,playbook_local,playbook_check_ssh,playbook_check_local,playbook_simulate,check_connections,list_facts_ssh,list_facts_local}; // FIXME: check modes coming
 use std::sync::{Arc,RwLock};
 use std::process;
 
@@ -41,6 +42,7 @@ fn liftoff() -> Result<(),String> {
 
     let mut cli_parser = CliParser::new();
     cli_parser.parse()?;
+    util::terminal::configure_color(cli_parser.no_color);
 
     // jetp --help was given, or no arguments
     if cli_parser.needs_help {
@@ -52,11 +54,19 @@ fn liftoff() -> Result<(),String> {
         return Ok(());
     }
 
+    // vault modes only work on the files they are given, no inventory or playbook is involved
+    match cli_parser.mode {
+        cli::parser::CLI_MODE_ENCRYPT | cli::parser::CLI_MODE_DECRYPT | cli::parser::CLI_MODE_REKEY => {
+            return handle_vault(&cli_parser);
+        },
+        _ => {}
+    };
+
     let inventory : Arc<RwLock<Inventory>> = Arc::new(RwLock::new(Inventory::new()));
 
     match cli_parser.mode {
-        cli::parser::CLI_MODE_SSH | cli::parser::CLI_MODE_CHECK_SSH | cli::parser::CLI_MODE_SHOW | cli::parser::CLI_MODE_SIMULATE => {
-            load_inventory(&inventory, Arc::clone(&cli_parser.inventory_paths))?;
+        cli::parser::CLI_MODE_SSH | cli::parser::CLI_MODE_CHECK_SSH | cli::parser::CLI_MODE_SHOW | cli::parser::CLI_MODE_SIMULATE | cli::parser::CLI_MODE_CHECK_CONNECTIONS => {
+            load_inventory(&inventory, Arc::clone(&cli_parser.inventory_paths), cli_parser.inventory_cache_ttl, cli_parser.flush_cache, cli_parser.verbosity)?;
             if ! cli_parser.inventory_set {
                 return Err(String::from("--inventory is required"));
             }
@@ -70,9 +80,9 @@ fn liftoff() -> Result<(),String> {
     };
 
     match cli_parser.mode {
-        cli::parser::CLI_MODE_SHOW => {},
+        cli::parser::CLI_MODE_SHOW | cli::parser::CLI_MODE_CHECK_CONNECTIONS => {},
         _ => {
-            if ! cli_parser.playbook_set {
+            if ! cli_parser.playbook_set && cli_parser.list_facts.is_none() {
                 return Err(String::from("--playbook is required"));
             }
         }
@@ -82,7 +92,14 @@ fn liftoff() -> Result<(),String> {
         rayon::ThreadPoolBuilder::new().num_threads(cli_parser.threads).build_global().expect("build global");
     };
 
-    let exit_status = match cli_parser.mode {
+    // --list-facts replaces the playbook run for whichever mode was chosen
+    let exit_status = if cli_parser.list_facts.is_some() {
+        match cli_parser.mode {
+            cli::parser::CLI_MODE_SSH | cli::parser::CLI_MODE_CHECK_SSH     => list_facts_ssh(&inventory, &cli_parser),
+            cli::parser::CLI_MODE_LOCAL | cli::parser::CLI_MODE_CHECK_LOCAL => list_facts_local(&inventory, &cli_parser),
+            _ => { println!("--list-facts is only supported in the ssh and local modes"); 1 }
+        }
+    } else { match cli_parser.mode {
         cli::parser::CLI_MODE_SHOW   => match handle_show(&inventory, &cli_parser) {
             Ok(_) => 0,
             Err(s) => {
@@ -95,9 +112,10 @@ fn liftoff() -> Result<(),String> {
         cli::parser::CLI_MODE_LOCAL       => playbook_local(&inventory, &cli_parser),
         cli::parser::CLI_MODE_CHECK_LOCAL => playbook_check_local(&inventory, &cli_parser),
         cli::parser::CLI_MODE_SIMULATE    => playbook_simulate(&inventory, &cli_parser),
+        cli::parser::CLI_MODE_CHECK_CONNECTIONS => check_connections(&inventory, &cli_parser),
 
         _ => { println!("invalid CLI mode"); 1 }
-    };
+    }};
     if exit_status != 0 {
         process::exit(exit_status);
     }
@@ -39,6 +39,9 @@ pub struct UserTask {
     pub create_user_group: Option<String>,
     pub gecos:             Option<String>,
     pub shell:             Option<String>,
+    pub home:              Option<String>,
+    pub authorized_keys:   Option<Vec<String>>,
+    pub state:             Option<String>,
     pub remove:            Option<String>,
     pub cleanup:           Option<String>,
     pub with:              Option<PreLogicInput>,
@@ -56,6 +59,8 @@ struct UserAction {
     pub create_user_group: bool,
     pub gecos:             Option<String>,
     pub shell:             Option<String>,
+    pub home:              Option<String>,
+    pub authorized_keys:   Option<Vec<String>>,
     pub remove:            bool,
     pub cleanup:           bool,
 }
@@ -66,6 +71,7 @@ struct UserDetails {
     gid:        Option<String>,
     groups:     Option<HashSet<String>>,
     gecos:      Option<String>,
+    home:       Option<String>,
     shell:      Option<String>,
 }
 
@@ -77,6 +83,32 @@ impl IsTask for UserTask {
 
     fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
 
+        // 'state: absent' is another way to say 'remove: true'
+        let mut remove = handle.template.boolean_option_default_false(&request, tm, &String::from("remove"), &self.remove)?;
+        let state = handle.template.string_option_no_spaces(request, tm, &String::from("state"), &self.state)?;
+        if tm == TemplateMode::Strict && state.is_some() {
+            if self.remove.is_some() {
+                return Err(handle.response.is_failed(request, &String::from("state cannot be combined with remove")));
+            }
+            remove = match state.as_ref().unwrap().as_str() {
+                "present" => false,
+                "absent"  => true,
+                x => { return Err(handle.response.is_failed(request, &format!("field (state): expecting present or absent, got '{}'", x))); }
+            };
+        }
+
+        // keys are written to a file and never pass through a shell, and contain characters such as '='
+        let authorized_keys = match &self.authorized_keys {
+            Some(keys) => {
+                let mut templated_keys : Vec<String> = Vec::new();
+                for key in keys.iter() {
+                    templated_keys.push(handle.template.string_unsafe_for_shell(request, tm, &String::from("authorized_keys"), key)?.trim().to_string());
+                }
+                Some(templated_keys)
+            },
+            None => None
+        };
+
         return Ok(
             EvaluatedTask {
                 action: Arc::new(UserAction {
@@ -101,7 +133,9 @@ impl IsTask for UserTask {
                     create_user_group: handle.template.boolean_option_default_true(&request, tm, &String::from("create_user_group"), &self.create_user_group)?,
                     gecos:             handle.template.string_option(request, tm, &String::from("gecos"), &self.gecos)?,
                     shell:             handle.template.string_option(request, tm, &String::from("shell"), &self.shell)?,
-                    remove:            handle.template.boolean_option_default_false(&request, tm, &String::from("remove"), &self.remove)?,
+                    home:              handle.template.string_option_no_spaces(request, tm, &String::from("home"), &self.home)?,
+                    authorized_keys:   authorized_keys,
+                    remove:            remove,
                     cleanup:           handle.template.boolean_option_default_false(&request, tm, &String::from("cleanup"), &self.cleanup)?,
                 }),
                 with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
@@ -138,7 +172,9 @@ impl IsAction for UserAction {
                         if UserAction::string_wants_change(&self.gid, &actual.gid) { changes.push(Field::Gid); }
                         if UserAction::string_wants_change(&self.gecos, &actual.gecos) { changes.push(Field::Gecos); }
                         if UserAction::string_wants_change(&self.shell, &actual.shell){ changes.push(Field::Shell); }
+                        if UserAction::string_wants_change(&self.home, &actual.home) { changes.push(Field::Home); }
                         if self.groups_wants_change(&actual) { changes.push(Field::Groups); }
+                        if self.authorized_keys_want_change(handle, request, &actual)? { changes.push(Field::AuthorizedKeys); }
 
                         match changes.len() {
                             0 => return Ok(handle.response.is_matched(request)),
@@ -151,13 +187,24 @@ impl IsAction for UserAction {
             TaskRequestType::Create => {
                 let cmd = self.create_user_command();
                 handle.remote.run(request, &cmd, CheckRc::Checked)?;
+                if self.authorized_keys.is_some() {
+                    let actual: UserDetails = self.get_user_details(handle, request)?;
+                    self.install_authorized_keys(handle, request, &actual)?;
+                }
                 return Ok(handle.response.is_created(request));
             },
 
             TaskRequestType::Modify => {
                 let actual: UserDetails = self.get_user_details(handle, request)?;
-                let cmd = self.modify_user_command(&actual);
-                handle.remote.run(request, &cmd, CheckRc::Checked)?;
+                if request.changes.iter().any(|x| *x != Field::AuthorizedKeys) {
+                    let cmd = self.modify_user_command(&actual);
+                    handle.remote.run(request, &cmd, CheckRc::Checked)?;
+                }
+                if request.changes.contains(&Field::AuthorizedKeys) {
+                    // the home directory may have just moved
+                    let actual: UserDetails = self.get_user_details(handle, request)?;
+                    self.install_authorized_keys(handle, request, &actual)?;
+                }
                 return Ok(handle.response.is_modified(request, request.changes.clone()));
             },
 
@@ -207,6 +254,7 @@ impl UserAction {
                     gid:        None,
                     groups:     None,
                     gecos:      None,
+                    home:       None,
                     shell:      None,
                 })
             }
@@ -220,6 +268,7 @@ impl UserAction {
                         gid:    gid,
                         groups: groups,
                         gecos:  Some(items[4].to_string()),
+                        home:   Some(items[5].to_string()),
                         shell:  Some(items[6].to_string()),
                     })
             }
@@ -267,6 +316,9 @@ impl UserAction {
         if self.shell.is_some() {
             cmd.push_str(&format!(" -s '{}'", self.shell.as_ref().unwrap()));
         }
+        if self.home.is_some() {
+            cmd.push_str(&format!(" -d '{}'", self.home.as_ref().unwrap()));
+        }
 
         cmd.push_str(&format!(" '{}'", self.user));
         return cmd;
@@ -287,6 +339,10 @@ impl UserAction {
         if self.shell.is_some() {
             cmd.push_str(&format!(" -s '{}'", self.shell.as_ref().unwrap()));
         }
+        if self.home.is_some() {
+            // -m moves the contents of the old home directory along with it
+            cmd.push_str(&format!(" -d '{}' -m", self.home.as_ref().unwrap()));
+        }
 
         if self.groups.is_some() {
             match self.append {
@@ -327,6 +383,55 @@ impl UserAction {
         }
     }
 
+    fn get_authorized_keys_path(&self, actual: &UserDetails) -> String {
+        let home = match &self.home {
+            Some(x) => x.clone(),
+            None => actual.home.clone().unwrap()
+        };
+        return format!("{}/.ssh/authorized_keys", home.trim_end_matches('/'));
+    }
+
+    fn get_current_authorized_keys(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, path: &String) -> Result<Vec<String>,Arc<TaskResponse>> {
+        // a missing file just means there are no keys yet
+        let cmd = format!("cat '{}'", path);
+        let result = handle.remote.run(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out) = cmd_info(&result);
+        if rc != 0 {
+            return Ok(Vec::new());
+        }
+        return Ok(out.lines().map(|x| x.trim().to_string()).filter(|x| ! x.is_empty()).collect());
+    }
+
+    fn authorized_keys_want_change(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, actual: &UserDetails) -> Result<bool,Arc<TaskResponse>> {
+        // keys listed in the task must be present, other keys already in the file are left alone
+        if self.authorized_keys.is_none() {
+            return Ok(false);
+        }
+        let path = self.get_authorized_keys_path(actual);
+        let current = self.get_current_authorized_keys(handle, request, &path)?;
+        return Ok(self.authorized_keys.as_ref().unwrap().iter().any(|key| ! current.contains(key)));
+    }
+
+    fn install_authorized_keys(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, actual: &UserDetails) -> Result<(),Arc<TaskResponse>> {
+        let path = self.get_authorized_keys_path(actual);
+        let ssh_dir = path.trim_end_matches("/authorized_keys").to_string();
+        let mut keys = self.get_current_authorized_keys(handle, request, &path)?;
+        for key in self.authorized_keys.as_ref().unwrap().iter() {
+            if ! keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        let mut data = keys.join("\n");
+        data.push_str("\n");
+        handle.remote.run(request, &format!("mkdir -p '{}'", ssh_dir), CheckRc::Checked)?;
+        handle.remote.write_data(request, &data, &path, |_f| { Ok(()) })?;
+        // sshd ignores keys that other users could have written
+        handle.remote.run(request, &format!("chown -R '{}:' '{}'", self.user, ssh_dir), CheckRc::Checked)?;
+        handle.remote.run(request, &format!("chmod 700 '{}'", ssh_dir), CheckRc::Checked)?;
+        handle.remote.run(request, &format!("chmod 600 '{}'", path), CheckRc::Checked)?;
+        return Ok(());
+    }
+
     fn get_user_gid_command(&self) -> String {
         // returns a string containing the primary group name.
         return format!("id -gn '{}'", self.user);
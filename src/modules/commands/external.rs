@@ -100,8 +100,10 @@ impl IsAction for ExternalAction {
                 let (_tmp_path1, tmp_file1) = handle.remote.get_transfer_location(request)?;
                 let (_tmp_path2, tmp_file2) = handle.remote.get_transfer_location(request)?;
 
-                let module_tmp_file = tmp_file1.as_ref().unwrap();
-                let param_tmp_file = tmp_file2.as_ref().unwrap();
+                let (module_tmp_file, param_tmp_file) = match (tmp_file1.as_ref(), tmp_file2.as_ref()) {
+                    (Some(x), Some(y)) => (x, y),
+                    _ => { return Err(handle.response.is_failed(request, &String::from("external modules are not supported on Windows"))); }
+                };
                 let module_str_path = module_tmp_file.as_path().display().to_string();
                 let param_str_path = param_tmp_file.as_path().display().to_string();
 
@@ -118,13 +120,19 @@ impl IsAction for ExternalAction {
                 handle.remote.run(request, &chmod, CheckRc::Checked)?;
 
                 let module_run = format!("{} < {}", module_str_path.clone(), param_str_path.clone());
-                let task_result = handle.remote.run_unsafe(request, &module_run, CheckRc::Checked)?;
-                let (rc, out) = cmd_info(&task_result);
+                // the return code is left unchecked so that failed_when can overrule it
+                let task_result = handle.remote.run_unsafe(request, &module_run, CheckRc::Unchecked)?;
+                let (rc, out, err) = cmd_info_with_err(&task_result);
 
                 handle.remote.delete_file(request, &param_str_path.clone())?;
                 handle.remote.delete_file(request, &module_str_path.clone())?;
 
-                let map_data = build_results_map(handle, request, rc, &out)?;
+                let map_data = match build_results_map(handle, request, rc, &out, &err) {
+                    Ok(x) => x,
+                    // a module that crashed will not have printed a result, report the command instead
+                    Err(_) if rc != 0 => { return Err(handle.response.command_failed(request, &Arc::clone(&task_result.command_result))); },
+                    Err(y) => { return Err(y); }
+                };
 
                 let should_fail = match self.failed_when.is_none() {
                     true => match rc { 0 => false, _ => true },
@@ -163,13 +171,14 @@ impl IsAction for ExternalAction {
 
 }
 
-fn build_results_map(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, rc: i32, out: &String) -> Result<serde_yaml::Mapping, Arc<TaskResponse>> {
+fn build_results_map(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, rc: i32, out: &String, err: &String) -> Result<serde_yaml::Mapping, Arc<TaskResponse>> {
     let mut result = serde_yaml::Mapping::new();
     let data : serde_yaml::Value = match serde_yaml::from_str(out) {
         Ok(x) => x,
         Err(_y) => { return Err(handle.response.is_failed(request, &format!("failed to parse external module response: {}", out))) },
     };
     result.insert(serde_yaml::Value::String(String::from("rc")), rc.into());
+    result.insert(serde_yaml::Value::String(String::from("err")), serde_yaml::Value::String(err.clone()));
     match data {
         serde_yaml::Value::Mapping(data_map) => {
             for (k,v) in data_map.iter() {
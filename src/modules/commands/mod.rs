@@ -18,3 +18,4 @@
 
 pub mod external;
 pub mod shell;
+pub mod win_command;
@@ -16,7 +16,6 @@
 
 use crate::tasks::*;
 use crate::handle::handle::TaskHandle;
-use crate::connection::command::cmd_info;
 use serde::{Deserialize};
 use std::sync::{Arc,RwLock};
 use crate::inventory::hosts::Host;
@@ -31,6 +30,9 @@ pub struct ShellTask {
     pub save: Option<String>, 
     pub failed_when: Option<String>, 
     pub changed_when: Option<String>, 
+    pub creates: Option<String>,
+    pub removes: Option<String>,
+    pub chdir: Option<String>,
     #[serde(rename = "unsafe")]
     pub unsafe_: Option<String>, /* FIXME: can use r#unsafe instead */
     pub with: Option<PreLogicInput>,
@@ -41,6 +43,9 @@ struct ShellAction {
     pub save: Option<String>, 
     pub failed_when: Option<String>,
     pub changed_when: Option<String>,
+    pub creates: Option<String>,
+    pub removes: Option<String>,
+    pub chdir: Option<String>,
     pub unsafe_: bool,
 }
 
@@ -68,6 +73,9 @@ impl IsTask for ShellTask {
                     save: handle.template.string_option_no_spaces(&request, tm, &String::from("save"), &self.save)?,
                     failed_when: handle.template.string_option_unsafe_for_shell(&request, tm, &String::from("failed_when"), &self.failed_when)?,
                     changed_when: handle.template.string_option_unsafe_for_shell(&request, tm, &String::from("changed_when"), &self.changed_when)?,
+                    creates: handle.template.string_option(&request, tm, &String::from("creates"), &self.creates)?,
+                    removes: handle.template.string_option(&request, tm, &String::from("removes"), &self.removes)?,
+                    chdir: handle.template.string_option(&request, tm, &String::from("chdir"), &self.chdir)?,
 
                 }),
                 with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
@@ -85,18 +93,29 @@ impl IsAction for ShellAction {
         match request.request_type {
 
             TaskRequestType::Query => {
+                // a missing working directory is reported here, in check mode too, rather than as a shell error later
+                if self.chdir.is_some() && ! handle.remote.get_directory_exists(request, self.chdir.as_ref().unwrap())? {
+                    return Err(handle.response.is_failed(&request, &format!("chdir: directory does not exist: {}", self.chdir.as_ref().unwrap())));
+                }
+                // creates/removes let a command stand in for a resource: skip it if its result is already there
+                if self.creates.is_some() && handle.remote.get_path_exists(request, self.creates.as_ref().unwrap())? {
+                    return Ok(handle.response.is_matched(&request));
+                }
+                if self.removes.is_some() && ! handle.remote.get_path_exists(request, self.removes.as_ref().unwrap())? {
+                    return Ok(handle.response.is_matched(&request));
+                }
                 return Ok(handle.response.needs_execution(&request));
             },
 
             TaskRequestType::Execute => {
                 let task_result : Arc<TaskResponse>;
                 if self.unsafe_ {
-                    task_result = handle.remote.run_unsafe(&request, &self.cmd.clone(), CheckRc::Unchecked)?;
+                    task_result = handle.remote.run_unsafe_in(&request, &self.cmd.clone(), &self.chdir, CheckRc::Unchecked)?;
                 } else {
-                    task_result = handle.remote.run(&request, &self.cmd.clone(), CheckRc::Unchecked)?;
+                    task_result = handle.remote.run_in(&request, &self.cmd.clone(), &self.chdir, CheckRc::Unchecked)?;
                 }
-                let (rc, out) = cmd_info(&task_result);
-                let map_data = build_results_map(rc, &out);
+                let (rc, out, err) = cmd_info_with_err(&task_result);
+                let map_data = build_results_map(rc, &out, &err);
 
                 let should_fail = match self.failed_when.is_none() {
                     true => match rc { 0 => false, _ => true },
@@ -135,13 +154,14 @@ impl IsAction for ShellAction {
 
 }
 
-fn build_results_map(rc: i32, out: &String) -> serde_yaml::Mapping {
+fn build_results_map(rc: i32, out: &String, err: &String) -> serde_yaml::Mapping {
     let mut result = serde_yaml::Mapping::new();
     let num : serde_yaml::Value = serde_yaml::from_str(&format!("{}", rc)).unwrap();
     result.insert(serde_yaml::Value::String(String::from("rc")), num);
     //result.insert(serde_yaml::Value::String(String::from("rc")),  serde_yaml::Value::String(format!("{}", rc)));
 
     result.insert(serde_yaml::Value::String(String::from("out")), serde_yaml::Value::String(out.clone()));
+    result.insert(serde_yaml::Value::String(String::from("err")), serde_yaml::Value::String(err.clone()));
     return result;
 }
 
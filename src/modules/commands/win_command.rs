@@ -0,0 +1,117 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::{TaskHandle,CheckRc};
+use crate::inventory::hosts::HostOSType;
+use serde::{Deserialize};
+use std::sync::Arc;
+
+const MODULE: &str = "win_command";
+
+// runs a PowerShell command on a Windows host reached with the winrm connection type.
+// the command is never given to sh, so the shell module's screening does not apply.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WinCommandTask {
+    pub name: Option<String>,
+    pub cmd: String,
+    pub chdir: Option<String>,
+    pub failed_when: Option<String>, 
+    pub changed_when: Option<String>, 
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>,
+}
+struct WinCommandAction {
+    pub cmd: String,
+    pub chdir: Option<String>,
+    pub failed_when: Option<String>,
+    pub changed_when: Option<String>,
+}
+
+impl IsTask for WinCommandTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(WinCommandAction {
+                    cmd:  handle.template.string_unsafe_for_shell(&request, tm, &String::from("cmd"), &self.cmd)?,
+                    chdir: handle.template.string_option(&request, tm, &String::from("chdir"), &self.chdir)?,
+                    failed_when: handle.template.string_option_unsafe_for_shell(&request, tm, &String::from("failed_when"), &self.failed_when)?,
+                    changed_when: handle.template.string_option_unsafe_for_shell(&request, tm, &String::from("changed_when"), &self.changed_when)?,
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl IsAction for WinCommandAction {
+    
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+    
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                if handle.remote.get_os_type() != HostOSType::Windows {
+                    return Err(handle.response.is_failed(&request, &String::from("win_command requires a Windows host, use the winrm connection type")));
+                }
+                if self.chdir.is_some() && ! handle.remote.get_directory_exists(request, self.chdir.as_ref().unwrap())? {
+                    return Err(handle.response.is_failed(&request, &format!("chdir: directory does not exist: {}", self.chdir.as_ref().unwrap())));
+                }
+                return Ok(handle.response.needs_execution(&request));
+            },
+
+            TaskRequestType::Execute => {
+                let task_result = handle.remote.run_unsafe_in(&request, &self.cmd, &self.chdir, CheckRc::Unchecked)?;
+                let (rc, out, err) = cmd_info_with_err(&task_result);
+                let mut map_data = serde_yaml::Mapping::new();
+                map_data.insert(serde_yaml::Value::String(String::from("rc")), rc.into());
+                map_data.insert(serde_yaml::Value::String(String::from("out")), serde_yaml::Value::String(out));
+                map_data.insert(serde_yaml::Value::String(String::from("err")), serde_yaml::Value::String(err));
+
+                let should_fail = match self.failed_when.as_ref() {
+                    None => rc != 0,
+                    Some(condition) => handle.template.test_condition_with_extra_data(request, TemplateMode::Strict, condition, &handle.host, map_data.clone())?
+                };
+                let should_mark_changed = match self.changed_when.as_ref() {
+                    None => true,
+                    Some(condition) => handle.template.test_condition_with_extra_data(request, TemplateMode::Strict, condition, &handle.host, map_data)?
+                };
+
+                return match should_fail {
+                    true => Err(handle.response.command_failed(request, &Arc::clone(&task_result.command_result))),
+                    false => match should_mark_changed {
+                        true => Ok(task_result),
+                        false => Ok(handle.response.is_passive(request))
+                    }
+                };
+            },
+    
+            _ => { return Err(handle.response.not_supported(&request)); }
+    
+        }
+    }
+
+}
@@ -39,12 +39,8 @@ pub struct AssertTask {
 struct AssertAction {
     pub name: String,
     pub msg: Option<String>,
-    pub r#true: bool,
-    pub r#false: bool,
-    pub all_true: Vec<bool>,
-    pub all_false: Vec<bool>,
-    pub some_true: Vec<bool>
-
+    // the first expression that did not hold, described as it was written in the task
+    pub failure: Option<String>
 }
 
 impl IsTask for AssertTask {
@@ -59,26 +55,7 @@ impl IsTask for AssertTask {
                 action: Arc::new(AssertAction {
                     name: self.name.clone().unwrap_or(String::from(MODULE)),
                     msg: handle.template.string_option_unsafe_for_shell(request, tm, &String::from("msg"), &self.msg)?,
-                    r#true: match self.r#true.is_some() {
-                            true => handle.template.test_condition(request, tm, &self.r#true.as_ref().unwrap())?,
-                            false => true
-                    },
-                    r#false: match self.r#false.is_some() {
-                            true => handle.template.test_condition(request, tm, &self.r#false.as_ref().unwrap())?,
-                            false => false
-                    },
-                    all_true: match self.all_true.is_some() {
-                        true => eval_list(handle, request, tm, self.all_true.as_ref().unwrap())?,
-                        false => vec![true]
-                    },
-                    all_false: match self.all_false.is_some() {
-                        true => eval_list(handle, request, tm, self.all_false.as_ref().unwrap())?,
-                        false => vec![false]
-                    },
-                    some_true: match self.some_true.is_some() {
-                        true => eval_list(handle, request, tm, self.some_true.as_ref().unwrap())?,
-                        false => vec![true]
-                    }
+                    failure: self.find_failure(handle, request, tm)?
                 }),
                 with: Arc::new(PreLogicInput::template(handle, request, tm, &self.with)?),
                 and: Arc::new(PostLogicInput::template(handle, request, tm, &self.and)?),
@@ -87,12 +64,50 @@ impl IsTask for AssertTask {
     }
 }
 
-fn eval_list(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode, list: &Vec<String>) -> Result<Vec<bool>,Arc<TaskResponse>> {
-    let mut results : Vec<bool> = Vec::new();
-    for item in list.iter() {
-        results.push(handle.template.test_condition(request, tm, item)?);
+impl AssertTask {
+
+    // checks every kind of assertion in the order they are documented and describes the first one that fails
+
+    fn find_failure(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<Option<String>,Arc<TaskResponse>> {
+        if let Some(expr) = self.r#true.as_ref() {
+            if ! handle.template.test_condition(request, tm, expr)? {
+                return Ok(Some(format!("true: {}", expr)));
+            }
+        }
+        if let Some(expr) = self.r#false.as_ref() {
+            if handle.template.test_condition(request, tm, expr)? {
+                return Ok(Some(format!("false: {}", expr)));
+            }
+        }
+        if let Some(list) = self.all_true.as_ref() {
+            for expr in list.iter() {
+                if ! handle.template.test_condition(request, tm, expr)? {
+                    return Ok(Some(format!("all_true: {}", expr)));
+                }
+            }
+        }
+        if let Some(list) = self.all_false.as_ref() {
+            for expr in list.iter() {
+                if handle.template.test_condition(request, tm, expr)? {
+                    return Ok(Some(format!("all_false: {}", expr)));
+                }
+            }
+        }
+        if let Some(list) = self.some_true.as_ref() {
+            let mut any = false;
+            for expr in list.iter() {
+                if handle.template.test_condition(request, tm, expr)? {
+                    any = true;
+                    break;
+                }
+            }
+            if ! any {
+                return Ok(Some(format!("some_true: none of {}", list.join(", "))));
+            }
+        }
+        return Ok(None);
     }
-    return Ok(results);
+
 }
 
 impl IsAction for AssertAction {
@@ -101,35 +116,13 @@ impl IsAction for AssertAction {
 
         match request.request_type {
 
+            // assertions change nothing, so the query is the whole task and behaves the same in check mode
             TaskRequestType::Query => {
-                return Ok(handle.response.needs_passive(request));
-            },
-
-            TaskRequestType::Passive => {
-                let mut fail = false;
-                if self.r#true == false {
-                    fail = true;
-                }
-                else if self.r#false == true {
-                    fail = true; 
-                }
-                else if self.all_true.contains(&false) {
-                    fail = true;
-                }
-                else if self.all_false.contains(&true) {
-                    fail = true;
-                } 
-                else if ! self.some_true.contains(&true) {
-                    fail = true;
-                }
-                if fail {
-                    if self.msg.is_some() {
-                        return Err(handle.response.is_failed(request, &format!("assertion failed: {}", self.msg.as_ref().unwrap())));
-                    } else {
-                        return Err(handle.response.is_failed(request, &format!("assertion failed")));
-                    }
-                }
-                return Ok(handle.response.is_passive(request));
+                return match (self.failure.as_ref(), self.msg.as_ref()) {
+                    (None, _)              => Ok(handle.response.is_matched(request)),
+                    (Some(f), Some(msg))   => Err(handle.response.is_failed(request, &format!("assertion failed: {} ({})", msg, f))),
+                    (Some(f), None)        => Err(handle.response.is_failed(request, &format!("assertion failed: {}", f)))
+                };
             },
 
             _ => { return Err(handle.response.not_supported(request)); }
@@ -138,4 +131,4 @@ impl IsAction for AssertAction {
 
     }
 
-}
\ No newline at end of file
+}
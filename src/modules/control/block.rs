@@ -0,0 +1,49 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use crate::registry::list::Task;
+use serde::Deserialize;
+use std::sync::Arc;
+
+const MODULE: &str = "block";
+
+// a block groups tasks for error handling.  When a task in 'tasks' fails for a host, that host runs
+// the 'rescue' tasks and, if they succeed, is no longer considered failed.  'always' runs for every
+// host that entered the block.  Blocks are not executed like other modules, traversal.rs walks them.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BlockTask {
+    pub name: Option<String>,
+    pub tasks: Vec<Task>,
+    pub rescue: Option<Vec<Task>>,
+    pub always: Option<Vec<Task>>
+}
+
+impl IsTask for BlockTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { None }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, _tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        return Err(handle.response.is_failed(request, &String::from("blocks are expanded by the playbook traversal and cannot be evaluated directly")));
+    }
+
+}
@@ -26,7 +26,9 @@ const MODULE: &str = "debug";
 #[serde(deny_unknown_fields)]
 pub struct DebugTask {
     pub name: Option<String>,
+    pub msg: Option<String>,
     pub vars: Option<Vec<String>>,
+    pub verbosity: Option<String>,
     pub with: Option<PreLogicInput>,
     pub and: Option<PostLogicInput>
 }
@@ -34,7 +36,9 @@ pub struct DebugTask {
 #[allow(dead_code)]
 struct DebugAction {
     pub name: String,
+    pub msg: Option<String>,
     pub vars: Option<Vec<String>>,
+    pub verbosity: u64,
 }
 
 impl IsTask for DebugTask {
@@ -44,11 +48,16 @@ impl IsTask for DebugTask {
     fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
 
     fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        if self.msg.is_some() && self.vars.is_some() {
+            return Err(handle.response.is_failed(request, &String::from("msg and vars are mutually exclusive")));
+        }
         return Ok(
             EvaluatedTask {
                 action: Arc::new(DebugAction {
-                    name: self.name.clone().unwrap_or(String::from(MODULE)),
-                    vars: self.vars.clone()
+                    name:      self.name.clone().unwrap_or(String::from(MODULE)),
+                    msg:       handle.template.string_option_unsafe_for_shell(request, tm, &String::from("msg"), &self.msg)?,
+                    vars:      self.vars.clone(),
+                    verbosity: handle.template.integer_option_to_integer(request, tm, &String::from("verbosity"), &self.verbosity, 0)?
                 }),
                 with: Arc::new(PreLogicInput::template(handle, request, tm, &self.with)?),
                 and: Arc::new(PostLogicInput::template(handle, request, tm, &self.and)?),
@@ -68,6 +77,15 @@ impl IsAction for DebugAction {
             },
 
             TaskRequestType::Passive => {
+                // with 'verbosity', nothing is shown unless the run was given at least that many -v flags
+                let verbosity = handle.run_state.context.read().unwrap().verbosity;
+                if (verbosity as u64) < self.verbosity {
+                    return Ok(handle.response.is_passive(request));
+                }
+                if self.msg.is_some() {
+                    handle.debug(request, self.msg.as_ref().unwrap());
+                    return Ok(handle.response.is_passive(request));
+                }
                 let mut map : serde_yaml::Mapping = serde_yaml::Mapping::new();
                 let no_vars = self.vars.is_none();
                 let blended = handle.run_state.context.read().unwrap().get_complete_blended_variables(&handle.host, BlendTarget::NotTemplateModule);
@@ -87,9 +87,12 @@ impl FactsAction {
         match os_type {
             Some(HostOSType::Linux)   => { self.do_linux_facts(handle, request, &facts)?   },
             Some(HostOSType::MacOS)   => { self.do_mac_facts(handle, request, &facts)?     },
+            Some(HostOSType::Windows) => { self.do_windows_facts(handle, request, &facts)? },
             None => { return Err(handle.response.is_failed(request, &String::from("facts not implemented for OS Type"))) }
         };
         self.do_arch(handle, request, &facts)?;
+        self.do_memory(handle, request, &facts)?;
+        self.do_ipv4_addresses(handle, request, &facts)?;
         if self.facter {
             self.do_facter(handle, request, &facts)?;
         }
@@ -118,9 +121,31 @@ impl FactsAction {
         return Ok(());
     }
 
+    fn do_windows_facts(&self, _handle: &Arc<TaskHandle>, _request: &Arc<TaskRequest>, mapping: &Arc<RwLock<serde_yaml::Mapping>>) -> Result<(), Arc<TaskResponse>> {
+        self.insert_string(mapping, &String::from("jet_os_type"), &String::from("Windows"));
+        self.insert_string(mapping, &String::from("jet_os_flavor"), &String::from("Windows"));
+        return Ok(());
+    }
+
     fn do_linux_facts(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, mapping: &Arc<RwLock<serde_yaml::Mapping>>) -> Result<(), Arc<TaskResponse>> {
         self.insert_string(mapping, &String::from("jet_os_type"), &String::from("Linux"));
         self.do_linux_os_release(handle, request, mapping)?;
+        self.do_linux_firewall(handle, request, mapping)?;
+        return Ok(());
+    }
+
+    fn do_linux_firewall(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, mapping: &Arc<RwLock<serde_yaml::Mapping>>) -> Result<(), Arc<TaskResponse>> {
+        // jet_firewall is 'firewalld' when firewalld is running, as it owns the nftables rules then, otherwise
+        // 'nftables' if the nft tool is installed, or 'none'.  The firewall module picks its backend from this.
+        let result = handle.remote.run(request, &String::from("firewall-cmd --state"), CheckRc::Unchecked)?;
+        let (rc, out) = cmd_info(&result);
+        if rc == 0 && out.trim().eq("running") {
+            self.insert_string(mapping, &String::from("jet_firewall"), &String::from("firewalld"));
+            return Ok(());
+        }
+        let result = handle.remote.run(request, &String::from("command -v nft"), CheckRc::Unchecked)?;
+        let (rc, _out) = cmd_info(&result);
+        self.insert_string(mapping, &String::from("jet_firewall"), &String::from(match rc { 0 => "nftables", _ => "none" }));
         return Ok(());
     }
 
@@ -150,12 +175,18 @@ impl FactsAction {
                         self.insert_string(mapping, &String::from("jet_os_flavor"), &String::from("Debian"))
                     } else if v1.find("arch").is_some() {
                         self.insert_string(mapping, &String::from("jet_os_flavor"), &String::from("Arch"))
+                    } else if v1.find("suse").is_some() {
+                        self.insert_string(mapping, &String::from("jet_os_flavor"), &String::from("Suse"))
                     }
                 }
-                // if /etc/os-release does not have ID_LIKE line, like Archlinux
-                if k1.eq("id") {
+                // if /etc/os-release does not have ID_LIKE line, like Archlinux, Debian, or Fedora
+                if k1.eq("id") && ! mapping.read().unwrap().contains_key("jet_os_flavor") {
                     if v1.find("arch").is_some() {
                         self.insert_string(mapping, &String::from("jet_os_flavor"), &String::from("Arch"));
+                    } else if v1.eq("debian") {
+                        self.insert_string(mapping, &String::from("jet_os_flavor"), &String::from("Debian"));
+                    } else if v1.eq("fedora") {
+                        self.insert_string(mapping, &String::from("jet_os_flavor"), &String::from("EL"));
                     }
                 }
             }
@@ -173,12 +204,67 @@ impl FactsAction {
             Ok(x) => x,
             Err(_) => { return Err(handle.response.is_failed(request, &format!("unable to determine arch command for {:?}", os_type))) },
         };
-        let result = handle.remote.run(request, &cmd, CheckRc::Checked)?;
+        // commands from the command library take no user input, and the PowerShell ones would not pass shell screening
+        let result = handle.remote.run_unsafe(request, &cmd, CheckRc::Checked)?;
         let (_rc, out) = cmd_info(&result);
         self.insert_string(mapping, &String::from("jet_arch"), &String::from(out));
         return Ok(());
     }
 
+    fn do_memory(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, mapping: &Arc<RwLock<serde_yaml::Mapping>>) -> Result<(), Arc<TaskResponse>> {
+        // jet_memtotal_mb, left out rather than failing the host if it cannot be read
+        let os_type = handle.host.read().unwrap().os_type.expect("os type");
+        let cmd = match crate::tasks::cmd_library::get_memory_command(os_type) {
+            Ok(x) => x,
+            Err(_) => { return Err(handle.response.is_failed(request, &format!("unable to determine memory command for {:?}", os_type))) },
+        };
+        let result = handle.remote.run_unsafe(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out) = cmd_info(&result);
+        if rc != 0 {
+            return Ok(());
+        }
+        // Linux: "MemTotal:       16266168 kB", MacOS: "17179869184"
+        let number = out.split_whitespace().find_map(|x| x.parse::<u64>().ok());
+        let megabytes = match (os_type, number) {
+            (_, None) => { return Ok(()); },
+            (HostOSType::Linux, Some(kb)) => kb / 1024,
+            (HostOSType::MacOS, Some(bytes)) | (HostOSType::Windows, Some(bytes)) => bytes / (1024 * 1024)
+        };
+        mapping.write().unwrap().insert(serde_yaml::Value::String(String::from("jet_memtotal_mb")), serde_yaml::Value::from(megabytes));
+        return Ok(());
+    }
+
+    fn do_ipv4_addresses(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, mapping: &Arc<RwLock<serde_yaml::Mapping>>) -> Result<(), Arc<TaskResponse>> {
+        // jet_ipv4_addresses is a list, possibly empty, and never includes loopback addresses
+        let os_type = handle.host.read().unwrap().os_type.expect("os type");
+        let cmd = match crate::tasks::cmd_library::get_ipv4_addresses_command(os_type) {
+            Ok(x) => x,
+            Err(_) => { return Err(handle.response.is_failed(request, &format!("unable to determine address command for {:?}", os_type))) },
+        };
+        let result = handle.remote.run_unsafe(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out) = cmd_info(&result);
+        let mut addresses : Vec<serde_yaml::Value> = Vec::new();
+        if rc == 0 {
+            let candidates : Vec<&str> = match os_type {
+                // "10.0.0.5 172.17.0.1 fe80::1"
+                HostOSType::Linux | HostOSType::Windows => out.split_whitespace().collect(),
+                // "	inet 10.0.0.5 netmask 0xffffff00 broadcast 10.0.0.255"
+                HostOSType::MacOS => out.lines().filter_map(|line| {
+                    let mut tokens = line.split_whitespace();
+                    match tokens.next() { Some("inet") => tokens.next(), _ => None }
+                }).collect()
+            };
+            for candidate in candidates {
+                match candidate.parse::<std::net::Ipv4Addr>() {
+                    Ok(ip) if ! ip.is_loopback() => { addresses.push(serde_yaml::Value::String(ip.to_string())); },
+                    _ => {}
+                }
+            }
+        }
+        mapping.write().unwrap().insert(serde_yaml::Value::String(String::from("jet_ipv4_addresses")), serde_yaml::Value::Sequence(addresses));
+        return Ok(());
+    }
+
     fn do_facter(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, mapping: &Arc<RwLock<serde_yaml::Mapping>>) -> Result<(), Arc<TaskResponse>> {
         let result = handle.remote.run(request, &String::from("facter --json"), CheckRc::Checked)?;
         let (_rc, out) = cmd_info(&result);
@@ -0,0 +1,48 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use serde::Deserialize;
+use std::sync::Arc;
+
+const MODULE: &str = "import_tasks";
+
+// import_tasks runs the tasks of another file in place, as if they were written where the import is.
+// The file is known before anything runs, so its tasks appear in --list-tasks and --plan-out and are
+// subject to tags and --start-at-task individually.  'vars' apply only to the imported tasks.
+// Relative paths are found next to the playbook, or in the role's tasks/ or handlers/ directory.
+// like blocks, imports are handled by traversal.rs and not executed like other modules.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ImportTasksTask {
+    pub name: Option<String>,
+    pub file: String,
+    pub vars: Option<serde_yaml::Mapping>
+}
+
+impl IsTask for ImportTasksTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { None }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, _tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        return Err(handle.response.is_failed(request, &String::from("imports are expanded by the playbook traversal and cannot be evaluated directly")));
+    }
+
+}
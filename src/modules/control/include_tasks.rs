@@ -0,0 +1,48 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use serde::Deserialize;
+use std::sync::Arc;
+
+const MODULE: &str = "include_tasks";
+
+// include_tasks runs the tasks of another file when the include is reached, and only on the hosts
+// for which its 'with: condition' is true.  Tags on the include decide whether it is reached at all.
+// Other 'with' options are not used.  'vars' apply only to the included tasks, and paths are found
+// the same way as for import_tasks.  Includes are handled by traversal.rs.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct IncludeTasksTask {
+    pub name: Option<String>,
+    pub file: String,
+    pub vars: Option<serde_yaml::Mapping>,
+    pub with: Option<PreLogicInput>
+}
+
+impl IsTask for IncludeTasksTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, _tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        return Err(handle.response.is_failed(request, &String::from("includes are handled by the playbook traversal and cannot be evaluated directly")));
+    }
+
+}
@@ -0,0 +1,51 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use serde::Deserialize;
+use std::sync::Arc;
+
+const MODULE: &str = "meta";
+
+// meta tasks change how the play itself proceeds rather than configuring anything.
+// 'flush_handlers' runs the handlers notified so far instead of waiting for the end of the play.
+// like blocks, meta tasks are handled by traversal.rs and not executed like other modules.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MetaTask {
+    pub name: Option<String>,
+    pub action: MetaAction
+}
+
+#[derive(Deserialize,Debug,Copy,Clone,PartialEq)]
+#[serde(rename_all="snake_case")]
+pub enum MetaAction {
+    FlushHandlers
+}
+
+impl IsTask for MetaTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { None }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, _tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        return Err(handle.response.is_failed(request, &String::from("meta tasks are handled by the playbook traversal and cannot be evaluated directly")));
+    }
+
+}
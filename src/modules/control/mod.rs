@@ -17,8 +17,14 @@
 /** ADD MODULES HERE, KEEP ALPHABETIZED **/
 
 pub mod assert;
+pub mod block;
 pub mod debug;
 pub mod echo;
 pub mod fail;
 pub mod facts;
-pub mod set;
\ No newline at end of file
+pub mod import_tasks;
+pub mod include_tasks;
+pub mod meta;
+pub mod pause;
+pub mod set;
+pub mod wait_for;
\ No newline at end of file
@@ -0,0 +1,114 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use crate::util::terminal::stdin_is_terminal;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::{thread, time};
+
+const MODULE: &str = "pause";
+
+// waits for a number of seconds, or until someone at the terminal presses enter.  Hosts are paused
+// independently, so use 'run_once' when a prompt should be answered once rather than for every host.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PauseTask {
+    pub name: Option<String>,
+    pub seconds: Option<String>,
+    pub prompt: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+struct PauseAction {
+    pub seconds: Option<u64>,
+    pub prompt: Option<String>,
+}
+
+impl IsTask for PauseTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        if tm == TemplateMode::Strict && self.seconds.is_some() == self.prompt.is_some() {
+            return Err(handle.response.is_failed(request, &String::from("pause requires exactly one of seconds or prompt")));
+        }
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(PauseAction {
+                    seconds: handle.template.integer_option(request, tm, &String::from("seconds"), &self.seconds, None)?,
+                    prompt:  handle.template.string_option_unsafe_for_shell(request, tm, &String::from("prompt"), &self.prompt)?,
+                }),
+                with: Arc::new(PreLogicInput::template(handle, request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(handle, request, tm, &self.and)?),
+            }
+        );
+    }
+}
+
+impl IsAction for PauseAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                return Ok(handle.response.needs_passive(request));
+            },
+
+            TaskRequestType::Passive => {
+                if let Some(seconds) = self.seconds {
+                    thread::sleep(time::Duration::from_secs(seconds));
+                    return Ok(handle.response.is_passive(request));
+                }
+                return self.prompt(handle, request);
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+
+    }
+
+}
+
+impl PauseAction {
+
+    // without a terminal there is nobody to answer, so the run either fails here or moves on
+    // when --auto-continue was given, rather than waiting forever
+
+    fn prompt(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+        let prompt = self.prompt.as_ref().unwrap();
+        if ! stdin_is_terminal() {
+            if handle.run_state.context.read().unwrap().auto_continue {
+                handle.debug(request, &format!("{} (continued, no terminal)", prompt));
+                return Ok(handle.response.is_passive(request));
+            }
+            return Err(handle.response.is_failed(request, &format!("cannot prompt without a terminal, use --auto-continue to skip: {}", prompt)));
+        }
+        let question = format!("{} (enter to continue, a to abort):", prompt);
+        return match handle.run_state.visitor.read().unwrap().ask_host(&handle.host, &question).as_deref() {
+            Some("a") | Some("abort") | None => Err(handle.response.is_failed(request, &String::from("aborted at pause"))),
+            _ => Ok(handle.response.is_passive(request))
+        };
+    }
+
+}
@@ -67,26 +67,18 @@ impl IsAction for SetAction {
             },
 
             TaskRequestType::Passive => {
-                
-                /* so far this only templates top level strings, which is probably sufficient, rather than strings found in deeper levels */
-
                 let mut mapping = serde_yaml::Mapping::new();
                 if self.vars.as_ref().is_some() {
                     for (k,v) in self.vars.as_ref().unwrap().iter() {
-                        if v.is_string() {
-                            let ks = v.as_str().unwrap().to_string();
-                            let vs = v.as_str().unwrap().to_string();
-                            let templated = handle.template.string_unsafe_for_shell(request, TemplateMode::Strict, &ks.clone(), &vs)?;
-                            mapping.insert(k.clone(), serde_yaml::Value::String(templated));
-                        } else {
-                            mapping.insert(k.clone(), v.clone());
-                        }   
+                        let field = match k.as_str() {
+                            Some(x) => x.to_string(),
+                            None => { return Err(handle.response.is_failed(request, &format!("variable names must be strings: {:?}", k))); }
+                        };
+                        mapping.insert(k.clone(), template_value(handle, request, &field, v)?);
                     }
                 }
-
                 handle.host.write().unwrap().update_variables(mapping);
                 return Ok(handle.response.is_passive(&request));
-            
             }
 
             _ => { return Err(handle.response.not_supported(request)); }
@@ -96,3 +88,41 @@ impl IsAction for SetAction {
 
 }
 
+
+// strings are templated wherever they appear, including inside lists and mappings.  A template that renders
+// to a boolean or an integer is stored as one, so computed flags can be tested directly by later conditions.
+
+fn template_value(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, field: &String, value: &serde_yaml::Value) -> Result<serde_yaml::Value, Arc<TaskResponse>> {
+    return match value {
+        serde_yaml::Value::String(template) => {
+            let templated = handle.template.string_unsafe_for_shell(request, TemplateMode::Strict, field, template)?;
+            if ! template.contains("{{") {
+                return Ok(serde_yaml::Value::String(templated));
+            }
+            Ok(match templated.trim() {
+                "true" => serde_yaml::Value::Bool(true),
+                "false" => serde_yaml::Value::Bool(false),
+                x => match x.parse::<i64>() {
+                    // leading zeros and signs are kept as written, '0755' is a mode not the number 755
+                    Ok(n) if n.to_string().eq(x) => serde_yaml::Value::Number(serde_yaml::Number::from(n)),
+                    _ => serde_yaml::Value::String(templated)
+                }
+            })
+        },
+        serde_yaml::Value::Sequence(items) => {
+            let mut result = serde_yaml::Sequence::new();
+            for item in items.iter() {
+                result.push(template_value(handle, request, field, item)?);
+            }
+            Ok(serde_yaml::Value::Sequence(result))
+        },
+        serde_yaml::Value::Mapping(map) => {
+            let mut result = serde_yaml::Mapping::new();
+            for (k,v) in map.iter() {
+                result.insert(k.clone(), template_value(handle, request, field, v)?);
+            }
+            Ok(serde_yaml::Value::Mapping(result))
+        },
+        _ => Ok(value.clone())
+    };
+}
@@ -0,0 +1,185 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::{TaskHandle,CheckRc};
+use crate::connection::command::cmd_info;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::{thread, time};
+
+const MODULE: &str = "wait_for";
+
+// blocks until a port on a host accepts connections (or stops accepting them), or until a path
+// on the target appears (or goes away). the checks are run from the target itself, so 'host'
+// defaults to the target's own loopback address.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WaitForTask {
+    pub name: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<String>,
+    pub path: Option<String>,
+    pub state: Option<String>,
+    pub timeout: Option<String>,
+    pub delay: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+#[derive(Debug,PartialEq)]
+enum WaitState {
+    Started,
+    Stopped,
+    Present,
+    Absent
+}
+
+enum Target {
+    Port(String, u64),
+    Path(String)
+}
+
+struct WaitForAction {
+    pub target: Target,
+    pub state: WaitState,
+    pub timeout: u64,
+    pub delay: u64,
+}
+
+impl IsTask for WaitForTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        if tm == TemplateMode::Strict && self.port.is_some() == self.path.is_some() {
+            return Err(handle.response.is_failed(request, &String::from("exactly one of port or path is required")));
+        }
+
+        let target = match &self.path {
+            Some(path) => Target::Path(handle.template.path(request, tm, &String::from("path"), path)?),
+            None => Target::Port(
+                handle.template.string_option_default(request, tm, &String::from("host"), &self.host, &String::from("127.0.0.1"))?,
+                handle.template.integer_option_to_integer(request, tm, &String::from("port"), &self.port, 0)?
+            )
+        };
+
+        let state = match handle.template.string_option_no_spaces(request, tm, &String::from("state"), &self.state)? {
+            None => match target { Target::Path(_) => WaitState::Present, Target::Port(_,_) => WaitState::Started },
+            Some(x) => match x.as_str() {
+                "started" => WaitState::Started,
+                "stopped" => WaitState::Stopped,
+                "present" => WaitState::Present,
+                "absent"  => WaitState::Absent,
+                _ if tm == TemplateMode::Off => WaitState::Started,
+                _ => { return Err(handle.response.is_failed(request, &format!("field (state): expecting started, stopped, present, or absent, got '{}'", x))); }
+            }
+        };
+
+        if tm == TemplateMode::Strict {
+            match (&target, &state) {
+                (Target::Port(host, port), WaitState::Started | WaitState::Stopped) => {
+                    if *port == 0 || *port > 65535 {
+                        return Err(handle.response.is_failed(request, &format!("field (port): invalid port number {}", port)));
+                    }
+                    if host.is_empty() || ! host.chars().all(|c| c.is_ascii_alphanumeric() || ".-:".contains(c)) {
+                        return Err(handle.response.is_failed(request, &format!("field (host): invalid host '{}'", host)));
+                    }
+                },
+                (Target::Path(_), WaitState::Present | WaitState::Absent) => {},
+                (Target::Port(_,_), _) => { return Err(handle.response.is_failed(request, &String::from("ports can only be waited for with state started or stopped"))); },
+                (Target::Path(_), _) => { return Err(handle.response.is_failed(request, &String::from("paths can only be waited for with state present or absent"))); }
+            }
+        }
+
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(WaitForAction {
+                    target:  target,
+                    state:   state,
+                    timeout: handle.template.integer_option_to_integer(request, tm, &String::from("timeout"), &self.timeout, 300)?,
+                    delay:   handle.template.integer_option_to_integer(request, tm, &String::from("delay"), &self.delay, 1)?,
+                }),
+                with: Arc::new(PreLogicInput::template(handle, request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(handle, request, tm, &self.and)?),
+            }
+        );
+    }
+}
+
+impl IsAction for WaitForAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                // waiting never changes anything, so the whole module happens in the query leg
+                let started = time::Instant::now();
+                loop {
+                    if self.is_satisfied(handle, request)? {
+                        return Ok(handle.response.is_matched(request));
+                    }
+                    if started.elapsed().as_secs() >= self.timeout {
+                        return Err(handle.response.is_failed(request, &format!("timed out after {} seconds waiting for {}", self.timeout, self.describe())));
+                    }
+                    thread::sleep(time::Duration::from_secs(self.delay.max(1)));
+                }
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+
+    }
+
+}
+
+impl WaitForAction {
+
+    fn is_satisfied(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<bool, Arc<TaskResponse>> {
+        let cmd = match &self.target {
+            // nc is not installed everywhere, so fall back to bash's /dev/tcp. host and port were
+            // checked during evaluation, so the redirections here are safe
+            Target::Port(host, port) => format!("nc -z -w 2 '{host}' {port} >/dev/null 2>&1 || bash -c 'exec 3<>/dev/tcp/{host}/{port}' >/dev/null 2>&1", host=host, port=port),
+            Target::Path(path) => format!("test -e '{}'", path)
+        };
+        let result = handle.remote.run_unsafe(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, _) = cmd_info(&result);
+        let found = rc == 0;
+        return Ok(match self.state {
+            WaitState::Started | WaitState::Present => found,
+            WaitState::Stopped | WaitState::Absent => ! found
+        });
+    }
+
+    fn describe(&self) -> String {
+        let state = match self.state {
+            WaitState::Started => "started", WaitState::Stopped => "stopped",
+            WaitState::Present => "present", WaitState::Absent => "absent"
+        };
+        return match &self.target {
+            Target::Port(host, port) => format!("port {}:{} to be {}", host, port, state),
+            Target::Path(path) => format!("path {} to be {}", path, state)
+        };
+    }
+
+}
@@ -31,6 +31,7 @@ pub struct CopyTask {
     pub name: Option<String>,
     pub src: String,
     pub dest: String,
+    pub backup: Option<String>,
     pub attributes: Option<FileAttributesInput>,
     pub with: Option<PreLogicInput>,
     pub and: Option<PostLogicInput>
@@ -38,6 +39,7 @@ pub struct CopyTask {
 struct CopyAction {
     pub src: PathBuf,
     pub dest: String,
+    pub backup: bool,
     pub attributes: Option<FileAttributesEvaluated>,
 }
 
@@ -54,6 +56,7 @@ impl IsTask for CopyTask {
                 action: Arc::new(CopyAction {
                     src:        handle.template.find_file_path(request, tm, &String::from("src"), &src)?,
                     dest:       handle.template.path(&request, tm, &String::from("dest"), &self.dest)?,
+                    backup:     handle.template.boolean_option_default_false(&request, tm, &String::from("backup"), &self.backup)?,
                     attributes: FileAttributesInput::template(&handle, &request, tm, &self.attributes)?
                 }),
                 with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
@@ -82,11 +85,16 @@ impl IsAction for CopyAction {
                 let src_path = self.src.as_path();
                 let local_512 = handle.local.get_sha512(request, &src_path, true)?;
                 let remote_512 = handle.remote.get_sha512(request, &self.dest)?;
+                let mut diff : Option<String> = None;
                 if ! remote_512.eq(&local_512) { 
                     changes.push(Field::Content); 
+                    diff = match std::fs::read(&src_path) {
+                        Ok(bytes) => handle.remote.get_content_diff(request, &self.dest, &bytes)?,
+                        Err(_) => None
+                    };
                 }
                 if ! changes.is_empty() {
-                    return Ok(handle.response.needs_modification(request, &changes));
+                    return Ok(handle.response.needs_modification_with_diff(request, &changes, diff));
                 }
                 return Ok(handle.response.is_matched(request));
             },
@@ -98,6 +106,10 @@ impl IsAction for CopyAction {
 
             TaskRequestType::Modify => {
                 if request.changes.contains(&Field::Content) {
+                    // the old content is only worth keeping when it is about to be replaced
+                    if self.backup {
+                        handle.remote.backup_file(request, &self.dest)?;
+                    }
                     self.do_copy(handle, request, Some(request.changes.clone()))?;
                 }
                 else {
@@ -0,0 +1,117 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use crate::tasks::fields::Field;
+use std::path::{Path,PathBuf};
+use serde::{Deserialize};
+use std::sync::Arc;
+
+const MODULE: &str = "fetch";
+
+// the inverse of copy.  Files are saved on the control machine under dest/<host name>/<remote path>
+// so that fetching the same file from many hosts does not have them overwrite each other.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FetchTask {
+    pub name: Option<String>,
+    pub src: String,
+    pub dest: String,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+struct FetchAction {
+    pub src: String,
+    pub dest: PathBuf,
+}
+
+impl IsTask for FetchTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        let src = handle.template.path(&request, tm, &String::from("src"), &self.src)?;
+        let dest = handle.template.path(&request, tm, &String::from("dest"), &self.dest)?;
+        let host_name = handle.host.read().unwrap().name.clone();
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(FetchAction {
+                    dest: Path::new(&dest).join(&host_name).join(src.trim_start_matches('/')),
+                    src:  src,
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl IsAction for FetchAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                if handle.remote.get_mode(request, &self.src)?.is_none() {
+                    return Err(handle.response.is_failed(request, &format!("remote file does not exist: {}", self.src)));
+                }
+                let remote_512 = handle.remote.get_sha512(request, &self.src)?;
+                if ! self.dest.exists() {
+                    return Ok(handle.response.needs_creation(request));
+                }
+                let local_512 = handle.local.get_sha512(request, &self.dest, false)?;
+                if ! remote_512.eq(&local_512) {
+                    return Ok(handle.response.needs_modification(request, &vec![Field::Content]));
+                }
+                return Ok(handle.response.is_matched(request));
+            },
+
+            TaskRequestType::Create => {
+                self.do_fetch(handle, request)?;
+                return Ok(handle.response.is_created(request));
+            },
+
+            TaskRequestType::Modify => {
+                self.do_fetch(handle, request)?;
+                return Ok(handle.response.is_modified(request, request.changes.clone()));
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+    }
+
+}
+
+impl FetchAction {
+
+    fn do_fetch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<(), Arc<TaskResponse>> {
+        let parent = self.dest.parent().unwrap();
+        if let Err(y) = std::fs::create_dir_all(parent) {
+            return Err(handle.response.is_failed(request, &format!("unable to create local directory {}: {}", parent.display(), y)));
+        }
+        return handle.remote.fetch_file(request, &self.src, &self.dest);
+    }
+
+}
@@ -29,14 +29,29 @@ const MODULE: &str = "file";
 pub struct FileTask {
     pub name: Option<String>,
     pub path: String,
+    pub state: Option<String>,
+    pub src: Option<String>,
     pub remove: Option<String>,
+    pub recurse: Option<String>,
     pub attributes: Option<FileAttributesInput>,
     pub with: Option<PreLogicInput>,
     pub and: Option<PostLogicInput>
 }
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+enum FileState {
+    File,
+    Touch,
+    Directory,
+    Link,
+    Absent
+}
+
 struct FileAction {
     pub path: String,
-    pub remove: bool,
+    pub state: FileState,
+    pub src: Option<String>,
+    pub recurse: Recurse,
     pub attributes: Option<FileAttributesEvaluated>,
 }
 
@@ -47,11 +62,56 @@ impl IsTask for FileTask {
     fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
 
     fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        // 'remove: true' predates 'state' and is another way to say 'state: absent'
+        let remove = handle.template.boolean_option_default_false(&request, tm, &String::from("remove"), &self.remove)?;
+        let state = match handle.template.string_option_no_spaces(request, tm, &String::from("state"), &self.state)? {
+            None => match remove {
+                true => FileState::Absent,
+                false => FileState::File
+            },
+            Some(x) => match x.as_str() {
+                "file"      => FileState::File,
+                "touch"     => FileState::Touch,
+                "directory" => FileState::Directory,
+                "link"      => FileState::Link,
+                "absent"    => FileState::Absent,
+                _ if tm == TemplateMode::Off => FileState::File,
+                _ => { return Err(handle.response.is_failed(request, &format!("field (state): expecting file, touch, directory, link or absent, got '{}'", x))); }
+            }
+        };
+        let recurse = match handle.template.boolean_option_default_false(&request, tm, &String::from("recurse"), &self.recurse)? {
+            true => Recurse::Yes,
+            false => Recurse::No
+        };
+        let src = handle.template.string_option_trim(&request, tm, &String::from("src"), &self.src)?;
+
+        if tm == TemplateMode::Strict {
+            if self.state.is_some() && self.remove.is_some() {
+                return Err(handle.response.is_failed(request, &String::from("state cannot be combined with remove")));
+            }
+            if state == FileState::Link && src.is_none() {
+                return Err(handle.response.is_failed(request, &String::from("field (src) is required with state: link")));
+            }
+            if state != FileState::Link && src.is_some() {
+                return Err(handle.response.is_failed(request, &String::from("field (src) is only used with state: link")));
+            }
+            // chown and chmod follow symlinks, so attributes would silently change the link target instead
+            if state == FileState::Link && self.attributes.is_some() {
+                return Err(handle.response.is_failed(request, &String::from("attributes are not supported with state: link")));
+            }
+            if recurse == Recurse::Yes && state != FileState::Directory && state != FileState::Absent {
+                return Err(handle.response.is_failed(request, &String::from("recurse only applies to state: directory or absent")));
+            }
+        }
+
         return Ok(
             EvaluatedTask {
                 action: Arc::new(FileAction {
-                    remove:     handle.template.boolean_option_default_false(&request, tm, &String::from("remove"), &self.remove)?,
                     path:       handle.template.path(&request, tm, &String::from("path"), &self.path)?,
+                    state:      state,
+                    src:        src,
+                    recurse:    recurse,
                     attributes: FileAttributesInput::template(&handle, &request, tm, &self.attributes)?
                 }),
                 with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
@@ -69,33 +129,43 @@ impl IsAction for FileAction {
         match request.request_type {
 
             TaskRequestType::Query => {
-                let mut changes : Vec<Field> = Vec::new();
-                let remote_mode = handle.remote.query_common_file_attributes(request, &self.path, &self.attributes, &mut changes, Recurse::No)?;                   
-                if remote_mode.is_none() {
-                    if self.remove             { return Ok(handle.response.is_matched(request)); } 
-                    else                       { return Ok(handle.response.needs_creation(request));  }
-                } else {
-                    let is_dir = handle.remote.get_is_directory(request, &self.path)?;
-                    if is_dir                  { return Err(handle.response.is_failed(request, &format!("{} is a directory", self.path))); }
-                    else if self.remove        { return Ok(handle.response.needs_removal(request)); }
-                    else if changes.is_empty() { return Ok(handle.response.is_matched(request)); }
-                    else                       { return Ok(handle.response.needs_modification(request, &changes)); }
-                }
+                return match self.state {
+                    FileState::Link   => self.query_link(handle, request),
+                    FileState::Absent => self.query_absent(handle, request),
+                    _                 => self.query_path(handle, request)
+                };
             },
 
             TaskRequestType::Create => {
-                handle.remote.touch_file(request, &self.path)?;               
-                handle.remote.process_all_common_file_attributes(request, &self.path, &self.attributes, Recurse::No)?;
+                match self.state {
+                    FileState::Directory => { handle.remote.create_directory(request, &self.path)?; },
+                    FileState::Link => {
+                        handle.remote.create_symlink(request, self.src.as_ref().unwrap(), &self.path)?;
+                        return Ok(handle.response.is_created(request));
+                    },
+                    _ => { handle.remote.touch_file(request, &self.path)?; }
+                };
+                handle.remote.process_all_common_file_attributes(request, &self.path, &self.attributes, self.recurse)?;
                 return Ok(handle.response.is_created(request));
             },
 
             TaskRequestType::Modify => {
-                handle.remote.process_common_file_attributes(request, &self.path, &self.attributes, &request.changes, Recurse::No)?;
+                if request.changes.contains(&Field::Target) {
+                    handle.remote.create_symlink(request, self.src.as_ref().unwrap(), &self.path)?;
+                }
+                if request.changes.contains(&Field::Timestamp) {
+                    handle.remote.touch_file(request, &self.path)?;
+                }
+                handle.remote.process_common_file_attributes(request, &self.path, &self.attributes, &request.changes, self.recurse)?;
                 return Ok(handle.response.is_modified(request, request.changes.clone()));
             },
 
             TaskRequestType::Remove => {
-                handle.remote.delete_file(request, &self.path)?;               
+                if handle.remote.get_link_target(request, &self.path)?.is_none() && handle.remote.get_is_directory(request, &self.path)? {
+                    handle.remote.delete_directory(request, &self.path, self.recurse)?;
+                } else {
+                    handle.remote.delete_file(request, &self.path)?;
+                }
                 return Ok(handle.response.is_removed(request))
             }
 
@@ -106,3 +176,52 @@ impl IsAction for FileAction {
         }
     }
 }
+
+impl FileAction {
+
+    // files, touched files, and directories share the same attribute checks but must not be mistaken for each other
+
+    fn query_path(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+        let mut changes : Vec<Field> = Vec::new();
+        let remote_mode = handle.remote.query_common_file_attributes(request, &self.path, &self.attributes, &mut changes, self.recurse)?;
+        if remote_mode.is_none() {
+            return Ok(handle.response.needs_creation(request));
+        }
+        let is_dir = handle.remote.get_is_directory(request, &self.path)?;
+        if is_dir && self.state != FileState::Directory {
+            return Err(handle.response.is_failed(request, &format!("{} is a directory", self.path)));
+        }
+        if ! is_dir && self.state == FileState::Directory {
+            return Err(handle.response.is_failed(request, &format!("{} is not a directory", self.path)));
+        }
+        // like the command line tool, touch updates the timestamps of a file that already exists every time
+        if self.state == FileState::Touch {
+            changes.push(Field::Timestamp);
+        }
+        if changes.is_empty() {
+            return Ok(handle.response.is_matched(request));
+        }
+        return Ok(handle.response.needs_modification(request, &changes));
+    }
+
+    fn query_link(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+        let target = handle.remote.get_link_target(request, &self.path)?;
+        return match target {
+            Some(x) if x.eq(self.src.as_ref().unwrap()) => Ok(handle.response.is_matched(request)),
+            Some(_) => Ok(handle.response.needs_modification(request, &vec![Field::Target])),
+            None => match handle.remote.get_mode(request, &self.path)? {
+                Some(_) => Err(handle.response.is_failed(request, &format!("{} exists and is not a symlink", self.path))),
+                None => Ok(handle.response.needs_creation(request))
+            }
+        };
+    }
+
+    fn query_absent(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+        // get_mode does not follow symlinks, so a dangling link still counts as present
+        return match handle.remote.get_mode(request, &self.path)? {
+            Some(_) => Ok(handle.response.needs_removal(request)),
+            None => Ok(handle.response.is_matched(request))
+        };
+    }
+
+}
@@ -30,8 +30,11 @@ const MODULE: &str = "git";
 pub struct GitTask {
     pub name: Option<String>,
     pub repo: String,
+    #[serde(alias = "dest")]
     pub path: String,
     pub branch: Option<String>,
+    pub version: Option<String>,
+    pub force: Option<String>,
     pub ssh_options: Option<HashMap<String,String>>,
     pub accept_keys: Option<String>,
     pub update: Option<String>,
@@ -44,6 +47,8 @@ struct GitAction {
     pub repo: String,
     pub path: String,
     pub branch: String,
+    pub version: Option<String>,
+    pub force: bool,
     pub ssh_options: Vec<String>,
     pub accept_keys: bool,
     pub update: bool,
@@ -63,6 +68,8 @@ impl IsTask for GitTask {
                     repo:         handle.template.string(&request, tm, &String::from("repo"), &self.repo)?,
                     path:         handle.template.path(&request, tm, &String::from("path"), &self.path)?,
                     branch:       handle.template.string_option_default(&request, tm, &String::from("branch"), &self.branch, &String::from("main"))?,
+                    version:      handle.template.string_option_no_spaces(&request, tm, &String::from("version"), &self.version)?,
+                    force:        handle.template.boolean_option_default_false(&request, tm, &String::from("force"), &self.force)?,
                     accept_keys:  handle.template.boolean_option_default_true(&request, tm, &String::from("accept_keys"), &self.accept_keys)?,
                     update:       handle.template.boolean_option_default_true(&request, tm, &String::from("update"), &self.update)?,
                     attributes:   FileAttributesInput::template(&handle, &request, tm, &self.attributes)?,
@@ -127,6 +134,13 @@ impl IsAction for GitAction {
                                 if local_version.is_none() {
                                     changes.push(Field::Version);
                                 }
+                                else if self.version.is_some() {
+                                    // a pinned tag or commit is checked out detached, so the branch does not matter
+                                    let wanted = self.get_pinned_version(handle, request)?;
+                                    if wanted.is_none() || ! wanted.unwrap().eq(local_version.as_ref().unwrap()) {
+                                        changes.push(Field::Version);
+                                    }
+                                }
                                 else {
                                     let remote_version = self.get_remote_version(handle, request)?;
                                     let local_branch = self.get_local_branch(handle, request)?;
@@ -154,20 +168,35 @@ impl IsAction for GitAction {
                 handle.remote.create_directory(request, &self.path)?;
                 handle.remote.process_all_common_file_attributes(request, &self.path, &self.attributes, Recurse::Yes)?;
                 self.clone(handle, request)?;
-                self.switch_branch(handle, request)?;                           
-                return Ok(handle.response.is_created(request));
+                match self.version.is_some() {
+                    true  => self.checkout_version(handle, request)?,
+                    false => self.switch_branch(handle, request)?
+                };
+                let commit = self.get_local_version(handle, request)?.unwrap_or_default();
+                return Ok(handle.response.is_created_with_msg(request, &commit));
             },
 
             TaskRequestType::Modify => {
 
                 handle.remote.process_common_file_attributes(request, &self.path, &self.attributes, &request.changes, Recurse::Yes)?;
-                if request.changes.contains(&Field::Branch) || request.changes.contains(&Field::Version) {
-                    self.pull(handle,request)?;
+                if ! (request.changes.contains(&Field::Branch) || request.changes.contains(&Field::Version)) {
+                    return Ok(handle.response.is_modified(request, request.changes.clone()));
                 }
-                if request.changes.contains(&Field::Branch) {
-                    self.switch_branch(handle, request)?;
+                if self.force {
+                    self.discard_local_changes(handle, request)?;
                 }
-                return Ok(handle.response.is_modified(request, request.changes.clone()));
+                if self.version.is_some() {
+                    self.fetch(handle, request)?;
+                    self.checkout_version(handle, request)?;
+                } else {
+                    if request.changes.contains(&Field::Branch) {
+                        self.fetch(handle, request)?;
+                        self.switch_branch(handle, request)?;
+                    }
+                    self.pull(handle,request)?;
+                }
+                let commit = self.get_local_version(handle, request)?.unwrap_or_default();
+                return Ok(handle.response.is_modified_with_msg(request, request.changes.clone(), &commit));
             },
 
             // no passive or execute leg
@@ -189,7 +218,7 @@ impl GitAction {
 
     fn get_ssh_options_string(&self) -> String {
         let options = self.ssh_options.join(" ");
-        if self.path.starts_with("http") {
+        if self.repo.starts_with("http") {
             // http or https:// passwords are intentionally not supported, use a key instead, see docs
             return String::from("GIT_TERMINAL_PROMPT=0");
         }
@@ -215,7 +244,7 @@ impl GitAction {
 
     fn get_remote_version(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<String, Arc<TaskResponse>> {
         let ssh_options = self.get_ssh_options_string();
-        let cmd = format!("{} git ls-remote {} | head -n 1 | cut -f 1", ssh_options, self.repo);
+        let cmd = format!("{} git ls-remote {} refs/heads/{} | head -n 1 | cut -f 1", ssh_options, self.repo, self.branch);
         let result = match self.is_ssh_repo() {
             true  => handle.remote.run_forwardable(request, &cmd, CheckRc::Checked)?,
             false => handle.remote.run_unsafe(&request, &cmd, CheckRc::Checked)?
@@ -254,7 +283,45 @@ impl GitAction {
     }
 
     fn switch_branch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<(), Arc<TaskResponse>> {
-        let cmd = format!("git -C {} switch {}", self.path, self.branch);
+        let cmd = match self.force {
+            true  => format!("git -C {} switch --discard-changes {}", self.path, self.branch),
+            false => format!("git -C {} switch {}", self.path, self.branch)
+        };
+        handle.remote.run_unsafe(request, &cmd, CheckRc::Checked)?;
+        return Ok(());
+    }
+
+    fn get_pinned_version(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Option<String>, Arc<TaskResponse>> {
+        // the commit a tag or (possibly abbreviated) hash points to, None if it is not known locally yet
+        let cmd = format!("git -C {} rev-parse --verify -q {}^0", self.path, self.version.as_ref().unwrap());
+        let result = handle.remote.run_unsafe(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out) = cmd_info(&result);
+        if rc == 0 {
+            return Ok(Some(out.replace("\n","")));
+        } else {
+            return Ok(None);
+        }
+    }
+
+    fn fetch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<(), Arc<TaskResponse>> {
+        let ssh_options = self.get_ssh_options_string();
+        let cmd = format!("{} git -C {} fetch --tags origin", ssh_options, self.path);
+        match self.is_ssh_repo() {
+            true  => handle.remote.run_forwardable(request, &cmd, CheckRc::Checked)?,
+            false => handle.remote.run_unsafe(&request, &cmd, CheckRc::Checked)?
+        };
+        return Ok(());
+    }
+
+    fn checkout_version(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<(), Arc<TaskResponse>> {
+        let force = match self.force { true => " -f", false => "" };
+        let cmd = format!("git -C {} checkout{} --detach {}", self.path, force, self.version.as_ref().unwrap());
+        handle.remote.run_unsafe(request, &cmd, CheckRc::Checked)?;
+        return Ok(());
+    }
+
+    fn discard_local_changes(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<(), Arc<TaskResponse>> {
+        let cmd = format!("git -C {} reset --hard", self.path);
         handle.remote.run_unsafe(request, &cmd, CheckRc::Checked)?;
         return Ok(());
     }
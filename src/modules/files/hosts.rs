@@ -0,0 +1,308 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use crate::tasks::fields::Field;
+use serde::{Deserialize};
+use std::sync::Arc;
+use std::vec::Vec;
+use std::net::IpAddr;
+
+const MODULE: &str = "hosts";
+
+// manages a single address to name mapping in /etc/hosts.  The file is parsed rather than matched line by line,
+// so whitespace and comments do not cause spurious changes.  Only lines for the given address are changed when
+// a mapping is present, names listed for other addresses are left as they are.
+// The file is replaced atomically, see replace_file in remote.rs.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HostsTask {
+    pub name: Option<String>,
+    pub ip: Option<String>,
+    pub hostname: String,
+    pub aliases: Option<Vec<String>>,
+    pub path: Option<String>,
+    pub state: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+#[derive(Debug,PartialEq)]
+enum HostsState {
+    Present,
+    Absent
+}
+
+struct HostsAction {
+    pub path: String,
+    pub ip: Option<String>,
+    pub names: Vec<String>,
+    pub state: HostsState,
+}
+
+impl IsTask for HostsTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        let state = match handle.template.string_option_no_spaces(request, tm, &String::from("state"), &self.state)? {
+            None => HostsState::Present,
+            Some(x) => match x.as_str() {
+                "present" => HostsState::Present,
+                "absent"  => HostsState::Absent,
+                _ if tm == TemplateMode::Off => HostsState::Present,
+                _ => { return Err(handle.response.is_failed(request, &format!("field (state): expecting present or absent, got '{}'", x))); }
+            }
+        };
+
+        let ip = handle.template.string_option_no_spaces(request, tm, &String::from("ip"), &self.ip)?;
+        let mut names : Vec<String> = Vec::new();
+        names.push(handle.template.string_no_spaces(request, tm, &String::from("hostname"), &self.hostname)?);
+        if let Some(aliases) = &self.aliases {
+            for alias in aliases.iter() {
+                names.push(handle.template.string_no_spaces(request, tm, &String::from("aliases"), alias)?);
+            }
+        }
+
+        if tm == TemplateMode::Strict {
+            match &ip {
+                Some(x) => if x.parse::<IpAddr>().is_err() {
+                    return Err(handle.response.is_failed(request, &format!("field (ip): not an IP address: {}", x)));
+                },
+                None => if state == HostsState::Present {
+                    return Err(handle.response.is_failed(request, &String::from("field (ip) is required unless state is absent")));
+                }
+            }
+            for name in names.iter() {
+                if name.is_empty() || name.contains('#') {
+                    return Err(handle.response.is_failed(request, &format!("invalid hostname or alias: '{}'", name)));
+                }
+            }
+        }
+
+        let path = match &self.path {
+            Some(x) => handle.template.path(request, tm, &String::from("path"), x)?,
+            None => String::from("/etc/hosts")
+        };
+
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(HostsAction {
+                    path:  path,
+                    ip:    ip,
+                    names: names,
+                    state: state
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl IsAction for HostsAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                let before = match handle.remote.read_file(request, &self.path)? {
+                    Some(x) => x,
+                    None => match self.state {
+                        HostsState::Absent => { return Ok(handle.response.is_matched(request)); },
+                        HostsState::Present => { return Ok(handle.response.needs_creation(request)); }
+                    }
+                };
+                let lines : Vec<String> = before.lines().map(|x| x.to_string()).collect();
+                let edited = self.edit(&lines);
+                if edited.eq(&lines) {
+                    return Ok(handle.response.is_matched(request));
+                }
+                let diff = handle.remote.get_text_diff(&self.path, &before, &edited.join("\n"));
+                return Ok(handle.response.needs_modification_with_diff(request, &vec![Field::Content], diff));
+            },
+
+            TaskRequestType::Create => {
+                handle.remote.replace_file(request, &HostsAction::to_data(&self.edit(&Vec::new())), &self.path)?;
+                return Ok(handle.response.is_created(request));
+            },
+
+            TaskRequestType::Modify => {
+                let before = match handle.remote.read_file(request, &self.path)? {
+                    Some(x) => x,
+                    None => { return Err(handle.response.is_failed(request, &format!("file does not exist: {}", self.path))); }
+                };
+                let lines : Vec<String> = before.lines().map(|x| x.to_string()).collect();
+                handle.remote.replace_file(request, &HostsAction::to_data(&self.edit(&lines)), &self.path)?;
+                return Ok(handle.response.is_modified(request, request.changes.clone()));
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+    }
+
+}
+
+impl HostsAction {
+
+    // splits a line into the address, the names, and any trailing comment.  Blank lines and
+    // comment-only lines return None and are always kept as they are.
+
+    fn parse(line: &String) -> Option<(String, Vec<String>, Option<String>)> {
+        let (body, comment) = match line.find('#') {
+            Some(i) => (&line[..i], Some(line[i..].to_string())),
+            None => (line.as_str(), None)
+        };
+        let mut tokens = body.split_whitespace();
+        let ip = tokens.next()?;
+        return Some((ip.to_string(), tokens.map(|x| x.to_string()).collect(), comment));
+    }
+
+    fn to_data(lines: &Vec<String>) -> String {
+        let mut data = lines.join("\n");
+        data.push_str("\n");
+        return data;
+    }
+
+    fn format(ip: &String, names: &Vec<String>, comment: &Option<String>) -> String {
+        let mut line = format!("{}\t{}", ip, names.join(" "));
+        if let Some(c) = comment {
+            line.push_str(" ");
+            line.push_str(c);
+        }
+        return line;
+    }
+
+    // returns the new file contents.  When present, only lines for the address are looked at: the first one
+    // listing the hostname becomes the wanted entry, otherwise the entry is appended.  Other addresses keep
+    // their names, so '::1 localhost' survives managing '127.0.0.1 localhost'.  When absent, the names are
+    // taken off every line, or only the lines of the given address, and a line is only dropped when it held
+    // nothing but these names.
+
+    fn edit(&self, lines: &Vec<String>) -> Vec<String> {
+
+        let hostname = &self.names[0];
+        let mut result : Vec<String> = Vec::new();
+        let mut found = false;
+
+        for line in lines.iter() {
+            let (ip, names, comment) = match HostsAction::parse(line) {
+                Some(x) => x,
+                None => { result.push(line.clone()); continue; }
+            };
+            let same_ip = self.ip.is_some() && ip.eq(self.ip.as_ref().unwrap());
+
+            match self.state {
+                HostsState::Present => {
+                    if same_ip && ! found && names.contains(hostname) {
+                        found = true;
+                        match names.eq(&self.names) {
+                            true  => result.push(line.clone()),
+                            false => result.push(HostsAction::format(&ip, &self.names, &comment))
+                        }
+                    } else {
+                        result.push(line.clone());
+                    }
+                },
+                HostsState::Absent => {
+                    if self.ip.is_some() && ! same_ip {
+                        result.push(line.clone());
+                        continue;
+                    }
+                    let remaining : Vec<String> = names.iter().filter(|x| ! self.names.contains(x)).cloned().collect();
+                    if remaining.len() == names.len() {
+                        result.push(line.clone());
+                    } else if ! remaining.is_empty() {
+                        result.push(HostsAction::format(&ip, &remaining, &comment));
+                    }
+                }
+            }
+        }
+
+        if self.state == HostsState::Present && ! found {
+            result.push(HostsAction::format(self.ip.as_ref().unwrap(), &self.names, &None));
+        }
+        return result;
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(ip: Option<&str>, names: Vec<&str>, state: HostsState) -> HostsAction {
+        return HostsAction {
+            path: String::from("/etc/hosts"),
+            ip: ip.map(|x| x.to_string()),
+            names: names.iter().map(|x| x.to_string()).collect(),
+            state: state
+        };
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        return text.lines().map(|x| x.to_string()).collect();
+    }
+
+    #[test]
+    fn test_present_keeps_other_families() {
+        let before = lines("127.0.0.1 localhost\n::1 localhost ip6-localhost\n");
+        let a = action(Some("127.0.0.1"), vec!["localhost"], HostsState::Present);
+        assert_eq!(a.edit(&before), before);
+        let a = action(Some("fe80::1"), vec!["localhost"], HostsState::Present);
+        assert_eq!(a.edit(&before), lines("127.0.0.1 localhost\n::1 localhost ip6-localhost\nfe80::1\tlocalhost"));
+    }
+
+    #[test]
+    fn test_present_ipv6() {
+        let a = action(Some("2001:db8::5"), vec!["db"], HostsState::Present);
+        let after = a.edit(&lines("# hosts\n10.0.0.5 db"));
+        assert_eq!(after, lines("# hosts\n10.0.0.5 db\n2001:db8::5\tdb"));
+        assert_eq!(a.edit(&after), after);
+    }
+
+    #[test]
+    fn test_present_aliases() {
+        let a = action(Some("10.0.0.9"), vec!["web", "www"], HostsState::Present);
+        assert_eq!(a.edit(&lines("10.0.0.9   web www  # front")), lines("10.0.0.9   web www  # front"));
+        assert_eq!(a.edit(&lines("10.0.0.9 web old # front\n10.0.0.9 other")), lines("10.0.0.9\tweb www # front\n10.0.0.9 other"));
+        assert_eq!(a.edit(&lines("10.0.0.9 other")), lines("10.0.0.9 other\n10.0.0.9\tweb www"));
+    }
+
+    #[test]
+    fn test_absent_without_ip() {
+        let a = action(None, vec!["db", "dbalias"], HostsState::Absent);
+        let before = lines("127.0.0.1 localhost\n10.0.0.5 db dbalias\n::5 db main # both\n");
+        assert_eq!(a.edit(&before), lines("127.0.0.1 localhost\n::5\tmain # both"));
+    }
+
+    #[test]
+    fn test_absent_with_ip() {
+        let a = action(Some("10.0.0.5"), vec!["db"], HostsState::Absent);
+        let before = lines("10.0.0.5 db\n::5 db");
+        assert_eq!(a.edit(&before), lines("::5 db"));
+    }
+
+}
@@ -0,0 +1,244 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use crate::tasks::fields::Field;
+use serde::{Deserialize};
+use std::sync::Arc;
+use std::vec::Vec;
+use regex::Regex;
+
+const MODULE: &str = "lineinfile";
+
+// manages a single line in an existing remote file, for cases where templating the entire
+// file would be overkill.  The file is read, edited in memory, and written back in full.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LineInFileTask {
+    pub name: Option<String>,
+    pub path: String,
+    pub regexp: Option<String>,
+    pub line: Option<String>,
+    pub insertafter: Option<String>,
+    pub insertbefore: Option<String>,
+    pub state: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+#[derive(Debug,PartialEq)]
+enum LineState {
+    Present,
+    Absent
+}
+
+// where a new line goes when nothing matched 'regexp'
+
+enum Insert {
+    After(Regex),
+    Before(Regex),
+    EndOfFile,
+    StartOfFile
+}
+
+struct LineInFileAction {
+    pub path: String,
+    pub regexp: Option<Regex>,
+    pub line: Option<String>,
+    pub insert: Insert,
+    pub state: LineState,
+}
+
+impl IsTask for LineInFileTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        let state = match handle.template.string_option_no_spaces(request, tm, &String::from("state"), &self.state)? {
+            None => LineState::Present,
+            Some(x) => match x.as_str() {
+                "present" => LineState::Present,
+                "absent"  => LineState::Absent,
+                _ if tm == TemplateMode::Off => LineState::Present,
+                _ => { return Err(handle.response.is_failed(request, &format!("field (state): expecting present or absent, got '{}'", x))); }
+            }
+        };
+
+        // the line and expressions are never passed to a shell, so shell screening does not apply here
+        let line = handle.template.string_option_unsafe_for_shell(request, tm, &String::from("line"), &self.line)?;
+        if tm == TemplateMode::Strict {
+            if line.is_some() && line.as_ref().unwrap().contains('\n') {
+                return Err(handle.response.is_failed(request, &String::from("field (line): must be a single line")));
+            }
+            if state == LineState::Present && line.is_none() {
+                return Err(handle.response.is_failed(request, &String::from("field (line) is required unless state is absent")));
+            }
+            if state == LineState::Absent && line.is_none() && self.regexp.is_none() {
+                return Err(handle.response.is_failed(request, &String::from("state: absent requires either line or regexp")));
+            }
+            if self.insertafter.is_some() && self.insertbefore.is_some() {
+                return Err(handle.response.is_failed(request, &String::from("insertafter and insertbefore are mutually exclusive")));
+            }
+        }
+
+        let insert = match (&self.insertafter, &self.insertbefore) {
+            (Some(x), _) => match handle.template.string_unsafe_for_shell(request, tm, &String::from("insertafter"), x)?.as_str() {
+                "EOF" => Insert::EndOfFile,
+                y => Insert::After(LineInFileTask::compile(handle, request, tm, &String::from("insertafter"), &y.to_string())?)
+            },
+            (None, Some(x)) => match handle.template.string_unsafe_for_shell(request, tm, &String::from("insertbefore"), x)?.as_str() {
+                "BOF" => Insert::StartOfFile,
+                y => Insert::Before(LineInFileTask::compile(handle, request, tm, &String::from("insertbefore"), &y.to_string())?)
+            },
+            (None, None) => Insert::EndOfFile
+        };
+
+        let regexp = match &self.regexp {
+            Some(x) => {
+                let pattern = handle.template.string_unsafe_for_shell(request, tm, &String::from("regexp"), x)?;
+                Some(LineInFileTask::compile(handle, request, tm, &String::from("regexp"), &pattern)?)
+            },
+            None => None
+        };
+
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(LineInFileAction {
+                    path:   handle.template.path(&request, tm, &String::from("path"), &self.path)?,
+                    regexp: regexp,
+                    line:   line,
+                    insert: insert,
+                    state:  state
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl LineInFileTask {
+
+    fn compile(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode, field: &String, pattern: &String) -> Result<Regex, Arc<TaskResponse>> {
+        // when templates are off the pattern may still contain unexpanded variables, so anything goes
+        if tm == TemplateMode::Off {
+            return Ok(Regex::new("").unwrap());
+        }
+        return match Regex::new(pattern) {
+            Ok(x) => Ok(x),
+            Err(y) => Err(handle.response.is_failed(request, &format!("field ({}): invalid regular expression: {}", field, y)))
+        };
+    }
+
+}
+
+impl IsAction for LineInFileAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                let before = match handle.remote.read_file(request, &self.path)? {
+                    Some(x) => x,
+                    None => match self.state {
+                        LineState::Absent => { return Ok(handle.response.is_matched(request)); },
+                        LineState::Present => { return Err(handle.response.is_failed(request, &format!("file does not exist: {}", self.path))); }
+                    }
+                };
+                let lines : Vec<String> = before.lines().map(|x| x.to_string()).collect();
+                let edited = self.edit(&lines);
+                if edited.eq(&lines) {
+                    return Ok(handle.response.is_matched(request));
+                }
+                let diff = handle.remote.get_text_diff(&self.path, &before, &edited.join("\n"));
+                return Ok(handle.response.needs_modification_with_diff(request, &vec![Field::Content], diff));
+            },
+
+            TaskRequestType::Modify => {
+                // the file is read again rather than carried over from the query, the edit is cheap
+                let before = match handle.remote.read_file(request, &self.path)? {
+                    Some(x) => x,
+                    None => { return Err(handle.response.is_failed(request, &format!("file does not exist: {}", self.path))); }
+                };
+                let lines : Vec<String> = before.lines().map(|x| x.to_string()).collect();
+                let mut data = self.edit(&lines).join("\n");
+                data.push_str("\n");
+                handle.remote.replace_file(request, &data, &self.path)?;
+                return Ok(handle.response.is_modified(request, request.changes.clone()));
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+    }
+
+}
+
+impl LineInFileAction {
+
+    fn is_match(&self, line: &String) -> bool {
+        return match (&self.regexp, &self.line) {
+            (Some(re), _)      => re.is_match(line),
+            (None, Some(want)) => line.eq(want),
+            (None, None)       => false
+        };
+    }
+
+    // returns the new file contents.  When present, the last line matching 'regexp' is replaced, otherwise the
+    // line is inserted unless an identical line already exists.  When absent, every matching line is dropped.
+
+    fn edit(&self, lines: &Vec<String>) -> Vec<String> {
+
+        if self.state == LineState::Absent {
+            return lines.iter().filter(|x| ! self.is_match(x)).cloned().collect();
+        }
+
+        let line = self.line.as_ref().unwrap();
+        let mut result = lines.clone();
+
+        if let Some(index) = lines.iter().rposition(|x| self.is_match(x)) {
+            result[index] = line.clone();
+            return result;
+        }
+        if lines.contains(line) {
+            return result;
+        }
+
+        let index = match &self.insert {
+            Insert::StartOfFile => 0,
+            Insert::EndOfFile => lines.len(),
+            Insert::After(re) => match lines.iter().rposition(|x| re.is_match(x)) {
+                Some(i) => i + 1,
+                None => lines.len()
+            },
+            Insert::Before(re) => match lines.iter().position(|x| re.is_match(x)) {
+                Some(i) => i,
+                None => lines.len()
+            }
+        };
+        result.insert(index, line.clone());
+        return result;
+    }
+
+}
@@ -18,7 +18,13 @@
 
 pub mod copy;
 pub mod directory;
+pub mod fetch;
 pub mod file;
 pub mod git;
+pub mod hosts;
+pub mod lineinfile;
+pub mod mount;
 pub mod stat;
-pub mod template;
\ No newline at end of file
+pub mod template;
+pub mod unarchive;
+pub mod win_copy;
\ No newline at end of file
@@ -0,0 +1,265 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::{TaskHandle,CheckRc};
+use crate::tasks::fields::Field;
+use crate::inventory::hosts::HostOSType;
+use serde::{Deserialize};
+use std::sync::Arc;
+use std::vec::Vec;
+
+const MODULE: &str = "mount";
+
+// manages a filesystem in /etc/fstab and whether it is mounted right now.  Like the service module, the
+// state is made of two parts: 'present' only keeps the fstab entry, 'mounted' also mounts it, 'unmounted'
+// only unmounts it and 'absent' does both of the opposite.  fstab is replaced atomically, see replace_file.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MountTask {
+    pub name: Option<String>,
+    pub path: String,
+    pub src: Option<String>,
+    pub fstype: Option<String>,
+    pub opts: Option<String>,
+    pub dump: Option<String>,
+    pub passno: Option<String>,
+    pub fstab: Option<String>,
+    pub state: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+#[derive(Debug,PartialEq)]
+enum MountState {
+    Mounted,
+    Unmounted,
+    Present,
+    Absent
+}
+
+struct MountAction {
+    pub path: String,
+    pub entry: Option<Vec<String>>,
+    pub fstab: String,
+    pub state: MountState,
+}
+
+impl IsTask for MountTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        let state = match handle.template.string_option_no_spaces(request, tm, &String::from("state"), &self.state)? {
+            None => MountState::Mounted,
+            Some(x) => match x.as_str() {
+                "mounted"   => MountState::Mounted,
+                "unmounted" => MountState::Unmounted,
+                "present"   => MountState::Present,
+                "absent"    => MountState::Absent,
+                _ if tm == TemplateMode::Off => MountState::Mounted,
+                _ => { return Err(handle.response.is_failed(request, &format!("field (state): expecting mounted, unmounted, present or absent, got '{}'", x))); }
+            }
+        };
+
+        let path = handle.template.path(request, tm, &String::from("path"), &self.path)?;
+        if tm == TemplateMode::Strict && (path.contains(char::is_whitespace) || ! path.starts_with("/")) {
+            return Err(handle.response.is_failed(request, &format!("field (path): expecting an absolute path without spaces: {}", path)));
+        }
+
+        // the fstab fields in order, only needed when the entry is to be written
+        let src = MountTask::fstab_field(handle, request, tm, &String::from("src"), &self.src)?;
+        let fstype = MountTask::fstab_field(handle, request, tm, &String::from("fstype"), &self.fstype)?;
+        let entry = match (state == MountState::Mounted || state == MountState::Present, src, fstype) {
+            (false, _, _) => None,
+            (true, Some(src), Some(fstype)) => Some(vec![
+                src,
+                path.clone(),
+                fstype,
+                MountTask::fstab_field(handle, request, tm, &String::from("opts"), &self.opts)?.unwrap_or(String::from("defaults")),
+                handle.template.integer_option_to_integer(request, tm, &String::from("dump"), &self.dump, 0)?.to_string(),
+                handle.template.integer_option_to_integer(request, tm, &String::from("passno"), &self.passno, 0)?.to_string()
+            ]),
+            (true, _, _) if tm == TemplateMode::Off => None,
+            (true, _, _) => { return Err(handle.response.is_failed(request, &String::from("fields (src) and (fstype) are required when state is mounted or present"))); }
+        };
+
+        let fstab = match &self.fstab {
+            Some(x) => handle.template.path(request, tm, &String::from("fstab"), x)?,
+            None => String::from("/etc/fstab")
+        };
+
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(MountAction {
+                    path:  path,
+                    entry: entry,
+                    fstab: fstab,
+                    state: state
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl MountTask {
+
+    // sources such as UUID=... and options such as size=1m need characters the shell screening refuses,
+    // the values are single quoted in commands instead, and fstab itself cannot hold spaces
+
+    fn fstab_field(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode, field: &String, template: &Option<String>) -> Result<Option<String>, Arc<TaskResponse>> {
+        let value = handle.template.string_option_unsafe_for_shell(request, tm, field, template)?;
+        if tm == TemplateMode::Strict && value.as_ref().is_some_and(|x| x.is_empty() || x.contains(char::is_whitespace) || x.contains('\'')) {
+            return Err(handle.response.is_failed(request, &format!("field ({}): must not be empty or contain spaces or quotes", field)));
+        }
+        return Ok(value);
+    }
+
+}
+
+impl IsAction for MountAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                if handle.remote.get_os_type() != HostOSType::Linux {
+                    return Err(handle.response.is_failed(request, &String::from("mount is only supported on Linux")));
+                }
+                let mut changes : Vec<Field> = Vec::new();
+                let before = handle.remote.read_file(request, &self.fstab)?.unwrap_or(String::new());
+                let lines : Vec<String> = before.lines().map(|x| x.to_string()).collect();
+                let edited = self.edit(&lines);
+                let mut diff : Option<String> = None;
+                if ! edited.eq(&lines) {
+                    changes.push(Field::Fstab);
+                    diff = handle.remote.get_text_diff(&self.fstab, &before, &edited.join("\n"));
+                }
+                let mounted = self.is_mounted(handle, request)?;
+                match self.state {
+                    // a mounted filesystem whose fstab entry changed is remounted so the new options take effect
+                    MountState::Mounted if ! mounted => { changes.push(Field::Mount); },
+                    MountState::Mounted if changes.contains(&Field::Fstab) => { changes.push(Field::Remount); },
+                    MountState::Unmounted | MountState::Absent if mounted => { changes.push(Field::Unmount); },
+                    _ => {}
+                }
+                if changes.is_empty() {
+                    return Ok(handle.response.is_matched(request));
+                }
+                return Ok(handle.response.needs_modification_with_diff(request, &changes, diff));
+            },
+
+            TaskRequestType::Modify => {
+                if request.changes.contains(&Field::Unmount) {
+                    handle.remote.run(request, &format!("umount '{}'", self.path), CheckRc::Checked)?;
+                }
+                if request.changes.contains(&Field::Fstab) {
+                    let before = handle.remote.read_file(request, &self.fstab)?.unwrap_or(String::new());
+                    let lines : Vec<String> = before.lines().map(|x| x.to_string()).collect();
+                    let mut data = self.edit(&lines).join("\n");
+                    data.push_str("\n");
+                    handle.remote.replace_file(request, &data, &self.fstab)?;
+                }
+                if request.changes.contains(&Field::Mount) {
+                    if ! handle.remote.get_directory_exists(request, &self.path)? {
+                        handle.remote.create_directory(request, &self.path)?;
+                    }
+                    self.mount(handle, request, false)?;
+                }
+                if request.changes.contains(&Field::Remount) {
+                    self.mount(handle, request, true)?;
+                }
+                return Ok(handle.response.is_modified(request, request.changes.clone()));
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+    }
+
+}
+
+impl MountAction {
+
+    // /proc/mounts has the same layout as fstab, the mount point is the second field
+
+    fn is_mounted(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<bool, Arc<TaskResponse>> {
+        let mounts = match handle.remote.read_file(request, &String::from("/proc/mounts"))? {
+            Some(x) => x,
+            None => { return Err(handle.response.is_failed(request, &String::from("unable to read /proc/mounts"))); }
+        };
+        return Ok(mounts.lines().any(|x| x.split_whitespace().nth(1) == Some(self.path.as_str())));
+    }
+
+    // mounts using the values given to the task rather than fstab, so that a custom 'fstab' path also works
+
+    fn mount(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, remount: bool) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+        let entry = self.entry.as_ref().unwrap();
+        let opts = match remount {
+            true  => format!("remount,{}", entry[3]),
+            false => entry[3].clone()
+        };
+        let cmd = format!("mount -t '{}' -o '{}' '{}' '{}'", entry[2], opts, entry[0], self.path);
+        return handle.remote.run_unsafe(request, &cmd, CheckRc::Checked);
+    }
+
+    // returns the new contents of fstab.  The entry for the mount point is replaced in place when any field
+    // differs, later entries for the same mount point are dropped, and a missing entry is appended.  When the
+    // state is absent, every entry for the mount point is removed.  Other states leave fstab alone.
+
+    fn edit(&self, lines: &Vec<String>) -> Vec<String> {
+        if self.state == MountState::Unmounted {
+            return lines.clone();
+        }
+        let mut result : Vec<String> = Vec::new();
+        let mut found = false;
+        for line in lines.iter() {
+            let fields : Vec<String> = line.split_whitespace().map(|x| x.to_string()).collect();
+            if fields.is_empty() || fields[0].starts_with('#') || fields.get(1) != Some(&self.path) {
+                result.push(line.clone());
+                continue;
+            }
+            if found || self.entry.is_none() {
+                continue;
+            }
+            found = true;
+            let entry = self.entry.as_ref().unwrap();
+            // dump and passno may be left out of fstab, where they default to 0
+            let mut existing = fields.clone();
+            while existing.len() < 6 {
+                existing.push(String::from("0"));
+            }
+            match existing.eq(entry) {
+                true  => result.push(line.clone()),
+                false => result.push(entry.join("\t"))
+            }
+        }
+        if ! found && self.entry.is_some() {
+            result.push(self.entry.as_ref().unwrap().join("\t"));
+        }
+        return result;
+    }
+
+}
@@ -85,17 +85,21 @@ impl IsAction for StatAction {
 struct StatResult {
     pub exists: bool,
     pub is_dir: bool,
+    pub size: Option<u64>,
     pub mode: Option<String>,
     pub owner: Option<String>,
     pub group: Option<String>,
+    pub checksum: Option<String>,
 }
 
 const DOESNT_EXIST: StatResult = StatResult{
     exists: false,
     is_dir: false,
+    size: None,
     mode: None,
     owner: None,
     group: None,
+    checksum: None,
 };
 
 fn stat_file(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, path: &String) -> Result<StatResult, Arc<TaskResponse>> {
@@ -104,6 +108,12 @@ fn stat_file(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, path: &String
         Some(mode) => {
             let is_dir = handle.remote.get_is_directory(request, path)?;
             let ownership = handle.remote.get_ownership(request, path)?;
+            let size = handle.remote.get_size(request, path)?;
+            // the checksum is the same SHA-512 the copy and template modules compare against, directories have none
+            let checksum = match is_dir {
+                true => None,
+                false => Some(handle.remote.get_sha512(request, path)?)
+            };
             if ownership.is_some() {
                 // we can add other properties here, such as file+directory size, including contents, SELinux attributes, etc
                 // return None for the ones that are not supported
@@ -111,9 +121,11 @@ fn stat_file(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, path: &String
                 return Ok(StatResult{
                     exists: true,
                     is_dir: is_dir,
+                    size: size,
                     mode: Some(format!("0o{}", mode)),
                     owner: Some(owner),
                     group: Some(group),
+                    checksum: checksum,
                 })
             }
             else {
@@ -82,11 +82,13 @@ impl IsAction for TemplateAction {
                 let data = self.do_template(handle, request, false, None)?;
                 let local_512 = sha512(&data);
                 let remote_512 = handle.remote.get_sha512(request, &self.dest)?;
+                let mut diff : Option<String> = None;
                 if ! remote_512.eq(&local_512) { 
                     changes.push(Field::Content); 
+                    diff = handle.remote.get_content_diff(request, &self.dest, data.as_bytes())?;
                 }
                 if ! changes.is_empty() {
-                    return Ok(handle.response.needs_modification(request, &changes));
+                    return Ok(handle.response.needs_modification_with_diff(request, &changes, diff));
                 }
                 return Ok(handle.response.is_matched(request));
             },
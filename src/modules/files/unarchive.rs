@@ -0,0 +1,156 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use crate::tasks::files::ArchiveFormat;
+use std::path::PathBuf;
+use serde::{Deserialize};
+use std::sync::Arc;
+
+const MODULE: &str = "unarchive";
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct UnarchiveTask {
+    pub name: Option<String>,
+    pub src: String,
+    pub dest: String,
+    pub remote_src: Option<String>,
+    pub creates: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+// archives either come from the control machine, in which case they are transferred first,
+// or are already sitting on the managed host
+
+enum ArchiveSource {
+    Local(PathBuf),
+    Remote(String)
+}
+
+struct UnarchiveAction {
+    pub src: ArchiveSource,
+    pub dest: String,
+    pub format: ArchiveFormat,
+    pub creates: Option<String>,
+}
+
+impl IsTask for UnarchiveTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        let remote_src = handle.template.boolean_option_default_false(&request, tm, &String::from("remote_src"), &self.remote_src)?;
+        let src = handle.template.string(&request, tm, &String::from("src"), &self.src)?;
+        let format = match (tm, ArchiveFormat::from_path(&src)) {
+            (_, Some(x)) => x,
+            (TemplateMode::Off, None) => ArchiveFormat::TarGz,
+            (_, None) => { return Err(handle.response.is_failed(request, &format!("field (src): unsupported archive type, expecting .tar.gz, .tar.bz2 or .zip: {}", src))); }
+        };
+
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(UnarchiveAction {
+                    src: match remote_src {
+                        true  => ArchiveSource::Remote(handle.template.path(&request, tm, &String::from("src"), &src)?),
+                        false => ArchiveSource::Local(handle.template.find_file_path(request, tm, &String::from("src"), &src)?)
+                    },
+                    dest:    handle.template.path(&request, tm, &String::from("dest"), &self.dest)?,
+                    format:  format,
+                    creates: match &self.creates {
+                        Some(x) => Some(handle.template.path(&request, tm, &String::from("creates"), x)?),
+                        None => None
+                    }
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl IsAction for UnarchiveAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                // 'creates' is the cheap and explicit way to make this idempotent.  Without it, every top
+                // level entry of the archive has to exist in the destination.
+                if self.creates.is_some() {
+                    return match handle.remote.get_mode(request, self.creates.as_ref().unwrap())? {
+                        Some(_) => Ok(handle.response.is_matched(request)),
+                        None => Ok(handle.response.needs_creation(request))
+                    };
+                }
+                if handle.remote.get_mode(request, &self.dest)?.is_none() {
+                    return Ok(handle.response.needs_creation(request));
+                }
+                let entries = match &self.src {
+                    ArchiveSource::Local(path) => handle.local.list_archive(request, path, self.format)?,
+                    ArchiveSource::Remote(path) => handle.remote.list_archive(request, path, self.format)?
+                };
+                for entry in entries.iter() {
+                    if handle.remote.get_mode(request, &format!("{}/{}", self.dest, entry))?.is_none() {
+                        return Ok(handle.response.needs_creation(request));
+                    }
+                }
+                return Ok(handle.response.is_matched(request));
+            },
+
+            TaskRequestType::Create => {
+                handle.remote.create_directory(request, &self.dest)?;
+                match &self.src {
+                    ArchiveSource::Local(path) => { self.extract_local(handle, request, path)?; },
+                    ArchiveSource::Remote(path) => { handle.remote.extract_archive(request, path, &self.dest, self.format)?; }
+                }
+                return Ok(handle.response.is_created(request));
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+    }
+
+}
+
+impl UnarchiveAction {
+
+    // local archives are copied into a temporary file on the remote, extracted, and then cleaned up
+
+    fn extract_local(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, path: &PathBuf) -> Result<(), Arc<TaskResponse>> {
+        let whoami = match handle.remote.get_whoami() {
+            Ok(x) => x,
+            Err(y) => { return Err(handle.response.is_failed(request, &format!("cannot determine current user: {}", y))); }
+        };
+        let (_temp_dir, temp_path) = handle.remote.make_temp_path(&whoami, request)?;
+        let temp_file = format!("{}", temp_path.display());
+        handle.remote.copy_file(request, path, &temp_file, |_f| { Ok(()) })?;
+        let result = handle.remote.extract_archive(request, &temp_file, &self.dest, self.format);
+        handle.remote.delete_file(request, &temp_file)?;
+        result?;
+        return Ok(());
+    }
+
+}
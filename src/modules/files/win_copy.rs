@@ -0,0 +1,118 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::{TaskHandle,CheckRc};
+use crate::tasks::fields::Field;
+use crate::inventory::hosts::HostOSType;
+use std::path::PathBuf;
+use serde::{Deserialize};
+use std::sync::Arc;
+
+const MODULE: &str = "win_copy";
+
+// copies a file to a Windows host reached with the winrm connection type.  Windows has no
+// unix modes or owners, so unlike copy there are no attributes, only content.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WinCopyTask {
+    pub name: Option<String>,
+    pub src: String,
+    pub dest: String,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+struct WinCopyAction {
+    pub src: PathBuf,
+    pub dest: String,
+}
+
+impl IsTask for WinCopyTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        let src = handle.template.string(&request, tm, &String::from("src"), &self.src)?;
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(WinCopyAction {
+                    src:  handle.template.find_file_path(request, tm, &String::from("src"), &src)?,
+                    dest: handle.template.path(&request, tm, &String::from("dest"), &self.dest)?,
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl IsAction for WinCopyAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+    
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                if handle.remote.get_os_type() != HostOSType::Windows {
+                    return Err(handle.response.is_failed(&request, &String::from("win_copy requires a Windows host, use the winrm connection type")));
+                }
+                let remote_512 = match self.get_remote_sha512(handle, request)? {
+                    Some(x) => x,
+                    None => { return Ok(handle.response.needs_creation(request)); }
+                };
+                let local_512 = handle.local.get_sha512(request, &self.src.as_path(), true)?;
+                if ! remote_512.eq(&local_512) {
+                    return Ok(handle.response.needs_modification(request, &vec![Field::Content]));
+                }
+                return Ok(handle.response.is_matched(request));
+            },
+
+            TaskRequestType::Create => {
+                handle.remote.copy_file(request, &self.src, &self.dest, |_f| { return Ok(()); })?;
+                return Ok(handle.response.is_created(request));
+            },
+
+            TaskRequestType::Modify => {
+                handle.remote.copy_file(request, &self.src, &self.dest, |_f| { return Ok(()); })?;
+                return Ok(handle.response.is_modified(request, request.changes.clone()));
+            },
+    
+            _ => { return Err(handle.response.not_supported(request)); }
+    
+        }
+    }
+
+}
+
+impl WinCopyAction {
+
+    fn get_remote_sha512(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Option<String>, Arc<TaskResponse>> {
+        let path = self.dest.replace("'", "''");
+        let cmd = format!("if (Test-Path -LiteralPath '{}' -PathType Leaf) {{ (Get-FileHash -Algorithm SHA512 -LiteralPath '{}').Hash.ToLower() }}", path, path);
+        let result = handle.remote.run_unsafe(request, &cmd, CheckRc::Checked)?;
+        let (_rc, out) = cmd_info(&result);
+        return match out.trim().is_empty() {
+            true => Ok(None),
+            false => Ok(Some(out.trim().to_string()))
+        };
+    }
+
+}
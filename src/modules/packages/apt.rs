@@ -95,9 +95,9 @@ impl PackageManagementModule for AptAction {
         let result = handle.remote.run_unsafe(request, &cmd, CheckRc::Unchecked);
         match result {
             Ok(r) => {
-                let (rc,out) = cmd_info(&r);
+                let (rc,out,err) = cmd_info_with_err(&r);
                 // (differentiated return code is unavailable for this module only, don't repeat this pattern)
-                if out.contains("No packages found") {
+                if err.contains("No packages found") {
                     return Ok(None);
                 }
                 if rc == 0 {
@@ -20,6 +20,7 @@ pub mod common;
 
 pub mod apt;
 pub mod homebrew;
+pub mod package;
 pub mod pacman;
 pub mod yum_dnf;
 pub mod zypper;
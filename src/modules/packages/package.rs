@@ -0,0 +1,131 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::TaskHandle;
+use crate::inventory::hosts::HostOSType;
+use crate::modules::packages::apt::AptTask;
+use crate::modules::packages::homebrew::HomebrewTask;
+use crate::modules::packages::pacman::PacmanTask;
+use crate::modules::packages::yum_dnf::YumDnfTask;
+use crate::modules::packages::zypper::ZypperTask;
+use serde::{Deserialize};
+use std::sync::Arc;
+
+const MODULE: &str = "package";
+
+// the package module does not manage packages itself, it picks the package manager module
+// that fits the host from the jet_os_type and jet_os_flavor facts and hands the work to it,
+// so a single task can cover a fleet of mixed distributions
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PackageTask {
+    pub name: Option<String>,
+    pub package: String,
+    pub version: Option<String>,
+    pub state: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+#[derive(Debug,PartialEq)]
+enum PackageBackend {
+    Apt,
+    Homebrew,
+    Pacman,
+    YumDnf,
+    Zypper
+}
+
+impl IsTask for PackageTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        // with templates off the values are placeholders, only 'with' is used, so any backend will do
+        let (update, remove) = match tm {
+            TemplateMode::Off => (false, false),
+            TemplateMode::Strict => {
+                let state = handle.template.string_option_default(request, tm, &String::from("state"), &self.state, &String::from("present"))?;
+                match state.as_str() {
+                    "present" => (false, false),
+                    "latest"  => (true,  false),
+                    "absent"  => (false, true),
+                    _ => { return Err(handle.response.is_failed(request, &format!("field (state): expecting 'present', 'latest', or 'absent', got '{}'", state))); }
+                }
+            }
+        };
+        let update = Some(update.to_string());
+        let remove = Some(remove.to_string());
+
+        let backend = match (tm, self.get_backend(handle)) {
+            (_, Ok(x)) => x,
+            (TemplateMode::Off, Err(_)) => PackageBackend::Apt,
+            (TemplateMode::Strict, Err(msg)) => { return Err(handle.response.is_failed(request, &msg)); }
+        };
+
+        return match backend {
+            PackageBackend::Apt => AptTask {
+                name: self.name.clone(), package: self.package.clone(), version: self.version.clone(),
+                update: update, remove: remove, with: self.with.clone(), and: self.and.clone()
+            }.evaluate(handle, request, tm),
+            PackageBackend::Homebrew => HomebrewTask {
+                name: self.name.clone(), package: self.package.clone(), version: self.version.clone(),
+                update: update, remove: remove, with: self.with.clone(), and: self.and.clone()
+            }.evaluate(handle, request, tm),
+            PackageBackend::Pacman => PacmanTask {
+                name: self.name.clone(), package: self.package.clone(), version: self.version.clone(),
+                update: update, remove: remove, with: self.with.clone(), and: self.and.clone()
+            }.evaluate(handle, request, tm),
+            PackageBackend::YumDnf => YumDnfTask {
+                name: self.name.clone(), package: self.package.clone(), version: self.version.clone(),
+                update: update, remove: remove, with: self.with.clone(), and: self.and.clone()
+            }.evaluate(handle, request, tm),
+            PackageBackend::Zypper => ZypperTask {
+                name: self.name.clone(), package: self.package.clone(), version: self.version.clone(),
+                update: update, remove: remove, with: self.with.clone(), and: self.and.clone()
+            }.evaluate(handle, request, tm)
+        };
+    }
+
+}
+
+impl PackageTask {
+
+    fn get_backend(&self, handle: &Arc<TaskHandle>) -> Result<PackageBackend, String> {
+        let host = handle.host.read().unwrap();
+        if host.os_type == Some(HostOSType::MacOS) {
+            return Ok(PackageBackend::Homebrew);
+        }
+        let flavor = match host.get_blended_variables().get("jet_os_flavor") {
+            Some(serde_yaml::Value::String(x)) => x.clone(),
+            _ => { return Err(String::from("the package module needs the jet_os_flavor fact, remove --no-facts or use a specific package module such as apt")); }
+        };
+        return match flavor.as_str() {
+            "Debian" => Ok(PackageBackend::Apt),
+            "EL"     => Ok(PackageBackend::YumDnf),
+            "Arch"   => Ok(PackageBackend::Pacman),
+            "Suse"   => Ok(PackageBackend::Zypper),
+            _ => Err(format!("the package module does not know the package manager for jet_os_flavor '{}', use a specific package module instead", flavor))
+        };
+    }
+
+}
@@ -0,0 +1,230 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::{TaskHandle,CheckRc};
+use crate::tasks::fields::Field;
+use serde::{Deserialize};
+use std::sync::Arc;
+use std::vec::Vec;
+
+const MODULE: &str = "cron";
+
+// each entry managed by this module is a pair of lines in the crontab, a marker comment naming
+// the entry followed by the job itself.  Lines without a marker are never touched.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CronTask {
+    pub name: Option<String>,
+    pub entry: String,
+    pub user: Option<String>,
+    pub minute: Option<String>,
+    pub hour: Option<String>,
+    pub day: Option<String>,
+    pub month: Option<String>,
+    pub weekday: Option<String>,
+    pub job: Option<String>,
+    pub remove: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+struct CronAction {
+    pub entry: String,
+    pub user: Option<String>,
+    pub schedule: String,
+    pub job: Option<String>,
+    pub remove: bool,
+}
+
+impl IsTask for CronTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        let remove = handle.template.boolean_option_default_false(&request, tm, &String::from("remove"), &self.remove)?;
+
+        // the job is written into the crontab file and not run by us, so it may use redirection and
+        // other shell syntax, but it must stay on one line
+        let job = match &self.job {
+            Some(x) => {
+                let templated = handle.template.string_unsafe_for_shell(request, tm, &String::from("job"), x)?;
+                if templated.contains('\n') {
+                    return Err(handle.response.is_failed(request, &String::from("field (job): must be a single line")));
+                }
+                Some(templated.trim().to_string())
+            },
+            None => None
+        };
+        if tm == TemplateMode::Strict && job.is_none() && ! remove {
+            return Err(handle.response.is_failed(request, &String::from("field (job) is required unless remove is set")));
+        }
+
+        let mut fields : Vec<String> = Vec::new();
+        for (field, value) in [("minute", &self.minute), ("hour", &self.hour), ("day", &self.day), ("month", &self.month), ("weekday", &self.weekday)] {
+            fields.push(CronTask::template_schedule_field(handle, request, tm, &String::from(field), value)?);
+        }
+
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(CronAction {
+                    entry:    handle.template.string(&request, tm, &String::from("entry"), &self.entry)?,
+                    user:     handle.template.string_option_no_spaces(&request, tm, &String::from("user"), &self.user)?,
+                    schedule: fields.join(" "),
+                    job:      job,
+                    remove:   remove
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl CronTask {
+
+    fn template_schedule_field(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode, field: &String, template: &Option<String>) -> Result<String, Arc<TaskResponse>> {
+        // schedule fields default to '*' and need characters like '*' and '/' that general input screening does not allow
+        if tm == TemplateMode::Off || template.is_none() {
+            return Ok(String::from("*"));
+        }
+        let value = handle.template.string_unsafe_for_shell(request, tm, field, template.as_ref().unwrap())?;
+        let value = value.trim().to_string();
+        if value.is_empty() || ! value.chars().all(|c| c.is_ascii_alphanumeric() || "*/,-".contains(c)) {
+            return Err(handle.response.is_failed(request, &format!("field ({}): invalid cron schedule value '{}'", field, value)));
+        }
+        return Ok(value);
+    }
+
+}
+
+impl IsAction for CronAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                let lines = self.get_crontab(handle, request)?;
+                return match (self.find_entry(&lines), self.remove) {
+                    (None, true)     => Ok(handle.response.is_matched(request)),
+                    (None, false)    => Ok(handle.response.needs_creation(request)),
+                    (Some(_), true)  => Ok(handle.response.needs_removal(request)),
+                    (Some(i), false) => match lines.get(i+1) {
+                        Some(line) if line.eq(&self.get_job_line()) => Ok(handle.response.is_matched(request)),
+                        _ => Ok(handle.response.needs_modification(request, &vec![Field::Content]))
+                    }
+                };
+            },
+
+            TaskRequestType::Create => {
+                let mut lines = self.get_crontab(handle, request)?;
+                lines.push(self.get_marker());
+                lines.push(self.get_job_line());
+                self.put_crontab(handle, request, &lines)?;
+                return Ok(handle.response.is_created(request));
+            },
+
+            TaskRequestType::Modify => {
+                let mut lines = self.get_crontab(handle, request)?;
+                let i = self.find_entry(&lines).unwrap();
+                // an entry whose job line was deleted by hand is just the marker
+                match lines.get(i+1) {
+                    Some(line) if ! line.starts_with("#") => { lines[i+1] = self.get_job_line(); },
+                    _ => { lines.insert(i+1, self.get_job_line()); }
+                }
+                self.put_crontab(handle, request, &lines)?;
+                return Ok(handle.response.is_modified(request, request.changes.clone()));
+            },
+
+            TaskRequestType::Remove => {
+                let mut lines = self.get_crontab(handle, request)?;
+                let i = self.find_entry(&lines).unwrap();
+                if lines.get(i+1).is_some() && ! lines[i+1].starts_with("#") {
+                    lines.remove(i+1);
+                }
+                lines.remove(i);
+                self.put_crontab(handle, request, &lines)?;
+                return Ok(handle.response.is_removed(request));
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+    }
+
+}
+
+impl CronAction {
+
+    fn get_marker(&self) -> String {
+        return format!("# jetp: {}", self.entry);
+    }
+
+    fn get_job_line(&self) -> String {
+        return format!("{} {}", self.schedule, self.job.as_ref().unwrap());
+    }
+
+    fn get_user_option(&self) -> String {
+        return match &self.user {
+            Some(user) => format!(" -u '{}'", user),
+            None => String::new()
+        };
+    }
+
+    fn find_entry(&self, lines: &Vec<String>) -> Option<usize> {
+        let marker = self.get_marker();
+        return lines.iter().position(|x| x.eq(&marker));
+    }
+
+    fn get_crontab(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Vec<String>, Arc<TaskResponse>> {
+        let cmd = format!("crontab -l{}", self.get_user_option());
+        let result = handle.remote.run(request, &cmd, CheckRc::Unchecked)?;
+        let (rc, out, err) = cmd_info_with_err(&result);
+        if rc != 0 {
+            // a user who never had a crontab is the same as an empty one
+            if err.contains("no crontab for") {
+                return Ok(Vec::new());
+            }
+            return Err(handle.response.is_failed(request, &format!("unable to read crontab: {}", err)));
+        }
+        return Ok(out.lines().map(|x| x.trim_end().to_string()).collect());
+    }
+
+    fn put_crontab(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, lines: &Vec<String>) -> Result<(), Arc<TaskResponse>> {
+        // crontab(1) installs a whole file at once, so the new contents are staged in the temp directory first
+        let whoami = match handle.remote.get_whoami() {
+            Ok(x) => x,
+            Err(y) => { return Err(handle.response.is_failed(request, &format!("cannot determine current user: {}", y))); }
+        };
+        let (_temp_dir, temp_path) = handle.remote.make_temp_path(&whoami, request)?;
+        let temp_file = format!("{}", temp_path.display());
+        let mut data = lines.join("\n");
+        data.push_str("\n");
+        handle.remote.write_data(request, &data, &temp_file, |_f| { Ok(()) })?;
+        let result = handle.remote.run(request, &format!("crontab{} '{}'", self.get_user_option(), temp_file), CheckRc::Checked);
+        handle.remote.run(request, &format!("rm -f '{}'", temp_file), CheckRc::Unchecked)?;
+        result?;
+        return Ok(());
+    }
+
+}
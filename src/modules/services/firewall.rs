@@ -0,0 +1,434 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::{TaskHandle,CheckRc};
+use crate::tasks::fields::Field;
+use crate::inventory::hosts::HostOSType;
+use serde::{Deserialize};
+use std::sync::Arc;
+use std::vec::Vec;
+use std::net::IpAddr;
+
+const MODULE: &str = "firewall";
+const NFT_CONF: &str = "/etc/nftables.conf";
+
+// ensures a single rule allowing or blocking a port is present or absent.  The backend comes from the
+// jet_firewall fact, firewalld where it runs and plain nftables otherwise, and can be set with 'backend'.
+//
+// like sysctl, the running rules and the rules loaded at boot are checked separately and reported as the
+// Runtime and Persisted changes.  Each side is changed on its own rather than reloading, so rules added
+// at runtime by other software (container runtimes, fail2ban) are left alone.
+//
+// with firewalld the permanent configuration is the persisted side.  With nftables the rule goes into an
+// existing chain, 'inet filter input' by default as in the stock /etc/nftables.conf, and is found again
+// by its comment.  The persisted side is /etc/nftables.conf, where the same rule line is added to or removed
+// from that chain's block.  Nothing else in the file is touched.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FirewallTask {
+    pub name: Option<String>,
+    pub port: String,
+    pub proto: Option<String>,
+    pub source: Option<String>,
+    pub action: Option<String>,
+    pub state: Option<String>,
+    pub backend: Option<String>,
+    pub zone: Option<String>,
+    pub table: Option<String>,
+    pub chain: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+#[derive(Debug,PartialEq)]
+enum Backend {
+    Firewalld,
+    Nftables
+}
+
+struct FirewallAction {
+    pub backend: Backend,
+    pub port: String,
+    pub proto: String,
+    pub source: Option<String>,
+    pub action: String,
+    pub present: bool,
+    pub zone: Option<String>,
+    pub table: String,
+    pub chain: String,
+}
+
+impl IsTask for FirewallTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        let present = match handle.template.string_option_default(request, tm, &String::from("state"), &self.state, &String::from("present"))?.as_str() {
+            "present" => true,
+            "absent"  => false,
+            _ if tm == TemplateMode::Off => true,
+            x => { return Err(handle.response.is_failed(request, &format!("field (state): expecting present or absent, got '{}'", x))); }
+        };
+
+        let port = handle.template.string_no_spaces(request, tm, &String::from("port"), &self.port)?;
+        let proto = handle.template.string_option_default(request, tm, &String::from("proto"), &self.proto, &String::from("tcp"))?;
+        let action = handle.template.string_option_default(request, tm, &String::from("action"), &self.action, &String::from("accept"))?;
+        // sources are CIDR blocks, the slash is fine as the value is checked below
+        let source = handle.template.string_option_no_spaces(request, tm, &String::from("source"), &self.source)?;
+
+        if tm == TemplateMode::Strict {
+            if ! FirewallTask::is_valid_port(&port) {
+                return Err(handle.response.is_failed(request, &format!("field (port): expecting a port number or a range such as 8000-8100, got '{}'", port)));
+            }
+            if ! (proto.eq("tcp") || proto.eq("udp")) {
+                return Err(handle.response.is_failed(request, &format!("field (proto): expecting tcp or udp, got '{}'", proto)));
+            }
+            if ! (action.eq("accept") || action.eq("drop") || action.eq("reject")) {
+                return Err(handle.response.is_failed(request, &format!("field (action): expecting accept, drop or reject, got '{}'", action)));
+            }
+            if source.as_ref().is_some_and(|x| FirewallTask::get_family(x).is_none()) {
+                return Err(handle.response.is_failed(request, &format!("field (source): expecting an address or CIDR block, got '{}'", source.as_ref().unwrap())));
+            }
+        }
+
+        let backend = match handle.template.string_option_no_spaces(request, tm, &String::from("backend"), &self.backend)? {
+            Some(x) => x,
+            None => match (tm, self.get_backend(handle)) {
+                (_, Ok(x)) => x,
+                (TemplateMode::Off, Err(_)) => String::from("nftables"),
+                (TemplateMode::Strict, Err(msg)) => { return Err(handle.response.is_failed(request, &msg)); }
+            }
+        };
+        let backend = match backend.as_str() {
+            "firewalld" => Backend::Firewalld,
+            "nftables"  => Backend::Nftables,
+            _ if tm == TemplateMode::Off => Backend::Nftables,
+            x => { return Err(handle.response.is_failed(request, &format!("field (backend): expecting firewalld or nftables, got '{}'", x))); }
+        };
+
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(FirewallAction {
+                    backend: backend,
+                    port:    port,
+                    proto:   proto,
+                    source:  source,
+                    action:  action,
+                    present: present,
+                    zone:    handle.template.string_option_no_spaces(request, tm, &String::from("zone"), &self.zone)?,
+                    table:   handle.template.string_option_default(request, tm, &String::from("table"), &self.table, &String::from("inet filter"))?,
+                    chain:   handle.template.string_option_default(request, tm, &String::from("chain"), &self.chain, &String::from("input"))?,
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl FirewallTask {
+
+    fn get_backend(&self, handle: &Arc<TaskHandle>) -> Result<String, String> {
+        let host = handle.host.read().unwrap();
+        return match host.get_blended_variables().get("jet_firewall") {
+            Some(serde_yaml::Value::String(x)) if x.eq("none") => Err(String::from("neither firewalld nor nftables were found on the host")),
+            Some(serde_yaml::Value::String(x)) => Ok(x.clone()),
+            _ => Err(String::from("the firewall module needs the jet_firewall fact, remove --no-facts or set 'backend'"))
+        };
+    }
+
+    fn is_valid_port(port: &String) -> bool {
+        let valid = |x: &str| x.parse::<u16>().is_ok_and(|n| n > 0);
+        return match port.split_once('-') {
+            Some((a, b)) => valid(a) && valid(b) && a.parse::<u16>().unwrap() < b.parse::<u16>().unwrap(),
+            None => valid(port)
+        };
+    }
+
+    // returns "ipv4" or "ipv6" for an address or CIDR block, None when it is neither
+
+    fn get_family(source: &String) -> Option<&'static str> {
+        let (address, prefix) = match source.split_once('/') {
+            Some((a, p)) => (a, Some(p)),
+            None => (source.as_str(), None)
+        };
+        let (family, max) = match address.parse::<IpAddr>() {
+            Ok(IpAddr::V4(_)) => ("ipv4", 32),
+            Ok(IpAddr::V6(_)) => ("ipv6", 128),
+            Err(_) => { return None; }
+        };
+        return match prefix {
+            Some(p) if ! p.parse::<u8>().is_ok_and(|n| n <= max) => None,
+            _ => Some(family)
+        };
+    }
+
+}
+
+impl IsAction for FirewallAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                if handle.remote.get_os_type() != HostOSType::Linux {
+                    return Err(handle.response.is_failed(request, &String::from("firewall is only supported on Linux")));
+                }
+                let mut changes : Vec<Field> = Vec::new();
+                if self.has_rule(handle, request, false)? != self.present {
+                    changes.push(Field::Runtime);
+                }
+                if self.has_rule(handle, request, true)? != self.present {
+                    changes.push(Field::Persisted);
+                }
+                if changes.is_empty() {
+                    return Ok(handle.response.is_matched(request));
+                }
+                return Ok(handle.response.needs_modification(request, &changes));
+            },
+
+            TaskRequestType::Modify => {
+                // nftables saves the running ruleset, so the running side has to be changed first
+                if request.changes.contains(&Field::Runtime) {
+                    self.change_rule(handle, request, false)?;
+                }
+                if request.changes.contains(&Field::Persisted) {
+                    self.change_rule(handle, request, true)?;
+                }
+                return Ok(handle.response.is_modified(request, request.changes.clone()));
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+    }
+
+}
+
+impl FirewallAction {
+
+    // firewalld keeps plain port openings apart from rich rules, anything else needs a rich rule
+
+    fn get_firewalld_option(&self) -> String {
+        if self.source.is_none() && self.action.eq("accept") {
+            return format!("port={}/{}", self.port, self.proto);
+        }
+        let mut rule = String::from("rule");
+        if let Some(source) = &self.source {
+            rule.push_str(&format!(" family=\"{}\" source address=\"{}\"", FirewallTask::get_family(source).unwrap(), source));
+        }
+        rule.push_str(&format!(" port port=\"{}\" protocol=\"{}\" {}", self.port, self.proto, self.action));
+        return format!("rich-rule='{}'", rule);
+    }
+
+    fn get_firewalld_command(&self, verb: &str, permanent: bool) -> String {
+        let mut cmd = String::from("firewall-cmd");
+        if let Some(zone) = &self.zone {
+            cmd.push_str(&format!(" --zone={}", zone));
+        }
+        if permanent {
+            cmd.push_str(" --permanent");
+        }
+        return format!("{} --{}-{}", cmd, verb, self.get_firewalld_option());
+    }
+
+    // nftables rules written by this module are found again by this comment, which names everything about them
+
+    fn get_nft_comment(&self) -> String {
+        return match &self.source {
+            Some(source) => format!("jetp {}/{} from {} {}", self.port, self.proto, source, self.action),
+            None => format!("jetp {}/{} {}", self.port, self.proto, self.action)
+        };
+    }
+
+    fn get_nft_rule(&self) -> String {
+        let mut rule = String::new();
+        if let Some(source) = &self.source {
+            let family = match FirewallTask::get_family(source) { Some("ipv6") => "ip6", _ => "ip" };
+            rule.push_str(&format!("{} saddr {} ", family, source));
+        }
+        rule.push_str(&format!("{} dport {} {} comment \"{}\"", self.proto, self.port, self.action, self.get_nft_comment()));
+        return rule;
+    }
+
+    fn has_rule(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, persisted: bool) -> Result<bool, Arc<TaskResponse>> {
+        match self.backend {
+            Backend::Firewalld => {
+                let cmd = self.get_firewalld_command("query", persisted);
+                let result = handle.remote.run(request, &cmd, CheckRc::Unchecked)?;
+                let (rc, out, err) = cmd_info_with_err(&result);
+                return match rc {
+                    0 => Ok(true),
+                    1 => Ok(false),
+                    _ => Err(handle.response.is_failed(request, &format!("firewall-cmd failed: {}{}", out.trim(), err.trim())))
+                };
+            },
+            Backend::Nftables => {
+                let marker = format!("comment \"{}\"", self.get_nft_comment());
+                if persisted {
+                    let saved = handle.remote.read_file(request, &String::from(NFT_CONF))?.unwrap_or(String::new());
+                    return Ok(saved.contains(&marker));
+                }
+                return Ok(self.find_nft_handle(handle, request)?.is_some());
+            }
+        }
+    }
+
+    // returns the handle of the rule in the chain, which 'nft delete rule' needs
+
+    fn find_nft_handle(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Option<String>, Arc<TaskResponse>> {
+        let result = handle.remote.run(request, &format!("nft -a list chain {} {}", self.table, self.chain), CheckRc::Unchecked)?;
+        let (rc, out, err) = cmd_info_with_err(&result);
+        if rc != 0 {
+            return Err(handle.response.is_failed(request, &format!("unable to list nftables chain '{} {}', set 'table' and 'chain': {}", self.table, self.chain, err.trim())));
+        }
+        let marker = format!("comment \"{}\"", self.get_nft_comment());
+        for line in out.lines() {
+            if line.contains(&marker) {
+                return Ok(line.rsplit_once("# handle ").map(|(_, h)| h.trim().to_string()));
+            }
+        }
+        return Ok(None);
+    }
+
+    // returns /etc/nftables.conf with the rule line added at the end of the block for the chain, or with every
+    // line carrying the rule's comment removed.  The block is found by following the braces of 'table' and then
+    // 'chain', so the same chain name in another table is not mistaken for it.
+
+    fn edit_nft_conf(&self, lines: &Vec<String>) -> Result<Vec<String>, String> {
+        let marker = format!("comment \"{}\"", self.get_nft_comment());
+        if ! self.present {
+            return Ok(lines.iter().filter(|x| ! x.contains(&marker)).cloned().collect());
+        }
+        if lines.iter().any(|x| x.contains(&marker)) {
+            return Ok(lines.clone());
+        }
+        let table_open = format!("table {} {{", self.table.split_whitespace().collect::<Vec<&str>>().join(" "));
+        let chain_open = format!("chain {} {{", self.chain);
+        let mut depth : usize = 0;
+        let mut table_depth : Option<usize> = None;
+        let mut chain : Option<(usize, String)> = None;
+        for (index, line) in lines.iter().enumerate() {
+            let code = match line.find('#') { Some(i) => &line[..i], None => line.as_str() };
+            let normalized = code.split_whitespace().collect::<Vec<&str>>().join(" ");
+            if table_depth.is_none() && normalized.starts_with(&table_open) {
+                table_depth = Some(depth);
+            } else if chain.is_none() && table_depth.is_some_and(|t| depth == t + 1) && normalized.starts_with(&chain_open) {
+                let indent : String = line.chars().take_while(|c| c.is_whitespace()).collect();
+                chain = Some((depth, indent));
+            }
+            depth = depth + code.matches('{').count();
+            depth = depth.saturating_sub(code.matches('}').count());
+            if let Some((chain_depth, indent)) = &chain {
+                if depth == *chain_depth {
+                    if normalized.starts_with(&chain_open) {
+                        return Err(format!("chain {} in {} is written on one line, cannot add a rule to it", self.chain, NFT_CONF));
+                    }
+                    let mut result = lines.clone();
+                    let step = match indent.contains('\t') { true => "\t", false => "    " };
+                    result.insert(index, format!("{}{}{}", indent, step, self.get_nft_rule()));
+                    return Ok(result);
+                }
+            }
+        }
+        return Err(format!("chain '{} {}' was not found in {}, set 'table' and 'chain'", self.table, self.chain, NFT_CONF));
+    }
+
+    fn change_rule(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, persisted: bool) -> Result<(), Arc<TaskResponse>> {
+        match self.backend {
+            Backend::Firewalld => {
+                let verb = match self.present { true => "add", false => "remove" };
+                handle.remote.run(request, &self.get_firewalld_command(verb, persisted), CheckRc::Checked)?;
+            },
+            Backend::Nftables if persisted => {
+                let path = String::from(NFT_CONF);
+                let before = match handle.remote.read_file(request, &path)? {
+                    Some(x) => x,
+                    None => { return Err(handle.response.is_failed(request, &format!("{} does not exist, rules could not be persisted", path))); }
+                };
+                let lines : Vec<String> = before.lines().map(|x| x.to_string()).collect();
+                let mut data = match self.edit_nft_conf(&lines) {
+                    Ok(x) => x.join("\n"),
+                    Err(y) => { return Err(handle.response.is_failed(request, &y)); }
+                };
+                data.push_str("\n");
+                handle.remote.replace_file(request, &data, &path)?;
+            },
+            Backend::Nftables => {
+                if self.present {
+                    let cmd = format!("nft 'add rule {} {} {}'", self.table, self.chain, self.get_nft_rule());
+                    handle.remote.run(request, &cmd, CheckRc::Checked)?;
+                } else if let Some(h) = self.find_nft_handle(handle, request)? {
+                    handle.remote.run(request, &format!("nft delete rule {} {} handle {}", self.table, self.chain, h), CheckRc::Checked)?;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(present: bool) -> FirewallAction {
+        return FirewallAction {
+            backend: Backend::Nftables,
+            port: String::from("22"),
+            proto: String::from("tcp"),
+            source: Some(String::from("10.0.0.0/8")),
+            action: String::from("accept"),
+            present: present,
+            zone: None,
+            table: String::from("inet filter"),
+            chain: String::from("input"),
+        };
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        return text.lines().map(|x| x.to_string()).collect();
+    }
+
+    const CONF: &str = "#!/usr/sbin/nft -f\n\ntable inet nat {\n\tchain input {\n\t}\n}\ntable inet filter {\n\tchain input {\n\t\ttype filter hook input priority filter;\n\t}\n\tchain forward {\n\t}\n}";
+
+    #[test]
+    fn test_nft_conf_add_and_remove() {
+        let before = lines(CONF);
+        let added = action(true).edit_nft_conf(&before).unwrap();
+        let rule = "\t\tip saddr 10.0.0.0/8 tcp dport 22 accept comment \"jetp 22/tcp from 10.0.0.0/8 accept\"";
+        assert_eq!(added.len(), before.len() + 1);
+        assert_eq!(added[9], rule);
+        assert_eq!(action(true).edit_nft_conf(&added).unwrap(), added);
+        assert_eq!(action(false).edit_nft_conf(&added).unwrap(), before);
+    }
+
+    #[test]
+    fn test_nft_conf_missing_chain() {
+        let mut a = action(true);
+        a.chain = String::from("output");
+        assert!(a.edit_nft_conf(&lines(CONF)).is_err());
+    }
+
+}
@@ -16,4 +16,8 @@
 
 /** ADD MODULES HERE, KEEP ALPHABETIZED **/
 
-pub mod sd_service;
\ No newline at end of file
+pub mod cron;
+pub mod firewall;
+pub mod sd_service;
+pub mod sd_unit;
+pub mod sysctl;
@@ -31,6 +31,7 @@ pub struct SystemdServiceTask {
     pub enabled: Option<String>,
     pub started: Option<String>,
     pub restart: Option<String>,
+    pub state: Option<String>,
     pub with: Option<PreLogicInput>,
     pub and: Option<PostLogicInput>
 }
@@ -40,6 +41,7 @@ struct SystemdServiceAction {
     pub enabled: Option<bool>,
     pub started: Option<bool>,
     pub restart: bool,
+    pub reload: bool,
 }
 
 #[derive(Clone,PartialEq,Debug)]
@@ -55,13 +57,33 @@ impl IsTask for SystemdServiceTask {
     fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
 
     fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+        let mut started = handle.template.boolean_option_default_none(&request, tm, &String::from("started"), &self.started)?;
+        let mut restart = handle.template.boolean_option_default_false(&request, tm, &String::from("restart"), &self.restart)?;
+        let mut reload = false;
+
+        // 'state' is a shorthand for started/restart, and also the only way to ask for a reload
+        let state = handle.template.string_option_no_spaces(&request, tm, &String::from("state"), &self.state)?;
+        if tm == TemplateMode::Strict && state.is_some() {
+            if self.started.is_some() || self.restart.is_some() {
+                return Err(handle.response.is_failed(request, &String::from("state cannot be combined with started or restart")));
+            }
+            match state.as_ref().unwrap().as_str() {
+                "started"   => { started = Some(true); },
+                "stopped"   => { started = Some(false); },
+                "restarted" => { started = Some(true); restart = true; },
+                "reloaded"  => { started = Some(true); reload = true; },
+                x => { return Err(handle.response.is_failed(request, &format!("field (state): expecting started, stopped, restarted or reloaded, got '{}'", x))); }
+            }
+        }
+
         return Ok(
             EvaluatedTask {
                 action: Arc::new(SystemdServiceAction {
                     service:    handle.template.string_no_spaces(request, tm, &String::from("service"), &self.service)?,
                     enabled:    handle.template.boolean_option_default_none(&request, tm, &String::from("enabled"), &self.enabled)?,
-                    started:    handle.template.boolean_option_default_none(&request, tm, &String::from("started"), &self.started)?,
-                    restart:    handle.template.boolean_option_default_false(&request, tm, &String::from("restart"), &self.restart)?
+                    started:    started,
+                    restart:    restart,
+                    reload:     reload
                 }),
                 with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
                 and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?)
@@ -99,6 +121,11 @@ impl IsAction for SystemdServiceAction {
                     _                            => { },
                 };
 
+                // a reload only makes sense for a running service, a stopped one is just started
+                if self.reload && actual.started {
+                    changes.push(Field::Reload);
+                }
+
 
                 if changes.len() > 0 {
                     return Ok(handle.response.needs_modification(request, &changes));
@@ -113,6 +140,7 @@ impl IsAction for SystemdServiceAction {
                 if request.changes.contains(&Field::Start)        { self.do_start(handle, request)?;   }
                 else if request.changes.contains(&Field::Stop)    { self.do_stop(handle, request)?;    }
                 else if request.changes.contains(&Field::Restart) { self.do_restart(handle, request)?; }
+                else if request.changes.contains(&Field::Reload)  { self.do_reload(handle, request)?;  }
 
                 if request.changes.contains(&Field::Enable)       { self.do_enable(handle, request)?;  }
                 else if request.changes.contains(&Field::Disable) { self.do_disable(handle, request)?; }
@@ -183,4 +211,9 @@ impl SystemdServiceAction {
         return handle.remote.run(request, &cmd, CheckRc::Checked);
     }
 
+    pub fn do_reload(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>,Arc<TaskResponse>> {
+        let cmd = format!("systemctl reload '{}'", self.service);
+        return handle.remote.run(request, &cmd, CheckRc::Checked);
+    }
+
 }
@@ -0,0 +1,208 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::*;
+use crate::handle::handle::{TaskHandle,CheckRc};
+use crate::tasks::fields::Field;
+use crate::inventory::hosts::HostOSType;
+use serde::{Deserialize};
+use std::sync::Arc;
+use std::vec::Vec;
+
+const MODULE: &str = "sysctl";
+
+// sets a kernel parameter.  The running value is changed with 'sysctl -w' and the persistent value is kept
+// as a 'key = value' line in a file under /etc/sysctl.d, read at boot.  Either side can be turned off with
+// 'apply: false' or 'persist: false', and the changes reported are Runtime and Persisted respectively.
+
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SysctlTask {
+    pub name: Option<String>,
+    pub key: String,
+    pub value: String,
+    pub file: Option<String>,
+    pub apply: Option<String>,
+    pub persist: Option<String>,
+    pub with: Option<PreLogicInput>,
+    pub and: Option<PostLogicInput>
+}
+
+struct SysctlAction {
+    pub key: String,
+    pub value: String,
+    pub file: String,
+    pub apply: bool,
+    pub persist: bool,
+}
+
+impl IsTask for SysctlTask {
+
+    fn get_module(&self) -> String { String::from(MODULE) }
+    fn get_name(&self) -> Option<String> { self.name.clone() }
+    fn get_with(&self) -> Option<PreLogicInput> { self.with.clone() }
+
+    fn evaluate(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode) -> Result<EvaluatedTask, Arc<TaskResponse>> {
+
+        let key = handle.template.string_no_spaces(request, tm, &String::from("key"), &self.key)?;
+        // values such as kernel.core_pattern may contain characters the shell cares about, they are single quoted instead
+        let value = handle.template.string_unsafe_for_shell(request, tm, &String::from("value"), &self.value)?;
+        let apply = handle.template.boolean_option_default_true(request, tm, &String::from("apply"), &self.apply)?;
+        let persist = handle.template.boolean_option_default_true(request, tm, &String::from("persist"), &self.persist)?;
+
+        if tm == TemplateMode::Strict {
+            if key.is_empty() || ! key.chars().all(|c| c.is_ascii_alphanumeric() || "._-/".contains(c)) {
+                return Err(handle.response.is_failed(request, &format!("field (key): not a kernel parameter name: {}", key)));
+            }
+            if value.contains('\'') || value.contains('\n') {
+                return Err(handle.response.is_failed(request, &String::from("field (value): must be a single line without single quotes")));
+            }
+            if ! apply && ! persist {
+                return Err(handle.response.is_failed(request, &String::from("apply and persist cannot both be false")));
+            }
+        }
+
+        let file = match &self.file {
+            Some(x) => handle.template.path(request, tm, &String::from("file"), x)?,
+            None => String::from("/etc/sysctl.d/99-jetp.conf")
+        };
+
+        return Ok(
+            EvaluatedTask {
+                action: Arc::new(SysctlAction {
+                    key:     key,
+                    value:   value,
+                    file:    file,
+                    apply:   apply,
+                    persist: persist
+                }),
+                with: Arc::new(PreLogicInput::template(&handle, &request, tm, &self.with)?),
+                and: Arc::new(PostLogicInput::template(&handle, &request, tm, &self.and)?),
+            }
+        );
+    }
+
+}
+
+impl IsAction for SysctlAction {
+
+    fn dispatch(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<Arc<TaskResponse>, Arc<TaskResponse>> {
+
+        match request.request_type {
+
+            TaskRequestType::Query => {
+                if handle.remote.get_os_type() != HostOSType::Linux {
+                    return Err(handle.response.is_failed(request, &String::from("sysctl is only supported on Linux")));
+                }
+                let mut changes : Vec<Field> = Vec::new();
+                let mut diff : Option<String> = None;
+                if self.apply && ! SysctlAction::same_value(&self.get_runtime_value(handle, request)?, &self.value) {
+                    changes.push(Field::Runtime);
+                }
+                if self.persist {
+                    let before = handle.remote.read_file(request, &self.file)?.unwrap_or(String::new());
+                    let lines : Vec<String> = before.lines().map(|x| x.to_string()).collect();
+                    let edited = self.edit(&lines);
+                    if ! edited.eq(&lines) {
+                        changes.push(Field::Persisted);
+                        diff = handle.remote.get_text_diff(&self.file, &before, &edited.join("\n"));
+                    }
+                }
+                if changes.is_empty() {
+                    return Ok(handle.response.is_matched(request));
+                }
+                return Ok(handle.response.needs_modification_with_diff(request, &changes, diff));
+            },
+
+            TaskRequestType::Modify => {
+                if request.changes.contains(&Field::Persisted) {
+                    let before = handle.remote.read_file(request, &self.file)?.unwrap_or(String::new());
+                    let lines : Vec<String> = before.lines().map(|x| x.to_string()).collect();
+                    let mut data = self.edit(&lines).join("\n");
+                    data.push_str("\n");
+                    handle.remote.replace_file(request, &data, &self.file)?;
+                }
+                if request.changes.contains(&Field::Runtime) {
+                    let cmd = format!("sysctl -w '{}={}'", self.key, self.value);
+                    handle.remote.run_unsafe(request, &cmd, CheckRc::Checked)?;
+                }
+                return Ok(handle.response.is_modified(request, request.changes.clone()));
+            },
+
+            _ => { return Err(handle.response.not_supported(request)); }
+
+        }
+    }
+
+}
+
+impl SysctlAction {
+
+    fn get_runtime_value(&self, handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>) -> Result<String, Arc<TaskResponse>> {
+        let result = handle.remote.run(request, &format!("sysctl -n '{}'", self.key), CheckRc::Unchecked)?;
+        let (rc, out, err) = cmd_info_with_err(&result);
+        if rc != 0 {
+            return Err(handle.response.is_failed(request, &format!("unable to read kernel parameter {}: {}", self.key, err.trim())));
+        }
+        return Ok(out);
+    }
+
+    // the kernel prints multi-part values such as net.ipv4.tcp_rmem separated by tabs, so only the words are compared
+
+    fn same_value(actual: &String, wanted: &String) -> bool {
+        return actual.split_whitespace().eq(wanted.split_whitespace());
+    }
+
+    // sysctl.conf allows either dots or slashes as separators in names
+
+    fn same_key(&self, key: &str) -> bool {
+        return key.replace("/", ".").eq(&self.key.replace("/", "."));
+    }
+
+    // returns the new contents of the persistent file.  The first line setting the key is replaced, unless it
+    // already has the right value, later ones are dropped so they cannot override it, otherwise a line is appended.
+
+    fn edit(&self, lines: &Vec<String>) -> Vec<String> {
+        let mut result : Vec<String> = Vec::new();
+        let mut found = false;
+        for line in lines.iter() {
+            let trimmed = line.trim_start();
+            let setting = match trimmed.starts_with('#') || trimmed.starts_with(';') {
+                true => None,
+                false => trimmed.trim_start_matches('-').split_once('=')
+            };
+            match setting {
+                Some((k, v)) if self.same_key(k.trim()) => {
+                    if found {
+                        continue;
+                    }
+                    found = true;
+                    match SysctlAction::same_value(&v.to_string(), &self.value) {
+                        true  => result.push(line.clone()),
+                        false => result.push(format!("{} = {}", self.key, self.value))
+                    }
+                },
+                _ => result.push(line.clone())
+            }
+        }
+        if ! found {
+            result.push(format!("{} = {}", self.key, self.value));
+        }
+        return result;
+    }
+
+}
@@ -0,0 +1,53 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::tasks::response::TaskResponse;
+use crate::playbooks::traversal::HandlerMode;
+use std::sync::Arc;
+
+// a callback receives the same events as the console output in visitor.rs, but as typed values
+// rather than text, for programs that drive playbooks and want the results instead of stdout.
+// every method has an empty default so implementations only need what they care about.
+// callbacks are registered with RunState::add_callback and are called in the order they were added,
+// from the worker threads, so they must be cheap and must not block.
+
+pub trait Callback: Send + Sync {
+
+    fn on_playbook_start(&self, _playbook_path: &String) {}
+
+    fn on_play_start(&self, _play: &String) {}
+
+    fn on_play_stop(&self, _play: &String, _failed: bool) {}
+
+    fn on_task_start(&self, _task: &String, _role: Option<&String>, _is_handler: HandlerMode) {}
+
+    fn on_task_skipped(&self, _task: &String, _reason: &String) {}
+
+    // called once per host for every task that succeeded, in check mode as well
+    fn on_host_task_ok(&self, _host: &String, _task: &String, _response: &Arc<TaskResponse>) {}
+
+    fn on_host_task_failed(&self, _host: &String, _task: &String, _response: &Arc<TaskResponse>) {}
+
+    fn on_host_connect_failed(&self, _host: &String) {}
+
+    fn on_exit(&self) {}
+
+    // called at the end of a run with the counts from the summary table, keyed as in the JSON SUMMARY event,
+    // plus the names of the hosts that failed under 'failed_host_names'
+    fn on_summary(&self, _summary: &serde_json::Map<String, serde_json::Value>) {}
+
+}
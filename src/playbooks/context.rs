@@ -19,8 +19,11 @@ use crate::playbooks::language::{Play,Role,RoleInvocation};
 use std::path::PathBuf;
 use std::collections::HashMap;
 use crate::inventory::hosts::Host;
-use std::sync::{Arc,RwLock};
+use std::sync::{Arc,RwLock,Mutex};
+use std::sync::atomic::{AtomicUsize,Ordering};
 use crate::connection::cache::ConnectionCache;
+use crate::connection::bastion::SshBastion;
+use crate::connection::ssh::{SshAuth,HostKeyChecking,parse_host_key_checking};
 use crate::registry::list::Task;
 use crate::util::yaml::blend_variables;
 use crate::playbooks::templar::{Templar,TemplateMode};
@@ -28,6 +31,10 @@ use crate::cli::parser::CliParser;
 use crate::handle::template::BlendTarget;
 use std::ops::Deref;
 use std::env;
+use crate::util::terminal::stdin_is_terminal;
+use crate::util::vault::redact;
+use crate::tasks::response::{TaskResponse,TaskStatus};
+use std::time::{Duration,Instant};
 use guid_create::GUID;
 use expanduser::expanduser;
 
@@ -37,7 +44,7 @@ use expanduser::expanduser;
 
 pub struct PlaybookContext {
 
-    pub verbosity: u32,
+    pub verbosity: i32,
 
     pub playbook_path: Option<String>,
     pub playbook_directory: Option<String>,
@@ -50,21 +57,26 @@ pub struct PlaybookContext {
 
     pub task_count: usize,
     pub task: Option<String>,
+    // with/no_log hides commands and their output for the current task, see visitor.rs
+    pub no_log: bool,
+
+    // exit code to use when nothing failed but changes were made, see --changed-exit-code
+    pub changed_exit_code:    Option<i32>,
+
+    // wall clock timing, only shown in the summary with --timing
+    pub show_timing:          bool,
+    play_started:             Option<Instant>,
+    task_started:             Option<(String, Instant)>,
+    play_durations:           Vec<(String, Duration)>,
+    task_durations:           Vec<(String, Duration)>,
     
     seen_hosts:               HashMap<String, Arc<RwLock<Host>>>,
     targetted_hosts:          HashMap<String, Arc<RwLock<Host>>>,
     failed_hosts:             HashMap<String, Arc<RwLock<Host>>>,
 
-    attempted_count_for_host: HashMap<String, usize>,
-    adjusted_count_for_host:  HashMap<String, usize>,
-    created_count_for_host:   HashMap<String, usize>,
-    removed_count_for_host:   HashMap<String, usize>,
-    modified_count_for_host:  HashMap<String, usize>,
-    executed_count_for_host:  HashMap<String, usize>,
-    passive_count_for_host:   HashMap<String, usize>,
-    matched_count_for_host:   HashMap<String, usize>,
-    skipped_count_for_host:   HashMap<String, usize>,
-    failed_count_for_host:    HashMap<String, usize>,
+    // the summary counts.  Each host has its own atomic counters, so workers reporting results only need a
+    // read lock on the context, and the map is only written the first time a host reports anything
+    counts_for_host:          RwLock<HashMap<String, Arc<HostCounts>>>,
     
     // TODO: some of these don't need to be pub.
     pub failed_tasks:           usize,
@@ -72,6 +84,7 @@ pub struct PlaybookContext {
     pub vars_storage:           RwLock<serde_yaml::Mapping>,
     pub role_defaults_storage:  RwLock<serde_yaml::Mapping>,
     pub role_vars_storage:      RwLock<serde_yaml::Mapping>,
+    pub include_vars_storage:   RwLock<serde_yaml::Mapping>,
     pub env_storage:            RwLock<serde_yaml::Mapping>,
     
     pub connection_cache:     RwLock<ConnectionCache>,
@@ -79,9 +92,46 @@ pub struct PlaybookContext {
 
     pub ssh_user:             String,
     pub ssh_port:             i64,
+    pub host_key_checking:    HostKeyChecking,
     pub sudo:                 Option<String>,
+    pub sudo_password:        Option<String>,
+    pub connection:           Option<String>,
+    pub start_at_task:        Option<String>,
+    pub step:                 bool,
+    pub auto_continue:        bool,
+    skipped_task_names:       Vec<String>,
     extra_vars:               serde_yaml::Value,
 
+    // with any_errors_fatal on a play, the first host to fail stops the whole run
+    any_errors_fatal:         bool,
+    fatal_cancels_running:    bool,
+    fatal_host:               Option<String>,
+
+    // handler accounting for the end of play summary, keyed by the name handlers subscribe to
+    handler_notified_by:      HashMap<String, Vec<String>>,
+    handler_hosts_run:        HashMap<String, Vec<String>>,
+
+    // with --diff-out, what each host changed (or would change in check mode), see record_change
+    pub diff_out:             Option<String>,
+    changes_for_host:         Mutex<HashMap<String, Vec<serde_json::Value>>>,
+
+}
+
+// per host summary counts, see PlaybookContext::counts
+
+#[derive(Default)]
+struct HostCounts {
+    attempted: AtomicUsize,
+    adjusted:  AtomicUsize,
+    created:   AtomicUsize,
+    removed:   AtomicUsize,
+    modified:  AtomicUsize,
+    executed:  AtomicUsize,
+    passive:   AtomicUsize,
+    matched:   AtomicUsize,
+    skipped:   AtomicUsize,
+    failed:    AtomicUsize,
+    retry:     AtomicUsize,
 }
 
 impl PlaybookContext {
@@ -92,36 +142,53 @@ impl PlaybookContext {
             playbook_path: None,
             playbook_directory: None,
             failed_tasks: 0,
+            any_errors_fatal: false,
+            fatal_cancels_running: false,
+            fatal_host: None,
+            handler_notified_by: HashMap::new(),
+            handler_hosts_run: HashMap::new(),
+            diff_out: parser.diff_out.clone(),
+            changes_for_host: Mutex::new(HashMap::new()),
             play: None,
             role: None,
             task: None,
+            no_log: false,
             play_count : 0,
             role_count : 0,
             task_count : 0,
+            changed_exit_code: parser.changed_exit_code,
+            show_timing: parser.show_timing,
+            play_started: None,
+            task_started: None,
+            play_durations: Vec::new(),
+            task_durations: Vec::new(),
             seen_hosts: HashMap::new(),
             targetted_hosts: HashMap::new(),
             failed_hosts: HashMap::new(),
             role_path: None,
-            adjusted_count_for_host:  HashMap::new(),
-            attempted_count_for_host: HashMap::new(),
-            created_count_for_host:   HashMap::new(),
-            removed_count_for_host:   HashMap::new(),
-            modified_count_for_host:  HashMap::new(),
-            executed_count_for_host:  HashMap::new(),
-            passive_count_for_host:   HashMap::new(),
-            matched_count_for_host:   HashMap::new(),
-            failed_count_for_host:    HashMap::new(),
-            skipped_count_for_host:   HashMap::new(),
+            counts_for_host:          RwLock::new(HashMap::new()),
             connection_cache:         RwLock::new(ConnectionCache::new()),
             templar:                  RwLock::new(Templar::new()),
             defaults_storage:         RwLock::new(serde_yaml::Mapping::new()),
             vars_storage:             RwLock::new(serde_yaml::Mapping::new()),
             role_vars_storage:        RwLock::new(serde_yaml::Mapping::new()),
+            include_vars_storage:     RwLock::new(serde_yaml::Mapping::new()),
             role_defaults_storage:    RwLock::new(serde_yaml::Mapping::new()),
             env_storage:              RwLock::new(serde_yaml::Mapping::new()),
             ssh_user:                 parser.default_user.clone(),
             ssh_port:                 parser.default_port,
+            host_key_checking:        parser.host_key_checking,
             sudo:                     parser.sudo.clone(),
+            sudo_password:            match &parser.sudo_password {
+                Some(x) => Some(x.clone()),
+                None => env::var("JET_SUDO_PASSWORD").ok()
+            },
+            connection:               None,
+            start_at_task:            parser.start_at_task.clone(),
+            // CI jobs have nobody to answer the question, so --step quietly does nothing there
+            step:                     parser.step && stdin_is_terminal(),
+            auto_continue:            parser.auto_continue,
+            skipped_task_names:       Vec::new(),
             extra_vars:               parser.extra_vars.clone(),
         };
         s.load_environment();
@@ -151,6 +218,13 @@ impl PlaybookContext {
         self.ssh_port = ssh_port;
     }
 
+    // plays may ask for 'connection: local' to manage the control machine without SSH,
+    // this is reset at the start of every play so it does not leak into the next one
+
+    pub fn set_connection(&mut self, connection: &Option<String>) {
+        self.connection = connection.clone();
+    }
+
     // used in traversal to tell the context what the current set of possible
     // hosts is.
 
@@ -180,6 +254,51 @@ impl PlaybookContext {
         
         self.targetted_hosts.remove(&hostname);
         self.failed_hosts.insert(hostname.clone(), Arc::clone(&host));
+        if self.any_errors_fatal && self.fatal_host.is_none() {
+            self.fatal_host = Some(hostname.clone());
+        }
+    }
+
+    // set once a host fails in a play with any_errors_fatal.  No new hosts are started after this,
+    // and hosts already running stop at their next opportunity if fatal_cancels_running is set.
+
+    pub fn get_fatal_host(&self) -> Option<String> {
+        return self.fatal_host.clone();
+    }
+
+    pub fn should_cancel_running(&self) -> bool {
+        return self.fatal_host.is_some() && self.fatal_cancels_running;
+    }
+
+    // a host that failed inside a block with a 'rescue' section gets another chance.  The failure
+    // no longer counts against it in the summary, though the rescue tasks may fail it again.
+
+    pub fn rescue_host(&mut self, host: &Arc<RwLock<Host>>) {
+        let hostname = host.read().unwrap().name.clone();
+        if self.failed_hosts.remove(&hostname).is_none() {
+            return;
+        }
+        self.failed_tasks = self.failed_tasks.saturating_sub(1);
+        if let Some(counts) = self.counts_for_host.read().unwrap().get(&hostname) {
+            let _ = counts.failed.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| Some(x.saturating_sub(1)));
+        }
+        self.targetted_hosts.insert(hostname, Arc::clone(&host));
+    }
+
+    // 'always' sections of blocks also run on hosts that failed, which stay failed afterwards.
+    // this targets the hosts without changing their failure status, set_targetted_hosts puts
+    // things back.
+
+    pub fn set_targetted_hosts_including_failed(&mut self, hosts: &Vec<Arc<RwLock<Host>>>) {
+        self.targetted_hosts.clear();
+        for host in hosts.iter() {
+            let hostname = host.read().unwrap().name.clone();
+            self.targetted_hosts.insert(hostname, Arc::clone(&host));
+        }
+    }
+
+    pub fn is_host_failed(&self, host: &Arc<RwLock<Host>>) -> bool {
+        return self.failed_hosts.contains_key(&host.read().unwrap().name);
     }
 
     pub fn set_playbook_path(&mut self, path: &PathBuf) {
@@ -189,11 +308,16 @@ impl PlaybookContext {
 
     pub fn set_task(&mut self, task: &Task) {
         self.task = Some(task.get_display_name());
+        self.no_log = task.get_with().and_then(|x| x.no_log).unwrap_or(false);
     }
 
     pub fn set_play(&mut self, play: &Play) {
         self.play = Some(play.name.clone());
         self.play_count = self.play_count + 1;
+        self.any_errors_fatal = play.any_errors_fatal.unwrap_or(false);
+        self.fatal_cancels_running = play.fatal_cancels_running.unwrap_or(false);
+        self.handler_notified_by.clear();
+        self.handler_hosts_run.clear();
     }
 
     pub fn get_play_name(&self) -> String {
@@ -203,15 +327,19 @@ impl PlaybookContext {
         }
     }
 
+    pub fn get_task_name(&self) -> String {
+        return match &self.task {
+            Some(x) => x.clone(),
+            None => panic!("attempting to read a task name before tasks have been evaluated")
+        }
+    }
+
     pub fn set_role(&mut self, role: &Role, invocation: &RoleInvocation, role_path: &String) {
         self.role = Some(role.clone());
         self.role_path = Some(role_path.clone());
-        if role.defaults.is_some() { 
-             *self.role_defaults_storage.write().unwrap() = role.defaults.as_ref().unwrap().clone();
-        }
-        if invocation.vars.is_some() { 
-            *self.role_vars_storage.write().unwrap() = invocation.vars.as_ref().unwrap().clone();
-        }
+        // replaced even when empty, so one role's defaults and vars never carry over into the next role
+        *self.role_defaults_storage.write().unwrap() = role.defaults.clone().unwrap_or_default();
+        *self.role_vars_storage.write().unwrap() = invocation.vars.clone().unwrap_or_default();
     }
 
     pub fn unset_role(&mut self) {
@@ -221,8 +349,35 @@ impl PlaybookContext {
         self.role_vars_storage.write().unwrap().clear();
     }
 
+    // vars given to import_tasks or include_tasks apply only to the tasks in that file.  Files included
+    // from there also see them, so the previous set is returned to be put back with restore_include_vars.
+
+    pub fn push_include_vars(&self, vars: &Option<serde_yaml::Mapping>) -> serde_yaml::Mapping {
+        let previous = self.include_vars_storage.read().unwrap().clone();
+        if let Some(vars) = vars {
+            let mut blended = serde_yaml::Value::Mapping(previous.clone());
+            blend_variables(&mut blended, serde_yaml::Value::Mapping(vars.clone()));
+            if let serde_yaml::Value::Mapping(x) = blended {
+                *self.include_vars_storage.write().unwrap() = x;
+            }
+        }
+        return previous;
+    }
+
+    pub fn restore_include_vars(&self, previous: serde_yaml::Mapping) {
+        *self.include_vars_storage.write().unwrap() = previous;
+    }
+
     // template functions need to access all the variables about a host taking variable precendence rules into effect
-    // to get a dictionary of variables to use in template expressions
+    // to get a dictionary of variables to use in template expressions.  From lowest to highest priority:
+    //
+    //    role defaults, play defaults, group vars, host vars, facts, play vars, role vars, include vars, --extra-vars
+    //
+    // role defaults are the role's defaults/main.yml with the 'defaults' from role.yml on top, and only apply
+    // while that role's tasks run.  Anything set by the play or inventory overrides them.
+    //
+    // group and host vars (and facts) are blended by the host itself, see hosts.rs. Env vars are only
+    // added for the template module and do not collide with the other sources.
 
     pub fn get_complete_blended_variables(&self, host: &Arc<RwLock<Host>>, blend_target: BlendTarget) -> serde_yaml::Mapping  {
         let blended = self.get_complete_blended_variables_as_value(host, blend_target);
@@ -235,14 +390,14 @@ impl PlaybookContext {
     pub fn get_complete_blended_variables_as_value(&self, host: &Arc<RwLock<Host>>, blend_target: BlendTarget) -> serde_yaml::Value  {
         
         let mut blended = serde_yaml::Value::from(serde_yaml::Mapping::new());
-        let src1 = self.defaults_storage.read().unwrap();
-        let src1a = src1.deref();
-        blend_variables(&mut blended, serde_yaml::Value::Mapping(src1a.clone()));
-        
         let src1r = self.role_defaults_storage.read().unwrap();
         let src1ar = src1r.deref();
         blend_variables(&mut blended, serde_yaml::Value::Mapping(src1ar.clone()));
 
+        let src1 = self.defaults_storage.read().unwrap();
+        let src1a = src1.deref();
+        blend_variables(&mut blended, serde_yaml::Value::Mapping(src1a.clone()));
+
         let src2 = host.read().unwrap().get_blended_variables();
         blend_variables(&mut blended, serde_yaml::Value::Mapping(src2));
 
@@ -254,6 +409,9 @@ impl PlaybookContext {
         let src3ar = src3r.deref();
         blend_variables(&mut blended, serde_yaml::Value::Mapping(src3ar.clone()));
 
+        let src3i = self.include_vars_storage.read().unwrap();
+        blend_variables(&mut blended, serde_yaml::Value::Mapping(src3i.clone()));
+
         blend_variables(&mut blended, self.extra_vars.clone());
 
         match blend_target {
@@ -306,12 +464,14 @@ impl PlaybookContext {
         let vars = self.get_complete_blended_variables(host,BlendTarget::NotTemplateModule);
         let host2 = host.read().unwrap();
 
-        let remote_hostname = match vars.contains_key(&String::from("jet_ssh_hostname")) {
-            true => match vars.get(&String::from("jet_ssh_hostname")).unwrap().as_str() {
+        // the inventory name is what shows up in output, while jet_ssh_hostname (or jet_host, which applies to
+        // any network connection) is the address actually dialed, for hosts behind NAT or without usable DNS names
+        let remote_hostname = match vars.get(&String::from("jet_ssh_hostname")).and_then(|x| x.as_str()) {
+            Some(x) => String::from(x),
+            None => match vars.get(&String::from("jet_host")).and_then(|x| x.as_str()) {
                 Some(x) => String::from(x),
                 None => host2.name.clone()
-            },
-            false => host2.name.clone()
+            }
         };
         let remote_user = match vars.contains_key(&String::from("jet_ssh_user")) {
             true => match vars.get(&String::from("jet_ssh_user")).unwrap().as_str() {
@@ -374,6 +534,221 @@ impl PlaybookContext {
         return (remote_hostname, remote_user, remote_port, keyfile, passphrase, key_comment)
     } 
 
+    // jet_ssh_auth picks how SSH logs in to a host: key, agent, password, or auto (the default), see SshAuth.
+    // With per-group jet_ssh_private_key_file values this lets segmented environments each use their own key.
+
+    pub fn get_ssh_auth(&self, host: &Arc<RwLock<Host>>) -> Result<SshAuth,String> {
+        let vars = self.get_complete_blended_variables(host,BlendTarget::NotTemplateModule);
+        return match vars.get(&String::from("jet_ssh_auth")) {
+            None => Ok(SshAuth::Auto),
+            Some(x) => match x.as_str() {
+                Some("auto")     => Ok(SshAuth::Auto),
+                Some("key")      => Ok(SshAuth::Key),
+                Some("agent")    => Ok(SshAuth::Agent),
+                Some("password") => Ok(SshAuth::Password),
+                _ => Err(format!("jet_ssh_auth must be one of 'auto', 'key', 'agent' or 'password', got {:?}", x))
+            }
+        };
+    }
+
+    // jet_ssh_host_key_checking overrides --host-key-checking for a host, see HostKeyChecking
+
+    pub fn get_host_key_checking(&self, host: &Arc<RwLock<Host>>) -> Result<HostKeyChecking,String> {
+        let vars = self.get_complete_blended_variables(host,BlendTarget::NotTemplateModule);
+        return match vars.get(&String::from("jet_ssh_host_key_checking")) {
+            None => Ok(self.host_key_checking),
+            Some(x) => match x.as_str() {
+                Some(y) => parse_host_key_checking(&String::from(y)).map_err(|e| format!("jet_ssh_host_key_checking: {}", e)),
+                None => Err(String::from("jet_ssh_host_key_checking must be a string"))
+            }
+        };
+    }
+
+    // jet_ssh_bastion (or $JET_SSH_BASTION for every host) routes SSH connections through one or more jump hosts,
+    // like OpenSSH's ProxyJump.  It is either a string of comma separated "user@host:port" entries, where the user and
+    // port are optional, or a list of those strings or of mappings with host, user, port, private_key_file,
+    // private_key_passphrase and key_comment keys.  Bastions are connected to in order, and log in with the same
+    // user and credentials as the target unless they say otherwise.
+
+    pub fn get_ssh_bastions(&self, host: &Arc<RwLock<Host>>, user: &String, auth: SshAuth, host_key_checking: HostKeyChecking, key: &Option<String>, passphrase: &Option<String>, 
+        key_comment: &Option<String>, login_password: &Option<String>) -> Result<Vec<SshBastion>,String> {
+
+        let vars = self.get_complete_blended_variables(host,BlendTarget::NotTemplateModule);
+        let entries : Vec<serde_yaml::Value> = match vars.get(&String::from("jet_ssh_bastion")) {
+            Some(serde_yaml::Value::String(x)) => x.split(',').map(|e| serde_yaml::Value::String(String::from(e))).collect(),
+            Some(serde_yaml::Value::Sequence(x)) => x.clone(),
+            Some(serde_yaml::Value::Null) => Vec::new(),
+            Some(_) => { return Err(String::from("jet_ssh_bastion must be a string or a list")); },
+            None => match env::var("JET_SSH_BASTION") {
+                Ok(x) if ! x.is_empty() => x.split(',').map(|e| serde_yaml::Value::String(String::from(e))).collect(),
+                _ => Vec::new()
+            }
+        };
+
+        let mut results : Vec<SshBastion> = Vec::new();
+        for entry in entries.iter() {
+            let mut bastion = SshBastion {
+                hostname: String::new(),
+                port: 22,
+                username: user.clone(),
+                auth: auth,
+                host_key_checking: host_key_checking,
+                login_password: login_password.clone(),
+                key: key.clone(),
+                passphrase: passphrase.clone(),
+                key_comment: key_comment.clone()
+            };
+            match entry {
+                serde_yaml::Value::String(spec) => {
+                    let spec = spec.trim();
+                    let (spec_user, rest) = match spec.split_once('@') {
+                        Some((u, r)) => (Some(u), r),
+                        None => (None, spec)
+                    };
+                    let (spec_host, spec_port) = match rest.rsplit_once(':') {
+                        Some((h, p)) => match p.parse::<i64>() {
+                            Ok(n) => (h, Some(n)),
+                            Err(_) => { return Err(format!("jet_ssh_bastion: invalid port in '{}'", spec)); }
+                        },
+                        None => (rest, None)
+                    };
+                    if let Some(u) = spec_user { bastion.username = String::from(u); }
+                    if let Some(p) = spec_port { bastion.port = p; }
+                    bastion.hostname = String::from(spec_host);
+                },
+                serde_yaml::Value::Mapping(map) => {
+                    let get_str = |k: &str| -> Result<Option<String>,String> {
+                        return match map.get(&serde_yaml::Value::String(String::from(k))) {
+                            Some(serde_yaml::Value::String(x)) => Ok(Some(x.clone())),
+                            Some(serde_yaml::Value::Number(x)) => Ok(Some(x.to_string())),
+                            None => Ok(None),
+                            Some(_) => Err(format!("jet_ssh_bastion: {} must be a string", k))
+                        };
+                    };
+                    for k in map.keys() {
+                        match k.as_str() {
+                            Some("host") | Some("user") | Some("port") | Some("private_key_file") | Some("private_key_passphrase") | Some("key_comment") => {},
+                            _ => { return Err(format!("jet_ssh_bastion: unknown key {:?}, expecting host, user, port, private_key_file, private_key_passphrase or key_comment", k)); }
+                        }
+                    }
+                    bastion.hostname = match get_str("host")? {
+                        Some(x) => x,
+                        None => { return Err(String::from("jet_ssh_bastion: each entry needs a host")); }
+                    };
+                    if let Some(u) = get_str("user")? { bastion.username = u; }
+                    if let Some(p) = get_str("port")? {
+                        bastion.port = match p.parse::<i64>() {
+                            Ok(n) => n,
+                            Err(_) => { return Err(format!("jet_ssh_bastion: invalid port '{}'", p)); }
+                        };
+                    }
+                    // a bastion with its own key does not also try the target's password or agent key
+                    if let Some(k) = get_str("private_key_file")? {
+                        bastion.key = match expanduser(k.clone()) {
+                            Ok(expanded) => Some(expanded.display().to_string()),
+                            Err(_) => Some(k)
+                        };
+                        bastion.passphrase = get_str("private_key_passphrase")?;
+                        bastion.login_password = None;
+                        bastion.key_comment = None;
+                        bastion.auth = SshAuth::Key;
+                    }
+                    if let Some(c) = get_str("key_comment")? {
+                        bastion.key_comment = Some(c);
+                        bastion.key = None;
+                        bastion.login_password = None;
+                        bastion.auth = SshAuth::Agent;
+                    }
+                },
+                _ => { return Err(String::from("jet_ssh_bastion: entries must be strings or mappings")); }
+            };
+            if bastion.hostname.is_empty() {
+                return Err(String::from("jet_ssh_bastion: empty bastion host name"));
+            }
+            results.push(bastion);
+        }
+        return Ok(results);
+    }
+
+    // the connection type for a host is "ssh" unless the play or the jet_connection
+    // magic variable in inventory says otherwise. The host variable wins.
+
+    pub fn get_connection_type(&self, host: &Arc<RwLock<Host>>) -> Result<String,String> {
+        let vars = self.get_complete_blended_variables(host,BlendTarget::NotTemplateModule);
+        let connection = match vars.get(&String::from("jet_connection")) {
+            Some(x) => match x.as_str() {
+                Some(y) => String::from(y),
+                None => { return Err(String::from("jet_connection must be a string")); }
+            },
+            None => match &self.connection {
+                Some(x) => x.clone(),
+                None => String::from("ssh")
+            }
+        };
+        return match connection.as_str() {
+            "ssh" | "local" | "docker" | "podman" | "winrm" => Ok(connection),
+            _ => Err(format!("unknown connection type '{}', expecting 'ssh', 'local', 'docker', 'podman' or 'winrm'", connection))
+        };
+    }
+
+    // Windows hosts are reached over WinRM at the jet_ssh_hostname, jet_host, or inventory name of the host, as the jet_ssh_user.
+    // jet_winrm_scheme picks http or https (the default), jet_winrm_port overrides the usual port for the scheme,
+    // jet_winrm_validate_certs can be turned off for self-signed certificates, and jet_winrm_password is used
+    // when no login password was given on the command line.  Returns the endpoint URL, password and whether to
+    // validate certificates.
+
+    pub fn get_winrm_connection_details(&self, host: &Arc<RwLock<Host>>) -> Result<(String,Option<String>,bool),String> {
+        let vars = self.get_complete_blended_variables(host,BlendTarget::NotTemplateModule);
+        let (hostname, _user, _port, _key, _passphrase, _comment) = self.get_ssh_connection_details(host);
+        let scheme = match vars.get(&String::from("jet_winrm_scheme")) {
+            Some(x) => match x.as_str() {
+                Some("http") => "http",
+                Some("https") => "https",
+                _ => { return Err(String::from("jet_winrm_scheme must be 'http' or 'https'")); }
+            },
+            None => "https"
+        };
+        let port = match vars.get(&String::from("jet_winrm_port")) {
+            Some(x) => match x.as_i64() {
+                Some(y) => y,
+                None => match x.as_str().map(|y| y.parse::<i64>()) {
+                    Some(Ok(y)) => y,
+                    _ => { return Err(String::from("jet_winrm_port must be a number")); }
+                }
+            },
+            None => match scheme { "http" => 5985, _ => 5986 }
+        };
+        let password = match vars.get(&String::from("jet_winrm_password")) {
+            Some(x) => match x.as_str() {
+                Some(y) => Some(String::from(y)),
+                None => { return Err(String::from("jet_winrm_password must be a string")); }
+            },
+            None => None
+        };
+        let validate_certs = match vars.get(&String::from("jet_winrm_validate_certs")) {
+            Some(x) => match x.as_bool() {
+                Some(y) => y,
+                None => { return Err(String::from("jet_winrm_validate_certs must be true or false")); }
+            },
+            None => true
+        };
+        return Ok((format!("{}://{}:{}/wsman", scheme, hostname, port), password, validate_certs));
+    }
+
+    // hosts reached with the docker or podman connection types name their container with
+    // jet_container, or else the inventory name is the container name
+
+    pub fn get_container_name(&self, host: &Arc<RwLock<Host>>) -> Result<String,String> {
+        let vars = self.get_complete_blended_variables(host,BlendTarget::NotTemplateModule);
+        return match vars.get(&String::from("jet_container")) {
+            Some(x) => match x.as_str() {
+                Some(y) => Ok(String::from(y)),
+                None => Err(String::from("jet_container must be a string"))
+            },
+            None => Ok(host.read().unwrap().name.clone())
+        };
+    }
+
     // loads environment variables into the context, adding an "ENV_foo" prefix
     // to each environment variable "foo". These variables will only be made available
     // to the template module since we use them for secret management features.
@@ -426,126 +801,289 @@ impl PlaybookContext {
         self.task_count = self.task_count + 1;
     }
 
-    pub fn increment_attempted_for_host(&mut self, host: &String) {
-        *self.attempted_count_for_host.entry(host.clone()).or_insert(0) += 1;
+    // returns the counters for a host, adding them on the first result for that host
+
+    fn counts(&self, host: &String) -> Arc<HostCounts> {
+        if let Some(counts) = self.counts_for_host.read().unwrap().get(host) {
+            return Arc::clone(counts);
+        }
+        return Arc::clone(self.counts_for_host.write().unwrap().entry(host.clone()).or_insert_with(|| Arc::new(HostCounts::default())));
+    }
+
+    // the sum of one counter over all hosts, and the number of hosts where it is not zero
+
+    fn get_total(&self, counter: fn(&HostCounts) -> &AtomicUsize) -> usize {
+        return self.counts_for_host.read().unwrap().values().fold(0, |ttl, x| ttl + counter(x).load(Ordering::Relaxed));
     }
 
-    pub fn increment_created_for_host(&mut self, host: &String) {
-        *self.created_count_for_host.entry(host.clone()).or_insert(0) += 1;
-        *self.adjusted_count_for_host.entry(host.clone()).or_insert(0) += 1;
+    fn get_hosts(&self, counter: fn(&HostCounts) -> &AtomicUsize) -> usize {
+        return self.counts_for_host.read().unwrap().values().filter(|x| counter(x).load(Ordering::Relaxed) > 0).count();
     }
 
-    pub fn increment_removed_for_host(&mut self, host: &String) {
-        *self.removed_count_for_host.entry(host.clone()).or_insert(0) += 1;
-        *self.adjusted_count_for_host.entry(host.clone()).or_insert(0) += 1;
+    pub fn increment_attempted_for_host(&self, host: &String) {
+        self.counts(host).attempted.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub fn increment_modified_for_host(&mut self, host: &String) {
-        *self.modified_count_for_host.entry(host.clone()).or_insert(0) += 1;
-        *self.adjusted_count_for_host.entry(host.clone()).or_insert(0) += 1;
+    pub fn increment_created_for_host(&self, host: &String) {
+        let counts = self.counts(host);
+        counts.created.fetch_add(1, Ordering::Relaxed);
+        counts.adjusted.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub fn increment_executed_for_host(&mut self, host: &String) {
-        *self.executed_count_for_host.entry(host.clone()).or_insert(0) += 1;
-        *self.adjusted_count_for_host.entry(host.clone()).or_insert(0) += 1;
+    pub fn increment_removed_for_host(&self, host: &String) {
+        let counts = self.counts(host);
+        counts.removed.fetch_add(1, Ordering::Relaxed);
+        counts.adjusted.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub fn increment_failed_for_host(&mut self, host: &String) {
-        *self.failed_count_for_host.entry(host.clone()).or_insert(0) += 1;
+    pub fn increment_modified_for_host(&self, host: &String) {
+        let counts = self.counts(host);
+        counts.modified.fetch_add(1, Ordering::Relaxed);
+        counts.adjusted.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub fn increment_passive_for_host(&mut self, host: &String) {
-        *self.passive_count_for_host.entry(host.clone()).or_insert(0) += 1;
+    pub fn increment_executed_for_host(&self, host: &String) {
+        let counts = self.counts(host);
+        counts.executed.fetch_add(1, Ordering::Relaxed);
+        counts.adjusted.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub fn increment_matched_for_host(&mut self, host: &String) {
-        *self.matched_count_for_host.entry(host.clone()).or_insert(0) += 1;
+    pub fn increment_failed_for_host(&self, host: &String) {
+        self.counts(host).failed.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub fn increment_skipped_for_host(&mut self, host: &String) {
-        *self.skipped_count_for_host.entry(host.clone()).or_insert(0) += 1;
+    pub fn increment_passive_for_host(&self, host: &String) {
+        self.counts(host).passive.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_matched_for_host(&self, host: &String) {
+        self.counts(host).matched.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_skipped_for_host(&self, host: &String) {
+        self.counts(host).skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_retry_for_host(&self, host: &String) {
+        self.counts(host).retry.fetch_add(1, Ordering::Relaxed);
     }
 
     pub fn get_total_attempted_count(&self) -> usize {
-        return self.attempted_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.attempted);
     }
 
     pub fn get_total_creation_count(&self) -> usize {
-        return self.created_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.created);
     }
 
     pub fn get_total_modified_count(&self) -> usize{
-        return self.modified_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.modified);
     }
 
     pub fn get_total_removal_count(&self) -> usize{
-        return self.removed_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.removed);
     }
 
     pub fn get_total_executions_count(&self) -> usize {
-        return self.executed_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.executed);
     }
 
     pub fn get_total_failed_count(&self) -> usize{
-        return self.failed_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.failed);
     }
 
     pub fn get_total_adjusted_count(&self) -> usize {
-        return self.adjusted_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.adjusted);
     }
 
     pub fn get_total_passive_count(&self) -> usize {
-        return self.passive_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.passive);
     }
 
     pub fn get_total_matched_count(&self) -> usize {
-        return self.matched_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.matched);
     }
 
     pub fn get_total_skipped_count(&self) -> usize {
-        return self.skipped_count_for_host.values().fold(0, |ttl, &x| ttl + x);
+        return self.get_total(|x| &x.skipped);
+    }
+
+    pub fn get_total_retry_count(&self) -> usize {
+        return self.get_total(|x| &x.retry);
     }
 
     pub fn get_hosts_creation_count(&self) -> usize {
-        return self.created_count_for_host.keys().len();
+        return self.get_hosts(|x| &x.created);
     }
 
     pub fn get_hosts_modified_count(&self) -> usize {
-        return self.modified_count_for_host.keys().len();
+        return self.get_hosts(|x| &x.modified);
     }
 
     pub fn get_hosts_removal_count(&self) -> usize {
-        return self.removed_count_for_host.keys().len();
+        return self.get_hosts(|x| &x.removed);
     }
 
     pub fn get_hosts_executions_count(&self) -> usize {
-        return self.executed_count_for_host.keys().len();
+        return self.get_hosts(|x| &x.executed);
     }
 
     pub fn get_hosts_passive_count(&self) -> usize {
-        return self.passive_count_for_host.keys().len();
+        return self.get_hosts(|x| &x.passive);
     }
 
     pub fn get_hosts_matched_count(&self) -> usize {
-        return self.matched_count_for_host.keys().len();
+        return self.get_hosts(|x| &x.matched);
     }
 
     pub fn get_hosts_skipped_count(&self) -> usize {
-        return self.skipped_count_for_host.keys().len();
+        return self.get_hosts(|x| &x.skipped);
     }
 
     pub fn get_hosts_failed_count(&self) -> usize {
-        return self.failed_count_for_host.keys().len();
+        return self.get_hosts(|x| &x.failed);
+    }
+
+    // --start-at-task skips everything until a task of that name comes up, after which
+    // the run proceeds normally.  Returns true while we are still skipping.
+
+    pub fn is_before_start_task(&mut self, task_name: &String) -> bool {
+        match &self.start_at_task {
+            None => { return false; },
+            Some(x) if x.eq(task_name) => { self.start_at_task = None; return false; },
+            Some(_) => {
+                if ! self.skipped_task_names.contains(task_name) {
+                    self.skipped_task_names.push(task_name.clone());
+                }
+                return true;
+            }
+        }
+    }
+
+    pub fn get_skipped_task_names(&self) -> Vec<String> {
+        return self.skipped_task_names.clone();
+    }
+
+    pub fn record_handler_notified(&mut self, handler: &String, task_name: &String) {
+        let tasks = self.handler_notified_by.entry(handler.clone()).or_insert(Vec::new());
+        if ! tasks.contains(task_name) {
+            tasks.push(task_name.clone());
+        }
+    }
+
+    pub fn record_handler_run(&mut self, handler: &String, host_name: &String) {
+        let hosts = self.handler_hosts_run.entry(handler.clone()).or_insert(Vec::new());
+        if ! hosts.contains(host_name) {
+            hosts.push(host_name.clone());
+        }
+    }
+
+    pub fn record_change(&self, host_name: &String, task_response: &Arc<TaskResponse>) {
+        if self.diff_out.is_none() {
+            return;
+        }
+        match task_response.status {
+            TaskStatus::IsCreated | TaskStatus::IsRemoved | TaskStatus::IsModified | TaskStatus::IsExecuted => {},
+            TaskStatus::NeedsCreation | TaskStatus::NeedsRemoval | TaskStatus::NeedsModification | TaskStatus::NeedsExecution => {},
+            _ => { return; }
+        }
+        let changes : Vec<String> = task_response.changes.iter().map(|x| { format!("{:?}", x) }).collect();
+        let entry = serde_json::json!({
+            "playbook": self.playbook_path,
+            "play":     self.play,
+            "role":     self.role.as_ref().map(|x| x.name.clone()),
+            "task":     self.task,
+            "status":   format!("{:?}", task_response.status),
+            "changes":  changes,
+            "diff":     task_response.diff.as_ref().map(|x| redact(x))
+        });
+        self.changes_for_host.lock().unwrap().entry(host_name.clone()).or_insert(Vec::new()).push(entry);
+    }
+
+    // hosts with no changes are listed too, so the report shows they were looked at
+
+    pub fn get_changes_by_host(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut results = serde_json::Map::new();
+        let mut names : Vec<&String> = self.seen_hosts.keys().collect();
+        names.sort();
+        let changes_for_host = self.changes_for_host.lock().unwrap();
+        for name in names {
+            results.insert(name.clone(), serde_json::json!(changes_for_host.get(name).cloned().unwrap_or(Vec::new())));
+        }
+        return results;
+    }
+
+    // returns (handler, number of hosts that ran it, tasks that notified it) for each handler that
+    // ran in the current play, sorted by handler name
+
+    pub fn get_handler_summary(&self) -> Vec<(String, usize, Vec<String>)> {
+        let mut results : Vec<(String, usize, Vec<String>)> = self.handler_hosts_run.iter().map(|(handler, hosts)| {
+            let notified_by = self.handler_notified_by.get(handler).cloned().unwrap_or(Vec::new());
+            (handler.clone(), hosts.len(), notified_by)
+        }).collect();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        return results;
+    }
+
+    pub fn get_failed_count_in(&self, hosts: &Vec<Arc<RwLock<Host>>>) -> usize {
+        return hosts.iter().filter(|h| self.failed_hosts.contains_key(&h.read().unwrap().name)).count();
+    }
+
+    pub fn get_failed_host_names(&self) -> Vec<String> {
+        let mut names : Vec<String> = self.failed_hosts.keys().map(|x| x.clone()).collect();
+        names.sort();
+        return names;
     }
 
     pub fn get_hosts_adjusted_count(&self) -> usize {
-        return self.adjusted_count_for_host.keys().len();
+        return self.get_hosts(|x| &x.adjusted);
+    }
+
+    pub fn get_hosts_retry_count(&self) -> usize {
+        return self.get_hosts(|x| &x.retry);
     }
 
     pub fn get_hosts_seen_count(&self) -> usize {
         return self.seen_hosts.keys().len();
     }
-    
 
+    // timers are started by the visitor as plays and tasks begin.  a task is considered
+    // finished when the next task starts or when the play ends.
+
+    pub fn start_play_timer(&mut self) {
+        self.play_started = Some(Instant::now());
+    }
+
+    pub fn stop_play_timer(&mut self) {
+        self.stop_task_timer();
+        if self.play_started.is_some() {
+            let elapsed = self.play_started.unwrap().elapsed();
+            self.play_durations.push((self.get_play_name(), elapsed));
+            self.play_started = None;
+        }
+    }
 
-}
\ No newline at end of file
+    pub fn start_task_timer(&mut self, label: &String) {
+        self.stop_task_timer();
+        self.task_started = Some((label.clone(), Instant::now()));
+    }
+
+    pub fn stop_task_timer(&mut self) {
+        if self.task_started.is_some() {
+            let (label, started) = self.task_started.take().unwrap();
+            self.task_durations.push((label, started.elapsed()));
+        }
+    }
+
+    pub fn get_play_durations(&self) -> Vec<(String, Duration)> {
+        return self.play_durations.clone();
+    }
+
+    pub fn get_slowest_tasks(&self, count: usize) -> Vec<(String, Duration)> {
+        let mut sorted = self.task_durations.clone();
+        sorted.sort_by(|a, b| b.1.cmp(&a.1));
+        sorted.truncate(count);
+        return sorted;
+    }
+
+}
@@ -30,11 +30,27 @@ pub struct Play {
     pub vars_files: Option<Vec<String>>,
     pub sudo: Option<String>,
     pub sudo_template: Option<String>,
+    // variables set for every command in the play, see with/environment
+    pub environment: Option<serde_yaml::Mapping>,
     pub ssh_user : Option<String>,
     pub ssh_port : Option<i64>,
+    pub connection : Option<String>,
     pub tasks : Option<Vec<Task>>,
     pub handlers : Option<Vec<Task>>,
     pub batch_size : Option<usize>,
+    pub serial : Option<SerialInput>,
+    pub max_fail_percentage : Option<usize>,
+    pub any_errors_fatal : Option<bool>,
+    pub fatal_cancels_running : Option<bool>,
+}
+
+// serial may be a number of hosts or a percentage of the play's hosts, ex: 10 or "10%"
+
+#[derive(Debug,Deserialize)]
+#[serde(untagged)]
+pub enum SerialInput {
+    Count(usize),
+    Percent(String),
 }
 
 #[derive(Debug,Deserialize,Clone)]
@@ -43,10 +59,12 @@ pub struct Role {
     pub name: String,
     pub defaults: Option<serde_yaml::Mapping>,
     pub tasks: Option<Vec<String>>,
-    pub handlers: Option<Vec<String>>
+    pub handlers: Option<Vec<String>>,
+    // roles that run before this one, each at most once per play however many roles depend on it
+    pub dependencies: Option<Vec<RoleInvocation>>
 }
 
-#[derive(Debug,Deserialize)]
+#[derive(Debug,Deserialize,Clone)]
 #[serde(deny_unknown_fields)]
 pub struct RoleInvocation {
     pub role: String,
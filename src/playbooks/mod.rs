@@ -17,6 +17,8 @@
 pub mod language;
 pub mod context;
 pub mod visitor;
+pub mod callback;
+pub mod webhook;
 pub mod traversal;
 pub mod templar;
 pub mod task_fsm;
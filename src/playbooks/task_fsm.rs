@@ -17,16 +17,18 @@
 use crate::registry::list::Task;
 use crate::connection::connection::Connection;
 use crate::handle::handle::TaskHandle;
+use crate::handle::response::Response;
 use crate::playbooks::traversal::RunState;
 use crate::inventory::hosts::Host;
 use crate::playbooks::traversal::HandlerMode;
 use crate::playbooks::language::Play;
-use crate::tasks::request::SudoDetails;
+use crate::tasks::request::{SudoDetails,AsyncDetails};
 use crate::tasks::*;
 use crate::handle::template::BlendTarget;
 use crate::playbooks::templar::TemplateMode;
-use crate::tasks::logic::template_items;
-use std::sync::{Arc,RwLock,Mutex};
+use crate::tasks::logic::{template_items,template_environment};
+use crate::util::interrupt::is_interrupted;
+use std::sync::{Arc,RwLock,Mutex,Condvar};
 use std::collections::HashMap;
 use rayon::prelude::*;
 use std::{thread, time};
@@ -47,51 +49,134 @@ pub fn fsm_run_task(run_state: &Arc<RunState>, play: &Play, task: &Task, are_han
     let mut host_objects : Vec<Arc<RwLock<Host>>> = Vec::new();
     for (_,v) in hosts { host_objects.push(Arc::clone(&v)); }
 
-    // use rayon to process hosts in different threads
-    let _total : i64 = host_objects.par_iter().map(|host| {
-
-        // get the connection to each host, which should be left open until the play ends
-        let connection_result = run_state.connection_factory.read().unwrap().get_connection(&run_state.context, &host);
-        match connection_result {
-            Ok(_)  => {
-                let connection = connection_result.unwrap();
-                run_state.visitor.read().unwrap().on_host_task_start(&run_state.context, &host);
-                // the actual task is invoked here
-                let task_response = run_task_on_host(&run_state,connection,&host,play,task,are_handlers);
-
-                match task_response {
-                    Ok(x) => {
-                        match check {
-                            // output slightly differs in check vs non-check modes
-                            false => run_state.visitor.read().unwrap().on_host_task_ok(&run_state.context, &x, &host),
-                            true => run_state.visitor.read().unwrap().on_host_task_check_ok(&run_state.context, &x, &host)
-                        }
-                    }
-                    Err(x) => {
-                        // hosts with task failures are removed from the pool
-                        run_state.context.write().unwrap().fail_host(&host);
-                        run_state.visitor.read().unwrap().on_host_task_failed(&run_state.context, &x, &host);
-                    },
+    // with run_once, hosts are tried one at a time in name order until one does not skip the task,
+    // and the remaining hosts are reported as skipped without running anything
+    if task.get_with().and_then(|x| x.run_once).unwrap_or(false) {
+        host_objects.sort_by(|a, b| a.read().unwrap().name.cmp(&b.read().unwrap().name));
+        let mut done = false;
+        for host in host_objects.iter() {
+            if done {
+                let response = Response::new(Arc::clone(run_state), Arc::clone(host));
+                let skipped = response.is_skipped(&TaskRequest::validate(), &String::from("run_once"));
+                match check {
+                    false => run_state.visitor.read().unwrap().on_host_task_ok(&run_state.context, &skipped, host),
+                    true => run_state.visitor.read().unwrap().on_host_task_check_ok(&run_state.context, &skipped, host)
                 }
-            },
-            Err(x) => {
-                // hosts with connection failures are removed from the pool
-                run_state.visitor.read().unwrap().debug_host(&host, &x);
-                run_state.context.write().unwrap().fail_host(&host);
-                run_state.visitor.read().unwrap().on_host_connect_failed(&run_state.context, &host);
+                continue;
             }
+            done = ! run_task_and_report(run_state, play, task, are_handlers, check, host);
         }
+        return Ok(());
+    }
+
+    // use rayon to process hosts in different threads.  Each host is its own unit of work so
+    // a worker that finishes a fast host takes the next waiting host rather than sitting behind
+    // a slow one that happened to be queued alongside it.  The pool size is --threads/--forks.
+    // with throttle, only that many hosts may be inside the task at once no matter how many
+    // threads are available, the other workers wait for a slot before starting their host
+    let throttle = match task.get_with().and_then(|x| x.throttle) {
+        Some(0) => { return Err(format!("throttle must be at least 1")); },
+        Some(n) => Some(TaskThrottle::new(n)),
+        None => None
+    };
+
+    let _total : i64 = host_objects.par_iter().with_max_len(1).map(|host| {
+        if let Some(ref t) = throttle { t.acquire(); }
+        run_task_and_report(run_state, play, task, are_handlers, check, host);
+        if let Some(ref t) = throttle { t.release(); }
         // rayon needs some math to add up, hence the 1. It seems to short-circuit without some work to do.
         return 1;
-
     }).sum();
     return Ok(());
 }
 
-fn get_actual_connection(run_state: &Arc<RunState>, host: &Arc<RwLock<Host>>, task: &Task, input_connection: Arc<Mutex<dyn Connection>>) -> Result<(Option<String>,Arc<Mutex<dyn Connection>>), String> {
+// a counting semaphore shared by the workers processing one task
+
+struct TaskThrottle {
+    available: Mutex<usize>,
+    freed: Condvar
+}
+
+impl TaskThrottle {
+
+    fn new(limit: usize) -> Self {
+        return Self { available: Mutex::new(limit), freed: Condvar::new() };
+    }
+
+    fn acquire(&self) {
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            available = self.freed.wait(available).unwrap();
+        }
+        *available -= 1;
+    }
+
+    fn release(&self) {
+        let mut available = self.available.lock().unwrap();
+        *available += 1;
+        self.freed.notify_one();
+    }
+}
+
+// runs the task on one host and reports the result.  Returns true if the task was skipped on the host,
+// which run_once uses to move on to the next host.
+
+fn run_task_and_report(run_state: &Arc<RunState>, play: &Play, task: &Task, are_handlers: HandlerMode, check: bool, host: &Arc<RwLock<Host>>) -> bool {
+
+    // with any_errors_fatal, once any host has failed no further hosts are started.
+    // handlers only get this far after a fatal failure with --force-handlers, so they are let through.
+    if are_handlers == HandlerMode::NormalTasks && run_state.context.read().unwrap().get_fatal_host().is_some() {
+        return false;
+    }
+
+    // after Ctrl-C, hosts already inside the task finish it but no more are started
+    if is_interrupted() {
+        return false;
+    }
+
+    // get the connection to each host, which should be left open until the play ends
+    let connection_result = run_state.connection_factory.read().unwrap().get_connection(&run_state.context, &host);
+    match connection_result {
+        Ok(_)  => {
+            let connection = connection_result.unwrap();
+            run_state.visitor.read().unwrap().on_host_task_start(&run_state.context, &host);
+            // the actual task is invoked here
+            let task_response = run_task_on_host(&run_state,connection,&host,play,task,are_handlers);
+
+            match task_response {
+                Ok(x) => {
+                    if are_handlers == HandlerMode::Handlers && x.status != TaskStatus::IsSkipped {
+                        record_handler_run(run_state, task, host);
+                    }
+                    match check {
+                        // output slightly differs in check vs non-check modes
+                        false => run_state.visitor.read().unwrap().on_host_task_ok(&run_state.context, &x, &host),
+                        true => run_state.visitor.read().unwrap().on_host_task_check_ok(&run_state.context, &x, &host)
+                    }
+                    return x.status == TaskStatus::IsSkipped;
+                }
+                Err(x) => {
+                    // hosts with task failures are removed from the pool
+                    run_state.context.write().unwrap().fail_host(&host);
+                    run_state.visitor.read().unwrap().on_host_task_failed(&run_state.context, &x, &host);
+                },
+            }
+        },
+        Err(x) => {
+            // hosts with connection failures are removed from the pool
+            run_state.visitor.read().unwrap().debug_host(&host, &x);
+            run_state.context.write().unwrap().fail_host(&host);
+            run_state.visitor.read().unwrap().on_host_connect_failed(&run_state.context, &host);
+        }
+    }
+    return false;
+}
+
+fn get_actual_connection(run_state: &Arc<RunState>, host: &Arc<RwLock<Host>>, task: &Task, input_connection: Arc<Mutex<dyn Connection>>) -> Result<(Option<Arc<RwLock<Host>>>,Arc<Mutex<dyn Connection>>), String> {
     
     // usually the connection we already have is the one we will use, but this is not the case for using the delegate_to feature
-    // this is a bit complex...
+    // this is a bit complex...  when delegating, the delegate host is returned along with its connection so commands
+    // run there, while variables and results still belong to the host being iterated over.
 
     return match task.get_with() {
         
@@ -118,7 +203,8 @@ fn get_actual_connection(run_state: &Arc<RunState>, host: &Arc<RwLock<Host>>, ta
                 else if delegate.eq(&String::from("localhost")) {
                     // localhost delegation has some security implications (see docs) so require a CLI flag for access
                     if run_state.allow_localhost_delegation {
-                        return Ok((Some(delegate.clone()), run_state.connection_factory.read().unwrap().get_local_connection(&run_state.context)?))
+                        let localhost = run_state.inventory.read().unwrap().get_host(&delegate);
+                        return Ok((Some(localhost), run_state.connection_factory.read().unwrap().get_local_connection(&run_state.context)?))
                     } else {
                         return Err(format!("localhost delegation has potential security implementations, pass --allow-localhost-delegation to sign off"));
                     }
@@ -130,7 +216,8 @@ fn get_actual_connection(run_state: &Arc<RunState>, host: &Arc<RwLock<Host>>, ta
                         return Err(format!("cannot delegate to a host not found in inventory: {}", delegate));
                     }
                     let host = run_state.inventory.read().unwrap().get_host(&delegate);
-                    return Ok((Some(delegate.clone()), run_state.connection_factory.read().unwrap().get_connection(&run_state.context, &host)?));
+                    let connection = run_state.connection_factory.read().unwrap().get_connection(&run_state.context, &host)?;
+                    return Ok((Some(host), connection));
                 } 
             },
             // there was no delegate keyword, use the original connection
@@ -161,24 +248,24 @@ fn run_task_on_host(
         Ok((None, ref conn)) => (
             None, 
             conn, 
-            Arc::new(TaskHandle::new(Arc::clone(run_state), Arc::clone(conn), Arc::clone(host)))
+            Arc::new(TaskHandle::new(Arc::clone(run_state), Arc::clone(conn), Arc::clone(host), None))
         ),
         // construct the TaskHandle if a delegate connection is to be used
-        Ok((Some(delegate), ref conn)) => (
-            Some(delegate.clone()), 
+        Ok((Some(ref delegate), ref conn)) => (
+            Some(delegate.read().unwrap().name.clone()), 
             conn, 
-            Arc::new(TaskHandle::new(Arc::clone(run_state), Arc::clone(conn), Arc::clone(host)))
+            Arc::new(TaskHandle::new(Arc::clone(run_state), Arc::clone(conn), Arc::clone(host), Some(Arc::clone(delegate))))
         ),
         // something went wrong when processing delegates, create a throw-away handle just so we can use the response functions
         Err(msg) => {
-            let tmp_handle = Arc::new(TaskHandle::new(Arc::clone(run_state), Arc::clone(&input_connection), Arc::clone(host)));
+            let tmp_handle = Arc::new(TaskHandle::new(Arc::clone(run_state), Arc::clone(&input_connection), Arc::clone(host), None));
             return Err(tmp_handle.response.is_failed(&validate, &msg));
         }
     };
 
     // if we are delegating, tell the user
     if delegated.is_some() {
-        run_state.visitor.read().unwrap().on_host_delegate(host, &delegated.unwrap());
+        run_state.visitor.read().unwrap().on_host_delegate(&run_state.context, host, &delegated.unwrap());
     }
 
     // process the YAML inputs of the task and turn them into something we can  use
@@ -191,7 +278,7 @@ fn run_task_on_host(
         if condition.is_some() {
             let cond = handle.template.test_condition(&validate, TemplateMode::Strict, &condition.as_ref().unwrap())?;
             if ! cond {
-                return Ok(handle.response.is_skipped(&Arc::clone(&validate)));
+                return Ok(handle.response.is_skipped(&Arc::clone(&validate), &String::from("condition false")));
             }
         }
     }
@@ -212,8 +299,16 @@ fn run_task_on_host(
     // even if we are not iterating over a list of items, make a list of one item to simplify the logic
     let evaluated_items = template_items(&handle, &validate, TemplateMode::Strict, &items_input)?;
 
+    // every item but the last is reported here, so each iteration shows up in the per-host counters.
+    // the final result is returned and reported by the caller as usual.
+    let check = run_state.visitor.read().unwrap().is_check_mode();
+    let item_count = evaluated_items.len();
+
     // walking over each item or just the single task if 'with_items' was not used
-    for item in evaluated_items.iter() {
+    for (item_index, item) in evaluated_items.iter().enumerate() {
+
+        // a host that already started may be cancelled between items and retries, see any_errors_fatal
+        check_cancelled(run_state, are_handlers, &handle, &validate)?;
             
         // store the 'items' variable for use in module parameters
         mapping.insert(serde_yaml::Value::String(String::from("item")), item.clone());
@@ -229,27 +324,65 @@ fn run_task_on_host(
             let delay = match evaluated.and.as_ref().is_some() {
             false => 1, true => evaluated.and.as_ref().as_ref().unwrap().delay
         };
+        // with and/until the task is repeated until the condition holds for its result, rather than until it succeeds
+        let until = match evaluated.and.as_ref().is_some() {
+            false => None, true => evaluated.and.as_ref().as_ref().unwrap().until.clone()
+        };
     
         // run the task as many times as defined by retry logic
         loop {
             
             // here we finally call the actual task, everything around this is just support
             // for delegation, loops, and retries!
-            match run_task_on_host_inner(run_state, &connection, host, play, task, are_handlers, &handle, &validate, &evaluated) {
-                Err(e) => match retries {
-                    // retries are used up
-                    0 => { return Err(e); },
+            let result = run_task_on_host_inner(run_state, &connection, host, play, task, are_handlers, &handle, &validate, &evaluated);
+            let done = match &until {
+                None => result.is_ok(),
+                Some(condition) => {
+                    let response = match &result { Ok(x) => x, Err(y) => y };
+                    let mut vars = serde_yaml::Mapping::new();
+                    vars.insert(serde_yaml::Value::String(String::from("result")), serde_yaml::Value::Mapping(result_to_mapping(response)));
+                    handle.template.test_condition_with_extra_data(&validate, TemplateMode::Strict, condition, host, vars)?
+                }
+            };
+
+            if ! done {
+                match retries {
+                    // retries are used up, an unmet until condition fails with the last result attached
+                    0 => { 
+                        return match &until {
+                            None => result,
+                            Some(condition) => {
+                                let response = match &result { Ok(x) => x, Err(y) => y };
+                                Err(handle.response.is_failed_with_result(&validate, &format!("until condition was not met: {}", condition), response))
+                            }
+                        };
+                    },
                     // we have retries left
                     _ => { 
+                        check_cancelled(run_state, are_handlers, &handle, &validate)?;
                         retries = retries - 1;
                         run_state.visitor.read().unwrap().on_host_task_retry(&run_state.context, host, retries, delay);
                         if delay > 0 {
                             let duration = time::Duration::from_secs(delay);
                             thread::sleep(duration);
                         }
+                        continue;
                     }
-                },
-                Ok(x) => { last = Some(Ok(x)); break }
+                }
+            }
+
+            match result {
+                Err(e) => { return Err(e); },
+                Ok(x) => { 
+                    if item_index + 1 < item_count {
+                        match check {
+                            false => run_state.visitor.read().unwrap().on_host_task_ok(&run_state.context, &x, host),
+                            true => run_state.visitor.read().unwrap().on_host_task_check_ok(&run_state.context, &x, host)
+                        }
+                    }
+                    last = Some(Ok(x)); 
+                    break 
+                }
             }
         }
     
@@ -266,6 +399,23 @@ fn run_task_on_host(
 
 }
 
+// handlers that were notified and actually ran are listed at the end of the play, see on_play_stop
+
+fn record_handler_run(run_state: &Arc<RunState>, task: &Task, host: &Arc<RwLock<Host>>) {
+    if let Some(subscribe) = task.get_with().and_then(|x| x.subscribe) {
+        let host_name = host.read().unwrap().name.clone();
+        run_state.context.write().unwrap().record_handler_run(&subscribe.trim().to_string(), &host_name);
+    }
+}
+
+fn check_cancelled(run_state: &Arc<RunState>, are_handlers: HandlerMode, handle: &Arc<TaskHandle>, validate: &Arc<TaskRequest>) -> Result<(), Arc<TaskResponse>> {
+    let ctx = run_state.context.read().unwrap();
+    if are_handlers == HandlerMode::NormalTasks && ctx.should_cancel_running() {
+        return Err(handle.response.is_failed(validate, &format!("cancelled, host {} failed and the play has any_errors_fatal set", ctx.get_fatal_host().unwrap())));
+    }
+    return Ok(());
+}
+
 // the "on this host" method body from _task
 fn run_task_on_host_inner(
     run_state: &Arc<RunState>,
@@ -292,12 +442,24 @@ fn run_task_on_host_inner(
         // minor FIXME: parameters like this are usually set on the run_state
         false => run_state.context.read().unwrap().sudo.clone() 
     };
-    // see if the sudo template is configured, if not use the most basic default
+    // a sudo password may come from --ask-sudo-password or $JET_SUDO_PASSWORD and is fed on standard input
+    let sudo_password = run_state.context.read().unwrap().sudo_password.clone();
+    // see if the sudo template is configured, if not use the most basic default.  Custom templates
+    // that need a password must read it from standard input themselves (ex: sudo -S)
     let sudo_template = match &play.sudo_template {
-        None => String::from("/usr/bin/sudo -u '{{jet_sudo_user}}' {{jet_command}}"),
+        None => match sudo_password.is_some() {
+            true  => String::from("/usr/bin/sudo -S -p '' -u '{{jet_sudo_user}}' {{jet_command}}"),
+            false => String::from("/usr/bin/sudo -u '{{jet_sudo_user}}' {{jet_command}}")
+        },
         Some(x) => x.clone()
     };
     
+    // commands give up after --timeout seconds unless the task says otherwise
+    let mut timeout : Option<u64> = run_state.timeout;
+    let mut async_details : Option<AsyncDetails> = None;
+    // the play's environment applies to every task, a task's own environment adds to and overrides it
+    let mut environment = template_environment(handle, validate, TemplateMode::Strict, &play.environment)?;
+
     // is 'with' provided?
     if pre_logic.is_some() {
         let logic = pre_logic.as_ref().as_ref().unwrap();
@@ -305,7 +467,7 @@ fn run_task_on_host_inner(
         if are_handlers == HandlerMode::Handlers  {
             // if we are running handlers at the moment, skip any un-notified handlers
             if ! my_host.is_notified(play_count, &logic.subscribe.as_ref().unwrap().clone()) {
-                return Ok(handle.response.is_skipped(&Arc::clone(&validate))); 
+                return Ok(handle.response.is_skipped(&Arc::clone(&validate), &String::from("handler not notified")));
             }
         }
         
@@ -313,11 +475,21 @@ fn run_task_on_host_inner(
         if logic.sudo.is_some() {
             sudo = Some(logic.sudo.as_ref().unwrap().clone());
         }
+        // likewise a task timeout overrides --timeout
+        if logic.timeout.is_some() {
+            timeout = logic.timeout;
+        }
+        async_details = logic.async_details;
+        for (name, value) in logic.environment.iter() {
+            environment.retain(|(k, _)| k != name);
+            environment.push((name.clone(), value.clone()));
+        }
     }
 
     let sudo_details = SudoDetails {
         user     : sudo.clone(),
-        template : sudo_template.clone()
+        template : sudo_template.clone(),
+        password : sudo_password.clone()
     };
 
     // we're about to get to the task finite state machine guts.
@@ -325,7 +497,7 @@ fn run_task_on_host_inner(
     // don't return the wrong states, even when returning an error, to prevent
     // unpredictability in the program
 
-    let query = TaskRequest::query(&sudo_details);
+    let query = TaskRequest::query(&sudo_details, timeout, &environment);
 
     // invoke the resource and see what actions it thinks need to be performed
 
@@ -338,7 +510,10 @@ fn run_task_on_host_inner(
         match qrc {
             Ok(ref qrc_ok) => match qrc_ok.status {
                 TaskStatus::NeedsPassive => { /* allow modules like !facts or set to execute */ },
-                _ => { return qrc; }
+                _ => {
+                    notify_handlers(run_state, host, post_logic, are_handlers, play_count, &qrc);
+                    return qrc;
+                }
             },
             _ => {}
         }
@@ -356,7 +531,7 @@ fn run_task_on_host_inner(
 
             TaskStatus::NeedsCreation => match modify_mode {
                 true => {
-                    let req = TaskRequest::create(&sudo_details);
+                    let req = TaskRequest::create(&sudo_details, timeout, async_details, &environment);
                     let crc = action.dispatch(&handle, &req);
                     match crc {
                         Ok(ref crc_ok) => match crc_ok.status {
@@ -375,7 +550,7 @@ fn run_task_on_host_inner(
 
             TaskStatus::NeedsRemoval => match modify_mode {
                 true => {
-                    let req = TaskRequest::remove(&sudo_details);
+                    let req = TaskRequest::remove(&sudo_details, timeout, async_details, &environment);
                     let rrc = action.dispatch(&handle, &req);
                     match rrc {
                         Ok(ref rrc_ok) => match rrc_ok.status {
@@ -393,7 +568,7 @@ fn run_task_on_host_inner(
 
             TaskStatus::NeedsModification => match modify_mode {
                 true => {
-                    let req = TaskRequest::modify(&sudo_details, qrc_ok.changes.clone());
+                    let req = TaskRequest::modify(&sudo_details, timeout, async_details, qrc_ok.changes.clone(), &environment);
                     let mrc = action.dispatch(&handle, &req);
                     match mrc {
                         Ok(ref mrc_ok) => match mrc_ok.status {
@@ -411,7 +586,7 @@ fn run_task_on_host_inner(
 
             TaskStatus::NeedsExecution => match modify_mode {
                 true => {
-                    let req = TaskRequest::execute(&sudo_details);
+                    let req = TaskRequest::execute(&sudo_details, timeout, async_details, &environment);
                     let erc = action.dispatch(&handle, &req);
                     match erc {
                         Ok(ref erc_ok) => match erc_ok.status {
@@ -429,7 +604,7 @@ fn run_task_on_host_inner(
             },
 
             TaskStatus::NeedsPassive => {
-                let req = TaskRequest::passive(&sudo_details);
+                let req = TaskRequest::passive(&sudo_details, timeout, &environment);
                 let prc = action.dispatch(&handle, &req);
                 match prc {
                     Ok(ref prc_ok) => match prc_ok.status {
@@ -478,18 +653,16 @@ fn run_task_on_host_inner(
 
     // if and/notify is present, notify handlers when changed actions are seen
 
-    if result.is_ok() && post_logic.is_some() {
+    notify_handlers(run_state, host, post_logic, are_handlers, play_count, &result);
+
+    // if and/register is present, keep the result around as a host variable for later conditions and templates.
+    // this happens after ignore_errors so a registered failure can be inspected by the next task.
+
+    if post_logic.is_some() {
         let logic = post_logic.as_ref().as_ref().unwrap();
-        if are_handlers == HandlerMode::NormalTasks && result.is_ok() && logic.notify.is_some() {
-            let notify = logic.notify.as_ref().unwrap().clone();
-            let status = &result.as_ref().unwrap().status;
-            match status {
-                TaskStatus::IsCreated | TaskStatus::IsModified | TaskStatus::IsRemoved | TaskStatus::IsExecuted => {
-                    run_state.visitor.read().unwrap().on_notify_handler(host, &notify.clone());
-                    host.write().unwrap().notify(play_count, &notify.clone());
-                },
-                _ => { }
-            }
+        if logic.register.is_some() {
+            let response = match &result { Ok(x) => x, Err(y) => y };
+            register_result(host, logic.register.as_ref().unwrap(), response);
         }
     }
 
@@ -497,3 +670,73 @@ fn run_task_on_host_inner(
 
     return result;
 }
+
+// a task with and/notify that changed something marks its handler as notified on the host.  Check mode stops
+// at the query, so there a task that would change something notifies as if it had, which lets the handlers
+// report what they would do as well.
+
+fn notify_handlers(run_state: &Arc<RunState>, host: &Arc<RwLock<Host>>, post_logic: &Arc<Option<PostLogicEvaluated>>, are_handlers: HandlerMode,
+    play_count: usize, result: &Result<Arc<TaskResponse>,Arc<TaskResponse>>) {
+
+    let logic = match (result, post_logic.as_ref()) {
+        (Ok(_), Some(x)) => x,
+        _ => { return; }
+    };
+    if are_handlers != HandlerMode::NormalTasks || logic.notify.is_none() {
+        return;
+    }
+    let notify = logic.notify.as_ref().unwrap().clone();
+    let check = run_state.visitor.read().unwrap().is_check_mode();
+    let changed = match result.as_ref().unwrap().status {
+        TaskStatus::IsCreated | TaskStatus::IsModified | TaskStatus::IsRemoved | TaskStatus::IsExecuted => true,
+        TaskStatus::NeedsCreation | TaskStatus::NeedsModification | TaskStatus::NeedsRemoval | TaskStatus::NeedsExecution => check,
+        _ => false
+    };
+    if changed {
+        run_state.visitor.read().unwrap().on_notify_handler(&run_state.context, host, &notify.clone());
+        let task_name = run_state.context.read().unwrap().get_task_name();
+        run_state.context.write().unwrap().record_handler_notified(&notify, &task_name);
+        host.write().unwrap().notify(play_count, &notify.clone());
+    }
+}
+
+// registered results look like { status: 'modified', changed: true, failed: false, changes: [ 'Content' ], msg: '', rc: 0, out: '' }
+// where rc, out and err are only present for tasks that ran a command that produced the final result.
+// and/until sees the same data as 'result'.
+
+fn register_result(host: &Arc<RwLock<Host>>, key: &String, response: &Arc<TaskResponse>) {
+    let mut mapping = serde_yaml::Mapping::new();
+    mapping.insert(serde_yaml::Value::String(key.clone()), serde_yaml::Value::Mapping(result_to_mapping(response)));
+    host.write().unwrap().update_variables(mapping);
+}
+
+fn result_to_mapping(response: &Arc<TaskResponse>) -> serde_yaml::Mapping {
+    let status = match response.status {
+        TaskStatus::IsCreated  => "created",
+        TaskStatus::IsRemoved  => "removed",
+        TaskStatus::IsModified => "modified",
+        TaskStatus::IsExecuted => "executed",
+        TaskStatus::IsPassive  => "passive",
+        TaskStatus::IsMatched  => "matched",
+        TaskStatus::IsSkipped  => "skipped",
+        TaskStatus::Failed     => "failed",
+        _ => "unknown"
+    };
+    let changed = match response.status {
+        TaskStatus::IsCreated | TaskStatus::IsRemoved | TaskStatus::IsModified | TaskStatus::IsExecuted => true,
+        _ => false
+    };
+    let mut data = serde_yaml::Mapping::new();
+    data.insert(serde_yaml::Value::String(String::from("status")), serde_yaml::Value::String(String::from(status)));
+    data.insert(serde_yaml::Value::String(String::from("changed")), serde_yaml::Value::Bool(changed));
+    data.insert(serde_yaml::Value::String(String::from("failed")), serde_yaml::Value::Bool(response.status == TaskStatus::Failed));
+    let changes : Vec<serde_yaml::Value> = response.changes.iter().map(|x| serde_yaml::Value::String(format!("{:?}", x))).collect();
+    data.insert(serde_yaml::Value::String(String::from("changes")), serde_yaml::Value::Sequence(changes));
+    data.insert(serde_yaml::Value::String(String::from("msg")), serde_yaml::Value::String(response.msg.clone().unwrap_or_default()));
+    if let Some(cmd_result) = response.command_result.as_ref() {
+        data.insert(serde_yaml::Value::String(String::from("rc")), serde_yaml::Value::Number(serde_yaml::Number::from(cmd_result.rc)));
+        data.insert(serde_yaml::Value::String(String::from("out")), serde_yaml::Value::String(cmd_result.out.clone()));
+        data.insert(serde_yaml::Value::String(String::from("err")), serde_yaml::Value::String(cmd_result.err.clone()));
+    }
+    return data;
+}
@@ -14,22 +14,42 @@
 // You should have received a copy of the GNU General Public License
 // long with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::playbooks::language::Play;
+use crate::playbooks::language::{Play,SerialInput};
 use crate::playbooks::visitor::PlaybookVisitor;
+use crate::playbooks::callback::Callback;
 use crate::playbooks::context::PlaybookContext;
 use crate::playbooks::language::{Role,RoleInvocation};
 use crate::connection::factory::ConnectionFactory;
 use crate::registry::list::Task;
+use crate::modules::control::facts::FactsTask;
+use crate::modules::control::block::BlockTask;
+use crate::modules::control::meta::{MetaTask,MetaAction};
+use crate::modules::control::include_tasks::IncludeTasksTask;
 use crate::playbooks::task_fsm::fsm_run_task;
 use crate::inventory::inventory::Inventory;
 use crate::inventory::hosts::Host;
-use crate::util::io::{jet_file_open,directory_as_string};
+use crate::connection::connection::Connection;
+use crate::connection::no::NoConnection;
+use crate::handle::handle::TaskHandle;
+use crate::tasks::request::TaskRequest;
+use crate::inventory::pattern::HostPattern;
+use crate::handle::template::BlendTarget;
+use crate::playbooks::templar::TemplateMode;
+use crate::util::io::{jet_file_open,directory_as_string,read_local_file};
 use crate::util::yaml::{blend_variables,show_yaml_error_in_context};
+use crate::util::vault::{load_vars_file,is_encrypted,redact_json};
+use crate::util::terminal::ask;
+use crate::util::interrupt::is_interrupted;
 use std::path::PathBuf;
 use std::collections::HashMap;
-use std::sync::{Arc,RwLock};
+use std::sync::{Arc,RwLock,Mutex};
 use std::path::Path;
 use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+
+use rayon::prelude::*;
 
 // this module contains the start of everything related to playbook evaluation
 
@@ -43,6 +63,17 @@ pub enum HandlerMode {
 // the run state is a quasi-global that can be used to access all
 // import 'objects' related to playbook evaluation
 
+// --order decides which hosts go into the first batches.  inventory is the order hosts were first loaded in,
+// shuffle is seeded so the same order can be had again.
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum HostOrder {
+    Sorted,
+    Reverse,
+    Inventory,
+    Shuffle(u64)
+}
+
 pub struct RunState {
     pub inventory: Arc<RwLock<Inventory>>,
     pub playbook_paths: Arc<RwLock<Vec<PathBuf>>>,
@@ -50,17 +81,62 @@ pub struct RunState {
     pub module_paths: Arc<RwLock<Vec<PathBuf>>>,
     pub limit_hosts: Vec<String>,
     pub limit_groups: Vec<String>,
+    pub limit: Option<HostPattern>,
     pub batch_size: Option<usize>,
+    pub order: HostOrder,
+    pub preflight: bool,
+    pub preflight_max_fail: Option<usize>,
     pub context: Arc<RwLock<PlaybookContext>>,
     pub visitor: Arc<RwLock<PlaybookVisitor>>,
     pub connection_factory: Arc<RwLock<dyn ConnectionFactory>>,
     pub tags: Option<Vec<String>>,
-    pub allow_localhost_delegation: bool
+    pub skip_tags: Option<Vec<String>>,
+    pub allow_localhost_delegation: bool,
+    pub show_diff: bool,
+    pub timeout: Option<u64>,
+    pub gather_facts: bool,
+    pub list_hosts: bool,
+    pub list_tasks: bool,
+    pub syntax_check: bool,
+    pub force_handlers: bool,
+    pub plan_out: Option<String>
+}
+
+impl RunState {
+
+    // programs driving a playbook can register their own callbacks before the traversal starts,
+    // see callback.rs
+
+    #[allow(dead_code)]
+    pub fn add_callback(&self, callback: Arc<dyn Callback>) {
+        self.visitor.write().unwrap().add_callback(callback);
+    }
+
 }
 
 // this is the top end traversal function that is called from cli/playbooks.rs
 
 pub fn playbook_traversal(run_state: &Arc<RunState>) -> Result<(), String> {
+
+    validate_limit_pattern(run_state)?;
+
+    if run_state.syntax_check {
+        return syntax_check_traversal(run_state);
+    }
+
+    // every task is validated before anything runs, so that all the bad parameters are reported together
+    // instead of one per run.  Validation that needs host variables still happens per host as each task runs.
+    if ! (run_state.list_hosts || run_state.list_tasks) {
+        let problems = find_syntax_problems(run_state);
+        if ! problems.is_empty() {
+            return Err(format!("validation found {} problem(s), nothing was run:\n  {}", problems.len(), problems.join("\n  ")));
+        }
+    }
+
+    // the plan covers every playbook, so it is written out before any of them start
+    if let Some(plan_path) = &run_state.plan_out {
+        write_plan(run_state, plan_path)?;
+    }
         
     // it's possible to specify multiple playbooks seperated by colons on the command line
 
@@ -73,7 +149,10 @@ pub fn playbook_traversal(run_state: &Arc<RunState>) -> Result<(), String> {
             ctx.set_playbook_path(playbook_path); 
         }
 
-        run_state.visitor.read().unwrap().on_playbook_start(&run_state.context);
+        let listing = run_state.list_hosts || run_state.list_tasks;
+        if ! listing {
+            run_state.visitor.read().unwrap().on_playbook_start(&run_state.context);
+        }
 
         // parse the playbook file
         let playbook_file = jet_file_open(&playbook_path)?;
@@ -96,27 +175,549 @@ pub fn playbook_traversal(run_state: &Arc<RunState>) -> Result<(), String> {
         // walk each play in the playbook
         let plays: Vec<Play> = parsed.unwrap();
         for play in plays.iter() {
-            match handle_play(&run_state, play) {
+            let result = match listing {
+                true  => list_play(&run_state, play),
+                false => handle_play(&run_state, play)
+            };
+            match result {
                 Ok(_) => {},
-                Err(s) => { return Err(s); }
+                Err(s) => { 
+                    // changes made before the failure are still reported
+                    if let Err(y) = write_diff_out(run_state) {
+                        return Err(format!("{}\n{}", s, y));
+                    }
+                    // a run that stopped because hosts failed or because of Ctrl-C still gets the usual summary
+                    let failed = run_state.context.read().unwrap().get_hosts_failed_count() > 0;
+                    if failed || is_interrupted() || run_state.context.read().unwrap().get_fatal_host().is_some() {
+                        run_state.context.read().unwrap().connection_cache.write().unwrap().clear();
+                        run_state.visitor.read().unwrap().on_exit(&run_state.context);
+                    }
+                    return Err(s); 
+                }
             }
-            // disconnect from all hosts between plays
-            run_state.context.read().unwrap().connection_cache.write().unwrap().clear();
         }
-        // disconnect from all hosts between playbooks
-        run_state.context.read().unwrap().connection_cache.write().unwrap().clear();
 
         // switch back to the original directory
         env::set_current_dir(&previous).expect("could not restore previous directory");
 
 
     }
+
+    // --list-hosts and --list-tasks have printed everything there is to print
+    if run_state.list_hosts || run_state.list_tasks {
+        return Ok(());
+    }
+
+    // nothing has been run if we never found the task to start at, so say what could have been used instead
+    let start_at_task = run_state.context.read().unwrap().start_at_task.clone();
+    if start_at_task.is_some() {
+        let available = run_state.context.read().unwrap().get_skipped_task_names();
+        return Err(format!("--start-at-task: no task named '{}' was found, available tasks are:\n  {}", start_at_task.unwrap(), available.join("\n  ")));
+    }
+
+    write_diff_out(run_state)?;
+
     // disconnect from all hosts and exit. 
     run_state.context.read().unwrap().connection_cache.write().unwrap().clear();
     run_state.visitor.read().unwrap().on_exit(&run_state.context);
     return Ok(())
 }
 
+// 'jetp check-connections' runs no tasks, it only tries to connect to every host
+// in inventory (subject to --limit-groups and --limit-hosts) and reports who answered
+
+pub fn connection_traversal(run_state: &Arc<RunState>) -> Result<(), String> {
+
+    validate_limit_pattern(run_state)?;
+    let hosts = get_group_hosts(run_state, &vec![String::from("all")]);
+    if hosts.is_empty() {
+        return Err(String::from("no hosts selected by --limit-groups or --limit-hosts"));
+    }
+    run_state.context.write().unwrap().set_targetted_hosts(&hosts);
+
+    hosts.par_iter().with_max_len(1).for_each(|host| {
+        let connection_result = run_state.connection_factory.read().unwrap().get_connection(&run_state.context, &host);
+        match connection_result {
+            Ok(_) => {
+                run_state.visitor.read().unwrap().on_host_connect_ok(&run_state.context, &host);
+            },
+            Err(x) => {
+                run_state.visitor.read().unwrap().debug_host(&host, &x);
+                run_state.context.write().unwrap().fail_host(&host);
+                run_state.visitor.read().unwrap().on_host_connect_failed(&run_state.context, &host);
+            }
+        }
+    });
+
+    run_state.context.read().unwrap().connection_cache.write().unwrap().clear();
+    run_state.visitor.read().unwrap().show_connection_summary(&run_state.context);
+    return Ok(());
+}
+
+// 'jetp ssh --list-facts host' gathers facts from a single host and prints them, without a playbook
+
+pub fn facts_traversal(run_state: &Arc<RunState>, host_name: &String) -> Result<String, String> {
+
+    let host = match run_state.inventory.read().unwrap().hosts.get(host_name) {
+        Some(x) => Arc::clone(x),
+        None => { return Err(format!("--list-facts: host not found in inventory: {}", host_name)); }
+    };
+    let play : Play = match serde_yaml::from_str("name: list facts\ngroups: []") {
+        Ok(x) => x,
+        Err(y) => { return Err(format!("internal error building play: {}", y)); }
+    };
+    {
+        let mut ctx = run_state.context.write().unwrap();
+        ctx.set_play(&play);
+        ctx.set_targetted_hosts(&vec![Arc::clone(&host)]);
+    }
+    gather_facts(run_state, &play)?;
+    run_state.context.read().unwrap().connection_cache.write().unwrap().clear();
+    if run_state.context.read().unwrap().get_failed_count_in(&vec![Arc::clone(&host)]) > 0 {
+        return Err(format!("unable to gather facts from {}", host_name));
+    }
+    // group variables, host variables, and facts, resolved the same way templates will see them
+    let facts = host.read().unwrap().get_blended_variables_yaml();
+    return facts;
+}
+
+// --list-hosts and --list-tasks show what a run would touch, with limits and tags applied,
+// without connecting to anything or sending any tasks
+
+fn list_play(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
+
+    validate_limit_groups(run_state, play)?;
+    validate_limit_hosts(run_state, play)?;
+    validate_groups(run_state, play)?;
+    let hosts = get_play_hosts(run_state, play);
+
+    println!("play: {}", play.name);
+
+    if run_state.list_hosts {
+        let mut names : Vec<String> = hosts.iter().map(|x| x.read().unwrap().name.clone()).collect();
+        names.sort();
+        println!("  hosts ({}):", names.len());
+        for name in names.iter() {
+            println!("    {}", name);
+        }
+    }
+
+    if run_state.list_tasks {
+        for (label, are_handlers) in [("tasks", HandlerMode::NormalTasks), ("handlers", HandlerMode::Handlers)] {
+            let mut names : Vec<String> = Vec::new();
+            for invocation in resolve_roles(run_state, play)?.iter() {
+                let (role, role_path) = find_role(run_state, play, invocation.role.clone())?;
+                for task_path in get_role_task_paths(&role, &role_path, are_handlers) {
+                    for task in load_task_file(&task_path)?.iter() {
+                        push_task_names(run_state, play, task, are_handlers, Some(invocation), &format!("{} : ", role.name), &mut names)?;
+                    }
+                }
+            }
+            let loose = match are_handlers {
+                HandlerMode::NormalTasks => &play.tasks,
+                HandlerMode::Handlers    => &play.handlers
+            };
+            if let Some(tasks) = loose {
+                for task in tasks.iter() {
+                    push_task_names(run_state, play, task, are_handlers, None, &String::new(), &mut names)?;
+                }
+            }
+            if are_handlers == HandlerMode::Handlers && names.is_empty() {
+                continue;
+            }
+            println!("  {} ({}):", label, names.len());
+            for name in names.iter() {
+                println!("    {}", name);
+            }
+        }
+    }
+    return Ok(());
+}
+
+// blocks are listed as their contents, with rescue and always sections labelled.  Imported and
+// included files are listed as their contents too, though an include may not run on every host.
+
+fn push_task_names(run_state: &Arc<RunState>, play: &Play, task: &Task, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>, 
+    prefix: &String, names: &mut Vec<String>) -> Result<(), String> {
+
+    if let Task::Block(block) = task {
+        for (section, tasks) in [("", Some(&block.tasks)), ("rescue : ", block.rescue.as_ref()), ("always : ", block.always.as_ref())] {
+            for inner in tasks.into_iter().flatten() {
+                push_task_names(run_state, play, inner, are_handlers, role_invocation, &format!("{}{}", prefix, section), names)?;
+            }
+        }
+        return Ok(());
+    }
+    let included_file = match task {
+        Task::Import_Tasks(x) => Some(&x.file),
+        Task::Include_Tasks(x) if check_tags(run_state, task, role_invocation).is_ok() => Some(&x.file),
+        Task::Include_Tasks(_) => { return Ok(()); },
+        _ => None
+    };
+    if let Some(file) = included_file {
+        let path = get_included_task_path(run_state, play, file, are_handlers, role_invocation)?;
+        for inner in load_task_file(&path)?.iter() {
+            push_task_names(run_state, play, inner, are_handlers, role_invocation, prefix, names)?;
+        }
+        return Ok(());
+    }
+    if check_tags(run_state, task, role_invocation).is_ok() {
+        names.push(format!("{}{}", prefix, task.get_display_name()));
+    }
+    return Ok(());
+}
+
+// --syntax-check parses every playbook along with the roles, task files and vars files it refers to, and runs
+// the same validation of each task that happens before it runs, without connecting to anything.  Problems are
+// collected rather than stopping at the first, so that one run reports everything that needs fixing.
+
+fn syntax_check_traversal(run_state: &Arc<RunState>) -> Result<(), String> {
+    let problems = find_syntax_problems(run_state);
+    if problems.is_empty() {
+        println!("syntax ok");
+        return Ok(());
+    }
+    return Err(format!("syntax check found {} problem(s):\n  {}", problems.len(), problems.join("\n  ")));
+}
+
+fn find_syntax_problems(run_state: &Arc<RunState>) -> Vec<String> {
+
+    let mut problems : Vec<String> = Vec::new();
+    let p1 = env::current_dir().expect("could not get current directory");
+    let previous = p1.as_path();
+
+    for playbook_path in run_state.playbook_paths.read().unwrap().iter() {
+
+        run_state.context.write().unwrap().set_playbook_path(playbook_path);
+        let source = playbook_path.display().to_string();
+        let contents = match read_local_file(playbook_path.as_path()) {
+            Ok(x) => x,
+            Err(y) => { problems.push(y); continue; }
+        };
+        let plays : Vec<Play> = match serde_yaml::from_str(&contents) {
+            Ok(x) => x,
+            Err(y) => { problems.push(format!("{}: {}", source, y)); continue; }
+        };
+
+        // relative paths in the playbook are relative to its directory, as when running it
+        let pbdirname = directory_as_string(playbook_path);
+        if ! pbdirname.eq(&String::from("")) {
+            env::set_current_dir(Path::new(&pbdirname)).expect("could not chdir into playbook directory");
+        }
+        for play in plays.iter() {
+            syntax_check_play(run_state, play, &source, &mut problems);
+        }
+        env::set_current_dir(&previous).expect("could not restore previous directory");
+    }
+    return problems;
+}
+
+fn syntax_check_play(run_state: &Arc<RunState>, play: &Play, source: &String, problems: &mut Vec<String>) {
+
+    let label = format!("{}: play '{}'", source, play.name);
+
+    for pathname in play.vars_files.iter().flatten() {
+        let path = Path::new(pathname);
+        let checked = read_local_file(path).and_then(|contents| match is_encrypted(&contents) {
+            true => Ok(()),
+            false => serde_yaml::from_str::<serde_yaml::Mapping>(&contents).map(|_| ()).map_err(|y| format!("{}: {}", pathname, y))
+        });
+        if let Err(y) = checked {
+            problems.push(format!("{}: vars_files: {}", label, y));
+        }
+    }
+
+    let roles = match resolve_roles(run_state, play) {
+        Ok(x) => x,
+        Err(y) => { problems.push(format!("{}: {}", label, y)); Vec::new() }
+    };
+    for are_handlers in [HandlerMode::NormalTasks, HandlerMode::Handlers] {
+        for invocation in roles.iter() {
+            let (role, role_path) = match find_role(run_state, play, invocation.role.clone()) {
+                Ok(x) => x,
+                Err(y) => { problems.push(format!("{}: {}", label, y)); continue; }
+            };
+            for task_path in get_role_task_paths(&role, &role_path, are_handlers) {
+                syntax_check_task_file(run_state, play, &task_path, are_handlers, Some(invocation), &mut Vec::new(), problems);
+            }
+        }
+        let loose = match are_handlers {
+            HandlerMode::NormalTasks => &play.tasks,
+            HandlerMode::Handlers    => &play.handlers
+        };
+        if let Some(tasks) = loose {
+            syntax_check_tasks(run_state, play, tasks, are_handlers, None, source, &mut Vec::new(), problems);
+        }
+    }
+}
+
+// the chain is the task files currently being checked, so a file that imports itself is reported instead of recursing forever
+
+fn syntax_check_task_file(run_state: &Arc<RunState>, play: &Play, path: &PathBuf, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>,
+    chain: &mut Vec<PathBuf>, problems: &mut Vec<String>) {
+
+    if chain.contains(path) {
+        chain.push(path.clone());
+        let names : Vec<String> = chain.iter().map(|x| x.display().to_string()).collect();
+        problems.push(format!("task file include cycle: {}", names.join(" -> ")));
+        chain.pop();
+        return;
+    }
+    let source = path.display().to_string();
+    let tasks : Vec<Task> = match read_local_file(path.as_path()) {
+        Ok(contents) => match serde_yaml::from_str(&contents) {
+            Ok(x) => x,
+            Err(y) => { problems.push(format!("{}: {}", source, y)); return; }
+        },
+        Err(y) => { problems.push(y); return; }
+    };
+    chain.push(path.clone());
+    syntax_check_tasks(run_state, play, &tasks, are_handlers, role_invocation, &source, chain, problems);
+    chain.pop();
+}
+
+fn syntax_check_tasks(run_state: &Arc<RunState>, play: &Play, tasks: &Vec<Task>, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>,
+    source: &String, chain: &mut Vec<PathBuf>, problems: &mut Vec<String>) {
+
+    for task in tasks.iter() {
+        let included_file = match task {
+            Task::Block(block) => {
+                for section in [Some(&block.tasks), block.rescue.as_ref(), block.always.as_ref()] {
+                    if let Some(inner) = section {
+                        syntax_check_tasks(run_state, play, inner, are_handlers, role_invocation, source, chain, problems);
+                    }
+                }
+                continue;
+            },
+            Task::Meta(_) => { continue; },
+            Task::Import_Tasks(x) => &x.file,
+            Task::Include_Tasks(x) => &x.file,
+            _ => {
+                // templates are not rendered, there are no host variables to render them with
+                let host = Arc::new(RwLock::new(Host::new(&String::from("syntax-check"))));
+                let connection : Arc<Mutex<dyn Connection>> = Arc::new(Mutex::new(NoConnection::new()));
+                let handle = Arc::new(TaskHandle::new(Arc::clone(run_state), connection, host, None));
+                if let Err(response) = task.evaluate(&handle, &TaskRequest::validate(), TemplateMode::Off) {
+                    problems.push(format!("{}: task '{}': {}", source, task.get_display_name(), response.msg.clone().unwrap_or(String::from("invalid"))));
+                }
+                continue;
+            }
+        };
+        match get_included_task_path(run_state, play, included_file, are_handlers, role_invocation) {
+            Ok(path) if ! path.exists() => problems.push(format!("{}: task '{}': task file not found: {}", source, task.get_display_name(), path.display())),
+            Ok(path) => syntax_check_task_file(run_state, play, &path, are_handlers, role_invocation, chain, problems),
+            Err(y) => problems.push(format!("{}: task '{}': {}", source, task.get_display_name(), y))
+        }
+    }
+}
+
+// --plan-out writes what a run is about to do as a JSON (or YAML, by file extension) document, for review
+// before the run and for diffing between commits.  For each play it records the hosts selected after limits,
+// the tasks and handlers selected after tags with their unrendered parameters, and the variables each host
+// will start with from inventory, the play and --extra-vars.  Facts and role variables are not known yet.
+// Like --list-tasks it does not connect to anything, and the file is only readable by the current user
+// since resolved variables can include decrypted secrets.
+
+fn write_plan(run_state: &Arc<RunState>, plan_path: &String) -> Result<(), String> {
+
+    let mut playbooks : Vec<serde_json::Value> = Vec::new();
+    let p1 = env::current_dir().expect("could not get current directory");
+
+    for playbook_path in run_state.playbook_paths.read().unwrap().iter() {
+        let contents = read_local_file(playbook_path)?;
+        let plays : Vec<Play> = match serde_yaml::from_str(&contents) {
+            Ok(x) => x,
+            Err(y) => {
+                show_yaml_error_in_context(&y, &playbook_path);
+                return Err(format!("edit the file and try again?"));
+            }
+        };
+        let raw_plays : Vec<serde_yaml::Value> = match serde_yaml::from_str(&contents) {
+            Ok(x) => x,
+            Err(y) => { return Err(format!("{}: {}", playbook_path.display(), y)); }
+        };
+
+        let pbdirname = directory_as_string(playbook_path);
+        if ! pbdirname.is_empty() {
+            env::set_current_dir(Path::new(&pbdirname)).expect("could not chdir into playbook directory");
+        }
+        run_state.context.write().unwrap().set_playbook_path(playbook_path);
+        // collect the plays before propagating any error so the working directory is always restored
+        let plays_json : Result<Vec<serde_json::Value>, String> = plays.iter().zip(raw_plays.iter())
+            .map(|(play, raw_play)| plan_play(run_state, play, raw_play))
+            .collect();
+        env::set_current_dir(&p1).expect("could not restore previous directory");
+        let plays_json = plays_json?;
+
+        playbooks.push(serde_json::json!({
+            "path": playbook_path.display().to_string(),
+            "plays": plays_json
+        }));
+    }
+
+    let plan = serde_json::json!({ "playbooks": playbooks });
+    return write_report(&String::from("--plan-out"), plan_path, &plan);
+}
+
+// --diff-out records every change each host made, or would make in check mode, along with any diffs
+// collected, so that what-if runs can be kept and reviewed later.  Like the plan it is only readable
+// by the current user.
+
+fn write_diff_out(run_state: &Arc<RunState>) -> Result<(), String> {
+    let ctx = run_state.context.read().unwrap();
+    if let Some(diff_path) = &ctx.diff_out {
+        let report = serde_json::json!({
+            "check": run_state.visitor.read().unwrap().is_check_mode(),
+            "hosts": ctx.get_changes_by_host()
+        });
+        return write_report(&String::from("--diff-out"), diff_path, &report);
+    }
+    return Ok(());
+}
+
+// reports are written as JSON, or as YAML when the path ends in .yml or .yaml
+
+fn write_report(flag: &String, path: &String, report: &serde_json::Value) -> Result<(), String> {
+    let data = match path.ends_with(".yml") || path.ends_with(".yaml") {
+        true  => serde_yaml::to_string(report).map_err(|e| format!("{}: {}", flag, e))?,
+        false => serde_json::to_string_pretty(report).map_err(|e| format!("{}: {}", flag, e))? + "\n"
+    };
+    let mut file = match OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path) {
+        Ok(x) => x,
+        Err(e) => { return Err(format!("{}: cannot open {}: {}", flag, path, e)); }
+    };
+    return match file.write_all(data.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("{}: cannot write {}: {}", flag, path, e))
+    };
+}
+
+// the plan is meant to be shared and diffed, so anything that came out of a vault file is masked
+
+fn plan_host_variables(vars: &serde_yaml::Mapping) -> Result<serde_json::Value, String> {
+    let value = serde_json::to_value(vars).map_err(|e| format!("--plan-out: {}", e))?;
+    return Ok(redact_json(value));
+}
+
+fn plan_play(run_state: &Arc<RunState>, play: &Play, raw_play: &serde_yaml::Value) -> Result<serde_json::Value, String> {
+
+    {
+        let mut ctx = run_state.context.write().unwrap();
+        ctx.set_play(play);
+        ctx.unset_role();
+    }
+    validate_limit_groups(run_state, play)?;
+    validate_limit_hosts(run_state, play)?;
+    validate_groups(run_state, play)?;
+    let hosts = get_play_hosts(run_state, play);
+    load_vars_into_context(run_state, play)?;
+
+    let mut host_names : Vec<String> = hosts.iter().map(|x| x.read().unwrap().name.clone()).collect();
+    host_names.sort();
+    let mut variables = serde_json::Map::new();
+    for host in hosts.iter() {
+        let vars = run_state.context.read().unwrap().get_complete_blended_variables(host, BlendTarget::NotTemplateModule);
+        let name = host.read().unwrap().name.clone();
+        variables.insert(name, plan_host_variables(&vars)?);
+    }
+
+    let mut sections = serde_json::Map::new();
+    for (label, are_handlers) in [("tasks", HandlerMode::NormalTasks), ("handlers", HandlerMode::Handlers)] {
+        let mut entries : Vec<serde_json::Value> = Vec::new();
+        for invocation in resolve_roles(run_state, play)?.iter() {
+            let (role, role_path) = find_role(run_state, play, invocation.role.clone())?;
+            for task_path in get_role_task_paths(&role, &role_path, are_handlers) {
+                let tasks = load_task_file(&task_path)?;
+                let raw_tasks = load_raw_task_file(&task_path)?;
+                for (task, raw) in tasks.iter().zip(raw_tasks.iter()) {
+                    push_task_plan(run_state, play, task, raw, are_handlers, Some(invocation), Some(&role.name), None, &mut entries)?;
+                }
+            }
+        }
+        let loose = match are_handlers {
+            HandlerMode::NormalTasks => &play.tasks,
+            HandlerMode::Handlers    => &play.handlers
+        };
+        if let Some(tasks) = loose {
+            let raw_tasks = raw_play.get(label).and_then(|x| x.as_sequence()).cloned().unwrap_or(Vec::new());
+            for (task, raw) in tasks.iter().zip(raw_tasks.iter()) {
+                push_task_plan(run_state, play, task, raw, are_handlers, None, None, None, &mut entries)?;
+            }
+        }
+        sections.insert(String::from(label), serde_json::Value::Array(entries));
+    }
+
+    return Ok(serde_json::json!({
+        "name": play.name,
+        "groups": play.groups,
+        "hosts": host_names,
+        "tasks": sections.remove("tasks").unwrap(),
+        "handlers": sections.remove("handlers").unwrap(),
+        "variables": variables
+    }));
+}
+
+// tasks in a block are listed as their contents, in the order they would run, with the block and section noted.
+// imported and included files are listed as their contents in the same way.
+
+fn push_task_plan(run_state: &Arc<RunState>, play: &Play, task: &Task, raw: &serde_yaml::Value, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>,
+    role_name: Option<&String>, block: Option<(&String, &str)>, entries: &mut Vec<serde_json::Value>) -> Result<(), String> {
+
+    let (module, params) = match raw {
+        serde_yaml::Value::Tagged(tagged) => (tagged.tag.to_string().trim_start_matches('!').to_string(), tagged.value.clone()),
+        x => (task.get_module(), x.clone())
+    };
+
+    if let Task::Block(inner) = task {
+        let block_name = task.get_display_name();
+        for (section, tasks) in [("tasks", Some(&inner.tasks)), ("rescue", inner.rescue.as_ref()), ("always", inner.always.as_ref())] {
+            let raw_tasks = params.get(section).and_then(|x| x.as_sequence()).cloned().unwrap_or(Vec::new());
+            for (inner_task, inner_raw) in tasks.into_iter().flatten().zip(raw_tasks.iter()) {
+                push_task_plan(run_state, play, inner_task, inner_raw, are_handlers, role_invocation, role_name, Some((&block_name, section)), entries)?;
+            }
+        }
+        return Ok(());
+    }
+    let included_file = match task {
+        Task::Import_Tasks(x) => Some(&x.file),
+        Task::Include_Tasks(x) if check_tags(run_state, task, role_invocation).is_ok() => Some(&x.file),
+        Task::Include_Tasks(_) => { return Ok(()); },
+        _ => None
+    };
+    if let Some(file) = included_file {
+        let path = get_included_task_path(run_state, play, file, are_handlers, role_invocation)?;
+        let tasks = load_task_file(&path)?;
+        let raw_tasks = load_raw_task_file(&path)?;
+        for (inner_task, inner_raw) in tasks.iter().zip(raw_tasks.iter()) {
+            push_task_plan(run_state, play, inner_task, inner_raw, are_handlers, role_invocation, role_name, block, entries)?;
+        }
+        return Ok(());
+    }
+    if check_tags(run_state, task, role_invocation).is_err() {
+        return Ok(());
+    }
+
+    let mut tags : Vec<String> = task.get_with().and_then(|x| x.tags).unwrap_or(Vec::new());
+    if let Some(role_tags) = role_invocation.and_then(|x| x.tags.as_ref()) {
+        tags.extend(role_tags.iter().cloned());
+    }
+    let mut entry = serde_json::json!({
+        "name": task.get_display_name(),
+        "module": module,
+        "tags": tags,
+        "params": params
+    });
+    if let Some(role) = role_name {
+        entry["role"] = serde_json::json!(role);
+    }
+    if let Some((block_name, section)) = block {
+        entry["block"] = serde_json::json!(block_name);
+        entry["section"] = serde_json::json!(section);
+    }
+    entries.push(entry);
+    return Ok(());
+}
+
 fn handle_play(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
 
     {
@@ -133,6 +734,11 @@ fn handle_play(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
         if play.ssh_port.is_some() {
             ctx.set_ssh_port(play.ssh_port.unwrap());
         }
+        match play.connection.as_deref() {
+            None | Some("ssh") | Some("local") | Some("docker") | Some("podman") | Some("winrm") => {},
+            Some(x) => { return Err(format!("play '{}': unknown connection type '{}', expecting 'ssh', 'local', 'docker', 'podman' or 'winrm'", play.name, x)); }
+        }
+        ctx.set_connection(&play.connection);
         ctx.unset_role();
     }
     run_state.visitor.read().unwrap().on_play_start(&run_state.context);
@@ -147,10 +753,14 @@ fn handle_play(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
     validate_hosts(run_state, play, &hosts)?;
     load_vars_into_context(run_state, play)?;
 
+    if run_state.preflight {
+        preflight_connections(run_state, play, &hosts)?;
+    }
+
     // support for serialization if using push configuration
     // means we may not configure hosts all at once but may take
     // several passes to do a smaller number of them
-    let (_batch_size, batch_count, batches) = get_host_batches(run_state, play, hosts);
+    let (_batch_size, batch_count, batches) = get_host_batches(run_state, play, hosts)?;
 
     let mut failed: bool = false;
     let mut failure_message: String = String::new();
@@ -161,7 +771,7 @@ fn handle_play(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
             break;
         }
         let hosts = batches.get(&batch_num).unwrap();
-        run_state.visitor.read().unwrap().on_batch(batch_num, batch_count, hosts.len());
+        run_state.visitor.read().unwrap().on_batch(&run_state.context, batch_num, batch_count, hosts.len());
         match handle_batch(run_state, play, hosts) {
             Ok(_) => {},
             Err(s) => {
@@ -170,6 +780,17 @@ fn handle_play(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
                 failure_message.push_str(&s.clone());
             }
         }
+        // in a rolling update, too many failures in one batch stops the later batches from starting
+        if ! failed && play.max_fail_percentage.is_some() {
+            let max_fail = play.max_fail_percentage.unwrap();
+            let failed_ct = run_state.context.read().unwrap().get_failed_count_in(hosts);
+            if failed_ct * 100 > max_fail * hosts.len() {
+                failed = true;
+                failure_message.clear();
+                failure_message.push_str(&format!("batch {}/{}: {} of {} hosts failed, exceeding max_fail_percentage of {}%, remaining batches aborted",
+                    batch_num + 1, batch_count, failed_ct, hosts.len(), max_fail));
+            }
+        }
         // disconect from hosts between batches, one of the reasons we may be using
         // this is we have a very large number of machines to manage
         run_state.context.read().unwrap().connection_cache.write().unwrap().clear();
@@ -185,16 +806,78 @@ fn handle_play(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
     }
 }
 
+// --preflight connects to every host of the play before anything runs, so that hosts which cannot be reached
+// or logged into are reported together instead of one at a time as the first task gets to them.  They are
+// failed right away and the batches skip them.  Connections that work stay cached for the first batch.
+
+fn preflight_connections(run_state: &Arc<RunState>, play: &Play, hosts: &Vec<Arc<RwLock<Host>>>) -> Result<(), String> {
+
+    run_state.context.write().unwrap().set_targetted_hosts(hosts);
+    let failures : Mutex<Vec<(String,String)>> = Mutex::new(Vec::new());
+
+    hosts.par_iter().with_max_len(1).for_each(|host| {
+        let connection_result = run_state.connection_factory.read().unwrap().get_connection(&run_state.context, &host);
+        match connection_result {
+            Ok(_) => {
+                run_state.visitor.read().unwrap().on_host_connect_ok(&run_state.context, &host);
+            },
+            Err(x) => {
+                run_state.visitor.read().unwrap().debug_host(&host, &x);
+                run_state.context.write().unwrap().fail_host(&host);
+                run_state.visitor.read().unwrap().on_host_connect_failed(&run_state.context, &host);
+                failures.lock().unwrap().push((host.read().unwrap().name.clone(), x));
+            }
+        }
+    });
+
+    let mut failures = failures.into_inner().unwrap();
+    failures.sort();
+    run_state.visitor.read().unwrap().on_preflight(&run_state.context, hosts.len(), &failures);
+
+    if let Some(max_fail) = run_state.preflight_max_fail {
+        if failures.len() * 100 > max_fail * hosts.len() {
+            return Err(format!("play '{}': {} of {} hosts could not be reached, exceeding --preflight-max-fail of {}%, nothing was run",
+                play.name, failures.len(), hosts.len(), max_fail));
+        }
+    }
+    return Ok(());
+}
+
 fn handle_batch(run_state: &Arc<RunState>, play: &Play, hosts: &Vec<Arc<RwLock<Host>>>) -> Result<(), String> {
 
     // assign the batch
     { let mut ctx = run_state.context.write().unwrap(); ctx.set_targetted_hosts(&hosts); }
 
-    // handle role tasks
-    if play.roles.is_some() {
-        let roles = play.roles.as_ref().unwrap();
-        for invocation in roles.iter() { process_role(run_state, &play, &invocation, HandlerMode::NormalTasks)?; }
+    // facts like jet_os_type are collected before anything else so tasks and conditions can use them
+    if run_state.gather_facts {
+        gather_facts(run_state, play)?;
+    }
+
+    // the hosts that start the batch are the ones --force-handlers may bring back for handlers
+    let batch_hosts : Vec<Arc<RwLock<Host>>> = run_state.context.read().unwrap().get_remaining_hosts().values().cloned().collect();
+
+    let tasks_result = handle_batch_tasks(run_state, play);
+    if tasks_result.is_err() && ! run_state.force_handlers {
+        return tasks_result;
     }
+
+    // with --force-handlers, hosts that failed still run the handlers they were notified for, which may be
+    // needed to recover from a partial change. the FSM skips handlers a host was not notified for.
+    if run_state.force_handlers {
+        run_state.context.write().unwrap().set_targetted_hosts_including_failed(&batch_hosts);
+    }
+    let handlers_result = handle_batch_handlers(run_state, play);
+    if run_state.force_handlers {
+        run_state.context.write().unwrap().set_targetted_hosts(&batch_hosts);
+    }
+    tasks_result?;
+    return handlers_result;
+}
+
+fn handle_batch_tasks(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
+
+    // handle role tasks, dependencies included
+    for invocation in resolve_roles(run_state, play)?.iter() { process_role(run_state, &play, &invocation, HandlerMode::NormalTasks)?; }
     { let mut ctx = run_state.context.write().unwrap(); ctx.unset_role(); }
 
     // handle loose play tasks
@@ -202,12 +885,13 @@ fn handle_batch(run_state: &Arc<RunState>, play: &Play, hosts: &Vec<Arc<RwLock<H
         let tasks = play.tasks.as_ref().unwrap();
         for task in tasks.iter() { process_task(run_state, &play, &task, HandlerMode::NormalTasks, None)?; }
     }
+    return Ok(());
+}
+
+fn handle_batch_handlers(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
 
-    // handle role handlers
-    if play.roles.is_some() {
-        let roles = play.roles.as_ref().unwrap();
-        for invocation in roles.iter() { process_role(run_state, &play, &invocation, HandlerMode::Handlers)?; }
-    }   
+    // handle role handlers, dependencies included
+    for invocation in resolve_roles(run_state, play)?.iter() { process_role(run_state, &play, &invocation, HandlerMode::Handlers)?; }
     { let mut ctx = run_state.context.write().unwrap(); ctx.unset_role(); }  
 
     // handle loose play handlers
@@ -219,43 +903,50 @@ fn handle_batch(run_state: &Arc<RunState>, play: &Play, hosts: &Vec<Arc<RwLock<H
 
 }
 
-fn check_tags(run_state: &Arc<RunState>, task: &Task, role_invocation: Option<&RoleInvocation>) -> bool {
+// the tags a task carries, its own followed by those of the role it came from
+
+fn get_task_tags(task: &Task, role_invocation: Option<&RoleInvocation>) -> Vec<String> {
+    let mut task_tags : Vec<String> = Vec::new();
+    if let Some(task_with) = task.get_with() {
+        if let Some(tags) = task_with.tags {
+            task_tags.extend(tags);
+        }
+    }
+    if let Some(role_invoke) = role_invocation {
+        if let Some(tags) = &role_invoke.tags {
+            task_tags.extend(tags.iter().cloned());
+        }
+    }
+    return task_tags;
+}
+
+fn check_tags(run_state: &Arc<RunState>, task: &Task, role_invocation: Option<&RoleInvocation>) -> Result<(), String> {
 
-    // a given task may have tags associated from either the current role or directly on the task
+    // a given task may have tags associated from either the current role or directly on the task.
     // if the CLI --tags argument was used, we will skip the task if those tags don't match or
-    // if the tags are ommitted
-
-    match &run_state.tags {
-        Some(cli_tags) => {
-            // CLI tags were specified
-            match task.get_with() {
-                // a with section was present
-                Some(task_with) => match task_with.tags {
-                    // tags are applied to the task
-                    Some(task_tags) => {
-                        for x in task_tags.iter() {  if cli_tags.contains(&x) { return true; } }
-                    },
-                    // no tags
-                    None => {}
-                },
-                None => {}
-            };
-            match role_invocation {
-                // the role invocation has tags applied
-                Some(role_invoke) => match &role_invoke.tags {
-                    Some(role_tags) => {
-                        for x in role_tags.iter() { if cli_tags.contains(&x) { return true; } }
-                    },
-                    None => {}
-                },
-                None => {}
-            };
+    // if the tags are ommitted.  --skip-tags always wins.  Tasks tagged 'always' run unless
+    // skipped by name and tasks tagged 'never' only run when one of their tags is asked for.
+    // the error string is the reason the task was skipped.
+
+    let task_tags = get_task_tags(task, role_invocation);
+
+    if let Some(skip_tags) = &run_state.skip_tags {
+        if task_tags.iter().any(|x| skip_tags.contains(x)) {
+            return Err(String::from("tag skipped"));
         }
-        // no CLI tags so run the task
-        None => { return true; }
+    }
+    let selected = match &run_state.tags {
+        Some(cli_tags) => task_tags.iter().any(|x| cli_tags.contains(x)),
+        None => false
+    };
+    if task_tags.contains(&String::from("never")) && ! selected {
+        return Err(String::from("tagged never"));
+    }
+    if run_state.tags.is_none() || selected || task_tags.contains(&String::from("always")) {
+        return Ok(());
     }
     // we didn't match any tags, so don't run the task
-    return false;
+    return Err(String::from("tag not selected"));
 }
 
 fn process_task(run_state: &Arc<RunState>, play: &Play, task: &Task, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>) -> Result<(), String> {
@@ -263,21 +954,238 @@ fn process_task(run_state: &Arc<RunState>, play: &Play, task: &Task, are_handler
     // this function is the final wrapper before fsm_run_task, the low-level finite state machine around task execution that is wrapped
     // by rayon, for multi-threaded execution with our thread worker pool.
 
+    // after Ctrl-C nothing new is started, see util/interrupt.rs
+    if is_interrupted() { return Err(String::from("interrupted, remaining tasks were not run")) }
+
     let hosts : HashMap<String, Arc<RwLock<Host>>> = run_state.context.read().unwrap().get_remaining_hosts();
     if hosts.len() == 0 { return Err(String::from("no hosts remaining")) }
 
+    // blocks are not run by the FSM, instead each of the tasks inside them is processed here
+    if let Task::Block(block) = task {
+        return process_block(run_state, play, block, are_handlers, role_invocation);
+    }
+
+    // meta tasks change the flow of the play and also do not go through the FSM
+    if let Task::Meta(meta) = task {
+        return process_meta(run_state, play, meta, are_handlers, role_invocation);
+    }
+
+    // imported and included files are walked here, their tasks are processed like any others
+    if let Task::Import_Tasks(import) = task {
+        let path = get_included_task_path(run_state, play, &import.file, are_handlers, role_invocation)?;
+        return process_task_file(run_state, play, &path, &import.vars, are_handlers, role_invocation);
+    }
+    if let Task::Include_Tasks(include) = task {
+        return match check_tags(run_state, task, role_invocation) {
+            Ok(_) => process_include(run_state, play, include, are_handlers, role_invocation),
+            Err(reason) => {
+                run_state.visitor.read().unwrap().on_task_skipped(&run_state.context, &task.get_display_name(), &reason);
+                Ok(())
+            }
+        };
+    }
+
+    // when resuming with --start-at-task nothing runs until we reach it, handlers included
+    let task_name = task.get_display_name();
+    if run_state.context.write().unwrap().is_before_start_task(&task_name) {
+        run_state.visitor.read().unwrap().on_task_skipped(&run_state.context, &task_name, &String::from("before --start-at-task"));
+        return Ok(());
+    }
+
     // we will run tasks with the FSM only if not skipped by tags
-    let should_run = check_tags(run_state, task, role_invocation);
-    if should_run {
-        run_state.context.write().unwrap().set_task(&task);
-        run_state.visitor.read().unwrap().on_task_start(&run_state.context, are_handlers);
-        run_state.context.write().unwrap().increment_task_count();
-        fsm_run_task(run_state, play, task, are_handlers)?;
+    match check_tags(run_state, task, role_invocation) {
+        Ok(_) => {
+            if ! confirm_step(run_state, &task_name) {
+                run_state.visitor.read().unwrap().on_task_skipped(&run_state.context, &task_name, &String::from("declined at --step"));
+                return Ok(());
+            }
+            run_state.context.write().unwrap().set_task(&task);
+            run_state.visitor.read().unwrap().on_task_start(&run_state.context, are_handlers, &get_task_tags(task, role_invocation));
+            run_state.context.write().unwrap().increment_task_count();
+            fsm_run_task(run_state, play, task, are_handlers)?;
+            if are_handlers == HandlerMode::NormalTasks {
+                if let Some(fatal_host) = run_state.context.read().unwrap().get_fatal_host() {
+                    return Err(format!("host {} failed and the play has any_errors_fatal set, stopping", fatal_host));
+                }
+            }
+        },
+        Err(reason) => {
+            run_state.visitor.read().unwrap().on_task_skipped(&run_state.context, &task.get_display_name(), &reason);
+        }
+    }
+
+    return Ok(());
+}
+
+// runs the tasks of a block, then the rescue tasks for any hosts that failed in the block, then
+// the always tasks for every host that entered it.  Hosts that fail in the block are dropped from
+// the remaining block tasks as usual, but running out of hosts is not an error until after the block.
+
+fn process_block(run_state: &Arc<RunState>, play: &Play, block: &BlockTask, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>) -> Result<(), String> {
+
+    let entering : Vec<Arc<RwLock<Host>>> = run_state.context.read().unwrap().get_remaining_hosts().values().cloned().collect();
+
+    process_block_section(run_state, play, &block.tasks, are_handlers, role_invocation)?;
+
+    let failed_in_block : Vec<Arc<RwLock<Host>>> = get_failed_among(run_state, &entering);
+    if let Some(rescue) = &block.rescue {
+        if ! failed_in_block.is_empty() {
+            {
+                let mut ctx = run_state.context.write().unwrap();
+                ctx.set_targetted_hosts(&Vec::new());
+                for host in failed_in_block.iter() {
+                    ctx.rescue_host(host);
+                }
+            }
+            process_block_section(run_state, play, rescue, are_handlers, role_invocation)?;
+            run_state.context.write().unwrap().set_targetted_hosts(&entering);
+        }
+    }
+
+    if let Some(always) = &block.always {
+        run_state.context.write().unwrap().set_targetted_hosts_including_failed(&entering);
+        process_block_section(run_state, play, always, are_handlers, role_invocation)?;
+        run_state.context.write().unwrap().set_targetted_hosts(&entering);
+    }
+
+    return Ok(());
+}
+
+fn process_meta(run_state: &Arc<RunState>, play: &Play, meta: &MetaTask, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>) -> Result<(), String> {
+
+    match meta.action {
+        MetaAction::FlushHandlers => {
+
+            if are_handlers == HandlerMode::Handlers {
+                run_state.visitor.read().unwrap().on_task_skipped(&run_state.context, &meta.name.clone().unwrap_or(String::from("meta")), &String::from("flush_handlers has no effect inside handlers"));
+                return Ok(());
+            }
+
+            // run the same handler traversal as the end of the play.  The FSM skips handlers a host was not notified for.
+            let flushing : Vec<Arc<RwLock<Host>>> = run_state.context.read().unwrap().get_remaining_hosts().values().cloned().collect();
+            handle_batch_handlers(run_state, play)?;
+
+            // hosts that were flushed start over, so a handler only runs again at the end if notified again
+            let play_count = run_state.context.read().unwrap().play_count;
+            for host in flushing.iter() {
+                host.write().unwrap().clear_notified(play_count);
+            }
+
+            // running role handlers unsets the role, so put back the one this task belongs to
+            if let Some(invocation) = role_invocation {
+                let (role, role_path) = find_role(run_state, &play, invocation.role.clone())?;
+                run_state.context.write().unwrap().set_role(&role, invocation, &directory_as_string(&role_path));
+            }
+        }
+    }
+    return Ok(());
+}
+
+// an include only runs on the hosts its condition is true for.  The other hosts sit out the included
+// tasks and carry on afterwards, along with any that did not fail inside.
+
+fn process_include(run_state: &Arc<RunState>, play: &Play, include: &IncludeTasksTask, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>) -> Result<(), String> {
+
+    let path = get_included_task_path(run_state, play, &include.file, are_handlers, role_invocation)?;
+    let entering : Vec<Arc<RwLock<Host>>> = run_state.context.read().unwrap().get_remaining_hosts().values().cloned().collect();
+    let condition = include.with.as_ref().and_then(|x| x.condition.clone());
+    if condition.is_none() {
+        return process_task_file(run_state, play, &path, &include.vars, are_handlers, role_invocation);
     }
 
+    let mut included : Vec<Arc<RwLock<Host>>> = Vec::new();
+    for host in entering.iter() {
+        match run_state.context.read().unwrap().test_condition(condition.as_ref().unwrap(), host, TemplateMode::Strict) {
+            Ok(true) => { included.push(Arc::clone(host)); },
+            Ok(false) => {},
+            Err(y) => { return Err(format!("include_tasks {}: host {}: {}", include.file, host.read().unwrap().name, y)); }
+        }
+    }
+    if included.is_empty() {
+        let name = include.name.clone().unwrap_or(format!("include_tasks {}", include.file));
+        run_state.visitor.read().unwrap().on_task_skipped(&run_state.context, &name, &String::from("condition false on every host"));
+        return Ok(());
+    }
+
+    // hosts that entered already failed are here because of a block's always section and stay targetted
+    let failed_before : Vec<String> = entering.iter().filter(|h| run_state.context.read().unwrap().is_host_failed(h))
+        .map(|h| h.read().unwrap().name.clone()).collect();
+    run_state.context.write().unwrap().set_targetted_hosts_including_failed(&included);
+    let result = process_task_file(run_state, play, &path, &include.vars, are_handlers, role_invocation);
+    let mut ctx = run_state.context.write().unwrap();
+    let leaving : Vec<Arc<RwLock<Host>>> = entering.iter().filter(|h| failed_before.contains(&h.read().unwrap().name) || ! ctx.is_host_failed(h))
+        .cloned().collect();
+    ctx.set_targetted_hosts_including_failed(&leaving);
+    return result;
+}
+
+// runs every task in an imported or included file with the file's vars in effect
+
+fn process_task_file(run_state: &Arc<RunState>, play: &Play, path: &PathBuf, vars: &Option<serde_yaml::Mapping>, are_handlers: HandlerMode, 
+    role_invocation: Option<&RoleInvocation>) -> Result<(), String> {
+
+    let tasks = load_task_file(path)?;
+    let previous = run_state.context.read().unwrap().push_include_vars(vars);
+    let result = process_block_section(run_state, play, &tasks, are_handlers, role_invocation);
+    run_state.context.read().unwrap().restore_include_vars(previous);
+    return result;
+}
+
+fn process_block_section(run_state: &Arc<RunState>, play: &Play, tasks: &Vec<Task>, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>) -> Result<(), String> {
+    for task in tasks.iter() {
+        if run_state.context.read().unwrap().get_remaining_hosts().is_empty() {
+            break;
+        }
+        process_task(run_state, play, task, are_handlers, role_invocation)?;
+    }
     return Ok(());
 }
 
+fn get_failed_among(run_state: &Arc<RunState>, hosts: &Vec<Arc<RwLock<Host>>>) -> Vec<Arc<RwLock<Host>>> {
+    let ctx = run_state.context.read().unwrap();
+    return hosts.iter().filter(|h| ctx.is_host_failed(h)).cloned().collect();
+}
+
+// gathering facts is an implicit task at the start of each batch.  It is not subject to tags,
+// --start-at-task or --step, and is not counted as one of the playbook's tasks.
+
+fn gather_facts(run_state: &Arc<RunState>, play: &Play) -> Result<(), String> {
+    let hosts : HashMap<String, Arc<RwLock<Host>>> = run_state.context.read().unwrap().get_remaining_hosts();
+    if hosts.len() == 0 { return Err(String::from("no hosts remaining")) }
+    let task = Task::Facts(FactsTask {
+        name: Some(String::from("gather facts")),
+        facter: None,
+        ohai: None,
+        with: None,
+        and: None
+    });
+    run_state.context.write().unwrap().set_task(&task);
+    run_state.visitor.read().unwrap().on_task_start(&run_state.context, HandlerMode::NormalTasks, &Vec::new());
+    return fsm_run_task(run_state, play, &task, HandlerMode::NormalTasks);
+}
+
+// with --step the user confirms each task before it is sent to the hosts.  Answering
+// 'c' runs this task and stops asking for the rest of the run.
+
+fn confirm_step(run_state: &Arc<RunState>, task_name: &String) -> bool {
+    if ! run_state.context.read().unwrap().step {
+        return true;
+    }
+    loop {
+        match ask(&format!("> run task: {}? (y)es/(n)o/(c)ontinue without asking: ", task_name)).as_deref() {
+            Some("y") | Some("yes") => { return true; },
+            Some("n") | Some("no") => { return false; },
+            Some("c") | Some("continue") => {
+                run_state.context.write().unwrap().step = false;
+                return true;
+            },
+            // nobody is left to answer, so nothing else should run
+            None => { return false; },
+            _ => {}
+        }
+    }
+}
+
 fn process_role(run_state: &Arc<RunState>, play: &Play, invocation: &RoleInvocation, are_handlers: HandlerMode) -> Result<(), String> {
 
     // traversal code for roles.  This is called twice, once for normal tasks and again when processing handler tasks.
@@ -303,8 +1211,8 @@ fn process_role(run_state: &Arc<RunState>, play: &Play, invocation: &RoleInvocat
     // depends on whether we are in handler mode or not
 
     let files = match are_handlers {
-        HandlerMode::NormalTasks => role.tasks,
-        HandlerMode::Handlers    => role.handlers
+        HandlerMode::NormalTasks => &role.tasks,
+        HandlerMode::Handlers    => &role.handlers
     };
 
     // the file sections are optional...
@@ -321,35 +1229,9 @@ fn process_role(run_state: &Arc<RunState>, play: &Play, invocation: &RoleInvocat
 
         // for each task file path that is mentioned
 
-        for task_file in files.unwrap().iter() {
-
-            // find the likely path location, which is organized into subdirectories for relative paths
-
-            let task_buf = match task_file.starts_with("/") {
-                true => {
-                    Path::new(task_file).to_path_buf()
-                }
-                false => {
-                    let mut pb = PathBuf::new();
-                    pb.push(role_path.clone());
-                    match are_handlers {
-                        HandlerMode::NormalTasks => { pb.push("tasks"); },
-                        HandlerMode::Handlers    => { pb.push("handlers"); },
-                    };
-                    pb.push(task_file);
-                    pb
-                }
-            };
-
-            // parse the YAML file
+        for task_buf in get_role_task_paths(&role, &role_path, are_handlers) {
 
-            let task_fh = jet_file_open(&task_buf.as_path())?;
-            let parsed: Result<Vec<Task>, serde_yaml::Error> = serde_yaml::from_reader(task_fh);
-            if parsed.is_err() {
-                show_yaml_error_in_context(&parsed.unwrap_err(), &task_buf.as_path());
-                return Err(format!("edit the file and try again?"));
-            }   
-            let tasks = parsed.unwrap();
+            let tasks = load_task_file(&task_buf)?;
             for task in tasks.iter() {
 
                 // process all tasks in the YAML file, this is the same function used
@@ -372,14 +1254,133 @@ fn process_role(run_state: &Arc<RunState>, play: &Play, invocation: &RoleInvocat
 
 }
 
+fn get_role_task_paths(role: &Role, role_path: &PathBuf, are_handlers: HandlerMode) -> Vec<PathBuf> {
+
+    // find the likely path location of each task file, which is organized into subdirectories for relative paths
+
+    let files = match are_handlers {
+        HandlerMode::NormalTasks => &role.tasks,
+        HandlerMode::Handlers    => &role.handlers
+    };
+    let mut results : Vec<PathBuf> = Vec::new();
+    if files.is_none() {
+        return results;
+    }
+    for task_file in files.as_ref().unwrap().iter() {
+        let task_buf = match task_file.starts_with("/") {
+            true => {
+                Path::new(task_file).to_path_buf()
+            }
+            false => {
+                let mut pb = PathBuf::new();
+                pb.push(role_path.clone());
+                match are_handlers {
+                    HandlerMode::NormalTasks => { pb.push("tasks"); },
+                    HandlerMode::Handlers    => { pb.push("handlers"); },
+                };
+                pb.push(task_file);
+                pb
+            }
+        };
+        results.push(task_buf);
+    }
+    return results;
+}
+
+// finds the file named by import_tasks or include_tasks.  Relative paths are next to the playbook, or
+// inside a role in its tasks/ or handlers/ directory, like the files the role itself lists.
+
+fn get_included_task_path(run_state: &Arc<RunState>, play: &Play, file: &String, are_handlers: HandlerMode, role_invocation: Option<&RoleInvocation>) -> Result<PathBuf, String> {
+    if file.starts_with("/") {
+        return Ok(PathBuf::from(file));
+    }
+    return match role_invocation {
+        Some(invocation) => {
+            let (_role, role_path) = find_role(run_state, play, invocation.role.clone())?;
+            let mut pb = role_path.clone();
+            match are_handlers {
+                HandlerMode::NormalTasks => { pb.push("tasks"); },
+                HandlerMode::Handlers    => { pb.push("handlers"); },
+            };
+            pb.push(file);
+            Ok(pb)
+        },
+        None => {
+            let mut pb = PathBuf::new();
+            if let Some(dir) = run_state.context.read().unwrap().playbook_directory.as_ref() {
+                pb.push(dir);
+            }
+            pb.push(file);
+            Ok(pb)
+        }
+    };
+}
+
+fn load_task_file(task_buf: &PathBuf) -> Result<Vec<Task>, String> {
+    let task_fh = jet_file_open(&task_buf.as_path())?;
+    let parsed: Result<Vec<Task>, serde_yaml::Error> = serde_yaml::from_reader(task_fh);
+    if parsed.is_err() {
+        show_yaml_error_in_context(&parsed.unwrap_err(), &task_buf.as_path());
+        return Err(format!("edit the file and try again?"));
+    }
+    return Ok(parsed.unwrap());
+}
+
+// the same task file as plain YAML, so a plan can show task parameters as they were written
+
+fn load_raw_task_file(task_buf: &PathBuf) -> Result<Vec<serde_yaml::Value>, String> {
+    let contents = read_local_file(task_buf.as_path())?;
+    return match serde_yaml::from_str(&contents) {
+        Ok(x) => Ok(x),
+        Err(y) => Err(format!("{}: {}", task_buf.display(), y))
+    };
+}
+
+// a Fisher-Yates shuffle driven by splitmix64, so a seed gives the same order on every platform
+
+fn shuffle_hosts(hosts: &mut Vec<Arc<RwLock<Host>>>, seed: u64) {
+    let mut state = seed;
+    for i in (1..hosts.len()).rev() {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z = z ^ (z >> 31);
+        hosts.swap(i, (z % (i as u64 + 1)) as usize);
+    }
+}
+
 fn get_host_batches(run_state: &Arc<RunState>, play: &Play, hosts: Vec<Arc<RwLock<Host>>>) 
-    -> (usize, usize, HashMap<usize, Vec<Arc<RwLock<Host>>>>) {
+    -> Result<(usize, usize, HashMap<usize, Vec<Arc<RwLock<Host>>>>), String> {
 
     // the --batch-size CLI parameter can be used to split a large amount of possible hosts
     // into smaller subsets, where the playbook will pass over them in multiple waves
-    // this can also be set on the play
+    // this can also be set on the play, either as batch_size or as serial, which
+    // also accepts a percentage of the hosts in the play
 
-    let batch_size = match play.batch_size {
+    if play.batch_size.is_some() && play.serial.is_some() {
+        return Err(format!("play '{}': batch_size and serial cannot be used together", play.name));
+    }
+    let serial_size = match &play.serial {
+        None => None,
+        Some(SerialInput::Count(x)) => Some(*x),
+        Some(SerialInput::Percent(x)) => {
+            let percent = match x.trim().strip_suffix("%").map(|p| p.trim().parse::<usize>()) {
+                Some(Ok(p)) if p > 0 && p <= 100 => p,
+                _ => { return Err(format!("play '{}': serial must be a number of hosts or a percentage from 1% to 100%, got: {}", play.name, x)); }
+            };
+            // round up, so that 10% of 5 hosts is one host at a time and not zero
+            Some((hosts.len() * percent + 99) / 100)
+        }
+    };
+    if serial_size == Some(0) || play.batch_size == Some(0) {
+        return Err(format!("play '{}': batch sizes must be greater than zero", play.name));
+    }
+    if play.max_fail_percentage.is_some() && play.max_fail_percentage.unwrap() > 100 {
+        return Err(format!("play '{}': max_fail_percentage must be from 0 to 100", play.name));
+    }
+
+    let batch_size = match play.batch_size.or(serial_size) {
         Some(x) => x,
         None => match run_state.batch_size {
             Some(y) => y,
@@ -400,10 +1401,23 @@ fn get_host_batches(run_state: &Arc<RunState>, play: &Play, hosts: Vec<Arc<RwLoc
         }
     };
 
-    // sort the hosts so the batches seem consistent when doing successive playbook executions
+    // sort the hosts so the batches seem consistent when doing successive playbook executions, see --order.
+    // batches are filled from the end of the list, so it is reversed afterwards
 
     let mut hosts_list : Vec<Arc<RwLock<Host>>> = hosts.iter().map(|v| Arc::clone(&v)).collect();
-    hosts_list.sort_by(|b, a| a.read().unwrap().name.partial_cmp(&b.read().unwrap().name).unwrap());
+    hosts_list.sort_by(|a, b| a.read().unwrap().name.cmp(&b.read().unwrap().name));
+    match run_state.order {
+        HostOrder::Sorted => {},
+        HostOrder::Reverse => { hosts_list.reverse(); },
+        HostOrder::Inventory => { hosts_list.sort_by_key(|x| x.read().unwrap().inventory_order); },
+        HostOrder::Shuffle(seed) => {
+            shuffle_hosts(&mut hosts_list, seed);
+            if run_state.context.read().unwrap().verbosity > 0 {
+                println!("> hosts shuffled, use --order shuffle:{} to repeat this order", seed);
+            }
+        }
+    }
+    hosts_list.reverse();
 
     // put the hosts into ththe assigned batches
 
@@ -421,17 +1435,20 @@ fn get_host_batches(run_state: &Arc<RunState>, play: &Play, hosts: Vec<Arc<RwLoc
         results.insert(batch_num, batch);
     }
 
-    return (batch_size, batch_count, results);
+    return Ok((batch_size, batch_count, results));
 
 }
 
 fn get_play_hosts(run_state: &Arc<RunState>,play: &Play) -> Vec<Arc<RwLock<Host>>> {
+    return get_group_hosts(run_state, &play.groups);
+}
+
+fn get_group_hosts(run_state: &Arc<RunState>, groups: &Vec<String>) -> Vec<Arc<RwLock<Host>>> {
 
     // the hosts we want to talk to are the ones specified in the play but may
     // be further constrained by the parameters --limit-hosts and limit--groups
     // from the CLI.
     
-    let groups = &play.groups;
     let mut results : HashMap<String, Arc<RwLock<Host>>> = HashMap::new();
     
     let has_group_limits = match run_state.limit_groups.len() {
@@ -458,6 +1475,10 @@ fn get_play_hosts(run_state: &Arc<RunState>,play: &Play) -> Vec<Arc<RwLock<Host>
             if has_host_limits && ! run_state.limit_hosts.contains(k) {
                 continue;
             }
+
+            if run_state.limit.is_some() && ! run_state.limit.as_ref().unwrap().matches(&v.read().unwrap()) {
+                continue;
+            }
             
             if has_group_limits {
                 let mut ok = false;
@@ -523,6 +1544,24 @@ fn validate_groups(run_state: &Arc<RunState>, play: &Play) -> Result<(), String>
     return Ok(());
 }
 
+fn validate_limit_pattern(run_state: &Arc<RunState>) -> Result<(), String> {
+
+    // a --limit pattern that matches nothing in inventory is almost certainly a typo,
+    // so refuse to run rather than quietly doing nothing
+
+    if run_state.limit.is_none() {
+        return Ok(());
+    }
+    let pattern = run_state.limit.as_ref().unwrap();
+    let inv = run_state.inventory.read().unwrap();
+    for (_k,v) in inv.hosts.iter() {
+        if pattern.matches(&v.read().unwrap()) {
+            return Ok(());
+        }
+    }
+    return Err(format!("--limit {}: no hosts in inventory match this pattern", pattern.as_str()));
+}
+
 fn validate_hosts(_run_state: &Arc<RunState>, _play: &Play, hosts: &Vec<Arc<RwLock<Host>>>) -> Result<(), String> {
 
     // once hosts are selected we need to select more than one host, if the groups were all
@@ -556,13 +1595,8 @@ fn load_vars_into_context(run_state: &Arc<RunState>, play: &Play) -> Result<(),
         let vars_files = play.vars_files.as_ref().unwrap();
         for pathname in vars_files {
             let path = Path::new(&pathname);
-            let vars_file = jet_file_open(&path)?;
-            let parsed: Result<serde_yaml::Mapping, serde_yaml::Error> = serde_yaml::from_reader(vars_file);
-            if parsed.is_err() {
-                show_yaml_error_in_context(&parsed.unwrap_err(), &path);
-                return Err(format!("edit the file and try again?"));
-            }
-            blend_variables(&mut ctx_vars_storage, serde_yaml::Value::Mapping(parsed.unwrap()));
+            let parsed = load_vars_file(&path)?;
+            blend_variables(&mut ctx_vars_storage, serde_yaml::Value::Mapping(parsed));
         }
     }
 
@@ -585,6 +1619,40 @@ fn load_vars_into_context(run_state: &Arc<RunState>, play: &Play) -> Result<(),
     return Ok(());
 }
 
+// the roles a play runs, in order.  Roles listed in a role's 'dependencies' run before it, and a dependency
+// that already ran earlier in the play (as a dependency or listed by the play) is not run again.
+// Roles listed by the play itself always run where they are listed.
+
+fn resolve_roles(run_state: &Arc<RunState>, play: &Play) -> Result<Vec<RoleInvocation>, String> {
+    let mut resolved : Vec<RoleInvocation> = Vec::new();
+    if let Some(roles) = &play.roles {
+        for invocation in roles.iter() {
+            resolve_role(run_state, play, invocation, &mut Vec::new(), &mut resolved)?;
+            resolved.push(invocation.clone());
+        }
+    }
+    return Ok(resolved);
+}
+
+fn resolve_role(run_state: &Arc<RunState>, play: &Play, invocation: &RoleInvocation, chain: &mut Vec<String>, resolved: &mut Vec<RoleInvocation>) -> Result<(), String> {
+    if chain.contains(&invocation.role) {
+        chain.push(invocation.role.clone());
+        return Err(format!("role dependency cycle: {}", chain.join(" -> ")));
+    }
+    let (role, _role_path) = find_role(run_state, play, invocation.role.clone())?;
+    chain.push(invocation.role.clone());
+    for dependency in role.dependencies.iter().flatten() {
+        // a dependency is only visited once, so a cycle through one that already ran is found the first time
+        if resolved.iter().any(|x| x.role.eq(&dependency.role)) {
+            continue;
+        }
+        resolve_role(run_state, play, dependency, chain, resolved)?;
+        resolved.push(dependency.clone());
+    }
+    chain.pop();
+    return Ok(());
+}
+
 fn find_role(run_state: &Arc<RunState>, _play: &Play, role_name: String) -> Result<(Role,PathBuf), String> {
 
     // when we need to find a role we look for it in the configured role paths
@@ -610,8 +1678,22 @@ fn find_role(run_state: &Arc<RunState>, _play: &Play, role_name: String) -> Resu
                 show_yaml_error_in_context(&parsed.unwrap_err(), &path);
                 return Err(format!("edit the file and try again?"));
             }   
-            let role = parsed.unwrap();
-            
+            let mut role = parsed.unwrap();
+
+            // defaults may also live in defaults/main.yml, the role.yml 'defaults' win where both set a variable
+            let mut defaults_path = pb.clone();
+            defaults_path.push("defaults");
+            defaults_path.push("main.yml");
+            if defaults_path.exists() {
+                let mut defaults = serde_yaml::Value::Mapping(load_vars_file(&defaults_path)?);
+                if let Some(inline) = role.defaults.take() {
+                    blend_variables(&mut defaults, serde_yaml::Value::Mapping(inline));
+                }
+                if let serde_yaml::Value::Mapping(x) = defaults {
+                    role.defaults = Some(x);
+                }
+            }
+
             return Ok((role,pb));
         }
     }
@@ -619,3 +1701,72 @@ fn find_role(run_state: &Arc<RunState>, _play: &Play, role_name: String) -> Resu
 }  
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::vault::{encrypt,set_vault_password};
+
+    fn hosts(count: usize) -> Vec<Arc<RwLock<Host>>> {
+        return (0..count).map(|x| Arc::new(RwLock::new(Host::new(&format!("host-{}", x))))).collect();
+    }
+
+    fn names(hosts: &Vec<Arc<RwLock<Host>>>) -> Vec<String> {
+        return hosts.iter().map(|x| x.read().unwrap().name.clone()).collect();
+    }
+
+    #[test]
+    fn test_shuffle_is_repeatable() {
+        let mut a = hosts(20);
+        let mut b = hosts(20);
+        shuffle_hosts(&mut a, 42);
+        shuffle_hosts(&mut b, 42);
+        assert_eq!(names(&a), names(&b));
+    }
+
+    #[test]
+    fn test_shuffle_is_permutation() {
+        let mut shuffled = hosts(20);
+        shuffle_hosts(&mut shuffled, 7);
+        let mut sorted = names(&shuffled);
+        sorted.sort();
+        let mut expected = names(&hosts(20));
+        expected.sort();
+        assert_eq!(sorted, expected);
+        assert_ne!(names(&shuffled), names(&hosts(20)));
+    }
+
+    #[test]
+    fn test_shuffle_seeds_differ() {
+        let mut a = hosts(20);
+        let mut b = hosts(20);
+        shuffle_hosts(&mut a, 1);
+        shuffle_hosts(&mut b, 2);
+        assert_ne!(names(&a), names(&b));
+    }
+
+    #[test]
+    fn test_plan_hides_vault_values() {
+        let password = String::from("plan-test-password");
+        set_vault_password(&password);
+        let plaintext = String::from("db_password: plan-vault-secret-8841\nnested:\n  - key: plan-vault-secret-8841/x\n");
+        let path = env::temp_dir().join(format!("jetp-plan-{}.yml", std::process::id()));
+        std::fs::write(&path, encrypt(&plaintext, &password).unwrap()).unwrap();
+        let mut vars = load_vars_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        vars.insert(serde_yaml::Value::from("plain"), serde_yaml::Value::from("visible"));
+        let plan = serde_json::to_string(&plan_host_variables(&vars).unwrap()).unwrap();
+        assert!(! plan.contains("plan-vault-secret-8841"));
+        assert!(plan.contains("visible"));
+    }
+
+    #[test]
+    fn test_shuffle_small_lists() {
+        let mut empty = hosts(0);
+        shuffle_hosts(&mut empty, 3);
+        assert!(empty.is_empty());
+        let mut one = hosts(1);
+        shuffle_hosts(&mut one, 3);
+        assert_eq!(names(&one), vec![String::from("host-0")]);
+    }
+}
@@ -20,16 +20,26 @@ use std::sync::Arc;
 use crate::tasks::*;
 use std::sync::RwLock;
 use crate::inventory::hosts::Host;
-use inline_colorization::{color_red,color_blue,color_green,color_cyan,color_reset,color_yellow};
+use crate::util::terminal::{Palette,stdout_is_terminal,ask};
+use crate::util::vault::{redact,redact_json};
+use crate::cli::parser::CliParser;
+use std::sync::Mutex;
 use crate::connection::command::CommandResult;
 use crate::playbooks::traversal::HandlerMode;
+use crate::playbooks::callback::Callback;
 use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::fs::File;
+use std::path::PathBuf;
+use std::collections::HashMap;
+use std::os::unix::fs::OpenOptionsExt;
 use serde_json::json;
 use guid_create::GUID;
 use chrono::prelude::*;
 use std::env;
+use once_cell::sync::Lazy;
+
+static PROMPT_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
 // visitor contains various functions that are called from all over the program
 // to send feedback to the user and logs
@@ -40,11 +50,57 @@ pub enum CheckMode {
     No
 }
 
+// text output is meant for humans, json output writes one JSON object per line (NDJSON)
+// to stdout for each event, which is easier for CI systems to consume
+
+#[derive(PartialEq,Copy,Clone,Debug)]
+pub enum OutputFormat {
+    Text,
+    Json
+}
+
+// with text output the summary tables are drawn as markdown by default, which not every log viewer can show,
+// so they can also be a plain aligned table or CSV
+
+#[derive(PartialEq,Copy,Clone,Debug)]
+pub enum SummaryFormat {
+    Markdown,
+    Plain,
+    Csv
+}
+
+// an open --host-log-dir file, and the command result written to it last
+
+struct HostLog {
+    file: File,
+    last: Option<Arc<Option<CommandResult>>>
+}
+
+// with --progress the per-host lines for a task are replaced by a single redrawn line
+
+#[derive(Default)]
+struct ProgressCounts {
+    total: usize,
+    done: usize,
+    failed: usize
+}
+
 pub struct PlaybookVisitor {
     pub check_mode: CheckMode,
+    pub output_format: OutputFormat,
+    pub summary_format: SummaryFormat,
+    pub palette: Palette,
+    pub progress: bool,
+    progress_counts: Mutex<ProgressCounts>,
     pub logfile: Option<Arc<RwLock<File>>>,
+    pub metrics_out: Option<String>,
+    playbook_names: String,
+    host_log_dir: Option<PathBuf>,
+    host_logs: Mutex<HashMap<String, Arc<Mutex<HostLog>>>>,
+    retry_file: Option<PathBuf>,
     pub run_id: String,
-    pub utc_start: DateTime<Utc>
+    pub utc_start: DateTime<Utc>,
+    callbacks: Vec<Arc<dyn Callback>>
 }
 
 pub struct LogData {
@@ -57,6 +113,7 @@ pub struct LogData {
     pub cmd: Option<String>,
     pub cmd_rc: Option<i32>,
     pub cmd_out: Option<String>,
+    pub cmd_err: Option<String>,
     pub task_status: Option<String>,
     pub host: Option<String>,
     pub summary: Option<serde_json::map::Map<String,serde_json::Value>>
@@ -64,16 +121,20 @@ pub struct LogData {
 
 impl PlaybookVisitor {
 
-    pub fn new(check_mode: CheckMode) -> Self {
+    pub fn new(check_mode: CheckMode, parser: &CliParser) -> Self {
 
-        let logpath : String = match env::var("JET_LOG") {
-            Ok(x) => {
-                x
-            },
-            Err(_) => String::from("/var/log/jetp/jetp.log")
+        // --log-file wins over $JET_LOG, which wins over the default location
+
+        let logpath : String = match &parser.log_file {
+            Some(x) => x.clone(),
+            None => match env::var("JET_LOG") {
+                Ok(x) => {
+                    x
+                },
+                Err(_) => String::from("/var/log/jetp/jetp.log")
+            }
         };
 
-        // TODO: make logfile location configurable by environment variable
         let logfile : Option<Arc<RwLock<File>>> = match OpenOptions::new().write(true).create(true).append(true).open(logpath) {
             Ok(x) => Some(Arc::new(RwLock::new(x))),
             Err(_) => None
@@ -81,13 +142,34 @@ impl PlaybookVisitor {
 
         let s = Self {
             check_mode: check_mode,
+            output_format: parser.output_format,
+            summary_format: parser.summary_format,
+            palette: Palette::new(),
+            // progress bars make no sense when output is redirected, so fall back to lines
+            progress: parser.progress && parser.output_format == OutputFormat::Text && stdout_is_terminal(),
+            progress_counts: Mutex::new(ProgressCounts::default()),
             logfile: logfile,
+            metrics_out: parser.metrics_out.clone(),
+            host_log_dir: parser.host_log_dir.as_ref().map(|x| PathBuf::from(x)),
+            host_logs: Mutex::new(HashMap::new()),
+            retry_file: match &parser.retry_file {
+                Some(x) => Some(PathBuf::from(x)),
+                None => parser.playbook_paths.read().unwrap().first().map(|x| x.with_extension("retry"))
+            },
+            playbook_names: parser.playbook_paths.read().unwrap().iter().map(|x| x.file_name().unwrap_or_default().to_string_lossy().to_string()).collect::<Vec<String>>().join(":"),
             utc_start: Utc::now(),
-            run_id: GUID::rand().to_string()
+            run_id: GUID::rand().to_string(),
+            callbacks: Vec::new()
         };
         s
     }
 
+    // the console output here is always on, anything registered here gets the same events as typed values
+
+    pub fn add_callback(&mut self, callback: Arc<dyn Callback>) {
+        self.callbacks.push(callback);
+    }
+
     pub fn log_entry(&self, event: &String, context: Arc<RwLock<PlaybookContext>>) -> LogData {
         let ctx = context.read().unwrap();
         LogData {
@@ -109,6 +191,7 @@ impl PlaybookVisitor {
             cmd: None,
             cmd_rc: None,
             cmd_out: None,
+            cmd_err: None,
             task_status: None,
             host: None,
             summary: None
@@ -126,6 +209,7 @@ impl PlaybookVisitor {
         let mut obj =  serde_json::map::Map::new();
         obj.insert(String::from("event"), json!(log.event.clone()));
         obj.insert(String::from("run"), json!(self.run_id));
+        obj.insert(String::from("timestamp"), json!(now.to_rfc3339()));
         obj.insert(String::from("now"), json!(now.to_rfc2822()));
         obj.insert(String::from("start"), json!(self.utc_start.to_rfc2822()));
         let elapsed = now - self.utc_start;
@@ -146,7 +230,7 @@ impl PlaybookVisitor {
 
 
    
-        match serde_json::to_string(&obj) {
+        match serde_json::to_string(&redact_json(serde_json::Value::Object(obj))) {
             Ok(json_str) => {
                 let mut f = self.logfile.as_ref().unwrap().write().unwrap();
                 match writeln!(f, "{}",  json_str) {
@@ -159,33 +243,174 @@ impl PlaybookVisitor {
 
     }
 
+    // successful tasks that ran commands (shell, etc) also keep their output in the log
+
+    fn add_command_result_to_log(&self, context: &Arc<RwLock<PlaybookContext>>, log_entry: &mut LogData, task_response: &Arc<TaskResponse>) {
+        if task_response.command_result.is_some() {
+            let cmd_result = task_response.command_result.as_ref().as_ref().unwrap();
+            log_entry.cmd     = Some(self.scrub(context, &cmd_result.cmd));
+            log_entry.cmd_out = Some(self.scrub(context, &cmd_result.out));
+            log_entry.cmd_err = Some(self.scrub(context, &cmd_result.err));
+            log_entry.cmd_rc  = Some(cmd_result.rc.clone());
+        }
+    }
+
+    // with/no_log tasks do not show commands or their output at any verbosity, this is applied
+    // before anything is printed, logged or emitted as JSON
+
+    fn scrub(&self, context: &Arc<RwLock<PlaybookContext>>, msg: &String) -> String {
+        if context.read().unwrap().no_log {
+            return String::from("(hidden by no_log)");
+        }
+        return redact(msg);
+    }
+
     pub fn is_check_mode(&self) -> bool { 
         return self.check_mode == CheckMode::Yes; 
     }
 
-    pub fn banner(&self) {
+    pub fn is_json(&self) -> bool {
+        return self.output_format == OutputFormat::Json;
+    }
+
+    // in json mode every event is written to stdout as a single line, the event name
+    // mirrors the names used in the logfile so the two are easy to correlate
+
+    pub fn emit_json(&self, event: &str, data: serde_json::map::Map<String,serde_json::Value>) {
+        let mut obj = serde_json::map::Map::new();
+        obj.insert(String::from("event"), json!(event));
+        obj.insert(String::from("run"), json!(self.run_id));
+        for (k,v) in data.into_iter() {
+            obj.insert(k, redact_json(v));
+        }
+        match serde_json::to_string(&obj) {
+            Ok(json_str) => { println!("{}", json_str); },
+            Err(_y) => {}
+        }
+    }
+
+    // verbosity below zero (-q) hides everything except failures and the final summary
+
+    pub fn is_quiet(&self, context: &Arc<RwLock<PlaybookContext>>) -> bool {
+        return context.read().unwrap().verbosity < 0;
+    }
+
+    fn progress_start(&self, total: usize) {
+        let mut counts = self.progress_counts.lock().unwrap();
+        *counts = ProgressCounts { total: total, done: 0, failed: 0 };
+        self.progress_draw(&counts);
+    }
+
+    fn progress_tick(&self, failed: bool) {
+        let mut counts = self.progress_counts.lock().unwrap();
+        if counts.total == 0 {
+            return;
+        }
+        counts.done = counts.done + 1;
+        if failed {
+            counts.failed = counts.failed + 1;
+        }
+        self.progress_draw(&counts);
+    }
+
+    fn progress_draw(&self, counts: &ProgressCounts) {
+        let width : usize = 40;
+        let filled = match counts.total {
+            0 => width,
+            _ => std::cmp::min(width, counts.done * width / counts.total)
+        };
+        let fail_color = match counts.failed { 0 => "", _ => self.palette.red };
+        print!("\r[{}{}] {}/{} hosts, {}{} failed{}", "#".repeat(filled), ".".repeat(width - filled),
+            counts.done, counts.total, fail_color, counts.failed, self.palette.reset);
+        let _ = std::io::stdout().flush();
+    }
+
+    fn progress_redraw(&self) {
+        let counts = self.progress_counts.lock().unwrap();
+        if self.progress && counts.total > 0 {
+            self.progress_draw(&counts);
+        }
+    }
+
+    // erases the progress line so other output can be printed over it
+
+    fn progress_clear(&self) {
+        if self.progress && self.progress_counts.lock().unwrap().total > 0 {
+            print!("\r\x1b[K");
+        }
+    }
+
+    fn progress_end(&self) {
+        if ! self.progress {
+            return;
+        }
+        let mut counts = self.progress_counts.lock().unwrap();
+        if counts.total > 0 {
+            println!("");
+        }
+        *counts = ProgressCounts::default();
+    }
+
+    pub fn banner(&self, context: &Arc<RwLock<PlaybookContext>>) {
+        if self.is_json() || self.is_quiet(context) {
+            return;
+        }
         println!("----------------------------------------------------------");
     }
 
     // used by the echo module
+    // asks the user a question on behalf of a host, see the pause module.  Hosts run in parallel, so
+    // questions are asked one at a time to keep them from printing over each other.
+
+    pub fn ask_host(&self, host: &Arc<RwLock<Host>>, question: &String) -> Option<String> {
+        let _guard = PROMPT_LOCK.lock().unwrap();
+        self.progress_clear();
+        let answer = ask(&format!("{}  ..... {} : {}{} ", self.palette.cyan, host.read().unwrap().name, question, self.palette.reset));
+        self.progress_redraw();
+        return answer;
+    }
+
     pub fn debug_host(&self, host: &Arc<RwLock<Host>>, message: &String) {
-        println!("{color_cyan}  ..... {} : {}{color_reset}", host.read().unwrap().name, message);
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("host"), json!(host.read().unwrap().name));
+            data.insert(String::from("msg"),  json!(message));
+            self.emit_json("DEBUG", data);
+            return;
+        }
+        self.progress_clear();
+        println!("{}  ..... {} : {}{}", self.palette.cyan, host.read().unwrap().name, redact(message), self.palette.reset);
+        self.progress_redraw();
     }
 
     pub fn on_playbook_start(&self, context: &Arc<RwLock<PlaybookContext>>) {
-        let ctx = context.read().unwrap();
-        let path = ctx.playbook_path.as_ref().unwrap();
-        self.banner();
-        println!("> playbook start: {}", path);
+        let path = context.read().unwrap().playbook_path.clone().unwrap();
+        for callback in self.callbacks.iter() { callback.on_playbook_start(&path); }
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("playbook"), json!(path));
+            self.emit_json("PLAYBOOK_START", data);
+        } else if ! self.is_quiet(context) {
+            self.banner(context);
+            println!("> playbook start: {}", path);
+        }
 
         let log_entry = self.log_entry(&String::from("PLAYBOOK_START"), context.clone());
         self.log(&log_entry);
     }
 
     pub fn on_play_start(&self, context: &Arc<RwLock<PlaybookContext>>) {
-        let play = &context.read().unwrap().play;
-        self.banner();
-        println!("> play: {}", play.as_ref().unwrap());
+        context.write().unwrap().start_play_timer();
+        let play = context.read().unwrap().play.clone();
+        for callback in self.callbacks.iter() { callback.on_play_start(play.as_ref().unwrap()); }
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("play"), json!(play.as_ref().unwrap()));
+            self.emit_json("PLAY_START", data);
+        } else if ! self.is_quiet(context) {
+            self.banner(context);
+            println!("> play: {}", play.as_ref().unwrap());
+        }
 
         let log_entry = self.log_entry(&String::from("PLAY_START"), context.clone());
         self.log(&log_entry);
@@ -193,6 +418,13 @@ impl PlaybookVisitor {
     }
 
     pub fn on_role_start(&self, context: &Arc<RwLock<PlaybookContext>>) {
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            if let Some(role) = &context.read().unwrap().role {
+                data.insert(String::from("role"), json!(role.name));
+            }
+            self.emit_json("ROLE_START", data);
+        }
         let log_entry = self.log_entry(&String::from("ROLE_START"), context.clone());
         self.log(&log_entry);
     }
@@ -202,200 +434,558 @@ impl PlaybookVisitor {
 
     pub fn on_play_stop(&self, context: &Arc<RwLock<PlaybookContext>>, failed: bool) {
         // failed occurs if *ALL* hosts in a play have failed
-        let ctx = context.read().unwrap();
-        let play_name = ctx.get_play_name();
+        context.write().unwrap().stop_play_timer();
+        self.progress_end();
+        let play_name = context.read().unwrap().get_play_name();
+        let handlers = context.read().unwrap().get_handler_summary();
+        for callback in self.callbacks.iter() { callback.on_play_stop(&play_name, failed); }
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("play"),   json!(play_name));
+            data.insert(String::from("failed"), json!(failed));
+            let handler_data : Vec<serde_json::Value> = handlers.iter().map(|(handler, hosts, notified_by)| {
+                json!({ "handler": handler, "hosts": hosts, "notified_by": notified_by })
+            }).collect();
+            data.insert(String::from("handlers"), json!(handler_data));
+            self.emit_json("PLAY_STOP", data);
+            return;
+        }
         if ! failed {
-            self.banner();
+            if self.is_quiet(context) {
+                return;
+            }
+            self.show_handler_summary(context, &handlers);
+            self.banner(context);
             println!("> play complete: {}", play_name);
         } else {
-            self.banner();
-            println!("{color_red}> play failed: {}{color_reset}", play_name);
+            self.show_handler_summary(context, &handlers);
+            println!("----------------------------------------------------------");
+            println!("{}> play failed: {}{}", self.palette.red, play_name, self.palette.reset);
 
         }
     }
 
+    // lists the handlers that ran in a play, so it is easy to see a config change restarted what it should have.
+    // in check mode these are the handlers a real run would be expected to trigger.
+
+    fn show_handler_summary(&self, context: &Arc<RwLock<PlaybookContext>>, handlers: &Vec<(String, usize, Vec<String>)>) {
+        if handlers.is_empty() {
+            return;
+        }
+        self.banner(context);
+        match self.is_check_mode() {
+            true  => println!("> handlers that would run:"),
+            false => println!("> handlers run:")
+        };
+        for (handler, hosts, notified_by) in handlers.iter() {
+            let plural = match hosts { 1 => "", _ => "s" };
+            println!("  {} on {} host{} (notified by: {})", handler, hosts, plural, notified_by.join(", "));
+        }
+    }
+
     pub fn on_exit(&self, context: &Arc<RwLock<PlaybookContext>>) {
-        println!("----------------------------------------------------------");
-        println!("");
-        self.show_playbook_summary(context);
+        self.progress_end();
+        for callback in self.callbacks.iter() { callback.on_exit(); }
+        if ! self.is_json() && ! self.is_quiet(context) {
+            println!("----------------------------------------------------------");
+            println!("");
+        }
+        let summary = self.show_playbook_summary(context);
+        if context.read().unwrap().show_timing {
+            self.show_timing_summary(context);
+        }
+        if ! self.callbacks.is_empty() {
+            let mut data = summary.clone();
+            data.insert(String::from("failed_host_names"), json!(context.read().unwrap().get_failed_host_names()));
+            for callback in self.callbacks.iter() { callback.on_summary(&data); }
+        }
+        self.write_retry_file(context);
+        if let Some(metrics_path) = &self.metrics_out {
+            if let Err(y) = self.write_metrics(metrics_path, &summary) {
+                println!("{}! {}{}", self.palette.red, y, self.palette.reset);
+            }
+        }
+    }
+
+    // the failed hosts are listed one per line so that '--limit @file' can run again on just those.  A run
+    // without failures removes the file left by an earlier one, so a retry never targets hosts that have since succeeded.
+
+    fn write_retry_file(&self, context: &Arc<RwLock<PlaybookContext>>) {
+        let path = match &self.retry_file {
+            Some(x) => x,
+            None => { return; }
+        };
+        let failed = context.read().unwrap().get_failed_host_names();
+        if failed.is_empty() {
+            if path.exists() {
+                let _ = std::fs::remove_file(path);
+            }
+            return;
+        }
+        match std::fs::write(path, failed.join("\n") + "\n") {
+            Ok(_) => {
+                if ! self.is_json() {
+                    println!("to retry the failed hosts, use: --limit @{}\n", path.display());
+                }
+            },
+            Err(e) => { println!("{}! cannot write {}: {}{}", self.palette.red, path.display(), e, self.palette.reset); }
+        }
     }
 
-    pub fn on_task_start(&self, context: &Arc<RwLock<PlaybookContext>>, is_handler: HandlerMode) {
-        let context2 = context.read().unwrap();
-        let task = context2.task.as_ref().unwrap();
-        let role = &context2.role;
+    // --metrics-out writes the summary counts in the Prometheus text format, for the node exporter's textfile
+    // collector.  The file is written next to its final name and renamed, so the collector never reads half of it.
+
+    fn write_metrics(&self, metrics_path: &String, summary: &serde_json::map::Map<String,serde_json::Value>) -> Result<(), String> {
+        let playbook = self.playbook_names.replace("\\", "\\\\").replace("\"", "\\\"").replace("\n", "\\n");
+        let count = |key: &str| summary.get(key).and_then(|x| x.as_u64()).unwrap_or(0);
+        let mut out = String::new();
+        for (metric, key, help) in [
+            ("jetp_roles",      "role_ct",    "roles run"),
+            ("jetp_tasks",      "task_ct",    "tasks run"),
+            ("jetp_hosts_seen", "seen_hosts", "hosts the run was attempted on")] {
+            out.push_str(&format!("# HELP {metric} {help}\n# TYPE {metric} gauge\n{metric}{{playbook=\"{playbook}\"}} {}\n", count(key)));
+        }
+        // the same rows as the summary table, labelled as they are there
+        let results = [("matched", "matched"), ("created", "created"), ("modified", "modified"), ("removed", "removed"), ("executed", "executed"),
+            ("passive", "passive"), ("skipped", "skipped"), ("unchanged", "unchanged"), ("changed", "adjusted"), ("failed", "failed"), ("retries", "retry")];
+        for (metric, suffix, help) in [
+            ("jetp_items", "ct",    "task results by outcome"),
+            ("jetp_hosts", "hosts", "hosts with at least one task result of each outcome")] {
+            out.push_str(&format!("# HELP {metric} {help}\n# TYPE {metric} gauge\n"));
+            for (result, key) in results.iter() {
+                out.push_str(&format!("{metric}{{playbook=\"{playbook}\",result=\"{result}\"}} {}\n", count(&format!("{}_{}", key, suffix))));
+            }
+        }
+        out.push_str(&format!("# HELP jetp_last_run_timestamp_seconds when the run finished\n# TYPE jetp_last_run_timestamp_seconds gauge\n\
+            jetp_last_run_timestamp_seconds{{playbook=\"{playbook}\"}} {}\n", Utc::now().timestamp()));
+
+        let temp_path = format!("{}.tmp", metrics_path);
+        if let Err(e) = std::fs::write(&temp_path, out) {
+            return Err(format!("--metrics-out: cannot write {}: {}", temp_path, e));
+        }
+        return match std::fs::rename(&temp_path, metrics_path) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("--metrics-out: cannot write {}: {}", metrics_path, e))
+        };
+    }
+
+    // tags are only shown with -v, to check what --tags and --skip-tags will match
+
+    pub fn on_task_start(&self, context: &Arc<RwLock<PlaybookContext>>, is_handler: HandlerMode, tags: &Vec<String>) {
+        let (task, role) = {
+            let context2 = context.read().unwrap();
+            (context2.task.clone().unwrap(), context2.role.clone())
+        };
 
         let what = match is_handler {
             HandlerMode::NormalTasks => String::from("task"),
             HandlerMode::Handlers    => String::from("handler")
         };
 
-        self.banner();
-        if role.is_none() {
-            println!("> begin {}: {}", what, task);
-        }
-        else {
-            println!("> ({}) begin {}: {}", role.as_ref().unwrap().name, what, task);
+        let timer_label = match &role {
+            Some(r) => format!("({}) {}", r.name, task),
+            None => task.clone()
+        };
+        context.write().unwrap().start_task_timer(&timer_label);
+        for callback in self.callbacks.iter() { callback.on_task_start(&task, role.as_ref().map(|r| &r.name), is_handler); }
+
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("task"), json!(task));
+            data.insert(String::from("kind"), json!(what));
+            if role.is_some() {
+                data.insert(String::from("role"), json!(role.as_ref().unwrap().name));
+            }
+            if ! tags.is_empty() {
+                data.insert(String::from("tags"), json!(tags));
+            }
+            self.emit_json("TASK_START", data);
+        } else if ! self.is_quiet(context) {
+            self.progress_end();
+            self.banner(context);
+            if role.is_none() {
+                println!("> begin {}: {}", what, task);
+            }
+            else {
+                println!("> ({}) begin {}: {}", role.as_ref().unwrap().name, what, task);
+            }
+            if ! tags.is_empty() && context.read().unwrap().verbosity > 0 {
+                println!("  tags: {}", tags.join(", "));
+            }
+            if self.progress {
+                let total = context.read().unwrap().get_remaining_hosts().len();
+                self.progress_start(total);
+            }
         }
 
         let log_entry = self.log_entry(&String::from("TASK_START"), Arc::clone(context));
         self.log(&log_entry);
     }
 
-    pub fn on_batch(&self, batch_num: usize, batch_count: usize, batch_size: usize) {
-        self.banner();
+    pub fn on_batch(&self, context: &Arc<RwLock<PlaybookContext>>, batch_num: usize, batch_count: usize, batch_size: usize) {
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("batch"),       json!(batch_num+1));
+            data.insert(String::from("batch_count"), json!(batch_count));
+            data.insert(String::from("hosts"),       json!(batch_size));
+            self.emit_json("BATCH", data);
+            return;
+        }
+        if self.is_quiet(context) {
+            return;
+        }
+        self.progress_end();
+        self.banner(context);
         println!("> batch {}/{}, {} hosts", batch_num+1, batch_count, batch_size);
     }
 
-    pub fn on_host_task_start(&self, _context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>) {
+    pub fn on_host_task_start(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>) {
         let host2 = host.read().unwrap();
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("host"), json!(host2.name));
+            self.emit_json("HOST_TASK_START", data);
+            return;
+        }
+        if self.is_quiet(context) || self.progress {
+            return;
+        }
         println!("… {} => running", host2.name);
     }
 
-    pub fn on_notify_handler(&self, host: &Arc<RwLock<Host>>, which_handler: &String) {
+    pub fn on_notify_handler(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>, which_handler: &String) {
         let host2 = host.read().unwrap();
-        println!("… {} => notified: {}", host2.name, which_handler);
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("host"),    json!(host2.name));
+            data.insert(String::from("handler"), json!(which_handler));
+            data.insert(String::from("check"),   json!(self.is_check_mode()));
+            self.emit_json("NOTIFY", data);
+            return;
+        }
+        if self.is_quiet(context) {
+            return;
+        }
+        self.progress_clear();
+        match self.is_check_mode() {
+            true  => println!("… {} => would notify: {}", host2.name, which_handler),
+            false => println!("… {} => notified: {}", host2.name, which_handler)
+        };
+        self.progress_redraw();
     }
 
-    pub fn on_host_delegate(&self, host: &Arc<RwLock<Host>>, delegated: &String) {
+    pub fn on_host_delegate(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>, delegated: &String) {
         let host2 = host.read().unwrap();
-        println!("{color_blue}✓ {} => delegating to: {}{color_reset}",  &host2.name, delegated.clone());
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("host"),        json!(host2.name));
+            data.insert(String::from("delegate_to"), json!(delegated));
+            self.emit_json("DELEGATE", data);
+            return;
+        }
+        if self.is_quiet(context) {
+            return;
+        }
+        self.progress_clear();
+        println!("{}✓ {} => delegating to: {}{}", self.palette.blue, &host2.name, delegated.clone(), self.palette.reset);
+        self.progress_redraw();
+    }
+
+    // supporting code for the json variants of on_host_task_ok and on_host_task_check_ok
+
+    fn emit_json_task_status(&self, event: &str, task: &Option<String>, task_response: &Arc<TaskResponse>, host_name: &String) {
+        let mut data = serde_json::map::Map::new();
+        data.insert(String::from("host"),    json!(host_name));
+        data.insert(String::from("task"),    json!(task));
+        data.insert(String::from("status"),  json!(format!("{:?}", task_response.status)));
+        let changes : Vec<String> = task_response.changes.iter().map(|x| { format!("{:?}", x) }).collect();
+        data.insert(String::from("changes"), json!(changes));
+        if task_response.status == TaskStatus::IsSkipped && task_response.msg.is_some() {
+            data.insert(String::from("reason"), json!(task_response.msg));
+        } else if task_response.msg.is_some() {
+            data.insert(String::from("msg"), json!(task_response.msg));
+        }
+        if task_response.diff.is_some() {
+            data.insert(String::from("diff"), json!(task_response.diff));
+        }
+        self.emit_json(event, data);
+    }
+
+    fn skip_reason(&self, task_response: &Arc<TaskResponse>) -> String {
+        return match &task_response.msg {
+            Some(reason) => format!(" ({})", reason),
+            None => String::from(" ")
+        };
+    }
+
+    fn msg_suffix(&self, task_response: &Arc<TaskResponse>) -> String {
+        return match &task_response.msg {
+            Some(msg) => format!(" ({})", redact(msg)),
+            None => String::new()
+        };
+    }
+
+    // tasks excluded by --tags never reach the hosts, so they are reported once per task, and only with -v
+
+    pub fn on_task_skipped(&self, context: &Arc<RwLock<PlaybookContext>>, task_name: &String, reason: &String) {
+        {
+            // the task never reaches the hosts, but still counts as skipped for each of them in the summary
+            let ctx = context.read().unwrap();
+            for (name, _host) in ctx.get_remaining_hosts().iter() {
+                ctx.increment_skipped_for_host(name);
+            }
+        }
+        for callback in self.callbacks.iter() { callback.on_task_skipped(task_name, reason); }
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("task"),   json!(task_name));
+            data.insert(String::from("reason"), json!(reason));
+            self.emit_json("TASK_SKIPPED", data);
+            return;
+        }
+        if context.read().unwrap().verbosity > 0 {
+            println!("{}> skipping task: {} ({}){}", self.palette.yellow, task_name, reason, self.palette.reset);
+        }
     }
 
     pub fn on_host_task_ok(&self, context: &Arc<RwLock<PlaybookContext>>, task_response: &Arc<TaskResponse>, host: &Arc<RwLock<Host>>) {
         let host2 = host.read().unwrap();
+        self.callback_task_ok(context, task_response, &host2.name);
+        let json = self.is_json();
         {
-            let mut context2 = context.write().unwrap();
+            // counting only needs a read lock, see PlaybookContext::counts, so hosts do not wait on each other here
+            let context2 = context.read().unwrap();
+            let detail = ! json && context2.verbosity >= 0;
+            let text = detail && ! self.progress;
             context2.increment_attempted_for_host(&host2.name);
+            context2.record_change(&host2.name, task_response);
             match &task_response.status {
                 TaskStatus::IsCreated  =>  {
-                    println!("{color_blue}✓ {} => created{color_reset}",  &host2.name);
+                    if text { println!("{}✓ {} => created{}{}", self.palette.blue, &host2.name, self.msg_suffix(task_response), self.palette.reset); }
                     context2.increment_created_for_host(&host2.name);
                 },
                 TaskStatus::IsRemoved  =>  {
-                    println!("{color_blue}✓ {} => removed{color_reset}",  &host2.name);
+                    if text { println!("{}✓ {} => removed{}", self.palette.blue, &host2.name, self.palette.reset); }
                     context2.increment_removed_for_host(&host2.name);
                 },
                 TaskStatus::IsModified =>  {
                     let changes2 : Vec<String> = task_response.changes.iter().map(|x| { format!("{:?}", x) }).collect();
                     let change_str = changes2.join(",");
-                    println!("{color_blue}✓ {} => modified ({}){color_reset}", &host2.name, change_str);
+                    if text { println!("{}✓ {} => modified ({}){}{}", self.palette.blue, &host2.name, change_str, self.msg_suffix(task_response), self.palette.reset); }
                     context2.increment_modified_for_host(&host2.name);
                 },
                 TaskStatus::IsExecuted =>  {
-                    println!("{color_blue}✓ {} => complete{color_reset}", &host2.name);
+                    if text { println!("{}✓ {} => complete{}", self.palette.blue, &host2.name, self.palette.reset); }
                     context2.increment_executed_for_host(&host2.name);
                 },
                 TaskStatus::IsPassive  =>  {
-                    // println!("{color_green}! host: {} => ok (no effect) {color_reset}", &host2.name);
+                    // println!("{}! host: {} => ok (no effect) {}", self.palette.green, &host2.name, self.palette.reset);
                     context2.increment_passive_for_host(&host2.name);
                 }
                 TaskStatus::IsMatched  =>  {
-                    println!("{color_green}✓ {} => matched {color_reset}", &host2.name);
+                    if text { println!("{}✓ {} => matched {}", self.palette.green, &host2.name, self.palette.reset); }
                     context2.increment_matched_for_host(&host2.name);
                 }
                 TaskStatus::IsSkipped  =>  {
-                    println!("{color_yellow}✓ {} => skipped {color_reset}", &host2.name);
+                    if text { println!("{}✓ {} => skipped{}{}", self.palette.yellow, &host2.name, self.skip_reason(task_response), self.palette.reset); }
                     context2.increment_skipped_for_host(&host2.name);
                 }
                 TaskStatus::Failed => {
-                    println!("{color_yellow}✓ {} => failed (ignored){color_reset}", &host2.name);
+                    if text { println!("{}✓ {} => failed (ignored){}", self.palette.yellow, &host2.name, self.palette.reset); }
                 }
                 _ => {
                     panic!("on host {}, invalid final task return status, FSM should have rejected: {:?}", host2.name, task_response); 
                 }
             }
+            if self.progress {
+                self.progress_tick(false);
+            }
+            if json {
+                self.emit_json_task_status("TASK_STATUS", &context2.task, task_response, &host2.name);
+            }
         }
 
         let mut log_entry = self.log_entry(&String::from("TASK_STATUS"), Arc::clone(context));
         log_entry.host = Some(host2.name.clone());
         log_entry.task_status = Some(format!("{:?}", &task_response.status));
+        self.add_command_result_to_log(context, &mut log_entry, task_response);
         self.log(&log_entry);
 
     }
 
+    fn callback_task_ok(&self, context: &Arc<RwLock<PlaybookContext>>, task_response: &Arc<TaskResponse>, host_name: &String) {
+        if self.callbacks.is_empty() {
+            return;
+        }
+        let task = context.read().unwrap().get_task_name();
+        for callback in self.callbacks.iter() { callback.on_host_task_ok(host_name, &task, task_response); }
+    }
+
     // the check mode version of on_host_task_ok - different possible states, slightly different output
 
     pub fn on_host_task_check_ok(&self, context: &Arc<RwLock<PlaybookContext>>, task_response: &Arc<TaskResponse>, host: &Arc<RwLock<Host>>) {
         let host2 = host.read().unwrap();
+        self.callback_task_ok(context, task_response, &host2.name);
+        let json = self.is_json();
         {
-            let mut context2 = context.write().unwrap();
+            // counting only needs a read lock, see PlaybookContext::counts, so hosts do not wait on each other here
+            let context2 = context.read().unwrap();
+            let detail = ! json && context2.verbosity >= 0;
+            let text = detail && ! self.progress;
             context2.increment_attempted_for_host(&host2.name);
+            context2.record_change(&host2.name, task_response);
             match &task_response.status {
                 TaskStatus::NeedsCreation  =>  {
-                    println!("{color_blue}✓ {} => would create{color_reset}",  &host2.name);
+                    if text { println!("{}✓ {} => would create{}", self.palette.blue, &host2.name, self.palette.reset); }
                     context2.increment_created_for_host(&host2.name);
                 },
                 TaskStatus::NeedsRemoval  =>  {
-                    println!("{color_blue}✓ {} => would remove{color_reset}",  &host2.name);
+                    if text { println!("{}✓ {} => would remove{}", self.palette.blue, &host2.name, self.palette.reset); }
                     context2.increment_removed_for_host(&host2.name);
                 },
                 TaskStatus::NeedsModification =>  {
                     let changes2 : Vec<String> = task_response.changes.iter().map(|x| { format!("{:?}", x) }).collect();
                     let change_str = changes2.join(",");
-                    println!("{color_blue}✓ {} => would modify ({}) {color_reset}", &host2.name, change_str);
+                    if text { println!("{}✓ {} => would modify ({}) {}", self.palette.blue, &host2.name, change_str, self.palette.reset); }
+                    if detail && task_response.diff.is_some() {
+                        self.progress_clear();
+                        self.show_diff(task_response.diff.as_ref().unwrap());
+                    }
                     context2.increment_modified_for_host(&host2.name);
                 },
                 TaskStatus::NeedsExecution =>  {
-                    println!("{color_blue}✓ {} => would run{color_reset}", &host2.name);
+                    if text { println!("{}✓ {} => would run{}", self.palette.blue, &host2.name, self.palette.reset); }
                     context2.increment_executed_for_host(&host2.name);
                 },
                 TaskStatus::IsPassive  =>  {
                     context2.increment_passive_for_host(&host2.name);
                 }
                 TaskStatus::IsMatched  =>  {
-                    println!("{color_green}✓ {} => matched {color_reset}", &host2.name);
+                    if text { println!("{}✓ {} => matched {}", self.palette.green, &host2.name, self.palette.reset); }
                     context2.increment_matched_for_host(&host2.name);
                 }
                 TaskStatus::IsSkipped  =>  {
-                    println!("{color_yellow}✓ {} => skipped {color_reset}", &host2.name);
+                    if text { println!("{}✓ {} => skipped{}{}", self.palette.yellow, &host2.name, self.skip_reason(task_response), self.palette.reset); }
                     context2.increment_skipped_for_host(&host2.name);
                 }
                 TaskStatus::Failed => {
-                    println!("{color_yellow}✓ {} => failed (ignored){color_reset}", &host2.name);
+                    if text { println!("{}✓ {} => failed (ignored){}", self.palette.yellow, &host2.name, self.palette.reset); }
                 }
                 _ => {
                     panic!("on host {}, invalid check-mode final task return status, FSM should have rejected: {:?}", host2.name, task_response); 
                 }
             }
+            if self.progress {
+                self.progress_tick(false);
+            }
+            if json {
+                self.emit_json_task_status("TASK_CHECK_STATUS", &context2.task, task_response, &host2.name);
+            }
         }
 
         let mut log_entry = self.log_entry(&String::from("TASK_CHECK_STATUS"), Arc::clone(context));
         log_entry.host = Some(host2.name.clone());
         log_entry.task_status = Some(format!("{:?}", &task_response.status));
+        self.add_command_result_to_log(context, &mut log_entry, task_response);
         self.log(&log_entry);
     }
 
-    pub fn on_host_task_retry(&self, _context: &Arc<RwLock<PlaybookContext>>,host: &Arc<RwLock<Host>>, retries: u64, delay: u64) {
+    // diffs are only collected with --diff, see Remote::get_content_diff
+
+    // printed all at once so diffs from hosts reporting at the same time do not interleave
+
+    fn show_diff(&self, diff: &String) {
+        let mut output = String::new();
+        for line in redact(diff).lines() {
+            if line.starts_with("+++") || line.starts_with("---") {
+                output.push_str(&format!("    {}\n", line));
+            } else if line.starts_with("+") {
+                output.push_str(&format!("    {}{}{}\n", self.palette.green, line, self.palette.reset));
+            } else if line.starts_with("-") {
+                output.push_str(&format!("    {}{}{}\n", self.palette.red, line, self.palette.reset));
+            } else if line.starts_with("@@") {
+                output.push_str(&format!("    {}{}{}\n", self.palette.cyan, line, self.palette.reset));
+            } else {
+                output.push_str(&format!("    {}\n", line));
+            }
+        }
+        print!("{}", output);
+    }
+
+    pub fn on_host_task_retry(&self, context: &Arc<RwLock<PlaybookContext>>,host: &Arc<RwLock<Host>>, retries: u64, delay: u64) {
         let host2 = host.read().unwrap();
-        println!("{color_blue}! {} => retrying ({} retries left) in {} seconds{color_reset}",host2.name,retries,delay);
+        context.read().unwrap().increment_retry_for_host(&host2.name);
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("host"),    json!(host2.name));
+            data.insert(String::from("retries"), json!(retries));
+            data.insert(String::from("delay"),   json!(delay));
+            self.emit_json("TASK_RETRY", data);
+            return;
+        }
+        if self.is_quiet(context) {
+            return;
+        }
+        self.progress_clear();
+        println!("{}! {} => retrying ({} retries left) in {} seconds{}", self.palette.blue, host2.name, retries, delay, self.palette.reset);
+        self.progress_redraw();
     }
 
     pub fn on_host_task_failed(&self, context: &Arc<RwLock<PlaybookContext>>, task_response: &Arc<TaskResponse>, host: &Arc<RwLock<Host>>) {
         let mut log_entry = self.log_entry(&String::from("TASK_FAILED"), Arc::clone(context));
         let host2 = host.read().unwrap();
+        if ! self.callbacks.is_empty() {
+            let task = context.read().unwrap().get_task_name();
+            for callback in self.callbacks.iter() { callback.on_host_task_failed(&host2.name, &task, task_response); }
+        }
+        self.progress_clear();
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("host"),   json!(host2.name));
+            data.insert(String::from("task"),   json!(log_entry.task));
+            data.insert(String::from("status"), json!(format!("{:?}", task_response.status)));
+            data.insert(String::from("msg"),    json!(task_response.msg.as_ref().map(|x| self.scrub(context, x))));
+            if task_response.command_result.is_some() {
+                let cmd_result = task_response.command_result.as_ref().as_ref().unwrap();
+                data.insert(String::from("cmd"),     json!(self.scrub(context, &cmd_result.cmd)));
+                data.insert(String::from("cmd_out"), json!(self.scrub(context, &cmd_result.out)));
+                data.insert(String::from("cmd_err"), json!(self.scrub(context, &cmd_result.err)));
+                data.insert(String::from("cmd_rc"),  json!(cmd_result.rc));
+            }
+            self.emit_json("TASK_FAILED", data);
+        }
         if task_response.msg.is_some() {
             let msg = &task_response.msg;
             if task_response.command_result.is_some() {
                 {
                     let cmd_result = task_response.command_result.as_ref().as_ref().unwrap();
+                    let cmd = self.scrub(context, &cmd_result.cmd);
+                    let out = self.scrub(context, &cmd_result.out);
+                    let err = self.scrub(context, &cmd_result.err);
                     let _lock = context.write().unwrap();
-                    println!("{color_red}! {} => failed", host2.name);
-                    println!("    cmd: {}", cmd_result.cmd);
-                    println!("    out: {}", cmd_result.out);
-                    println!("    rc: {}{color_reset}", cmd_result.rc);
-                    log_entry.cmd     = Some(cmd_result.cmd.clone());
-                    log_entry.cmd_out = Some(cmd_result.out.clone());
+                    if ! self.is_json() {
+                        println!("{}! {} => failed", self.palette.red, host2.name);
+                        println!("    cmd: {}", cmd);
+                        println!("    out: {}", out);
+                        if ! err.is_empty() {
+                            println!("    err: {}", err);
+                        }
+                        println!("    rc: {}{}", cmd_result.rc, self.palette.reset);
+                    }
+                    log_entry.cmd     = Some(cmd);
+                    log_entry.cmd_out = Some(out);
+                    log_entry.cmd_err = Some(err);
                     log_entry.cmd_rc  = Some(cmd_result.rc.clone());
                 }
-            } else {
-                println!("{color_red}! error: {}: {}{color_reset}", host2.name, msg.as_ref().unwrap());
+            } else if ! self.is_json() {
+                println!("{}! error: {}: {}{}", self.palette.red, host2.name, self.scrub(context, msg.as_ref().unwrap()), self.palette.reset);
             }
-        } else {
-            println!("{color_red}! host failed: {}, {color_reset}", host2.name);
+        } else if ! self.is_json() {
+            println!("{}! host failed: {}, {}", self.palette.red, host2.name, self.palette.reset);
         }
 
-        context.write().unwrap().increment_failed_for_host(&host2.name);
+        context.read().unwrap().increment_failed_for_host(&host2.name);
+        self.progress_tick(true);
         log_entry.host = Some(host2.name.clone());
         log_entry.task_status = Some(format!("{:?}", &task_response.status));
         self.log(&log_entry);
@@ -403,17 +993,75 @@ impl PlaybookVisitor {
 
     pub fn on_host_connect_failed(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>) {
         let host2 = host.read().unwrap();
-        context.write().unwrap().increment_failed_for_host(&host2.name);
-        println!("{color_red}! connection failed to host: {}{color_reset}", host2.name);
+        context.read().unwrap().increment_failed_for_host(&host2.name);
+        for callback in self.callbacks.iter() { callback.on_host_connect_failed(&host2.name); }
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("host"), json!(host2.name));
+            self.emit_json("HOST_CONNECT_FAILED", data);
+        } else {
+            self.progress_clear();
+            println!("{}! connection failed to host: {}{}", self.palette.red, host2.name, self.palette.reset);
+            self.progress_tick(true);
+        }
         let mut log_entry = self.log_entry(&String::from("HOST_CONNECT_FAILED"), Arc::clone(context));
         log_entry.host = Some(host2.name.clone());
         self.log(&log_entry);
     }
 
+    // only used by 'jetp check-connections' and --preflight, playbooks are quiet about connections that work
+
+    pub fn on_host_connect_ok(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>) {
+        let host2 = host.read().unwrap();
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            data.insert(String::from("host"), json!(host2.name));
+            self.emit_json("HOST_CONNECT_OK", data);
+        } else if context.read().unwrap().verbosity > 0 {
+            println!("{}✓ connected to host: {}{}", self.palette.green, host2.name, self.palette.reset);
+        }
+        let mut log_entry = self.log_entry(&String::from("HOST_CONNECT_OK"), Arc::clone(context));
+        log_entry.host = Some(host2.name.clone());
+        self.log(&log_entry);
+    }
+
+    // the report at the end of --preflight, failures are (host, reason) sorted by host
+
+    pub fn on_preflight(&self, context: &Arc<RwLock<PlaybookContext>>, host_count: usize, failures: &Vec<(String,String)>) {
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            let failed : serde_json::Map<String, serde_json::Value> = failures.iter().map(|(k,v)| (k.clone(), json!(v))).collect();
+            data.insert(String::from("hosts"),  json!(host_count));
+            data.insert(String::from("failed"), json!(failed));
+            self.emit_json("PREFLIGHT", data);
+            return;
+        }
+        if failures.is_empty() {
+            if ! self.is_quiet(context) {
+                self.progress_end();
+                self.banner(context);
+                println!("{}> preflight: all {} hosts reachable{}", self.palette.green, host_count, self.palette.reset);
+            }
+            return;
+        }
+        self.progress_end();
+        self.banner(context);
+        println!("{}> preflight: {} of {} hosts could not be reached:", self.palette.red, failures.len(), host_count);
+        for (host_name, reason) in failures.iter() {
+            println!("    {}: {}", host_name, reason.trim());
+        }
+        print!("{}", self.palette.reset);
+    }
+
     pub fn get_exit_status(&self, context: &Arc<RwLock<PlaybookContext>>) -> i32 {
-        let failed_hosts = context.read().unwrap().get_hosts_failed_count();
+        let ctx = context.read().unwrap();
+        let failed_hosts = ctx.get_hosts_failed_count();
         return match failed_hosts {
-            0 => 0,
+            0 => match (ctx.changed_exit_code, ctx.get_hosts_adjusted_count()) {
+                // by request, signal that changes were made (or would be made in check mode)
+                (Some(code), adjusted) if adjusted > 0 => code,
+                _ => 0
+            },
             _ => 1
         };
     }
@@ -421,42 +1069,182 @@ impl PlaybookVisitor {
     pub fn on_before_transfer(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>, path: &String) {
         let host2 = host.read().unwrap();
         if context.read().unwrap().verbosity > 0 {
-            println!("{color_blue}! {} => transferring to: {}", host2.name, &path.clone());
+            if self.is_json() {
+                let mut data = serde_json::map::Map::new();
+                data.insert(String::from("host"), json!(host2.name));
+                data.insert(String::from("path"), json!(path));
+                self.emit_json("TRANSFER", data);
+                return;
+            }
+            println!("{}! {} => transferring to: {}", self.palette.blue, host2.name, &path.clone());
+        }
+    }
+
+    pub fn on_before_fetch(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>, path: &String) {
+        let host2 = host.read().unwrap();
+        if context.read().unwrap().verbosity > 0 {
+            if self.is_json() {
+                let mut data = serde_json::map::Map::new();
+                data.insert(String::from("host"), json!(host2.name));
+                data.insert(String::from("path"), json!(path));
+                self.emit_json("FETCH", data);
+                return;
+            }
+            println!("{}! {} => fetching from: {}", self.palette.blue, host2.name, &path.clone());
         }
     }
 
     pub fn on_command_run(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>, cmd: &String) {
         let host2 = host.read().unwrap();
         if context.read().unwrap().verbosity > 0 {
-            println!("{color_blue}! {} => exec: {}", host2.name, &cmd.clone());
+            if self.is_json() {
+                let mut data = serde_json::map::Map::new();
+                data.insert(String::from("host"), json!(host2.name));
+                data.insert(String::from("cmd"),  json!(self.scrub(context, cmd)));
+                self.emit_json("COMMAND_RUN", data);
+                return;
+            }
+            println!("{}! {} => exec: {}", self.palette.blue, host2.name, self.scrub(context, cmd));
+        }
+    }
+
+    // supporting code for the json variants of on_command_ok and on_command_failed
+
+    fn emit_json_command_result(&self, context: &Arc<RwLock<PlaybookContext>>, event: &str, host_name: &String, cmd_result: &CommandResult) {
+        let mut data = serde_json::map::Map::new();
+        data.insert(String::from("host"),    json!(host_name));
+        data.insert(String::from("cmd"),     json!(self.scrub(context, &cmd_result.cmd)));
+        data.insert(String::from("cmd_out"), json!(self.scrub(context, &cmd_result.out)));
+        data.insert(String::from("cmd_err"), json!(self.scrub(context, &cmd_result.err)));
+        data.insert(String::from("cmd_rc"),  json!(cmd_result.rc));
+        self.emit_json(event, data);
+    }
+
+    // with --host-log-dir every command and its complete output is appended to a file per host, whatever the verbosity.
+    // Each host has its own file handle, and a record is written with a single write while holding it, so hosts running
+    // in parallel never interleave within a file.  A module that fails a command which ran reports the same result
+    // a second time, that one is not written again.
+
+    fn write_host_log(&self, context: &Arc<RwLock<PlaybookContext>>, host_name: &String, result: &Arc<Option<CommandResult>>) {
+        let dir = match &self.host_log_dir {
+            Some(x) => x,
+            None => { return; }
+        };
+        let file = {
+            let mut logs = self.host_logs.lock().unwrap();
+            match logs.get(host_name) {
+                Some(x) => Arc::clone(x),
+                None => {
+                    let mut path = dir.clone();
+                    path.push(format!("{}.log", host_name));
+                    match OpenOptions::new().write(true).create(true).append(true).mode(0o600).open(&path) {
+                        Ok(x) => {
+                            let file = Arc::new(Mutex::new(HostLog { file: x, last: None }));
+                            logs.insert(host_name.clone(), Arc::clone(&file));
+                            file
+                        },
+                        Err(e) => {
+                            println!("{}! cannot open {}: {}{}", self.palette.red, path.display(), e, self.palette.reset);
+                            return;
+                        }
+                    }
+                }
+            }
+        };
+        let (play, task) = {
+            let ctx = context.read().unwrap();
+            (ctx.play.clone().unwrap_or_default(), ctx.task.clone().unwrap_or_default())
+        };
+        let cmd_result = result.as_ref().as_ref().expect("missing command result");
+        let record = format!("=== {} play: {} task: {}\ncmd: {}\nrc: {}\n--- out\n{}\n--- err\n{}\n\n",
+            Utc::now().to_rfc3339(), play, task, self.scrub(context, &cmd_result.cmd), cmd_result.rc,
+            self.scrub(context, &cmd_result.out), self.scrub(context, &cmd_result.err));
+        let mut log = file.lock().unwrap();
+        if log.last.as_ref().is_some_and(|x| Arc::ptr_eq(x, result)) {
+            return;
+        }
+        log.last = Some(Arc::clone(result));
+        let written = log.file.write_all(record.as_bytes());
+        if let Err(e) = written {
+            println!("{}! cannot write the log for {}: {}{}", self.palette.red, host_name, e, self.palette.reset);
         }
     }
 
     pub fn on_command_ok(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>, result: &Arc<Option<CommandResult>>,) {
         let host2 = host.read().unwrap();
         let cmd_result = result.as_ref().as_ref().expect("missing command result");
+        self.write_host_log(context, &host2.name, result);
         if context.read().unwrap().verbosity > 2 {
+            if self.is_json() {
+                self.emit_json_command_result(context, "COMMAND_OK", &host2.name, cmd_result);
+                return;
+            }
+            let cmd = self.scrub(context, &cmd_result.cmd);
+            let out = self.scrub(context, &cmd_result.out);
             let _ctx2 = context.write().unwrap(); // lock for multi-line output
-            println!("{color_blue}! {} ... command ok", host2.name);
-            println!("    cmd: {}", cmd_result.cmd);           
-            println!("    out: {}", cmd_result.out.clone());
-            println!("    rc: {}{color_reset}", cmd_result.rc);
+            println!("{}! {} ... command ok", self.palette.blue, host2.name);
+            println!("    cmd: {}", cmd);
+            println!("    out: {}", out);
+            println!("    rc: {}{}", cmd_result.rc, self.palette.reset);
         }
     }
 
     pub fn on_command_failed(&self, context: &Arc<RwLock<PlaybookContext>>, host: &Arc<RwLock<Host>>, result: &Arc<Option<CommandResult>>,) {
         let host2 = host.read().expect("context read");
         let cmd_result = result.as_ref().as_ref().expect("missing command result");
+        self.write_host_log(context, &host2.name, result);
         if context.read().unwrap().verbosity > 2 {
+            if self.is_json() {
+                self.emit_json_command_result(context, "COMMAND_FAILED", &host2.name, cmd_result);
+                return;
+            }
+            let cmd = self.scrub(context, &cmd_result.cmd);
+            let out = self.scrub(context, &cmd_result.out);
+            let err = self.scrub(context, &cmd_result.err);
             let _ctx2 = context.write().unwrap(); // lock for multi-line output
-            println!("{color_red}! {} ... command failed", host2.name);
-            println!("    cmd: {}", cmd_result.cmd);
-            println!("    out: {}", cmd_result.out.clone());
-            println!("    rc: {}{color_reset}", cmd_result.rc);
+            println!("{}! {} ... command failed", self.palette.red, host2.name);
+            println!("    cmd: {}", cmd);
+            println!("    out: {}", out);
+            println!("    err: {}", err);
+            println!("    rc: {}{}", cmd_result.rc, self.palette.reset);
         }
     }
 
-    pub fn show_playbook_summary(&self, context: &Arc<RwLock<PlaybookContext>>) {
+    pub fn show_timing_summary(&self, context: &Arc<RwLock<PlaybookContext>>) {
+
+        let ctx = context.read().unwrap();
+        let plays = ctx.get_play_durations();
+        let slowest = ctx.get_slowest_tasks(5);
+
+        if self.is_json() {
+            let mut data = serde_json::map::Map::new();
+            let play_list : Vec<serde_json::Value> = plays.iter().map(|(name, d)| {
+                json!({ "play": name, "seconds": d.as_secs_f64() })
+            }).collect();
+            let task_list : Vec<serde_json::Value> = slowest.iter().map(|(name, d)| {
+                json!({ "task": name, "seconds": d.as_secs_f64() })
+            }).collect();
+            data.insert(String::from("plays"), json!(play_list));
+            data.insert(String::from("slowest_tasks"), json!(task_list));
+            self.emit_json("TIMING", data);
+            return;
+        }
+
+        let mut timing_table = String::from("|:-|:-|\n| Timing | Seconds\n| --- | ---\n");
+        for (name, duration) in plays.iter() {
+            timing_table.push_str(&format!("| play: {} | {:.2}\n", name, duration.as_secs_f64()));
+        }
+        timing_table.push_str("| --- | ---\n| Slowest tasks |\n");
+        for (name, duration) in slowest.iter() {
+            timing_table.push_str(&format!("| {} | {:.2}\n", name, duration.as_secs_f64()));
+        }
+        timing_table.push_str("|-|-");
+
+        crate::util::terminal::markdown_print(&timing_table);
+        println!("");
+    }
+
+    pub fn show_playbook_summary(&self, context: &Arc<RwLock<PlaybookContext>>) -> serde_json::map::Map<String,serde_json::Value> {
 
         let ctx = context.read().unwrap();
 
@@ -484,46 +1272,21 @@ impl PlaybookVisitor {
         let unchanged_ct = action_ct - adjusted_ct;
         let failed_ct    = ctx.get_total_failed_count();
         let failed_hosts = ctx.get_hosts_failed_count();
+        let retry_ct     = ctx.get_total_retry_count();
+        let retry_hosts  = ctx.get_hosts_retry_count();
 
-        let summary = match failed_hosts {
-            0 => match adjusted_hosts {
-                0 => String::from(format!("{color_green}(✓) Perfect. All hosts matched policy.{color_reset}")),
-                _ => String::from(format!("{color_blue}(✓) Actions were applied.{color_reset}")),
-            },
-            _ => String::from(format!("{color_red}(X) Failures have occured.{color_reset}")),
-        };
-
-        let mode_table = format!("|:-|:-|:-|\n\
-                          | Results | Items | Hosts \n\
-                          | --- | --- | --- |\n\
-                          | Roles | {role_ct} | |\n\
-                          | Tasks | {task_ct} | {seen_hosts}|\n\
-                          | --- | --- | --- |\n\
-                          | Matched | {matched_ct} | {matched_hosts}\n\
-                          | Created | {created_ct} | {created_hosts}\n\
-                          | Modified | {modified_ct} | {modified_hosts}\n\
-                          | Removed | {removed_ct} | {removed_hosts}\n\
-                          | Executed | {executed_ct} | {executed_hosts}\n\
-                          | Passive | {passive_ct} | {passive_hosts}\n\
-                          | Skipped | {skipped_ct} | {skipped_hosts}\n\
-                          | --- | --- | ---\n\
-                          | Unchanged | {unchanged_ct} | {unchanged_hosts}\n\
-                          | Changed | {adjusted_ct} | {adjusted_hosts}\n\
-                          | Failed | {failed_ct} | {failed_hosts}\n\
-                          |-|-|-");
-
-        crate::util::terminal::markdown_print(&mode_table);
-        println!("{}", format!("\n{summary}"));
-        println!("");
-
-        let mut log_entry = self.log_entry(&String::from("SUMMARY"), Arc::clone(context));
         let mut map : serde_json::map::Map<String,serde_json::Value> = serde_json::map::Map::new();
+        map.insert(String::from("role_ct"),         json!(role_ct));
+        map.insert(String::from("task_ct"),         json!(task_ct));
+        map.insert(String::from("seen_hosts"),      json!(seen_hosts));
         map.insert(String::from("matched_ct"),      json!(matched_ct));
         map.insert(String::from("matched_hosts"),   json!(matched_hosts));
         map.insert(String::from("created_ct"),      json!(created_ct));
         map.insert(String::from("created_hosts"),   json!(created_hosts));
         map.insert(String::from("modified_ct"),     json!(modified_ct));
         map.insert(String::from("modified_hosts"),  json!(modified_hosts));
+        map.insert(String::from("removed_ct"),      json!(removed_ct));
+        map.insert(String::from("removed_hosts"),   json!(removed_hosts));
         map.insert(String::from("executed_ct"),     json!(executed_ct));
         map.insert(String::from("executed_hosts"),  json!(executed_hosts));
         map.insert(String::from("passive_ct"),      json!(passive_ct));
@@ -536,9 +1299,145 @@ impl PlaybookVisitor {
         map.insert(String::from("adjusted_hosts"),  json!(adjusted_hosts));
         map.insert(String::from("failed_ct"),       json!(failed_ct));
         map.insert(String::from("failed_hosts"),    json!(failed_hosts));
+        map.insert(String::from("retry_ct"),        json!(retry_ct));
+        map.insert(String::from("retry_hosts"),     json!(retry_hosts));
+
+        if self.is_json() {
+            self.emit_json("SUMMARY", map.clone());
+        } else {
+
+            let summary = match failed_hosts {
+                0 => match adjusted_hosts {
+                    0 => String::from(format!("{}(✓) Perfect. All hosts matched policy.{}", self.palette.green, self.palette.reset)),
+                    _ => String::from(format!("{}(✓) Actions were applied.{}", self.palette.blue, self.palette.reset)),
+                },
+                _ => String::from(format!("{}(X) Failures have occured.{}", self.palette.red, self.palette.reset)),
+            };
+
+            let row = |name: &str, items: String, hosts: String| vec![String::from(name), items, hosts];
+            let sections = vec![
+                vec![
+                    row("Roles", role_ct.to_string(), String::new()),
+                    row("Tasks", task_ct.to_string(), seen_hosts.to_string())
+                ],
+                vec![
+                    row("Matched",  matched_ct.to_string(),  matched_hosts.to_string()),
+                    row("Created",  created_ct.to_string(),  created_hosts.to_string()),
+                    row("Modified", modified_ct.to_string(), modified_hosts.to_string()),
+                    row("Removed",  removed_ct.to_string(),  removed_hosts.to_string()),
+                    row("Executed", executed_ct.to_string(), executed_hosts.to_string()),
+                    row("Passive",  passive_ct.to_string(),  passive_hosts.to_string()),
+                    row("Skipped",  skipped_ct.to_string(),  skipped_hosts.to_string())
+                ],
+                vec![
+                    row("Unchanged", unchanged_ct.to_string(), unchanged_hosts.to_string()),
+                    row("Changed",   adjusted_ct.to_string(),  adjusted_hosts.to_string()),
+                    row("Failed",    failed_ct.to_string(),    failed_hosts.to_string()),
+                    row("Retries",   retry_ct.to_string(),     retry_hosts.to_string())
+                ]
+            ];
+            self.print_summary_table(&vec!["Results", "Items", "Hosts"], &sections, &summary);
+        }
+
+        let mut log_entry = self.log_entry(&String::from("SUMMARY"), Arc::clone(context));
+        log_entry.summary = Some(map.clone());
+        self.log(&log_entry);
+        return map;
+    }
+
+    // the 'jetp check-connections' equivalent of the playbook summary above
+
+    pub fn show_connection_summary(&self, context: &Arc<RwLock<PlaybookContext>>) {
+
+        let ctx = context.read().unwrap();
+
+        let seen_hosts = ctx.get_hosts_seen_count();
+        let unreachable = ctx.get_failed_host_names();
+        let unreachable_hosts = unreachable.len();
+        let reachable_hosts = seen_hosts - unreachable_hosts;
+
+        let mut map : serde_json::map::Map<String,serde_json::Value> = serde_json::map::Map::new();
+        map.insert(String::from("seen_hosts"),        json!(seen_hosts));
+        map.insert(String::from("reachable_hosts"),   json!(reachable_hosts));
+        map.insert(String::from("unreachable_hosts"), json!(unreachable_hosts));
+        map.insert(String::from("unreachable"),       json!(unreachable));
+
+        if self.is_json() {
+            self.emit_json("CONNECTION_SUMMARY", map.clone());
+        } else {
+
+            let summary = match unreachable_hosts {
+                0 => String::from(format!("{}(✓) All hosts are reachable.{}", self.palette.green, self.palette.reset)),
+                _ => String::from(format!("{}(X) Some hosts are unreachable: {}{}", self.palette.red, unreachable.join(", "), self.palette.reset)),
+            };
+
+            let sections = vec![vec![
+                vec![String::from("Reachable"),   reachable_hosts.to_string()],
+                vec![String::from("Unreachable"), unreachable_hosts.to_string()]
+            ]];
+            self.print_summary_table(&vec!["Connections", "Hosts"], &sections, &summary);
+        }
+
+        let mut log_entry = self.log_entry(&String::from("CONNECTION_SUMMARY"), Arc::clone(context));
         log_entry.summary = Some(map.clone());
         self.log(&log_entry);
 
     }
 
-}
\ No newline at end of file
+    // draws a summary table in the chosen --summary-format.  Sections are separated by rules in the markdown
+    // and plain tables.  CSV output is just the table, so that it can be read by other programs as is.
+
+    fn print_summary_table(&self, header: &Vec<&str>, sections: &Vec<Vec<Vec<String>>>, summary: &String) {
+        match self.summary_format {
+            SummaryFormat::Markdown => {
+                let columns = header.len();
+                let mut markdown = format!("|{}\n| {} \n", ":-|".repeat(columns), header.join(" | "));
+                for section in sections.iter() {
+                    markdown.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+                    for row in section.iter() {
+                        markdown.push_str(&format!("| {}\n", row.join(" | ")));
+                    }
+                }
+                markdown.push_str(&format!("|{}", vec!["-"; columns].join("|")));
+                crate::util::terminal::markdown_print(&markdown);
+            },
+            SummaryFormat::Plain => {
+                let mut widths : Vec<usize> = header.iter().map(|x| x.len()).collect();
+                for row in sections.iter().flatten() {
+                    for (i, cell) in row.iter().enumerate() {
+                        widths[i] = widths[i].max(cell.len());
+                    }
+                }
+                let line = |cells: Vec<&str>| {
+                    let padded : Vec<String> = cells.iter().enumerate().map(|(i, x)| format!("{:width$}", x, width = widths[i])).collect();
+                    println!("{}", padded.join("  ").trim_end());
+                };
+                let rule : Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+                line(header.clone());
+                for section in sections.iter() {
+                    line(rule.iter().map(|x| x.as_str()).collect());
+                    for row in section.iter() {
+                        line(row.iter().map(|x| x.as_str()).collect());
+                    }
+                }
+            },
+            SummaryFormat::Csv => {
+                let line = |cells: Vec<&str>| {
+                    let quoted : Vec<String> = cells.iter().map(|x| match x.contains(',') || x.contains('"') {
+                        true  => format!("\"{}\"", x.replace("\"", "\"\"")),
+                        false => x.to_string()
+                    }).collect();
+                    println!("{}", quoted.join(","));
+                };
+                line(header.clone());
+                for row in sections.iter().flatten() {
+                    line(row.iter().map(|x| x.as_str()).collect());
+                }
+                return;
+            }
+        }
+        println!("{}", format!("\n{summary}"));
+        println!("");
+    }
+
+}
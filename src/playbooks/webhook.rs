@@ -0,0 +1,84 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use crate::playbooks::callback::Callback;
+use crate::cli::parser::CliParser;
+use serde_json::json;
+use std::time::Duration;
+
+// --webhook posts the summary of a run to a URL when it ends, for chat notifications and dashboards.
+// the payload has a 'text' line so it can be given straight to a Slack incoming webhook, along with
+// the summary counts and failed hosts for anything that wants the details.  A webhook that cannot be
+// reached is reported but never fails the run.
+
+pub struct WebhookCallback {
+    url: String,
+    playbooks: String,
+    check: bool,
+    agent: ureq::Agent
+}
+
+impl WebhookCallback {
+
+    pub fn new(url: &String, parser: &CliParser, check: bool) -> Self {
+        let playbooks : Vec<String> = parser.playbook_paths.read().unwrap().iter().map(|x| x.display().to_string()).collect();
+        return Self {
+            url: url.clone(),
+            playbooks: playbooks.join(":"),
+            check: check,
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(10)).build()
+        };
+    }
+
+    fn get_text(&self, summary: &serde_json::Map<String, serde_json::Value>, failed_hosts: &Vec<String>) -> String {
+        let count = |key: &str| summary.get(key).and_then(|x| x.as_u64()).unwrap_or(0);
+        let what = match self.check {
+            true => "check",
+            false => "run"
+        };
+        return match failed_hosts.is_empty() {
+            true => format!(":white_check_mark: jetp {} of {} finished: {} hosts, {} changed",
+                what, self.playbooks, count("seen_hosts"), count("adjusted_hosts")),
+            false => format!(":x: jetp {} of {} failed: {} of {} hosts failed ({})",
+                what, self.playbooks, failed_hosts.len(), count("seen_hosts"), failed_hosts.join(", "))
+        };
+    }
+}
+
+impl Callback for WebhookCallback {
+
+    fn on_summary(&self, summary: &serde_json::Map<String, serde_json::Value>) {
+        let failed_hosts : Vec<String> = match summary.get("failed_host_names") {
+            Some(x) => serde_json::from_value(x.clone()).unwrap_or_default(),
+            None => Vec::new()
+        };
+        let mut counts = summary.clone();
+        counts.remove("failed_host_names");
+        let payload = json!({
+            "text":         self.get_text(summary, &failed_hosts),
+            "status":       match failed_hosts.is_empty() { true => "ok", false => "failed" },
+            "check":        self.check,
+            "playbooks":    self.playbooks,
+            "summary":      counts,
+            "failed_hosts": failed_hosts
+        });
+        if let Err(e) = self.agent.post(&self.url).set("Content-Type", "application/json").send_string(&payload.to_string()) {
+            println!("! webhook {} could not be notified: {}", self.url, e);
+        }
+    }
+
+}
@@ -33,32 +33,50 @@ use crate::modules::access::user::UserTask;
 // commands
 use crate::modules::commands::external::ExternalTask;
 use crate::modules::commands::shell::ShellTask;
+use crate::modules::commands::win_command::WinCommandTask;
 
 // control
 use crate::modules::control::assert::AssertTask;
+use crate::modules::control::block::BlockTask;
 use crate::modules::control::debug::DebugTask;
 use crate::modules::control::echo::EchoTask;
 use crate::modules::control::fail::FailTask;
 use crate::modules::control::facts::FactsTask;
+use crate::modules::control::import_tasks::ImportTasksTask;
+use crate::modules::control::include_tasks::IncludeTasksTask;
+use crate::modules::control::meta::MetaTask;
+use crate::modules::control::pause::PauseTask;
 use crate::modules::control::set::SetTask;
+use crate::modules::control::wait_for::WaitForTask;
 
 // files
 use crate::modules::files::copy::CopyTask;
 use crate::modules::files::directory::DirectoryTask;
+use crate::modules::files::fetch::FetchTask;
 use crate::modules::files::file::FileTask;
 use crate::modules::files::git::GitTask;
+use crate::modules::files::hosts::HostsTask;
+use crate::modules::files::lineinfile::LineInFileTask;
+use crate::modules::files::mount::MountTask;
 use crate::modules::files::stat::StatTask;
 use crate::modules::files::template::TemplateTask;
+use crate::modules::files::unarchive::UnarchiveTask;
+use crate::modules::files::win_copy::WinCopyTask;
 
 // packages
 use crate::modules::packages::apt::AptTask;
 use crate::modules::packages::homebrew::HomebrewTask;
+use crate::modules::packages::package::PackageTask;
 use crate::modules::packages::pacman::PacmanTask;
 use crate::modules::packages::yum_dnf::YumDnfTask;
 use crate::modules::packages::zypper::ZypperTask;
 
 // services
+use crate::modules::services::cron::CronTask;
+use crate::modules::services::firewall::FirewallTask;
 use crate::modules::services::sd_service::SystemdServiceTask;
+use crate::modules::services::sd_unit::SystemdUnitTask;
+use crate::modules::services::sysctl::SysctlTask;
 
 #[allow(non_camel_case_types)]
 #[derive(Deserialize,Debug)]
@@ -67,7 +85,9 @@ pub enum Task {
     // ADD NEW MODULES HERE, KEEP ALPHABETIZED BY NAME
     Apt(AptTask),
     Assert(AssertTask),
+    Block(BlockTask),
     Copy(CopyTask),
+    Cron(CronTask),
     Debug(DebugTask),
     Directory(DirectoryTask),
     Dnf(YumDnfTask),
@@ -75,17 +95,35 @@ pub enum Task {
     External(ExternalTask),
     Facts(FactsTask),
     Fail(FailTask),
+    Fetch(FetchTask),
     File(FileTask),
+    Firewall(FirewallTask),
     Git(GitTask),
     Group(GroupTask),
     Homebrew(HomebrewTask),
+    Hosts(HostsTask),
+    Import_Tasks(ImportTasksTask),
+    Include_Tasks(IncludeTasksTask),
+    LineInFile(LineInFileTask),
+    Meta(MetaTask),
+    Mount(MountTask),
+    Package(PackageTask),
     Pacman(PacmanTask),
+    Pause(PauseTask),
     Sd_Service(SystemdServiceTask),
+    Sd_Unit(SystemdUnitTask),
+    Service(SystemdServiceTask),
+    #[serde(alias="set_fact")]
     Set(SetTask),
     Shell(ShellTask),
     Stat(StatTask),
+    Sysctl(SysctlTask),
     Template(TemplateTask),
+    Unarchive(UnarchiveTask),
     User(UserTask),
+    Wait_For(WaitForTask),
+    Win_Command(WinCommandTask),
+    Win_Copy(WinCopyTask),
     Yum(YumDnfTask),
     Zypper(ZypperTask),
 }
@@ -97,7 +135,9 @@ impl Task {
         return match self {
             Task::Apt(x)        => x.get_module(),
             Task::Assert(x)     => x.get_module(),
+            Task::Block(x)      => x.get_module(),
             Task::Copy(x)       => x.get_module(),
+            Task::Cron(x)       => x.get_module(),
             Task::Debug(x)      => x.get_module(),
             Task::Directory(x)  => x.get_module(),
             Task::Dnf(x)        => x.get_module(),
@@ -105,17 +145,34 @@ impl Task {
             Task::External(x)   => x.get_module(),
             Task::Facts(x)      => x.get_module(), 
             Task::Fail(x)       => x.get_module(), 
+            Task::Fetch(x)      => x.get_module(),
             Task::File(x)       => x.get_module(),
+            Task::Firewall(x)   => x.get_module(),
             Task::Git(x)        => x.get_module(), 
             Task::Group(x)      => x.get_module(),
             Task::Homebrew(x)   => x.get_module(),
+            Task::Hosts(x)      => x.get_module(),
+            Task::Import_Tasks(x) => x.get_module(),
+            Task::Include_Tasks(x) => x.get_module(),
+            Task::LineInFile(x) => x.get_module(),
+            Task::Meta(x)       => x.get_module(),
+            Task::Mount(x)      => x.get_module(),
+            Task::Package(x)    => x.get_module(),
             Task::Pacman(x)     => x.get_module(),
+            Task::Pause(x)      => x.get_module(),
             Task::Sd_Service(x) => x.get_module(),
+            Task::Sd_Unit(x)    => x.get_module(),
+            Task::Service(x)    => x.get_module(),
             Task::Set(x)        => x.get_module(), 
             Task::Shell(x)      => x.get_module(), 
             Task::Stat(x)       => x.get_module(), 
+            Task::Sysctl(x)     => x.get_module(),
             Task::Template(x)   => x.get_module(), 
+            Task::Unarchive(x)  => x.get_module(),
             Task::User(x)       => x.get_module(),
+            Task::Wait_For(x)   => x.get_module(),
+            Task::Win_Command(x) => x.get_module(),
+            Task::Win_Copy(x)   => x.get_module(),
             Task::Yum(x)        => x.get_module(),
             Task::Zypper(x)     => x.get_module(),
         };
@@ -126,7 +183,9 @@ impl Task {
         return match self {
             Task::Apt(x)        => x.get_name(),
             Task::Assert(x)     => x.get_name(),
+            Task::Block(x)      => x.get_name(),
             Task::Copy(x)       => x.get_name(),
+            Task::Cron(x)       => x.get_name(),
             Task::Debug(x)      => x.get_name(), 
             Task::Directory(x)  => x.get_name(),
             Task::Dnf(x)        => x.get_name(),
@@ -134,17 +193,34 @@ impl Task {
             Task::External(x)   => x.get_name(),
             Task::Facts(x)      => x.get_name(),
             Task::Fail(x)       => x.get_name(), 
+            Task::Fetch(x)      => x.get_name(),
             Task::File(x)       => x.get_name(), 
+            Task::Firewall(x)   => x.get_name(),
             Task::Git(x)        => x.get_name(),
             Task::Group(x)      => x.get_name(),
             Task::Homebrew(x)   => x.get_name(),
+            Task::Hosts(x)      => x.get_name(),
+            Task::Import_Tasks(x) => x.get_name(),
+            Task::Include_Tasks(x) => x.get_name(),
+            Task::LineInFile(x) => x.get_name(),
+            Task::Meta(x)       => x.get_name(),
+            Task::Mount(x)      => x.get_name(),
+            Task::Package(x)    => x.get_name(),
             Task::Pacman(x)     => x.get_name(),
+            Task::Pause(x)      => x.get_name(),
             Task::Sd_Service(x) => x.get_name(),
+            Task::Sd_Unit(x)    => x.get_name(),
+            Task::Service(x)    => x.get_name(),
             Task::Set(x)        => x.get_name(),
             Task::Shell(x)      => x.get_name(), 
             Task::Stat(x)       => x.get_name(),
+            Task::Sysctl(x)     => x.get_name(),
             Task::Template(x)   => x.get_name(), 
+            Task::Unarchive(x)  => x.get_name(),
             Task::User(x)       => x.get_name(),
+            Task::Wait_For(x)   => x.get_name(),
+            Task::Win_Command(x) => x.get_name(),
+            Task::Win_Copy(x)   => x.get_name(),
             Task::Yum(x)        => x.get_name(),
             Task::Zypper(x)     => x.get_name(),
         };
@@ -155,7 +231,9 @@ impl Task {
         return match self {
             Task::Apt(x)        => x.get_with(),
             Task::Assert(x)     => x.get_with(),
+            Task::Block(x)      => x.get_with(),
             Task::Copy(x)       => x.get_with(),
+            Task::Cron(x)       => x.get_with(),
             Task::Debug(x)      => x.get_with(), 
             Task::Directory(x)  => x.get_with(),
             Task::Dnf(x)        => x.get_with(),
@@ -163,17 +241,34 @@ impl Task {
             Task::External(x)   => x.get_with(),
             Task::Facts(x)      => x.get_with(),
             Task::Fail(x)       => x.get_with(), 
+            Task::Fetch(x)      => x.get_with(),
             Task::File(x)       => x.get_with(),
+            Task::Firewall(x)   => x.get_with(),
             Task::Git(x)        => x.get_with(), 
             Task::Group(x)      => x.get_with(),
             Task::Homebrew(x)   => x.get_with(),
+            Task::Hosts(x)      => x.get_with(),
+            Task::Import_Tasks(x) => x.get_with(),
+            Task::Include_Tasks(x) => x.get_with(),
+            Task::LineInFile(x) => x.get_with(),
+            Task::Meta(x)       => x.get_with(),
+            Task::Mount(x)      => x.get_with(),
+            Task::Package(x)    => x.get_with(),
             Task::Pacman(x)     => x.get_with(),
+            Task::Pause(x)      => x.get_with(),
             Task::Sd_Service(x) => x.get_with(),
+            Task::Sd_Unit(x)    => x.get_with(),
+            Task::Service(x)    => x.get_with(),
             Task::Set(x)        => x.get_with(),
             Task::Shell(x)      => x.get_with(), 
             Task::Stat(x)       => x.get_with(), 
+            Task::Sysctl(x)     => x.get_with(),
             Task::Template(x)   => x.get_with(),
+            Task::Unarchive(x)  => x.get_with(),
             Task::User(x)       => x.get_with(),
+            Task::Wait_For(x)   => x.get_with(),
+            Task::Win_Command(x) => x.get_with(),
+            Task::Win_Copy(x)   => x.get_with(),
             Task::Yum(x)        => x.get_with(), 
             Task::Zypper(x)     => x.get_with(),
         };
@@ -184,7 +279,9 @@ impl Task {
         return match self {
             Task::Apt(x)        => x.evaluate(handle, request, tm),
             Task::Assert(x)     => x.evaluate(handle, request, tm),
+            Task::Block(x)      => x.evaluate(handle, request, tm),
             Task::Copy(x)       => x.evaluate(handle, request, tm),
+            Task::Cron(x)       => x.evaluate(handle, request, tm),
             Task::Debug(x)      => x.evaluate(handle, request, tm), 
             Task::Directory(x)  => x.evaluate(handle, request, tm), 
             Task::Dnf(x)        => x.evaluate(handle, request, tm),
@@ -192,17 +289,34 @@ impl Task {
             Task::External(x)   => x.evaluate(handle, request, tm),
             Task::Facts(x)      => x.evaluate(handle, request, tm),
             Task::Fail(x)       => x.evaluate(handle, request, tm),  
+            Task::Fetch(x)      => x.evaluate(handle, request, tm),
             Task::File(x)       => x.evaluate(handle, request, tm), 
+            Task::Firewall(x)   => x.evaluate(handle, request, tm),
             Task::Git(x)        => x.evaluate(handle, request, tm),
             Task::Group(x)      => x.evaluate(handle, request, tm),
             Task::Homebrew(x)   => x.evaluate(handle, request, tm),
+            Task::Hosts(x)      => x.evaluate(handle, request, tm),
+            Task::Import_Tasks(x) => x.evaluate(handle, request, tm),
+            Task::Include_Tasks(x) => x.evaluate(handle, request, tm),
+            Task::LineInFile(x) => x.evaluate(handle, request, tm),
+            Task::Meta(x)       => x.evaluate(handle, request, tm),
+            Task::Mount(x)      => x.evaluate(handle, request, tm),
+            Task::Package(x)    => x.evaluate(handle, request, tm),
             Task::Pacman(x)     => x.evaluate(handle, request, tm),
+            Task::Pause(x)      => x.evaluate(handle, request, tm),
             Task::Sd_Service(x) => x.evaluate(handle, request, tm),
+            Task::Sd_Unit(x)    => x.evaluate(handle, request, tm),
+            Task::Service(x)    => x.evaluate(handle, request, tm),
             Task::Set(x)        => x.evaluate(handle, request, tm),
             Task::Shell(x)      => x.evaluate(handle, request, tm), 
             Task::Stat(x)       => x.evaluate(handle, request, tm),
+            Task::Sysctl(x)     => x.evaluate(handle, request, tm),
             Task::Template(x)   => x.evaluate(handle, request, tm), 
+            Task::Unarchive(x)  => x.evaluate(handle, request, tm),
             Task::User(x)       => x.evaluate(handle, request, tm),
+            Task::Wait_For(x)   => x.evaluate(handle, request, tm),
+            Task::Win_Command(x) => x.evaluate(handle, request, tm),
+            Task::Win_Copy(x)   => x.evaluate(handle, request, tm),
             Task::Yum(x)        => x.evaluate(handle, request, tm), 
             Task::Zypper(x)     => x.evaluate(handle, request, tm), 
         };
@@ -19,7 +19,7 @@
 
 use crate::inventory::hosts::HostOSType;
 use crate::tasks::FileAttributesInput;
-use crate::tasks::files::Recurse;
+use crate::tasks::files::{Recurse,ArchiveFormat};
 
 // **IMPORTANT**
 //
@@ -88,6 +88,16 @@ pub fn get_mode_command(os_type: HostOSType, untrusted_path: &String) -> Result<
     return match os_type {
         HostOSType::Linux => Ok(format!("stat --format '%a' '{}'", path)),
         HostOSType::MacOS => Ok(format!("stat -f '%A' '{}'", path)),
+        HostOSType::Windows => Err(String::from("file modes are not supported on Windows")),
+    }
+}
+
+pub fn get_size_command(os_type: HostOSType, untrusted_path: &String) -> Result<String,String>  {
+    let path = screen_path(untrusted_path)?;
+    return match os_type {
+        HostOSType::Linux => Ok(format!("stat --format '%s' '{}'", path)),
+        HostOSType::MacOS => Ok(format!("stat -f '%z' '{}'", path)),
+        HostOSType::Windows => Err(String::from("file sizes are not supported on Windows")),
     }
 }
 
@@ -96,9 +106,15 @@ pub fn get_sha512_command(os_type: HostOSType, untrusted_path: &String) -> Resul
     return match os_type {
         HostOSType::Linux => Ok(format!("sha512sum '{}'", path)),
         HostOSType::MacOS => Ok(format!("shasum -b -a 512 '{}'", path)),
+        HostOSType::Windows => Err(String::from("checksums are not supported on Windows")),
     }
 }
 
+pub fn get_cat_command(_os_type: HostOSType, untrusted_path: &String) -> Result<String,String>  {
+    let path = screen_path(untrusted_path)?;
+    return Ok(format!("cat '{}'", path));
+}
+
 pub fn get_ownership_command(_os_type: HostOSType, untrusted_path: &String) -> Result<String,String>  {
     let path = screen_path(untrusted_path)?;
     return Ok(format!("ls -ld '{}'", path));
@@ -109,11 +125,41 @@ pub fn get_is_directory_command(_os_type: HostOSType, untrusted_path: &String) -
     return Ok(format!("ls -ld '{}'", path));
 }
 
+pub fn get_path_exists_command(_os_type: HostOSType, untrusted_path: &String) -> Result<String,String>  {
+    let path = screen_path(untrusted_path)?;
+    return Ok(format!("test -e '{}'", path));
+}
+
+pub fn get_directory_exists_command(os_type: HostOSType, untrusted_path: &String) -> Result<String,String>  {
+    let path = screen_path(untrusted_path)?;
+    return match os_type {
+        HostOSType::Windows => Ok(format!("if (Test-Path -LiteralPath '{}' -PathType Container) {{ exit 0 }} else {{ exit 1 }}", path)),
+        _ => Ok(format!("test -d '{}'", path))
+    };
+}
+
 pub fn get_touch_command(_os_type: HostOSType, untrusted_path: &String) -> Result<String,String>  {
     let path = screen_path(untrusted_path)?;
     return Ok(format!("touch '{}'", path));
 }
 
+pub fn get_backup_file_command(_os_type: HostOSType, untrusted_path: &String, untrusted_backup_path: &String) -> Result<String,String>  {
+    let path = screen_path(untrusted_path)?;
+    let backup_path = screen_path(untrusted_backup_path)?;
+    return Ok(format!("cp -p '{}' '{}'", path, backup_path));
+}
+
+pub fn get_read_link_command(_os_type: HostOSType, untrusted_path: &String) -> Result<String,String>  {
+    let path = screen_path(untrusted_path)?;
+    return Ok(format!("readlink '{}'", path));
+}
+
+pub fn get_create_link_command(_os_type: HostOSType, untrusted_target: &String, untrusted_path: &String) -> Result<String,String>  {
+    let target = screen_path(untrusted_target)?;
+    let path = screen_path(untrusted_path)?;
+    return Ok(format!("ln -sfn '{}' '{}'", target, path));
+}
+
 pub fn get_create_directory_command(_os_type: HostOSType, untrusted_path: &String) -> Result<String,String>  {
     let path = screen_path(untrusted_path)?;
     return Ok(format!("mkdir -p '{}'", path));
@@ -132,6 +178,25 @@ pub fn get_delete_directory_command(_os_type: HostOSType, untrusted_path: &Strin
     }
 }
 
+pub fn get_archive_list_command(_os_type: HostOSType, untrusted_path: &String, format: ArchiveFormat) -> Result<String,String>  {
+    let path = screen_path(untrusted_path)?;
+    return match format {
+        ArchiveFormat::TarGz  => Ok(format!("tar -tzf '{}'", path)),
+        ArchiveFormat::TarBz2 => Ok(format!("tar -tjf '{}'", path)),
+        ArchiveFormat::Zip    => Ok(format!("unzip -Z1 '{}'", path))
+    };
+}
+
+pub fn get_extract_command(_os_type: HostOSType, untrusted_archive: &String, untrusted_dest: &String, format: ArchiveFormat) -> Result<String,String>  {
+    let archive = screen_path(untrusted_archive)?;
+    let dest = screen_path(untrusted_dest)?;
+    return match format {
+        ArchiveFormat::TarGz  => Ok(format!("tar -xzf '{}' -C '{}'", archive, dest)),
+        ArchiveFormat::TarBz2 => Ok(format!("tar -xjf '{}' -C '{}'", archive, dest)),
+        ArchiveFormat::Zip    => Ok(format!("unzip -o -q '{}' -d '{}'", archive, dest))
+    };
+}
+
 pub fn set_owner_command(_os_type: HostOSType, untrusted_path: &String, untrusted_owner: &String, recurse: Recurse) -> Result<String,String> {
     let path = screen_path(untrusted_path)?;
     let owner = screen_general_input_strict(untrusted_owner)?;
@@ -163,10 +228,31 @@ pub fn set_mode_command(_os_type: HostOSType, untrusted_path: &String, untrusted
 
 pub fn get_arch_command(os_type: HostOSType) -> Result<String,String> {
     match os_type {
+        HostOSType::Windows => { return Ok(String::from("$env:PROCESSOR_ARCHITECTURE")) },
         _ => { return Ok(String::from("uname -m")) },
     }
 }
 
+// total memory, in kilobytes on Linux and in bytes on MacOS and Windows
+
+pub fn get_memory_command(os_type: HostOSType) -> Result<String,String> {
+    return match os_type {
+        HostOSType::Linux => Ok(String::from("grep MemTotal /proc/meminfo")),
+        HostOSType::MacOS => Ok(String::from("sysctl -n hw.memsize")),
+        HostOSType::Windows => Ok(String::from("(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory")),
+    }
+}
+
+// non-loopback IPv4 addresses, output formats differ and are parsed by the facts module
+
+pub fn get_ipv4_addresses_command(os_type: HostOSType) -> Result<String,String> {
+    return match os_type {
+        HostOSType::Linux => Ok(String::from("hostname -I")),
+        HostOSType::MacOS => Ok(String::from("ifconfig -a inet")),
+        HostOSType::Windows => Ok(String::from("(Get-NetIPAddress -AddressFamily IPv4).IPAddress")),
+    }
+}
+
 
 
 
@@ -0,0 +1,192 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+// produces unified diffs for check mode previews (--diff).  There is no diff library in
+// our dependency tree so this is a straightforward LCS over lines, which is fine for the
+// sizes of configuration files jetp is expected to manage.
+
+const CONTEXT_LINES: usize = 3;
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug,PartialEq)]
+enum DiffOp {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize)
+}
+
+// content with NUL bytes is treated as binary, which is the same heuristic used by git
+
+pub fn is_binary(data: &[u8]) -> bool {
+    return data.contains(&0u8) || std::str::from_utf8(data).is_err();
+}
+
+pub fn binary_differs(path: &String) -> String {
+    return format!("binary files differ: {}", path);
+}
+
+pub fn unified_diff(path: &String, before: &String, after: &String) -> String {
+
+    let old : Vec<&str> = before.lines().collect();
+    let new : Vec<&str> = after.lines().collect();
+
+    if old.len().saturating_mul(new.len()) > MAX_DIFF_CELLS {
+        return format!("file too large to diff: {}", path);
+    }
+
+    let ops = diff_ops(&old, &new);
+    let mut out = format!("--- {}\n+++ {} (proposed)\n", path, path);
+
+    // find the op indexes that represent changes, then expand each by the context size,
+    // merging hunks that would overlap
+
+    let changed : Vec<usize> = ops.iter().enumerate().filter(|(_, op)| match op {
+        DiffOp::Same(_,_) => false,
+        _ => true
+    }).map(|(i,_)| i).collect();
+
+    let mut hunks : Vec<(usize,usize)> = Vec::new();
+    for idx in changed.iter() {
+        let start = idx.saturating_sub(CONTEXT_LINES);
+        let end = std::cmp::min(idx + CONTEXT_LINES + 1, ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => { last.1 = end; },
+            _ => { hunks.push((start, end)); }
+        }
+    }
+
+    for (start, end) in hunks.iter() {
+        let (old_start, new_start) = hunk_origin(&ops, *start);
+        let mut old_ct = 0;
+        let mut new_ct = 0;
+        let mut body = String::new();
+        for op in ops[*start..*end].iter() {
+            match op {
+                DiffOp::Same(i, _) => { body.push_str(&format!(" {}\n", old[*i])); old_ct += 1; new_ct += 1; },
+                DiffOp::Removed(i) => { body.push_str(&format!("-{}\n", old[*i])); old_ct += 1; },
+                DiffOp::Added(j)   => { body.push_str(&format!("+{}\n", new[*j])); new_ct += 1; }
+            }
+        }
+        // an empty range is numbered by the line before it, as diff(1) does
+        let old_start = match old_ct { 0 => old_start - 1, _ => old_start };
+        let new_start = match new_ct { 0 => new_start - 1, _ => new_start };
+        out.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_ct, new_start, new_ct));
+        out.push_str(&body);
+    }
+    return out;
+}
+
+// line numbers in hunk headers are 1-based and count the lines consumed before the hunk
+
+fn hunk_origin(ops: &Vec<DiffOp>, start: usize) -> (usize, usize) {
+    let mut old_line = 1;
+    let mut new_line = 1;
+    for op in ops[..start].iter() {
+        match op {
+            DiffOp::Same(_, _) => { old_line += 1; new_line += 1; },
+            DiffOp::Removed(_) => { old_line += 1; },
+            DiffOp::Added(_)   => { new_line += 1; }
+        }
+    }
+    return (old_line, new_line);
+}
+
+fn diff_ops(old: &Vec<&str>, new: &Vec<&str>) -> Vec<DiffOp> {
+    let n = old.len();
+    let m = new.len();
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true  => lcs[i+1][j+1] + 1,
+                false => std::cmp::max(lcs[i+1][j], lcs[i][j+1])
+            };
+        }
+    }
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push(DiffOp::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[i+1][j] >= lcs[i][j+1] {
+            ops.push(DiffOp::Removed(i));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Added(j));
+            j += 1;
+        }
+    }
+    while i < n { ops.push(DiffOp::Removed(i)); i += 1; }
+    while j < m { ops.push(DiffOp::Added(j)); j += 1; }
+    return ops;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(before: &str, after: &str) -> String {
+        return unified_diff(&String::from("f"), &String::from(before), &String::from(after));
+    }
+
+    #[test]
+    fn test_identical() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), "--- f\n+++ f (proposed)\n");
+    }
+
+    #[test]
+    fn test_single_change() {
+        assert_eq!(diff("a\nb\nc\n", "a\nx\nc\n"), "--- f\n+++ f (proposed)\n@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n");
+    }
+
+    #[test]
+    fn test_from_and_to_empty() {
+        assert_eq!(diff("", "a\nb\n"), "--- f\n+++ f (proposed)\n@@ -0,0 +1,2 @@\n+a\n+b\n");
+        assert_eq!(diff("a\n", ""), "--- f\n+++ f (proposed)\n@@ -1,1 +0,0 @@\n-a\n");
+    }
+
+    #[test]
+    fn test_hunks_are_separated_and_numbered() {
+        let before : Vec<String> = (1..=20).map(|x| x.to_string()).collect();
+        let mut after = before.clone();
+        after[1] = String::from("two");
+        after.insert(15, String::from("extra"));
+        let out = diff(&before.join("\n"), &after.join("\n"));
+        let headers : Vec<&str> = out.lines().filter(|x| x.starts_with("@@")).collect();
+        assert_eq!(headers, vec!["@@ -1,5 +1,5 @@", "@@ -13,6 +13,7 @@"]);
+        assert!(out.contains("-2\n+two\n"));
+        assert!(out.contains(" 15\n+extra\n 16\n"));
+    }
+
+    #[test]
+    fn test_nearby_changes_share_a_hunk() {
+        let out = diff("1\n2\n3\n4\n5\n6\n7\n8\n", "1\nA\n3\n4\n5\nB\n7\n8\n");
+        let headers : Vec<&str> = out.lines().filter(|x| x.starts_with("@@")).collect();
+        assert_eq!(headers, vec!["@@ -1,8 +1,8 @@"]);
+    }
+
+    #[test]
+    fn test_binary_and_size_limits() {
+        assert!(is_binary(b"abc\0def"));
+        assert!(is_binary(&[0xff, 0xfe]));
+        assert!(! is_binary(b"plain text\n"));
+        let big = "x\n".repeat(3000);
+        assert_eq!(diff(&big, &big), "file too large to diff: f");
+    }
+}
@@ -23,21 +23,32 @@ use std::vec::Vec;
 
 #[derive(Eq,Hash,PartialEq,Clone,Copy,Debug)]
 pub enum Field {
+    AuthorizedKeys,
     Branch,
     Content,
     Disable,
     Enable,
+    Fstab,
     Gecos,
     Gid,
     Group,
     Groups,
+    Home,
     Mode,
+    Mount,
     Owner,
+    Persisted,
+    Reload,
+    Remount,
     Restart,
+    Runtime,
     Shell,
     Start,
     Stop,
+    Target,
+    Timestamp,
     Uid,
+    Unmount,
     Users,
     Version,
 }
@@ -46,6 +46,49 @@ pub enum Recurse {
     Yes
 }
 
+// archive types understood by the unarchive module, chosen by file extension
+
+#[derive(Debug,Copy,Clone,PartialEq)]
+pub enum ArchiveFormat {
+    TarGz,
+    TarBz2,
+    Zip
+}
+
+impl ArchiveFormat {
+
+    pub fn from_path(path: &String) -> Option<Self> {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            return Some(ArchiveFormat::TarGz);
+        } else if lower.ends_with(".tar.bz2") || lower.ends_with(".tbz2") {
+            return Some(ArchiveFormat::TarBz2);
+        } else if lower.ends_with(".zip") {
+            return Some(ArchiveFormat::Zip);
+        }
+        return None;
+    }
+
+    // reduces an archive listing to the distinct top level entries, which are the files
+    // we expect to find in the destination once the archive has been extracted
+
+    pub fn top_level_entries(listing: &String) -> Vec<String> {
+        let mut results : Vec<String> = Vec::new();
+        for line in listing.lines() {
+            let trimmed = line.trim().trim_start_matches("./");
+            let first = match trimmed.split('/').next() {
+                Some(x) => x.to_string(),
+                None => continue
+            };
+            if ! first.is_empty() && ! results.contains(&first) {
+                results.push(first);
+            }
+        }
+        return results;
+    }
+
+}
+
 impl FileAttributesInput {
 
     // given an octal string, like 0o755 or 755, return the numeric value
@@ -15,7 +15,7 @@
 // long with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::handle::handle::TaskHandle;
-use crate::tasks::request::TaskRequest;
+use crate::tasks::request::{TaskRequest,AsyncDetails};
 use std::sync::Arc;
 use crate::tasks::response::TaskResponse;
 use serde::Deserialize;
@@ -28,19 +28,35 @@ use crate::playbooks::templar::TemplateMode;
 #[derive(Deserialize,Debug,Clone)]
 #[serde(deny_unknown_fields)]
 pub struct PreLogicInput {
+    // 'when' is accepted for those used to other tools
+    #[serde(alias="when")]
     pub condition: Option<String>,
     pub subscribe: Option<String>,
     pub sudo: Option<String>,
+    #[serde(alias="loop")]
     pub items: Option<ItemsInput>,
     pub tags: Option<Vec<String>>,
-    pub delegate_to: Option<String>
+    pub delegate_to: Option<String>,
+    pub timeout: Option<String>,
+    // 'async' is a keyword in Rust
+    #[serde(rename="async")]
+    pub async_limit: Option<String>,
+    pub poll: Option<String>,
+    // not templated, the visitor needs it before and after the task is evaluated
+    pub no_log: Option<bool>,
+    // not templated, the traversal decides which hosts run the task before evaluating it
+    pub run_once: Option<bool>,
+    // not templated, the traversal limits how many hosts run the task at the same time
+    pub throttle: Option<usize>,
+    // variables set for every command the task runs, on top of those from the play
+    pub environment: Option<serde_yaml::Mapping>
 }
 
 #[derive(Deserialize,Debug,Clone)]
 #[serde(untagged)]
 pub enum ItemsInput {
     ItemsString(String),
-    ItemsList(Vec<String>),
+    ItemsList(serde_yaml::Sequence),
 }
 
 #[derive(Debug)]
@@ -49,16 +65,22 @@ pub struct PreLogicEvaluated {
     pub subscribe: Option<String>,
     pub sudo: Option<String>,
     pub items: Option<ItemsInput>,
-    pub tags: Option<Vec<String>>
+    pub tags: Option<Vec<String>>,
+    pub timeout: Option<u64>,
+    pub async_details: Option<AsyncDetails>,
+    pub environment: Vec<(String,String)>
 }
 
-#[derive(Deserialize,Debug)]
+#[derive(Deserialize,Debug,Clone)]
 #[serde(deny_unknown_fields)]
 pub struct PostLogicInput {
     pub notify: Option<String>,
     pub ignore_errors: Option<String>,
+    #[serde(alias="retries")]
     pub retry: Option<String>,
-    pub delay: Option<String>
+    pub delay: Option<String>,
+    pub register: Option<String>,
+    pub until: Option<String>
 }
 
 #[derive(Debug)]
@@ -67,6 +89,8 @@ pub struct PostLogicEvaluated {
     pub ignore_errors: bool,
     pub retry: u64,
     pub delay: u64,
+    pub register: Option<String>,
+    pub until: Option<String>, // this is not evaluated here
 }
 
 
@@ -82,7 +106,19 @@ impl PreLogicInput {
             sudo: handle.template.string_option_no_spaces(request, tm, &String::from("sudo"), &input2.sudo)?,
             subscribe: handle.template.no_template_string_option_trim(&input2.subscribe),
             items: input2.items.clone(),
-            tags: input2.tags.clone()
+            tags: input2.tags.clone(),
+            timeout: match handle.template.integer_option_to_integer(request, tm, &String::from("timeout"), &input2.timeout, 0)? {
+                0 => None,
+                x => Some(x)
+            },
+            async_details: match input2.async_limit.is_some() {
+                false => None,
+                true => Some(AsyncDetails {
+                    limit: handle.template.integer_option_to_integer(request, tm, &String::from("async"), &input2.async_limit, 0)?,
+                    poll:  handle.template.integer_option_to_integer(request, tm, &String::from("poll"), &input2.poll, 10)?
+                })
+            },
+            environment: template_environment(handle, request, tm, &input2.environment)?
         }));
     }
 
@@ -101,10 +137,43 @@ impl PostLogicInput {
             delay:         handle.template.integer_option_to_integer(request, tm, &String::from("delay"), &input2.delay, 1)?,
             ignore_errors: handle.template.boolean_option_default_false(request, tm, &String::from("ignore_errors"), &input2.ignore_errors)?,
             retry:         handle.template.integer_option_to_integer(request, tm, &String::from("retry"), &input2.retry, 0)?,
+            register:      handle.template.string_option_no_spaces(request, tm, &String::from("register"), &input2.register)?,
+            until:         input2.until.clone(),
         }));
     }
 }
 
+// environment variables for commands, from a task's with/environment or a play's environment.  Values may be
+// templated from host variables, and are quoted when the command is built so they may contain anything.
+
+pub fn template_environment(handle: &TaskHandle, request: &Arc<TaskRequest>, tm: TemplateMode, input: &Option<serde_yaml::Mapping>)
+    -> Result<Vec<(String,String)>, Arc<TaskResponse>> {
+
+    let mut results : Vec<(String,String)> = Vec::new();
+    for (k, v) in input.iter().flatten() {
+        let name = match k.as_str() {
+            Some(x) if is_environment_name(x) => String::from(x),
+            _ => { return Err(handle.response.is_failed(request, &format!("environment: invalid variable name: {:?}", k))); }
+        };
+        let value = match v {
+            serde_yaml::Value::String(x) => handle.template.string_unsafe_for_shell(request, tm, &format!("environment/{}", name), x)?,
+            serde_yaml::Value::Number(x) => x.to_string(),
+            serde_yaml::Value::Bool(x) => x.to_string(),
+            _ => { return Err(handle.response.is_failed(request, &format!("environment: {} must be a string, number or boolean", name))); }
+        };
+        results.push((name, value));
+    }
+    return Ok(results);
+}
+
+fn is_environment_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    return match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false
+    };
+}
+
 /* this is called from the task_fsm, not above */
 pub fn template_items(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm: TemplateMode, items_input: &Option<ItemsInput>) 
     -> Result<Vec<serde_yaml::Value>, Arc<TaskResponse>> {
@@ -134,13 +203,8 @@ pub fn template_items(handle: &Arc<TaskHandle>, request: &Arc<TaskRequest>, tm:
                 }
             }
         },
-        Some(ItemsInput::ItemsList(x)) => {
-            let mut output : Vec<serde_yaml::Value> = Vec::new();
-            for item in x.iter() {
-                output.push(serde_yaml::Value::String(handle.template.string(request, tm, &String::from("items"), item)?));
-            }
-            Ok(output)
-        }
+        // with/items: [ ... ], which may also be a list of maps
+        Some(ItemsInput::ItemsList(x)) => template_serde_sequence(handle, request, tm, x.clone())
     }
 }
 
@@ -158,13 +222,28 @@ pub fn template_serde_sequence(
     let mut output : Vec<serde_yaml::Value> = Vec::new();
 
     for seq_item in vs.iter() {
-
-        match seq_item {   
-            serde_yaml::Value::String(x) => {
-                output.push(serde_yaml::Value::String(handle.template.string(request, tm, &String::from("items"), x)?))
-            },
-            x => { output.push(x.clone()) }
-        }
+        output.push(template_serde_value(handle, request, tm, seq_item)?);
     }
     return Ok(output);
 }
+
+// items can be nested data, such as a list of user definitions, so strings are templated at any depth
+
+fn template_serde_value(handle: &TaskHandle, request: &Arc<TaskRequest>, tm: TemplateMode, value: &serde_yaml::Value) -> Result<serde_yaml::Value,Arc<TaskResponse>> {
+    return match value {
+        serde_yaml::Value::String(x) => {
+            Ok(serde_yaml::Value::String(handle.template.string(request, tm, &String::from("items"), x)?))
+        },
+        serde_yaml::Value::Sequence(xs) => {
+            Ok(serde_yaml::Value::Sequence(template_serde_sequence(handle, request, tm, xs.clone())?))
+        },
+        serde_yaml::Value::Mapping(m) => {
+            let mut output = serde_yaml::Mapping::new();
+            for (k, v) in m.iter() {
+                output.insert(k.clone(), template_serde_value(handle, request, tm, v)?);
+            }
+            Ok(serde_yaml::Value::Mapping(output))
+        },
+        x => Ok(x.clone())
+    };
+}
@@ -22,8 +22,9 @@ pub mod files;
 pub mod fields;
 pub mod cmd_library;
 pub mod checksum;
+pub mod diff;
 
-pub use crate::connection::command::cmd_info;
+pub use crate::connection::command::{cmd_info,cmd_info_with_err};
 pub use crate::tasks::common::{IsTask,IsAction,EvaluatedTask};
 pub use crate::tasks::logic::{PreLogicInput,PreLogicEvaluated,PostLogicInput,PostLogicEvaluated};
 pub use crate::handle::handle::{TaskHandle,CheckRc};
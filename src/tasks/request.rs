@@ -39,13 +39,39 @@ pub enum TaskRequestType {
 pub struct TaskRequest {
     pub request_type: TaskRequestType,
     pub changes: Vec<Field>,
-    pub sudo_details: Option<SudoDetails>
+    pub sudo_details: Option<SudoDetails>,
+    pub timeout: Option<u64>,
+    pub async_details: Option<AsyncDetails>,
+    // set for each command run, see Remote::add_environment
+    pub environment: Vec<(String,String)>
 }
 
-#[derive(Debug,PartialEq,Clone)]
+#[derive(PartialEq,Clone)]
 pub struct SudoDetails {
     pub user: Option<String>,
-    pub template: String
+    pub template: String,
+    pub password: Option<String>
+}
+
+// with/async runs commands in the background on the host, checking every 'poll' seconds whether they finished.
+// a poll of 0 starts the command and does not wait for it.
+
+#[derive(PartialEq,Clone,Copy,Debug)]
+pub struct AsyncDetails {
+    pub limit: u64,
+    pub poll: u64
+}
+
+// the password must never end up in debug output, so this is written out by hand
+
+impl std::fmt::Debug for SudoDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let password = match self.password.is_some() {
+            true => "<hidden>",
+            false => "<none>"
+        };
+        return f.debug_struct("SudoDetails").field("user", &self.user).field("template", &self.template).field("password", &password).finish();
+    }
 }
 
 // most of the various methods in task requests are constructors for different TaskRequest type variants
@@ -58,67 +84,88 @@ impl TaskRequest {
             Self { 
                 request_type: TaskRequestType::Validate, 
                 changes: Vec::new(),
-                sudo_details: None
+                sudo_details: None,
+                timeout: None,
+                async_details: None,
+                environment: Vec::new()
             }
         )
     }
 
-    pub fn query(sudo_details: &SudoDetails) -> Arc<Self> {
+    pub fn query(sudo_details: &SudoDetails, timeout: Option<u64>, environment: &Vec<(String,String)>) -> Arc<Self> {
         return Arc::new(
             Self { 
                 request_type: TaskRequestType::Query, 
                 changes: Vec::new(),
-                sudo_details: Some(sudo_details.clone())
+                sudo_details: Some(sudo_details.clone()),
+                timeout: timeout,
+                async_details: None,
+                environment: environment.clone()
             }
         )
     }
 
-    pub fn create(sudo_details: &SudoDetails) -> Arc<Self> {
+    pub fn create(sudo_details: &SudoDetails, timeout: Option<u64>, async_details: Option<AsyncDetails>, environment: &Vec<(String,String)>) -> Arc<Self> {
         return Arc::new(
             Self { 
                 request_type: TaskRequestType::Create, 
                 changes: Vec::new(),
-                sudo_details: Some(sudo_details.clone())
+                sudo_details: Some(sudo_details.clone()),
+                timeout: timeout,
+                async_details: async_details,
+                environment: environment.clone()
             }
         )
     }
 
-    pub fn remove(sudo_details: &SudoDetails) -> Arc<Self> {
+    pub fn remove(sudo_details: &SudoDetails, timeout: Option<u64>, async_details: Option<AsyncDetails>, environment: &Vec<(String,String)>) -> Arc<Self> {
         return Arc::new(
             Self { 
                 request_type: TaskRequestType::Remove, 
                 changes: Vec::new(),
-                sudo_details: Some(sudo_details.clone())
+                sudo_details: Some(sudo_details.clone()),
+                timeout: timeout,
+                async_details: async_details,
+                environment: environment.clone()
             }
         )
     }
 
-    pub fn modify(sudo_details: &SudoDetails, changes: Vec<Field>) -> Arc<Self> {
+    pub fn modify(sudo_details: &SudoDetails, timeout: Option<u64>, async_details: Option<AsyncDetails>, changes: Vec<Field>, environment: &Vec<(String,String)>) -> Arc<Self> {
         return Arc::new(
             Self { 
                 request_type: TaskRequestType::Modify, 
                 changes: changes,
-                sudo_details: Some(sudo_details.clone())
+                sudo_details: Some(sudo_details.clone()),
+                timeout: timeout,
+                async_details: async_details,
+                environment: environment.clone()
             }
         )
     }
 
-    pub fn execute(sudo_details: &SudoDetails) -> Arc<Self> {
+    pub fn execute(sudo_details: &SudoDetails, timeout: Option<u64>, async_details: Option<AsyncDetails>, environment: &Vec<(String,String)>) -> Arc<Self> {
         return Arc::new(
             Self { 
                 request_type: TaskRequestType::Execute, 
                 changes: Vec::new(),
-                sudo_details: Some(sudo_details.clone())
+                sudo_details: Some(sudo_details.clone()),
+                timeout: timeout,
+                async_details: async_details,
+                environment: environment.clone()
             }
         )
     }
 
-    pub fn passive(sudo_details: &SudoDetails) -> Arc<Self> {
+    pub fn passive(sudo_details: &SudoDetails, timeout: Option<u64>, environment: &Vec<(String,String)>) -> Arc<Self> {
         return Arc::new(
             Self { 
                 request_type: TaskRequestType::Passive, 
                 changes: Vec::new(),
-                sudo_details: Some(sudo_details.clone())
+                sudo_details: Some(sudo_details.clone()),
+                timeout: timeout,
+                async_details: None,
+                environment: environment.clone()
             }
         )
     }
@@ -49,7 +49,8 @@ pub struct TaskResponse {
     pub msg: Option<String>,
     pub command_result: Arc<Option<CommandResult>>,
     pub with: Arc<Option<PreLogicEvaluated>>,
-    pub and: Arc<Option<PostLogicEvaluated>>
+    pub and: Arc<Option<PostLogicEvaluated>>,
+    pub diff: Option<String>
 }
 
 //impl TaskResponse {
@@ -0,0 +1,53 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool,Ordering};
+use std::{thread, time};
+use signal_hook::consts::SIGINT;
+use signal_hook::flag;
+
+// the first Ctrl-C during a playbook run only sets a flag.  No new hosts or tasks are started after that,
+// tasks already running on hosts are left to finish, and the summary of what was done is shown before
+// exiting with INTERRUPTED_EXIT_CODE.  A second Ctrl-C exits immediately.
+
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: Lazy<Arc<AtomicBool>> = Lazy::new(|| Arc::new(AtomicBool::new(false)));
+
+pub fn install_interrupt_handler() -> Result<(), String> {
+    // the order matters, the shutdown check sees the flag before the first signal sets it
+    if let Err(e) = flag::register_conditional_shutdown(SIGINT, INTERRUPTED_EXIT_CODE, Arc::clone(&INTERRUPTED)) {
+        return Err(format!("could not install the interrupt handler: {}", e));
+    }
+    if let Err(e) = flag::register(SIGINT, Arc::clone(&INTERRUPTED)) {
+        return Err(format!("could not install the interrupt handler: {}", e));
+    }
+    // nothing may be printed from inside a signal handler, so a thread says what is going on instead
+    thread::spawn(|| {
+        while ! is_interrupted() {
+            thread::sleep(time::Duration::from_millis(100));
+        }
+        println!("\n! interrupted, waiting for running tasks to finish, press Ctrl-C again to quit now");
+    });
+    return Ok(());
+}
+
+pub fn is_interrupted() -> bool {
+    return INTERRUPTED.load(Ordering::Relaxed);
+}
@@ -14,8 +14,80 @@
 // You should have received a copy of the GNU General Public License
 // long with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::sync::atomic::{AtomicBool,Ordering};
+use std::io::{IsTerminal,Write};
+
+// the color policy is decided once in main.rs, after CLI parsing. color is used only
+// when stdout is a terminal and --no-color was not given, so that piping output to a
+// file or through tee does not leave escape codes behind.
+
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn configure_color(no_color_requested: bool) {
+    let enabled = (! no_color_requested) && stdout_is_terminal();
+    COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn stdout_is_terminal() -> bool {
+    return std::io::stdout().is_terminal();
+}
+
+pub fn stdin_is_terminal() -> bool {
+    return std::io::stdin().is_terminal();
+}
+
+// asks a question on the terminal and returns the answer, trimmed and lowercased.
+// None means standard input was closed.
+
+pub fn ask(question: &String) -> Option<String> {
+    print!("{}", question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    return match std::io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_lowercase())
+    };
+}
+
+pub fn is_color_enabled() -> bool {
+    return COLOR_ENABLED.load(Ordering::Relaxed);
+}
+
+// the palette is what output code should consult instead of using inline_colorization
+// directly, when color is disabled every entry is an empty string.
+
+#[derive(Clone,Copy,Debug)]
+pub struct Palette {
+    pub red: &'static str,
+    pub blue: &'static str,
+    pub green: &'static str,
+    pub cyan: &'static str,
+    pub yellow: &'static str,
+    pub reset: &'static str
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        if is_color_enabled() {
+            return Self {
+                red:    inline_colorization::color_red,
+                blue:   inline_colorization::color_blue,
+                green:  inline_colorization::color_green,
+                cyan:   inline_colorization::color_cyan,
+                yellow: inline_colorization::color_yellow,
+                reset:  inline_colorization::color_reset
+            };
+        }
+        return Self { red: "", blue: "", green: "", cyan: "", yellow: "", reset: "" };
+    }
+}
+
 pub fn markdown_print(markdown: &String) {
-    termimad::print_text(markdown);
+    if is_color_enabled() {
+        termimad::print_text(markdown);
+    } else {
+        termimad::MadSkin::no_style().print_text(markdown);
+    }
 }
 
 pub fn banner(msg: &String) {
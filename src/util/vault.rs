@@ -0,0 +1,305 @@
+// Jetporch
+// Copyright (C) 2023 - Michael DeHaan <michael@michaeldehaan.net> + contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// at your option) any later version.
+// 
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+// 
+// You should have received a copy of the GNU General Public License
+// long with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::util::io::read_local_file;
+use crate::util::yaml::show_yaml_error_in_context;
+use aes_gcm::{Aes256Gcm,Key,Nonce};
+use aes_gcm::aead::{Aead,AeadCore,KeyInit,OsRng};
+use aes_gcm::aead::rand_core::RngCore;
+use once_cell::sync::Lazy;
+use sha2::Sha256;
+use std::sync::RwLock;
+use std::path::Path;
+use std::io;
+
+// vault files hold variables encrypted with AES-256-GCM so that passwords and API keys can be
+// committed next to the rest of the automation content.  The file is a header line followed by
+// the hex encoded salt, nonce and ciphertext.  The key is derived from the vault password with
+// PBKDF2-SHA256 and a salt unique to the file.
+
+const VAULT_HEADER: &str = "$JETP_VAULT;1;AES256-GCM";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KDF_ROUNDS: u32 = 100_000;
+const LINE_WIDTH: usize = 80;
+const MIN_SECRET_LEN: usize = 4;
+
+// the password comes from --vault-password-file or is asked for the first time a vault file is seen.
+// every string decrypted from a vault file is remembered so the visitor can redact it from output.
+
+static VAULT_PASSWORD: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+static VAULT_SECRETS: Lazy<RwLock<Vec<String>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+pub fn set_vault_password(password: &String) {
+    *VAULT_PASSWORD.write().unwrap() = Some(password.clone());
+}
+
+pub fn read_password_file(path: &Path) -> Result<String, String> {
+    let password = String::from(read_local_file(path)?.trim());
+    if password.is_empty() {
+        return Err(format!("vault password file is empty: {}", path.display()));
+    }
+    return Ok(password);
+}
+
+pub fn ask_password(prompt: &str) -> Result<String, String> {
+    let mut value = String::new();
+    println!("{}", prompt);
+    match io::stdin().read_line(&mut value) {
+        Ok(_) => {},
+        Err(e) => { return Err(format!("failure reading input: {}", e)); }
+    }
+    let password = String::from(value.trim());
+    if password.is_empty() {
+        return Err(String::from("the vault password cannot be empty"));
+    }
+    return Ok(password);
+}
+
+pub fn get_vault_password() -> Result<String, String> {
+    if let Some(password) = VAULT_PASSWORD.read().unwrap().as_ref() {
+        return Ok(password.clone());
+    }
+    let password = ask_password("enter vault password:")?;
+    set_vault_password(&password);
+    return Ok(password);
+}
+
+pub fn is_encrypted(contents: &String) -> bool {
+    return contents.starts_with(VAULT_HEADER);
+}
+
+fn derive_key(password: &String, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, KDF_ROUNDS, &mut key);
+    return key;
+}
+
+pub fn encrypt(plaintext: &String, password: &String) -> Result<String, String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(password, &salt);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = match cipher.encrypt(&nonce, plaintext.as_bytes()) {
+        Ok(x) => x,
+        Err(_) => { return Err(String::from("encryption failed")); }
+    };
+
+    let mut data : Vec<u8> = Vec::new();
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(nonce.as_slice());
+    data.extend_from_slice(&ciphertext);
+    let encoded = to_hex(&data);
+
+    let mut result = String::from(VAULT_HEADER);
+    for chunk in encoded.as_bytes().chunks(LINE_WIDTH) {
+        result.push_str("\n");
+        result.push_str(std::str::from_utf8(chunk).unwrap());
+    }
+    result.push_str("\n");
+    return Ok(result);
+}
+
+pub fn decrypt(contents: &String, password: &String) -> Result<String, String> {
+    if ! is_encrypted(contents) {
+        return Err(String::from("not a vault file"));
+    }
+    let encoded : String = contents.lines().skip(1).map(|x| x.trim()).collect();
+    let data = from_hex(&encoded)?;
+    if data.len() < SALT_LEN + NONCE_LEN {
+        return Err(String::from("vault file is truncated"));
+    }
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let key = derive_key(password, salt);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let plaintext = match cipher.decrypt(Nonce::from_slice(nonce), ciphertext) {
+        Ok(x) => x,
+        // authentication failures do not say which, but it is almost always the password
+        Err(_) => { return Err(String::from("vault decryption failed, is the vault password correct?")); }
+    };
+    return match String::from_utf8(plaintext) {
+        Ok(x) => Ok(x),
+        Err(_) => Err(String::from("vault contents are not valid UTF-8"))
+    };
+}
+
+fn to_hex(data: &[u8]) -> String {
+    return data.iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+fn from_hex(encoded: &String) -> Result<Vec<u8>, String> {
+    if encoded.len() % 2 != 0 || ! encoded.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(String::from("vault file is corrupt"));
+    }
+    return Ok((0..encoded.len()).step_by(2).map(|i| u8::from_str_radix(&encoded[i..i+2], 16).unwrap()).collect());
+}
+
+// loads a YAML file of variables, decrypting it first if it is a vault file.  Used for group_vars,
+// host_vars, vars_files and --extra-vars @file.
+
+pub fn load_vars_file(path: &Path) -> Result<serde_yaml::Mapping, String> {
+    let contents = read_local_file(path)?;
+    if ! is_encrypted(&contents) {
+        let parsed: Result<serde_yaml::Mapping, serde_yaml::Error> = serde_yaml::from_str(&contents);
+        if parsed.is_err() {
+            show_yaml_error_in_context(&parsed.unwrap_err(), &path);
+            return Err(format!("edit the file and try again?"));
+        }
+        return Ok(parsed.unwrap());
+    }
+
+    let password = get_vault_password()?;
+    let plaintext = match decrypt(&contents, &password) {
+        Ok(x) => x,
+        Err(e) => { return Err(format!("{}: {}", path.display(), e)); }
+    };
+    // the error is not shown in context, that would print the decrypted lines
+    let parsed: serde_yaml::Mapping = match serde_yaml::from_str(&plaintext) {
+        Ok(x) => x,
+        Err(e) => { return Err(format!("{}: decrypted contents are not a valid YAML mapping: {}", path.display(), e)); }
+    };
+    remember_secrets(path, &String::new(), &serde_yaml::Value::Mapping(parsed.clone()));
+    return Ok(parsed);
+}
+
+// decrypted strings and numbers are kept so they can be masked wherever output is shown.  Very short values would
+// mask every matching piece of unrelated output, so they are left out and reported instead.
+
+fn remember_secrets(path: &Path, key: &String, value: &serde_yaml::Value) {
+    let secret = match value {
+        serde_yaml::Value::String(s) => s.clone(),
+        serde_yaml::Value::Number(n) => n.to_string(),
+        serde_yaml::Value::Sequence(seq) => {
+            for x in seq.iter() { remember_secrets(path, key, x); }
+            return;
+        },
+        serde_yaml::Value::Mapping(map) => {
+            for (k, v) in map.iter() {
+                let name = match k {
+                    serde_yaml::Value::String(x) => x.clone(),
+                    _ => key.clone()
+                };
+                remember_secrets(path, &name, v);
+            }
+            return;
+        },
+        serde_yaml::Value::Tagged(tagged) => {
+            remember_secrets(path, key, &tagged.value);
+            return;
+        },
+        _ => { return; }
+    };
+    if secret.is_empty() {
+        return;
+    }
+    if secret.chars().count() < MIN_SECRET_LEN {
+        println!("vault: {}: the value of '{}' is shorter than {} characters and will not be masked in output", path.display(), key, MIN_SECRET_LEN);
+        return;
+    }
+    let mut secrets = VAULT_SECRETS.write().unwrap();
+    if ! secrets.contains(&secret) {
+        secrets.push(secret);
+    }
+}
+
+// replaces every value that came out of a vault file, longest first so a secret that contains
+// another is not partially revealed
+
+pub fn redact(msg: &String) -> String {
+    let secrets = VAULT_SECRETS.read().unwrap();
+    if secrets.is_empty() {
+        return msg.clone();
+    }
+    let mut sorted : Vec<&String> = secrets.iter().collect();
+    sorted.sort_by(|a, b| b.len().cmp(&a.len()));
+    let mut result = msg.clone();
+    for secret in sorted.iter() {
+        result = result.replace(secret.as_str(), "********");
+    }
+    return result;
+}
+
+// the same masking applied to every string inside a JSON value, for logs, events and reports
+
+pub fn redact_json(value: serde_json::Value) -> serde_json::Value {
+    return match value {
+        serde_json::Value::String(x) => serde_json::Value::String(redact(&x)),
+        serde_json::Value::Array(x)  => serde_json::Value::Array(x.into_iter().map(redact_json).collect()),
+        serde_json::Value::Object(x) => serde_json::Value::Object(x.into_iter().map(|(k,v)| (k, redact_json(v))).collect()),
+        serde_json::Value::Number(x) => match redact(&x.to_string()) == x.to_string() {
+            true  => serde_json::Value::Number(x),
+            false => serde_json::Value::String(redact(&x.to_string()))
+        },
+        x => x
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let password = String::from("hunter2");
+        let plaintext = String::from("db_password: s3cret\nlist:\n  - a\n");
+        let encrypted = encrypt(&plaintext, &password).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(! encrypted.contains("s3cret"));
+        assert!(encrypted.lines().all(|x| x.len() <= LINE_WIDTH || x.starts_with(VAULT_HEADER)));
+        assert_eq!(decrypt(&encrypted, &password).unwrap(), plaintext);
+        // the salt and nonce are random, so the same input never encrypts the same way twice
+        assert_ne!(encrypt(&plaintext, &password).unwrap(), encrypted);
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let encrypted = encrypt(&String::from("secret"), &String::from("right")).unwrap();
+        assert!(decrypt(&encrypted, &String::from("wrong")).is_err());
+    }
+
+    #[test]
+    fn test_tampered_ciphertext() {
+        let password = String::from("pw");
+        let encrypted = encrypt(&String::from("secret"), &password).unwrap();
+        let mut lines : Vec<String> = encrypted.lines().map(|x| x.to_string()).collect();
+        let last = lines.last_mut().unwrap();
+        let flipped = match last.pop().unwrap() { '0' => '1', _ => '0' };
+        last.push(flipped);
+        assert!(decrypt(&lines.join("\n"), &password).is_err());
+    }
+
+    #[test]
+    fn test_numbers_are_masked_and_short_values_are_not() {
+        let path = Path::new("vault-test.yml");
+        let secrets : serde_yaml::Value = serde_yaml::from_str("pin: 73918264\nflag: 'yes'\nport: 1\nnested:\n  - token: vault-test-token\n").unwrap();
+        remember_secrets(path, &String::new(), &secrets);
+        assert_eq!(redact(&String::from("pin=73918264 token=vault-test-token")), "pin=******** token=********");
+        assert_eq!(redact(&String::from("yes 1")), "yes 1");
+        let json = redact_json(serde_json::json!({ "pin": 73918264, "count": 2 }));
+        assert_eq!(json, serde_json::json!({ "pin": "********", "count": 2 }));
+    }
+
+    #[test]
+    fn test_bad_header_and_truncation() {
+        let password = String::from("pw");
+        assert!(decrypt(&String::from("plain: yaml\n"), &password).is_err());
+        assert!(decrypt(&format!("{}\n00ff", VAULT_HEADER), &password).is_err());
+        assert!(decrypt(&format!("{}\nzz", VAULT_HEADER), &password).is_err());
+    }
+
+}